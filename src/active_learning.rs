@@ -0,0 +1,89 @@
+use std::{path::Path, time::Duration};
+
+#[cfg(feature = "onnx")]
+use crate::media::extract_frames;
+#[cfg(feature = "onnx")]
+use crate::onnx::{ImageModel, IMAGENET};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Suggestion {
+    pub time: Duration,
+    // entropy of the model's class scores, 0 when it's sure and 1 when it can't tell
+    // any class apart
+    pub uncertainty: f32,
+}
+
+// runs the classifier at `model` on the frames of `video` at `times` and returns the
+// `count` it is least sure about, most uncertain first
+#[cfg(feature = "onnx")]
+pub fn rank_uncertain(
+    model: &Path,
+    video: &Path,
+    times: &[Duration],
+    count: usize,
+    mut progress: impl FnMut(usize),
+) -> Result<Vec<Suggestion>, String> {
+    let model = ImageModel::load(model, IMAGENET)?;
+
+    let mut suggestions = Vec::with_capacity(times.len());
+    let mut failed = None;
+    extract_frames(video, times, |time, image| {
+        if failed.is_some() {
+            return;
+        }
+        match model.run(&image) {
+            Ok(scores) => suggestions.push(Suggestion {
+                time,
+                uncertainty: normalized_entropy(&probabilities(scores)),
+            }),
+            Err(err) => failed = Some(err),
+        }
+        progress(suggestions.len());
+    })
+    .map_err(|err| format!("failed to extract frames: {err}"))?;
+    if let Some(err) = failed {
+        return Err(err);
+    }
+
+    suggestions.sort_by(|a, b| b.uncertainty.total_cmp(&a.uncertainty));
+    suggestions.truncate(count);
+    Ok(suggestions)
+}
+
+#[cfg(not(feature = "onnx"))]
+pub fn rank_uncertain(
+    _model: &Path,
+    _video: &Path,
+    _times: &[Duration],
+    _count: usize,
+    _progress: impl FnMut(usize),
+) -> Result<Vec<Suggestion>, String> {
+    Err(String::from("this build can't run models, rebuild with `--features onnx`"))
+}
+
+// models end in a softmax or hand out the logits before it, these are probabilities
+// either way
+#[cfg(feature = "onnx")]
+fn probabilities(scores: Vec<f32>) -> Vec<f32> {
+    let sum = scores.iter().sum::<f32>();
+    if scores.iter().all(|score| (0. ..=1.).contains(score)) && (sum - 1.).abs() < 1e-3 {
+        return scores;
+    }
+    let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exps = scores.iter().map(|score| (score - max).exp()).collect::<Vec<_>>();
+    let sum = exps.iter().sum::<f32>();
+    exps.into_iter().map(|exp| exp / sum).collect()
+}
+
+#[cfg(feature = "onnx")]
+fn normalized_entropy(probabilities: &[f32]) -> f32 {
+    if probabilities.len() < 2 {
+        return 0.;
+    }
+    let entropy = probabilities
+        .iter()
+        .filter(|p| **p > 0.)
+        .map(|p| -p * p.ln())
+        .sum::<f32>();
+    entropy / (probabilities.len() as f32).ln()
+}
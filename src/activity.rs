@@ -0,0 +1,241 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Read, Write},
+    path::Path,
+};
+
+use cushy::{
+    value::{Destination, Dynamic, MapEach, Source},
+    widget::MakeWidget,
+};
+
+use crate::{
+    settings::config_dir,
+    tags::{ActivityEvent, ActivityKind},
+};
+
+const ACTIVITY_FILE: &str = "activity.jsonl";
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+// gaps longer than this between two edits count as a break, not as work
+const IDLE_THRESHOLD: u64 = 5 * 60;
+const BAR_WIDTH: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    Week,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeriodStats {
+    pub created: usize,
+    pub edited: usize,
+    pub working_seconds: u64,
+}
+
+// everything ever tagged, across sessions, kept as json lines in the config dir
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActivityLog {
+    events: Vec<ActivityEvent>,
+}
+
+impl ActivityLog {
+    // only the first `length` bytes, so events appended after startup aren't read back
+    fn load(path: &Path, length: u64) -> Self {
+        let Ok(file) = std::fs::File::open(path) else {
+            return Self::default();
+        };
+
+        let events = std::io::BufReader::new(file.take(length))
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect();
+
+        Self { events }
+    }
+
+    // past sessions are only needed for the chart, so reading them doesn't hold up
+    // startup. the file is measured before this session appends to it, and anything
+    // recorded in the meantime stays after the loaded events
+    pub fn load_in_background(log: Dynamic<ActivityLog>) {
+        let Some(path) = config_dir().map(|dir| dir.join(ACTIVITY_FILE)) else {
+            return;
+        };
+        let Ok(length) = std::fs::metadata(&path).map(|metadata| metadata.len()) else {
+            return;
+        };
+
+        std::thread::Builder::new()
+            .name("Activity Loader Thread".into())
+            .spawn(move || {
+                let _span = tracing::info_span!("activity_load").entered();
+                let loaded = Self::load(&path, length);
+                log.lock().events.splice(0..0, loaded.events);
+            })
+            .unwrap();
+    }
+
+    pub fn append(&mut self, events: &[ActivityEvent]) -> std::io::Result<()> {
+        self.events.extend_from_slice(events);
+
+        let Some(dir) = config_dir() else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(&dir)?;
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(ACTIVITY_FILE))?;
+        for event in events {
+            serde_json::to_writer(&mut file, event)?;
+            file.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    // keyed by the first day of the period, in days since the unix epoch
+    pub fn stats(&self, period: Period) -> BTreeMap<u64, PeriodStats> {
+        let mut stats = BTreeMap::<u64, PeriodStats>::new();
+        let mut previous: Option<u64> = None;
+
+        for event in &self.events {
+            let entry = stats.entry(period_start(event.at, period)).or_default();
+            match event.kind {
+                ActivityKind::Created => entry.created += 1,
+                ActivityKind::Edited | ActivityKind::Deleted => entry.edited += 1,
+            }
+
+            if let Some(previous) = previous {
+                let gap = event.at.saturating_sub(previous);
+                if gap <= IDLE_THRESHOLD {
+                    entry.working_seconds += gap;
+                }
+            }
+            previous = Some(event.at);
+        }
+
+        stats
+    }
+}
+
+fn period_start(unix_seconds: u64, period: Period) -> u64 {
+    let day = unix_seconds / SECONDS_PER_DAY;
+    match period {
+        Period::Day => day,
+        // 1970-01-01 was a thursday, weeks start on monday
+        Period::Week => ((day + 3) / 7 * 7).saturating_sub(3),
+    }
+}
+
+// days since the unix epoch to a yyyy-mm-dd date
+pub fn format_day(days: u64) -> String {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+pub fn activity_chart(log: Dynamic<ActivityLog>) -> impl MakeWidget {
+    let period = Dynamic::new(Period::Day);
+
+    let chart = (&log, &period).map_each(|(log, period)| {
+        let stats = log.stats(*period);
+        if stats.is_empty() {
+            return String::from("no tagging activity recorded yet");
+        }
+
+        let most = stats
+            .values()
+            .map(|stats| stats.created + stats.edited)
+            .max()
+            .unwrap_or(1)
+            .max(1);
+
+        stats
+            .iter()
+            .map(|(start, stats)| {
+                let total = stats.created + stats.edited;
+                let bar = "#".repeat(total * BAR_WIDTH / most);
+                let prefix = match period {
+                    Period::Day => "",
+                    Period::Week => "week of ",
+                };
+                format!(
+                    "{prefix}{}  {bar:<BAR_WIDTH$}  {} created, {} edited, {:.1}h",
+                    format_day(*start),
+                    stats.created,
+                    stats.edited,
+                    stats.working_seconds as f64 / 3600.
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    });
+
+    let per_day = "per day".into_button().on_click({
+        let period = period.clone();
+        move |_| period.set(Period::Day)
+    });
+    let per_week = "per week".into_button().on_click(move |_| period.set(Period::Week));
+
+    "Tagging activity"
+        .and(per_day.and(per_week).into_columns())
+        .and(chart.vertical_scroll())
+        .into_rows()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn event(at: u64) -> ActivityEvent {
+        ActivityEvent {
+            at,
+            kind: ActivityKind::Created,
+            tag: None,
+            video: "a.mp4".into(),
+            label: String::from("goal"),
+            note: String::new(),
+            time: Duration::from_secs(at),
+            end: None,
+            sequence: 0,
+        }
+    }
+
+    #[test]
+    fn events_appended_after_startup_are_not_loaded() {
+        let path = std::env::temp_dir()
+            .join(format!("video-tagger-activity-{}.jsonl", std::process::id()));
+        let write = |events: &[ActivityEvent]| {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .unwrap();
+            for event in events {
+                serde_json::to_writer(&mut file, event).unwrap();
+                file.write_all(b"\n").unwrap();
+            }
+        };
+
+        write(&[event(1), event(2)]);
+        let length = std::fs::metadata(&path).unwrap().len();
+        write(&[event(3)]);
+        let loaded = ActivityLog::load(&path, length);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.events, [event(1), event(2)]);
+    }
+}
@@ -0,0 +1,151 @@
+use cushy::{
+    figures::units::Lp,
+    styles::{
+        components::{IntrinsicPadding, LineHeight, TextSize},
+        Dimension,
+    },
+    value::{Destination, Dynamic, MapEach, Source},
+    widget::{MakeWidget, WidgetList},
+};
+use serde::{Deserialize, Serialize};
+
+// cushy's own defaults, everything else scales from these
+const BASE_TEXT_SIZE: f32 = 12.;
+const BASE_LINE_HEIGHT: f32 = 16.;
+const BASE_PADDING: f32 = 5.;
+
+const MIN_FONT_SCALE: f32 = 0.5;
+const MAX_FONT_SCALE: f32 = 3.;
+const FONT_SCALE_STEP: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Density {
+    Compact,
+    #[default]
+    Comfortable,
+}
+
+impl Density {
+    fn padding_factor(self) -> f32 {
+        match self {
+            Self::Compact => 0.4,
+            Self::Comfortable => 1.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Appearance {
+    pub density: Density,
+    // multiplies every text size, 1 is cushy's default
+    #[serde(deserialize_with = "font_scale")]
+    pub font_scale: f32,
+}
+
+// a hand-edited settings file could shrink text to nothing or blow it up past the window
+fn font_scale<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let scale = f32::deserialize(deserializer)?;
+    if scale.is_finite() {
+        Ok(scale.clamp(MIN_FONT_SCALE, MAX_FONT_SCALE))
+    } else {
+        Ok(1.)
+    }
+}
+
+impl Default for Appearance {
+    fn default() -> Self {
+        Self {
+            density: Density::default(),
+            font_scale: 1.,
+        }
+    }
+}
+
+impl Appearance {
+    pub fn scaled_by(self, steps: f32) -> Self {
+        let font_scale = (self.font_scale + steps * FONT_SCALE_STEP).clamp(MIN_FONT_SCALE, MAX_FONT_SCALE);
+        Self {
+            // keeps repeated steps from drifting away from round percentages
+            font_scale: (font_scale * 10.).round() / 10.,
+            ..self
+        }
+    }
+}
+
+// styles inherit down the tree, so applying them at the root reaches every label,
+// list and timecode readout
+pub fn apply(root: impl MakeWidget, appearance: &Dynamic<Appearance>) -> impl MakeWidget {
+    let text_size = appearance.map_each(|appearance| {
+        Dimension::from(Lp::points_f(BASE_TEXT_SIZE * appearance.font_scale))
+    });
+    let line_height = appearance.map_each(|appearance| {
+        Dimension::from(Lp::points_f(BASE_LINE_HEIGHT * appearance.font_scale))
+    });
+    let padding = appearance.map_each(|appearance| {
+        Dimension::from(Lp::points_f(
+            BASE_PADDING * appearance.density.padding_factor() * appearance.font_scale.max(1.),
+        ))
+    });
+
+    root.with(&TextSize, text_size)
+        .with(&LineHeight, line_height)
+        .with(&IntrinsicPadding, padding)
+}
+
+pub fn appearance_controls(appearance: Dynamic<Appearance>) -> impl MakeWidget {
+    let smaller = "A-".into_button().on_click({
+        let appearance = appearance.clone();
+        move |_| {
+            let scaled = appearance.get().scaled_by(-1.);
+            appearance.set(scaled);
+        }
+    });
+    let larger = "A+".into_button().on_click({
+        let appearance = appearance.clone();
+        move |_| {
+            let scaled = appearance.get().scaled_by(1.);
+            appearance.set(scaled);
+        }
+    });
+    let scale = appearance.map_each(|appearance| format!("{:.0}%", appearance.font_scale * 100.));
+
+    let densities = [(Density::Compact, "compact"), (Density::Comfortable, "comfortable")]
+        .into_iter()
+        .map(|(density, name)| {
+            let label = appearance.map_each(move |appearance| {
+                if appearance.density == density {
+                    format!("[{name}]")
+                } else {
+                    name.to_string()
+                }
+            });
+            label.into_button().on_click({
+                let appearance = appearance.clone();
+                move |_| appearance.lock().density = density
+            })
+        })
+        .collect::<WidgetList>();
+
+    smaller
+        .and(scale)
+        .and(larger)
+        .and(densities.into_columns())
+        .into_columns()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn font_scale_is_clamped_on_load() {
+        let load = |json: &str| serde_json::from_str::<Appearance>(json).unwrap().font_scale;
+
+        assert_eq!(load(r#"{"font_scale": 1.5}"#), 1.5);
+        assert_eq!(load(r#"{"font_scale": 0}"#), MIN_FONT_SCALE);
+        assert_eq!(load(r#"{"font_scale": -2}"#), MIN_FONT_SCALE);
+        assert_eq!(load(r#"{"font_scale": 1e30}"#), MAX_FONT_SCALE);
+        assert_eq!(load("{}"), 1.);
+    }
+}
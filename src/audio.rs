@@ -0,0 +1,401 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{Receiver, RecvTimeoutError, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use ffmpeg_next as ffmpeg;
+
+use crate::clock::PlaybackClock;
+use crate::video_player::CancelToken;
+use crate::{DecoderInput, CANCEL_POLL_INTERVAL};
+
+// audio packets are small, this is a bit over a second for most codecs
+const AUDIO_QUEUE_LENGTH: usize = 256;
+// decoded audio waiting for the device, enough to ride out a slow video frame
+const MAX_BUFFERED: Duration = Duration::from_millis(500);
+
+// resampled interleaved samples on their way to the device. everything here is
+// shared with the device callback, which runs on a thread of cpal's
+#[derive(Debug)]
+struct AudioBuffer {
+    samples: VecDeque<f32>,
+    // stream time of the sample at the front
+    front_seconds: f64,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl AudioBuffer {
+    fn buffered(&self) -> Duration {
+        Duration::from_secs_f64(
+            self.samples.len() as f64 / f64::from(self.channels) / f64::from(self.sample_rate),
+        )
+    }
+
+    fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    // drops what the clock already passed
+    fn skip_to(&mut self, seconds: f64) {
+        let frames = ((seconds - self.front_seconds) * f64::from(self.sample_rate)).max(0.) as usize;
+        let len = (frames * usize::from(self.channels)).min(self.samples.len());
+        self.samples.drain(..len);
+        self.front_seconds += (len / usize::from(self.channels.max(1))) as f64
+            / f64::from(self.sample_rate.max(1));
+    }
+}
+
+// decodes the best audio stream next to the video and plays it on the default
+// output device. fed by the same packet reader as `VideoDecoder`
+pub struct AudioDecoder {
+    input_sender: Option<SyncSender<DecoderInput>>,
+    pending_seeks: Arc<AtomicUsize>,
+    buffer: Arc<Mutex<AudioBuffer>>,
+    thread: Option<JoinHandle<()>>,
+    // audio can go away on its own while the video keeps playing
+    stop: CancelToken,
+}
+
+impl Drop for AudioDecoder {
+    fn drop(&mut self) {
+        self.stop.cancel();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl AudioDecoder {
+    // none when the stream can't be decoded or there is no output device, the
+    // video then plays silently on its own clock
+    pub fn start(
+        stream: &ffmpeg::format::stream::Stream,
+        cancel: CancelToken,
+        clock: PlaybackClock,
+    ) -> Option<Self> {
+        let decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+            .and_then(|context| context.decoder().audio())
+            .map_err(|err| tracing::error!("failed to open the audio decoder: {err}"))
+            .ok()?;
+        let time_base = f64::from(stream.time_base());
+
+        let (input_sender, input_receiver) = std::sync::mpsc::sync_channel(AUDIO_QUEUE_LENGTH);
+        let (ready_sender, ready_receiver) = std::sync::mpsc::channel();
+        let pending_seeks = Arc::new(AtomicUsize::new(0));
+        let stop = CancelToken::default();
+        let buffer = Arc::new(Mutex::new(AudioBuffer {
+            samples: VecDeque::new(),
+            front_seconds: 0.,
+            sample_rate: 0,
+            channels: 0,
+        }));
+
+        let thread = std::thread::Builder::new()
+            .name("Audio Thread".into())
+            .spawn({
+                let buffer = buffer.clone();
+                let pending_seeks = pending_seeks.clone();
+                let stop = stop.clone();
+                move || {
+                    // the device stream can't leave the thread that made it
+                    let output = match open_output(buffer.clone(), clock) {
+                        Ok(output) => output,
+                        Err(err) => {
+                            let _ = ready_sender.send(Err(err));
+                            return;
+                        }
+                    };
+                    let _ = ready_sender.send(Ok(()));
+
+                    let mut audio = AudioThread {
+                        decoder,
+                        resampler: None,
+                        time_base,
+                        buffer,
+                        pending_seeks,
+                        seek_target: None,
+                        cancel,
+                        stop,
+                    };
+                    audio.run(&input_receiver);
+                    drop(output);
+                }
+            })
+            .unwrap();
+
+        match ready_receiver.recv() {
+            Ok(Ok(())) => Some(Self {
+                input_sender: Some(input_sender),
+                pending_seeks,
+                buffer,
+                thread: Some(thread),
+                stop,
+            }),
+            Ok(Err(err)) => {
+                tracing::warn!("no audio: {err}");
+                let _ = thread.join();
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    pub fn try_get_packet(
+        &self,
+        packet: ffmpeg::codec::packet::Packet,
+    ) -> Result<(), TrySendError<ffmpeg::codec::packet::Packet>> {
+        let Some(sender) = &self.input_sender else {
+            return Err(TrySendError::Disconnected(packet));
+        };
+        sender.try_send(DecoderInput::Packet(packet)).map_err(|err| match err {
+            TrySendError::Full(DecoderInput::Packet(packet)) => TrySendError::Full(packet),
+            TrySendError::Disconnected(DecoderInput::Packet(packet)) => {
+                TrySendError::Disconnected(packet)
+            }
+            _ => unreachable!("only packets are sent here"),
+        })
+    }
+
+    // same as the video side: what is queued is dropped, decoding goes on up to `target`
+    pub fn seek(&self, target: Duration) -> bool {
+        let Some(sender) = &self.input_sender else {
+            return false;
+        };
+        self.pending_seeks.fetch_add(1, Ordering::AcqRel);
+        self.buffer.lock().unwrap().clear();
+        sender.send(DecoderInput::Seek(target)).is_ok()
+    }
+
+    pub fn end_of_stream(&self) -> bool {
+        match &self.input_sender {
+            Some(sender) => sender.send(DecoderInput::EndOfStream).is_ok(),
+            None => false,
+        }
+    }
+}
+
+struct AudioThread {
+    decoder: ffmpeg::decoder::Audio,
+    // made from the first frame, decoders only know their format once they decoded
+    resampler: Option<ffmpeg::software::resampling::Context>,
+    time_base: f64,
+    buffer: Arc<Mutex<AudioBuffer>>,
+    pending_seeks: Arc<AtomicUsize>,
+    seek_target: Option<f64>,
+    cancel: CancelToken,
+    stop: CancelToken,
+}
+
+impl AudioThread {
+    fn cancelled(&self) -> bool {
+        self.cancel.is_cancelled() || self.stop.is_cancelled()
+    }
+
+    fn run(&mut self, inputs: &Receiver<DecoderInput>) {
+        loop {
+            if self.cancelled() {
+                break;
+            }
+
+            let input = match inputs.recv_timeout(CANCEL_POLL_INTERVAL) {
+                Ok(input) => input,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            match input {
+                // queued before a seek that is still on its way
+                DecoderInput::Packet(_) if self.pending_seeks.load(Ordering::Acquire) > 0 => {
+                    continue
+                }
+                DecoderInput::Packet(packet) => {
+                    if self.decoder.send_packet(&packet).is_err() {
+                        continue;
+                    }
+                }
+                DecoderInput::Seek(target) => {
+                    if self.pending_seeks.fetch_sub(1, Ordering::AcqRel) == 1 {
+                        self.decoder.flush();
+                        self.buffer.lock().unwrap().clear();
+                        self.seek_target = Some(target.as_secs_f64());
+                    }
+                    continue;
+                }
+                DecoderInput::EndOfStream => {
+                    let _ = self.decoder.send_eof();
+                }
+            }
+
+            let mut decoded = ffmpeg::util::frame::Audio::empty();
+            while self.decoder.receive_frame(&mut decoded).is_ok() {
+                let start = decoded.timestamp().unwrap_or(0) as f64 * self.time_base;
+                let Some(samples) = self.resample(&decoded) else {
+                    continue;
+                };
+                if !self.push(start, samples) {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn resample(&mut self, decoded: &ffmpeg::util::frame::Audio) -> Option<Vec<f32>> {
+        let (sample_rate, channels) = {
+            let buffer = self.buffer.lock().unwrap();
+            (buffer.sample_rate, buffer.channels)
+        };
+
+        if self.resampler.is_none() {
+            let layout = if decoded.channel_layout().is_empty() {
+                ffmpeg::ChannelLayout::default(i32::from(decoded.channels()))
+            } else {
+                decoded.channel_layout()
+            };
+            self.resampler = ffmpeg::software::resampling::Context::get(
+                decoded.format(),
+                layout,
+                decoded.rate(),
+                ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Packed),
+                ffmpeg::ChannelLayout::default(i32::from(channels)),
+                sample_rate,
+            )
+            .map_err(|err| tracing::error!("failed to set up audio resampling: {err}"))
+            .ok();
+        }
+
+        let mut resampled = ffmpeg::util::frame::Audio::empty();
+        self.resampler.as_mut()?.run(decoded, &mut resampled).ok()?;
+
+        let len = resampled.samples() * usize::from(channels);
+        let samples = resampled.data(0)[..len * std::mem::size_of::<f32>()]
+            .chunks_exact(std::mem::size_of::<f32>())
+            .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        Some(samples)
+    }
+
+    // queues a frame's samples, waiting while the device has plenty. false once cancelled
+    fn push(&mut self, start: f64, mut samples: Vec<f32>) -> bool {
+        loop {
+            if self.cancelled() {
+                return false;
+            }
+            if self.pending_seeks.load(Ordering::Acquire) > 0 {
+                return true;
+            }
+
+            let mut buffer = self.buffer.lock().unwrap();
+            let sample_rate = f64::from(buffer.sample_rate);
+            let channels = usize::from(buffer.channels);
+
+            // decoding up to a seek target drops what comes before it
+            let mut start = start;
+            if let Some(target) = self.seek_target {
+                let frames = samples.len() / channels;
+                let skip = (((target - start) * sample_rate).max(0.) as usize).min(frames);
+                if skip == frames {
+                    return true;
+                }
+                samples.drain(..skip * channels);
+                start += skip as f64 / sample_rate;
+                self.seek_target = None;
+            }
+
+            if buffer.buffered() < MAX_BUFFERED || buffer.samples.is_empty() {
+                if buffer.samples.is_empty() {
+                    buffer.front_seconds = start;
+                }
+                buffer.samples.extend(samples);
+                return true;
+            }
+            drop(buffer);
+            std::thread::sleep(MAX_BUFFERED / 10);
+        }
+    }
+}
+
+fn open_output(
+    buffer: Arc<Mutex<AudioBuffer>>,
+    clock: PlaybackClock,
+) -> Result<cpal::Stream, String> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| String::from("no output device"))?;
+    let config = device.default_output_config().map_err(|err| err.to_string())?;
+
+    {
+        let mut buffer = buffer.lock().unwrap();
+        buffer.sample_rate = config.sample_rate().0;
+        buffer.channels = config.channels();
+    }
+
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config.into(), buffer, clock),
+        cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config.into(), buffer, clock),
+        cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config.into(), buffer, clock),
+        format => return Err(format!("unsupported sample format {format}")),
+    }
+    .map_err(|err| err.to_string())?;
+    stream.play().map_err(|err| err.to_string())?;
+    Ok(stream)
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    buffer: Arc<Mutex<AudioBuffer>>,
+    clock: PlaybackClock,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            let mut buffer = buffer.lock().unwrap();
+            // nothing plays while paused, or after a seek until the video is there
+            if clock.is_paused() || !clock.is_anchored() {
+                data.fill(T::from_sample(0.));
+                return;
+            }
+            // sped up or slowed down audio would sound wrong, it stays quiet and
+            // keeps up with the clock instead of leading it
+            if clock.rate() != 1. {
+                if let Some(seconds) = clock.seconds() {
+                    buffer.skip_to(seconds);
+                }
+                data.fill(T::from_sample(0.));
+                return;
+            }
+
+            // only a full callback says where playback is, an underrun would
+            // hold the clock back and the video with it
+            if buffer.samples.len() >= data.len() {
+                clock.anchor(buffer.front_seconds);
+            }
+            let mut taken = 0;
+            for sample in data.iter_mut() {
+                *sample = match buffer.samples.pop_front() {
+                    Some(value) => {
+                        taken += 1;
+                        T::from_sample(value)
+                    }
+                    None => T::from_sample(0.),
+                };
+            }
+
+            let frames = taken / usize::from(buffer.channels.max(1));
+            buffer.front_seconds += frames as f64 / f64::from(buffer.sample_rate.max(1));
+        },
+        |err| tracing::error!("audio output failed: {err}"),
+        None,
+    )
+}
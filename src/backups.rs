@@ -0,0 +1,412 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use cushy::{
+    value::{Destination, Dynamic, MapEach, Source, Switchable},
+    widget::{MakeWidget, WidgetList},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::activity::format_day;
+use crate::database::{self, DatabaseError, TagDatabase};
+use crate::settings::config_dir;
+use crate::tags::TagStore;
+
+const BACKUP_PREFIX: &str = "tags-";
+const BACKUP_EXTENSION: &str = "json";
+// a folder picked by hand may hold anything, the backups get one of their own inside it
+const BACKUP_FOLDER: &str = "video-tagger-backups";
+// minutes between backups the panel steps through
+const INTERVALS: [u64; 6] = [1, 5, 10, 15, 30, 60];
+// how often the backup thread looks at the settings again
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    // defaults to `backups` in the config dir
+    pub dir: Option<PathBuf>,
+    pub keep: usize,
+    pub interval_minutes: u64,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            keep: 20,
+            interval_minutes: 10,
+        }
+    }
+}
+
+impl BackupSettings {
+    pub fn resolved_dir(&self) -> Option<PathBuf> {
+        match &self.dir {
+            Some(dir) => Some(dir.join(BACKUP_FOLDER)),
+            None => config_dir().map(|dir| dir.join("backups")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backup {
+    pub path: PathBuf,
+    // unix milliseconds
+    pub taken_at: u64,
+}
+
+// taken this process, for names that don't collide within a millisecond
+static TAKEN: AtomicU64 = AtomicU64::new(0);
+
+// tags-<unix ms>-<process>-<count>.json. backups from before that were named
+// tags-<unix seconds>.json
+fn backup_name(taken_at: u64) -> String {
+    let count = TAKEN.fetch_add(1, Ordering::Relaxed);
+    format!(
+        "{BACKUP_PREFIX}{taken_at}-{}-{count}.{BACKUP_EXTENSION}",
+        std::process::id()
+    )
+}
+
+// when a backup was taken, in unix milliseconds, for names `backup_name` makes and the
+// older ones. anything else in the folder isn't a backup and is never touched
+fn taken_at(path: &Path) -> Option<u64> {
+    if path.extension()? != BACKUP_EXTENSION {
+        return None;
+    }
+    let stem = path.file_stem()?.to_str()?.strip_prefix(BACKUP_PREFIX)?;
+    let parts = stem.split('-').collect::<Vec<_>>();
+    if parts.iter().any(|part| part.is_empty() || !part.bytes().all(|byte| byte.is_ascii_digit())) {
+        return None;
+    }
+    match parts.as_slice() {
+        [seconds] => seconds.parse::<u64>().ok()?.checked_mul(1000),
+        [millis, _process, _count] => millis.parse().ok(),
+        _ => None,
+    }
+}
+
+// newest first
+pub fn list(settings: &BackupSettings) -> Vec<Backup> {
+    let Some(entries) = settings
+        .resolved_dir()
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut backups = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let path = entry.path();
+            let taken_at = taken_at(&path)?;
+            Some(Backup { path, taken_at })
+        })
+        .collect::<Vec<_>>();
+    backups.sort_by(|a, b| (b.taken_at, &b.path).cmp(&(a.taken_at, &a.path)));
+    backups
+}
+
+// writes a snapshot and drops the oldest ones beyond `keep`. `store` should have every
+// video's tags, see `TagDatabase::everything`
+pub fn snapshot(store: &TagStore, settings: &BackupSettings) -> Result<(), DatabaseError> {
+    let Some(dir) = settings.resolved_dir() else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(&dir)?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64);
+    database::write_document(store, &dir.join(backup_name(now)))?;
+
+    for old in list(settings).into_iter().skip(settings.keep.max(1)) {
+        std::fs::remove_file(old.path)?;
+    }
+    Ok(())
+}
+
+// snapshots the tags every `interval_minutes`, skipping rounds where nothing changed
+pub fn start(
+    tags: Dynamic<TagStore>,
+    database: TagDatabase,
+    settings: Dynamic<BackupSettings>,
+    backups: Dynamic<Vec<Backup>>,
+) {
+    std::thread::Builder::new()
+        .name("Backup Thread".into())
+        .spawn(move || {
+            let mut last_backed_up: Option<TagStore> = None;
+            let mut since_last = Duration::ZERO;
+            loop {
+                std::thread::sleep(CHECK_INTERVAL);
+                since_last += CHECK_INTERVAL;
+
+                let current_settings = settings.get();
+                if since_last < Duration::from_secs(current_settings.interval_minutes.max(1) * 60) {
+                    continue;
+                }
+                since_last = Duration::ZERO;
+
+                let store = tags.get();
+                if last_backed_up.as_ref() == Some(&store) {
+                    continue;
+                }
+                let everything = match database.everything(&store) {
+                    Ok(everything) if everything.tags().is_empty() => continue,
+                    Ok(everything) => everything,
+                    Err(err) => {
+                        tracing::error!("failed to back up tags: {err}");
+                        continue;
+                    }
+                };
+
+                match snapshot(&everything, &current_settings) {
+                    Ok(()) => {
+                        last_backed_up = Some(store);
+                        backups.set(list(&current_settings));
+                    }
+                    Err(err) => tracing::error!("failed to back up tags: {err}"),
+                }
+            }
+        })
+        .unwrap();
+}
+
+fn format_backup_time(unix_millis: u64) -> String {
+    let unix_seconds = unix_millis / 1000;
+    let seconds_of_day = unix_seconds % (24 * 60 * 60);
+    format!(
+        "{} {:02}:{:02}:{:02} UTC",
+        format_day(unix_seconds / (24 * 60 * 60)),
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    )
+}
+
+pub fn backup_panel(
+    tags: Dynamic<TagStore>,
+    database: TagDatabase,
+    settings: Dynamic<BackupSettings>,
+    backups: Dynamic<Vec<Backup>>,
+) -> impl MakeWidget {
+    let refresh = {
+        let settings = settings.clone();
+        let backups = backups.clone();
+        move || backups.set(list(&settings.get()))
+    };
+
+    let back_up_now = "back up now".into_button().on_click({
+        let tags = tags.clone();
+        let database = database.clone();
+        let settings = settings.clone();
+        let refresh = refresh.clone();
+        move |_| {
+            let everything = database.everything(&tags.get());
+            if let Err(err) = everything.and_then(|store| snapshot(&store, &settings.get())) {
+                tracing::error!("failed to back up tags: {err}");
+            }
+            refresh();
+        }
+    });
+
+    let choose_folder = "choose folder".into_button().on_click({
+        let settings = settings.clone();
+        let refresh = refresh.clone();
+        move |_| {
+            let settings = settings.clone();
+            let refresh = refresh.clone();
+            std::thread::Builder::new()
+                .name("File Picker Thread".into())
+                .spawn(move || {
+                    if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                        settings.lock().dir = Some(dir);
+                        refresh();
+                    }
+                })
+                .unwrap();
+        }
+    });
+
+    let location = settings.map_each(|settings| {
+        let dir = settings
+            .resolved_dir()
+            .map_or_else(|| String::from("nowhere"), |dir| dir.display().to_string());
+        format!(
+            "every {} minutes to {dir}, keeping {}",
+            settings.interval_minutes, settings.keep
+        )
+    });
+
+    let fewer = "keep fewer".into_button().on_click({
+        let settings = settings.clone();
+        move |_| {
+            let mut settings = settings.lock();
+            settings.keep = settings.keep.saturating_sub(5).max(1);
+        }
+    });
+    let more = "keep more".into_button().on_click({
+        let settings = settings.clone();
+        move |_| settings.lock().keep += 5
+    });
+    let more_often = "more often".into_button().on_click({
+        let settings = settings.clone();
+        move |_| {
+            let mut settings = settings.lock();
+            let current = settings.interval_minutes;
+            settings.interval_minutes = INTERVALS
+                .into_iter()
+                .rev()
+                .find(|minutes| *minutes < current)
+                .unwrap_or(INTERVALS[0]);
+        }
+    });
+    let less_often = "less often".into_button().on_click({
+        let settings = settings.clone();
+        move |_| {
+            let mut settings = settings.lock();
+            let current = settings.interval_minutes;
+            settings.interval_minutes = INTERVALS
+                .into_iter()
+                .find(|minutes| *minutes > current)
+                .unwrap_or(INTERVALS[INTERVALS.len() - 1]);
+        }
+    });
+
+    let entries = backups.clone().switcher(move |backups, _| {
+        if backups.is_empty() {
+            return "no backups yet".make_widget();
+        }
+
+        backups
+            .iter()
+            .map(|backup| {
+                // the current state gets a backup of its own first, so restoring
+                // the wrong snapshot can be undone
+                let restore = "restore".into_button().on_click({
+                    let tags = tags.clone();
+                    let database = database.clone();
+                    let settings = settings.clone();
+                    let path = backup.path.clone();
+                    let refresh = refresh.clone();
+                    move |_| {
+                        let restored = match database::load(&path) {
+                            Ok(restored) => restored,
+                            Err(err) => {
+                                tracing::error!("failed to read {}: {err}", path.display());
+                                return;
+                            }
+                        };
+                        let everything = database.everything(&tags.get());
+                        let backed_up =
+                            everything.and_then(|store| snapshot(&store, &settings.get()));
+                        if let Err(err) = backed_up {
+                            tracing::error!(
+                                "not restoring, backing up the current tags failed: {err}"
+                            );
+                            return;
+                        }
+                        tags.set(restored);
+                        refresh();
+                    }
+                });
+
+                format_backup_time(backup.taken_at)
+                    .expand_horizontally()
+                    .and(restore)
+                    .into_columns()
+            })
+            .collect::<WidgetList>()
+            .into_rows()
+            .make_widget()
+    });
+
+    "Backups"
+        .and(location)
+        .and(
+            back_up_now
+                .and(choose_folder)
+                .and(fewer)
+                .and(more)
+                .and(more_often)
+                .and(less_often)
+                .into_columns(),
+        )
+        .and(entries.vertical_scroll())
+        .into_rows()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use super::*;
+
+    fn round_trip(store: &TagStore) -> TagStore {
+        database::from_document(database::to_document(store).unwrap()).unwrap()
+    }
+
+    // the restore button swaps in a store with a shorter activity log than the one the
+    // observer in lib.rs has already gone through
+    #[test]
+    fn restore_after_an_edit() {
+        let video = PathBuf::from("match.mp4");
+        let mut tags = TagStore::default();
+        tags.add(video.clone(), Duration::from_secs(1), "kickoff".into());
+        let backup = round_trip(&tags);
+
+        tags.add(video.clone(), Duration::from_secs(5), "goal".into());
+        tags.add(video.clone(), Duration::from_secs(9), "foul".into());
+        let seen = tags.activity_since(0).last().unwrap().sequence;
+
+        let mut restored = backup;
+        assert_eq!(restored.tags().len(), 1);
+        assert!(restored.activity_since(seen).is_empty());
+
+        restored.add(video, Duration::from_secs(7), "save".into());
+        let new_events = restored.activity_since(seen);
+        assert_eq!(new_events.len(), 1);
+        assert_eq!(new_events[0].label, "save");
+    }
+
+    #[test]
+    fn backup_names() {
+        let first = backup_name(1_700_000_000_123);
+        let second = backup_name(1_700_000_000_123);
+        assert_ne!(first, second);
+        assert_eq!(taken_at(Path::new(&first)), Some(1_700_000_000_123));
+        assert_eq!(taken_at(Path::new("tags-1700000000.json")), Some(1_700_000_000_000));
+        let others = ["tags-.json", "tags-1-2.json", "tags-1-x-3.json", "notes-1.json", "tags-1.txt"];
+        for other in others {
+            assert_eq!(taken_at(Path::new(other)), None, "{other}");
+        }
+    }
+
+    #[test]
+    fn only_backups_are_pruned() {
+        let dir = std::env::temp_dir().join(format!("tag-backups-{}", std::process::id()));
+        let settings = BackupSettings {
+            dir: Some(dir.clone()),
+            keep: 2,
+            interval_minutes: 10,
+        };
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("tags-1.json"), "not ours").unwrap();
+
+        let store = TagStore::new();
+        for _ in 0..4 {
+            snapshot(&store, &settings).unwrap();
+        }
+        let backups = list(&settings);
+        let kept_ours = dir.join("tags-1.json").exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert!(backups.iter().all(|backup| backup.path.starts_with(dir.join(BACKUP_FOLDER))));
+        assert!(kept_ours);
+    }
+}
@@ -0,0 +1,209 @@
+use cushy::{
+    figures::units::Lp,
+    styles::{components::TextColor, Color},
+    value::{Destination, Dynamic, MapEach, Source, Switchable},
+    widget::{MakeWidget, SharedCallback, WidgetList},
+    widgets::layers::OverlayLayer,
+};
+
+use crate::keybindings::{KeyCombo, KeyRecorder, Keybindings};
+use crate::palette::{MarkerShape, Palette};
+use crate::tags::TagStore;
+
+#[derive(Debug, Clone, PartialEq)]
+struct CategoryRow {
+    name: String,
+    shortcut: Option<KeyCombo>,
+    locked: bool,
+    color: Color,
+    shape: MarkerShape,
+}
+
+impl CategoryRow {
+    fn marker(&self) -> impl MakeWidget {
+        self.shape.glyph().to_string().with(&TextColor, self.color)
+    }
+}
+
+fn category_rows(
+    categories: &Dynamic<Vec<String>>,
+    keybindings: &Dynamic<Keybindings>,
+    tags: &Dynamic<TagStore>,
+    palette: &Dynamic<Palette>,
+) -> Dynamic<Vec<CategoryRow>> {
+    (categories, keybindings, tags, palette).map_each(|(categories, keybindings, tags, palette)| {
+        categories
+            .iter()
+            .enumerate()
+            .map(|(idx, category)| CategoryRow {
+                name: category.clone(),
+                shortcut: keybindings.shortcut_for(category).cloned(),
+                locked: tags.is_category_locked(category),
+                color: palette.color(idx),
+                shape: MarkerShape::for_category(idx),
+            })
+            .collect()
+    })
+}
+
+// finger sized, for the tablet layout
+const LARGE_BUTTON_HEIGHT: Lp = Lp::mm(15);
+
+// one button per category, clicking tags the current moment. `large` spreads them
+// over the whole width at a height that is easy to hit on a touchscreen
+pub fn category_buttons(
+    categories: Dynamic<Vec<String>>,
+    keybindings: Dynamic<Keybindings>,
+    tags: Dynamic<TagStore>,
+    palette: Dynamic<Palette>,
+    large: Dynamic<bool>,
+    tooltips: OverlayLayer,
+    on_category: SharedCallback<String>,
+) -> impl MakeWidget {
+    let rows = category_rows(&categories, &keybindings, &tags, &palette);
+    (&rows, &large)
+        .map_each(|(rows, large)| (rows.clone(), *large))
+        .switcher(move |(categories, large), _| {
+            let large = *large;
+            categories
+                .iter()
+                .map(|row| {
+                    let CategoryRow { name: category, shortcut, locked, .. } = row;
+                    let tip = match shortcut {
+                        Some(shortcut) => format!("tag \"{category}\" ({shortcut})"),
+                        None => format!("tag \"{category}\""),
+                    };
+                    let label = if *locked {
+                        format!("{category} (locked)")
+                    } else {
+                        category.clone()
+                    };
+
+                    let button = row
+                        .marker()
+                        .and(label)
+                        .into_columns()
+                        .into_button()
+                        .with_enabled(!*locked)
+                        .on_click({
+                            let category = category.clone();
+                            let on_category = on_category.clone();
+                            move |_| on_category.invoke(category.clone())
+                        })
+                        .tooltip(&tooltips, tip);
+
+                    if large {
+                        button
+                            .height(LARGE_BUTTON_HEIGHT)
+                            .expand_horizontally()
+                            .make_widget()
+                    } else {
+                        button.make_widget()
+                    }
+                })
+                .collect::<WidgetList>()
+                .into_columns()
+                .make_widget()
+        })
+}
+
+pub fn category_editor(
+    categories: Dynamic<Vec<String>>,
+    keybindings: Dynamic<Keybindings>,
+    tags: Dynamic<TagStore>,
+    palette: Dynamic<Palette>,
+) -> impl MakeWidget {
+    let new_category = Dynamic::new(String::new());
+
+    let add = "add".into_button().on_click({
+        let categories = categories.clone();
+        let new_category = new_category.clone();
+        move |_| {
+            let name = new_category.take();
+            let name = name.trim();
+            if !name.is_empty() && !categories.get().iter().any(|existing| existing == name) {
+                categories.lock().push(name.to_string());
+            }
+        }
+    });
+
+    let palettes = Palette::ALL
+        .into_iter()
+        .map(|option| {
+            let label = palette.map_each(move |palette| {
+                if *palette == option {
+                    format!("[{}]", option.name())
+                } else {
+                    option.name().to_string()
+                }
+            });
+            label.into_button().on_click({
+                let palette = palette.clone();
+                move |_| palette.set(option)
+            })
+        })
+        .collect::<WidgetList>();
+
+    let rows = category_rows(&categories, &keybindings, &tags, &palette)
+        .switcher({
+            let categories = categories.clone();
+            let keybindings = keybindings.clone();
+            move |rows, _| {
+                rows.iter()
+                    .map(|row| {
+                        let CategoryRow { name: category, shortcut, locked, .. } = row;
+                        let recorder = KeyRecorder::new(
+                            shortcut.clone(),
+                            SharedCallback::new({
+                                let category = category.clone();
+                                let keybindings = keybindings.clone();
+                                move |combo| keybindings.lock().bind_category(&category, combo)
+                            }),
+                        );
+
+                        let clear = "clear".into_button().on_click({
+                            let category = category.clone();
+                            let keybindings = keybindings.clone();
+                            move |_| {
+                                keybindings.lock().categories.remove(&category);
+                            }
+                        });
+
+                        let remove = "remove".into_button().on_click({
+                            let category = category.clone();
+                            let categories = categories.clone();
+                            let keybindings = keybindings.clone();
+                            move |_| {
+                                categories.lock().retain(|existing| *existing != category);
+                                keybindings.lock().categories.remove(&category);
+                            }
+                        });
+
+                        let lock = if *locked { "unlock" } else { "lock" };
+                        let lock = lock.into_button().on_click({
+                            let category = category.clone();
+                            let tags = tags.clone();
+                            let locked = *locked;
+                            move |_| tags.lock().set_category_locked(category.clone(), !locked)
+                        });
+
+                        row.marker()
+                            .and(category.clone().expand_horizontally())
+                            .and(recorder)
+                            .and(clear)
+                            .and(lock)
+                            .and(remove.with_enabled(!*locked))
+                            .into_columns()
+                    })
+                    .collect::<WidgetList>()
+                    .into_rows()
+                    .make_widget()
+            }
+        });
+
+    "Tag categories"
+        .and("palette".and(palettes).into_columns())
+        .and(rows.vertical_scroll())
+        .and(new_category.into_input().expand_horizontally().and(add).into_columns())
+        .into_rows()
+}
@@ -0,0 +1,134 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use cushy::{
+    value::{Destination, Dynamic, MapEach, Source, Switchable},
+    widget::{MakeWidget, WidgetList},
+};
+
+use crate::format_timestamp;
+use crate::formats::from_seconds;
+use crate::media::open_input;
+use crate::video_player::VideoPlayer;
+
+// going back this far into a chapter restarts it instead of going to the one before,
+// like a remote's previous button
+const RESTART_WITHIN: Duration = Duration::from_secs(3);
+
+// a chapter of the container, timed like the player's position
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub start: Duration,
+    pub title: String,
+}
+
+// the chapters of `video`, in order. files without any, or that don't open, have none
+pub fn read(video: &Path) -> Vec<Chapter> {
+    let Ok(ictx) = open_input(video) else {
+        return Vec::new();
+    };
+    let mut chapters = ictx
+        .chapters()
+        .enumerate()
+        .filter_map(|(idx, chapter)| {
+            // a broken time base makes the chapter unusable, not the whole list
+            let start = from_seconds(chapter.start() as f64 * f64::from(chapter.time_base()))?;
+            let title = chapter
+                .metadata()
+                .get("title")
+                .filter(|title| !title.trim().is_empty())
+                .map_or_else(|| format!("chapter {}", idx + 1), str::to_string);
+            Some(Chapter { start, title })
+        })
+        .collect::<Vec<_>>();
+    chapters.sort_by_key(|chapter| chapter.start);
+    chapters
+}
+
+// where the next chapter after `position` starts
+pub fn next(chapters: &[Chapter], position: Duration) -> Option<Duration> {
+    chapters
+        .iter()
+        .map(|chapter| chapter.start)
+        .find(|start| *start > position)
+}
+
+// the start of the chapter playing, or of the one before when it only just started
+pub fn previous(chapters: &[Chapter], position: Duration) -> Option<Duration> {
+    let current = chapters.iter().rposition(|chapter| chapter.start <= position)?;
+    let start = chapters[current].start;
+    if position.saturating_sub(start) > RESTART_WITHIN || current == 0 {
+        Some(start)
+    } else {
+        Some(chapters[current - 1].start)
+    }
+}
+
+// reads the chapters of whatever opens
+pub fn follow(video_source: &Dynamic<Option<PathBuf>>, chapters: Dynamic<Vec<Chapter>>) {
+    let current = video_source.clone();
+    video_source
+        .for_each(move |video| {
+            chapters.set(Vec::new());
+            let Some(video) = video.clone() else {
+                return;
+            };
+            let current = current.clone();
+            let chapters = chapters.clone();
+            std::thread::Builder::new()
+                .name("Chapter Thread".into())
+                .spawn(move || {
+                    let read = read(&video);
+                    // another video may have opened in the meantime
+                    if current.get().as_ref() == Some(&video) {
+                        chapters.set(read);
+                    }
+                })
+                .unwrap();
+        })
+        .persist();
+}
+
+// the chapters of the open video, the one playing marked. clicking one seeks to it
+pub fn chapter_list_panel(
+    chapters: Dynamic<Vec<Chapter>>,
+    video_player: VideoPlayer,
+) -> impl MakeWidget {
+    // only changes as playback crosses into another chapter
+    let position = video_player.position();
+    let current = (&chapters, &position).map_each(|(chapters, position)| {
+        chapters.iter().rposition(|chapter| chapter.start <= *position)
+    });
+
+    let rows = chapters.switcher(move |chapters, _| {
+        if chapters.is_empty() {
+            return "no chapters in this video".make_widget();
+        }
+
+        chapters
+            .iter()
+            .enumerate()
+            .map(|(idx, chapter)| {
+                let start = chapter.start;
+                let text = format!("{}  {}", format_timestamp(start), chapter.title);
+                current
+                    .map_each(move |current| {
+                        let marker = if *current == Some(idx) { "> " } else { "  " };
+                        format!("{marker}{text}")
+                    })
+                    .into_button()
+                    .on_click({
+                        let video_player = video_player.clone();
+                        move |_| video_player.seek(start)
+                    })
+                    .make_widget()
+            })
+            .collect::<WidgetList>()
+            .into_rows()
+            .make_widget()
+    });
+
+    "Chapters".and(rows.vertical_scroll().expand()).into_rows()
+}
@@ -0,0 +1,441 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use cushy::context::EventContext;
+use cushy::kludgine::app::winit::event::DeviceId;
+use cushy::kludgine::app::winit::keyboard::{Key, NamedKey};
+use cushy::value::{Destination, Dynamic, MapEach, Source};
+use cushy::widget::{EventHandling, MakeWidget, WidgetRef, WrapperWidget, HANDLED, IGNORED};
+use cushy::window::KeyEvent;
+
+use crate::active_learning::{rank_uncertain, Suggestion};
+use crate::format_timestamp;
+use crate::media::extract_frames;
+use crate::renditions::{probe_duration, Renditions};
+use crate::tag_tools::csv_field;
+use crate::tags::{FrameLabels, TagStore};
+use crate::video_player::{ControlCommand, VideoPlayer};
+
+// whole-frame labels for an image classification dataset. while classifying, the
+// video is sampled every so many seconds, 1-9 toggle the categories on the frame on
+// screen and enter moves on to the next sample, shift+enter back. with a model picked,
+// enter goes through the frames it is least sure about first
+#[derive(Debug, Clone)]
+pub struct Classifier {
+    pub active: Dynamic<bool>,
+    // seconds between samples, as typed
+    interval: Dynamic<String>,
+    // an onnx image classifier and how many frames to pick with it
+    model: Dynamic<Option<PathBuf>>,
+    suggestion_count: Dynamic<String>,
+    // on the original's timeline, most uncertain first, and which one is on screen
+    suggestions: Dynamic<Vec<Suggestion>>,
+    suggestion: Dynamic<Option<usize>>,
+    tags: Dynamic<TagStore>,
+    video_source: Dynamic<Option<PathBuf>>,
+    renditions: Dynamic<Renditions>,
+    categories: Dynamic<Vec<String>>,
+    player: VideoPlayer,
+    status: Dynamic<String>,
+}
+
+impl Classifier {
+    pub fn new(
+        tags: Dynamic<TagStore>,
+        video_source: Dynamic<Option<PathBuf>>,
+        renditions: Dynamic<Renditions>,
+        categories: Dynamic<Vec<String>>,
+        player: VideoPlayer,
+    ) -> Self {
+        Self {
+            active: Dynamic::new(false),
+            interval: Dynamic::new(String::from("1")),
+            model: Dynamic::new(None),
+            suggestion_count: Dynamic::new(String::from("20")),
+            suggestions: Dynamic::new(Vec::new()),
+            suggestion: Dynamic::new(None),
+            tags,
+            video_source,
+            renditions,
+            categories,
+            player,
+            status: Dynamic::new(String::new()),
+        }
+    }
+
+    // the frame on screen, on the original's timeline like the labels
+    fn current_frame(&self) -> Option<(PathBuf, Duration)> {
+        let video = self.video_source.get()?;
+        let position = self.player.position().get();
+        Some(self.renditions.map_ref(|renditions| {
+            (
+                renditions.original_of(&video),
+                renditions.to_original(&video, position),
+            )
+        }))
+    }
+
+    // the category at `idx` on or off for the frame on screen, false if there's none
+    fn toggle(&self, idx: usize) -> bool {
+        let Some(category) = self.categories.map_ref(|categories| categories.get(idx).cloned())
+        else {
+            return false;
+        };
+        let Some((video, time)) = self.current_frame() else {
+            self.status.set(String::from("open a video before classifying"));
+            return true;
+        };
+
+        let at = format_timestamp(time);
+        self.status
+            .set(match self.tags.lock().toggle_frame_label(video, time, category.clone()) {
+                Some(true) => format!("{category} on {at}"),
+                Some(false) => format!("{category} off {at}"),
+                None => format!("locked, not labeled: {category} on {at}"),
+            });
+        true
+    }
+
+    fn interval(&self) -> Option<f64> {
+        match self.interval.get().trim().parse::<f64>() {
+            Ok(every) if every > 0. => Some(every),
+            _ => {
+                self.status.set(String::from("invalid interval, use seconds e.g. 0.5"));
+                None
+            }
+        }
+    }
+
+    // pauses on the next sample after the playhead, or the one before it. suggested
+    // frames come first, in their order
+    fn step(&self, forward: bool) {
+        if self.step_suggestion(forward) {
+            return;
+        }
+        let Some(every) = self.interval() else {
+            return;
+        };
+
+        // a playhead between samples goes to the nearest one that way, the small
+        // margin keeps one sitting on a sample from landing on it again
+        let index = self.player.position().get().as_secs_f64() / every;
+        let index = if forward {
+            (index + 1e-6).floor() + 1.
+        } else {
+            (index - 1e-6).ceil() - 1.
+        };
+        if index < 0. {
+            return;
+        }
+        let target = Duration::from_secs_f64(index * every);
+        let duration = self.player.duration().get();
+        if !duration.is_zero() && target > duration {
+            self.status.set(String::from("no samples left"));
+            return;
+        }
+
+        self.player.send_control_message(ControlCommand::Pause);
+        self.player.seek(target);
+    }
+
+    // false once there are no suggestions left that way
+    fn step_suggestion(&self, forward: bool) -> bool {
+        let suggestions = self.suggestions.get();
+        let current = self.suggestion.get();
+        let next = match (forward, current) {
+            (true, None) => 0,
+            (true, Some(idx)) => idx + 1,
+            (false, Some(idx)) if idx > 0 => idx - 1,
+            (false, _) => return false,
+        };
+        let Some(suggestion) = suggestions.get(next) else {
+            if !suggestions.is_empty() {
+                self.suggestions.set(Vec::new());
+                self.suggestion.set(None);
+                self.status
+                    .set(String::from("all suggested frames visited, back to sampling"));
+            }
+            return false;
+        };
+        let Some(video) = self.video_source.get() else {
+            return false;
+        };
+
+        let time = self
+            .renditions
+            .map_ref(|renditions| renditions.from_original(&video, suggestion.time));
+        self.suggestion.set(Some(next));
+        self.status.set(format!(
+            "suggestion {} of {}, uncertainty {:.2}",
+            next + 1,
+            suggestions.len(),
+            suggestion.uncertainty
+        ));
+        self.player.send_control_message(ControlCommand::Pause);
+        self.player.seek(time);
+        true
+    }
+
+    fn choose_model(&self) {
+        let model = self.model.clone();
+        std::thread::Builder::new()
+            .name("File Picker Thread".into())
+            .spawn(move || {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("onnx model", &["onnx"])
+                    .pick_file()
+                {
+                    model.set(Some(path));
+                }
+            })
+            .unwrap();
+    }
+
+    // scores every unlabeled sample of the open video with the model and queues the
+    // least certain ones up for enter
+    fn suggest_frames(&self) {
+        let Some(model) = self.model.get() else {
+            self.status.set(String::from("choose a model first"));
+            return;
+        };
+        let Some((video, _)) = self.current_frame() else {
+            self.status.set(String::from("no video open"));
+            return;
+        };
+        let Some(every) = self.interval() else {
+            return;
+        };
+        let Ok(count) = self.suggestion_count.get().trim().parse::<usize>() else {
+            self.status.set(String::from("invalid number of frames"));
+            return;
+        };
+
+        let this = self.clone();
+        std::thread::Builder::new()
+            .name("Active Learning Thread".into())
+            .spawn(move || {
+                this.status.set(String::from("reading the video"));
+                let duration = probe_duration(&video).unwrap_or_default();
+                let times = this.tags.map_ref(|tags| {
+                    (0..)
+                        .map(|idx| Duration::from_secs_f64(idx as f64 * every))
+                        .take_while(|time| *time < duration)
+                        .filter(|time| tags.labels_at(&video, *time).is_empty())
+                        .collect::<Vec<_>>()
+                });
+
+                let ranked = rank_uncertain(&model, &video, &times, count, |scored| {
+                    this.status
+                        .set(format!("scored {scored} of {} frames", times.len()));
+                });
+                match ranked {
+                    Ok(suggestions) => {
+                        this.status.set(format!(
+                            "{} frames to label, enter goes to the least certain",
+                            suggestions.len()
+                        ));
+                        this.suggestions.set(suggestions);
+                        this.suggestion.set(None);
+                    }
+                    Err(err) => this.status.set(err),
+                }
+            })
+            .unwrap();
+    }
+
+    // the labels of the frame on screen, for the panel
+    fn current_labels(&self) -> Dynamic<String> {
+        let position = self.player.position();
+        (&self.tags, &self.video_source, &self.renditions, &position).map_each(
+            |(tags, video, renditions, position)| {
+                let Some(video) = video else {
+                    return String::new();
+                };
+                let time = renditions.to_original(video, *position);
+                let labels = tags.labels_at(&renditions.original_of(video), time);
+                if labels.is_empty() {
+                    format!("{}: unlabeled", format_timestamp(time))
+                } else {
+                    format!("{}: {}", format_timestamp(time), labels.join(", "))
+                }
+            },
+        )
+    }
+
+    // a png per labeled frame of the open video and a labels.csv listing them,
+    // several labels of one frame separated by semicolons
+    fn export_dataset(&self) {
+        let Some((video, _)) = self.current_frame() else {
+            self.status.set(String::from("no video open"));
+            return;
+        };
+        let this = self.clone();
+        std::thread::Builder::new()
+            .name("File Picker Thread".into())
+            .spawn(move || {
+                let Some(folder) = rfd::FileDialog::new().pick_folder() else {
+                    return;
+                };
+                let frames = this.tags.map_ref(|tags| {
+                    tags.frame_labels()
+                        .iter()
+                        .filter(|frame| frame.video == video)
+                        .cloned()
+                        .collect::<Vec<_>>()
+                });
+                this.status.set(match write_dataset(&video, &frames, &folder, &this.status) {
+                    Ok(()) => format!("{} frames exported to {}", frames.len(), folder.display()),
+                    Err(err) => err,
+                });
+            })
+            .unwrap();
+    }
+
+    pub fn panel(&self) -> impl MakeWidget {
+        let export = "export dataset".into_button().on_click({
+            let this = self.clone();
+            move |_| this.export_dataset()
+        });
+        let previous = "previous sample".into_button().on_click({
+            let this = self.clone();
+            move |_| this.step(false)
+        });
+        let next = "next sample".into_button().on_click({
+            let this = self.clone();
+            move |_| this.step(true)
+        });
+
+        let choose_model = self
+            .model
+            .map_each(|model| match model {
+                Some(model) => format!(
+                    "model: {}",
+                    model.file_name().unwrap_or_default().to_string_lossy()
+                ),
+                None => String::from("choose model"),
+            })
+            .into_button()
+            .on_click({
+                let this = self.clone();
+                move |_| this.choose_model()
+            });
+        let suggest = "pick uncertain frames".into_button().on_click({
+            let this = self.clone();
+            move |_| this.suggest_frames()
+        });
+
+        "classify every seconds"
+            .and(self.interval.clone().into_input())
+            .and(previous)
+            .and(next)
+            .and(self.current_labels())
+            .and(export)
+            .and(self.status.clone())
+            .into_columns()
+            .and(
+                choose_model
+                    .and("frames to pick")
+                    .and(self.suggestion_count.clone().into_input())
+                    .and(suggest)
+                    .into_columns(),
+            )
+            .and("1-9 toggle the categories in order, enter moves to the next sample, shift+enter back")
+            .into_rows()
+    }
+}
+
+fn write_dataset(
+    video: &Path,
+    frames: &[FrameLabels],
+    folder: &Path,
+    status: &Dynamic<String>,
+) -> Result<(), String> {
+    let stem = video
+        .file_stem()
+        .map_or_else(|| String::from("video"), |stem| stem.to_string_lossy().into_owned());
+    let times = frames.iter().map(|frame| frame.time).collect::<Vec<_>>();
+
+    let mut csv = String::from("file,labels\n");
+    let mut failed = None;
+    let mut saved = 0;
+    extract_frames(video, &times, |time, image| {
+        if failed.is_some() {
+            return;
+        }
+        status.set(format!("extracting frame {} of {}", saved + 1, times.len()));
+        let name = format!("{stem}-{}.png", format_timestamp(time).replace([':', '.'], "-"));
+        if let Err(err) = image.save(folder.join(&name)) {
+            failed = Some(format!("failed to save {name}: {err}"));
+            return;
+        }
+        if let Some(frame) = frames.iter().find(|frame| frame.time == time) {
+            csv.push_str(&format!("{},{}\n", csv_field(&name), csv_field(&frame.labels.join(";"))));
+        }
+        saved += 1;
+    })
+    .map_err(|err| format!("failed to extract frames: {err}"))?;
+    if let Some(err) = failed {
+        return Err(err);
+    }
+
+    std::fs::write(folder.join("labels.csv"), csv)
+        .map_err(|err| format!("failed to write labels.csv: {err}"))
+}
+
+// the classification keys, only while the classify panel is open
+#[derive(Debug)]
+pub struct FrameClassifier {
+    child: WidgetRef,
+    classifier: Classifier,
+}
+
+impl FrameClassifier {
+    pub fn new(child: impl MakeWidget, classifier: Classifier) -> Self {
+        Self {
+            child: WidgetRef::new(child),
+            classifier,
+        }
+    }
+}
+
+impl WrapperWidget for FrameClassifier {
+    fn child_mut(&mut self) -> &mut WidgetRef {
+        &mut self.child
+    }
+
+    fn keyboard_input(
+        &mut self,
+        device_id: DeviceId,
+        input: KeyEvent,
+        is_synthetic: bool,
+        context: &mut EventContext<'_>,
+    ) -> EventHandling {
+        let modifiers = context.modifiers().state();
+        if !self.classifier.active.get()
+            || !input.state.is_pressed()
+            || modifiers.control_key()
+            || modifiers.alt_key()
+        {
+            return IGNORED;
+        }
+
+        match &input.logical_key {
+            Key::Named(NamedKey::Enter) => {
+                self.classifier.step(!modifiers.shift_key());
+                HANDLED
+            }
+            Key::Character(text) if !input.repeat => {
+                let Some(digit) = text.chars().next().and_then(|c| c.to_digit(10)) else {
+                    return IGNORED;
+                };
+                if digit == 0 || !self.classifier.toggle(digit as usize - 1) {
+                    return IGNORED;
+                }
+                HANDLED
+            }
+            _ => IGNORED,
+        }
+    }
+}
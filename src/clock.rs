@@ -0,0 +1,123 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// where playback is on the stream's timeline, shared by the reader, the video
+// decoder and the audio device. wall time moves it along at the playback rate,
+// the audio device re-anchors it to what comes out of the speakers while it plays
+#[derive(Debug, Clone)]
+pub struct PlaybackClock(Arc<Mutex<ClockState>>);
+
+#[derive(Debug)]
+struct ClockState {
+    // stream seconds at an instant. none after opening or seeking, until the
+    // first frame there is presented
+    anchor: Option<(f64, Instant)>,
+    paused: bool,
+    rate: f64,
+}
+
+impl ClockState {
+    fn seconds(&self) -> Option<f64> {
+        let (seconds, at) = self.anchor?;
+        if self.paused {
+            return Some(seconds);
+        }
+        Some(seconds + at.elapsed().as_secs_f64() * self.rate)
+    }
+
+    // keeps the current time, so what changes next only applies from now on
+    fn reanchor(&mut self) {
+        self.anchor = self.seconds().map(|seconds| (seconds, Instant::now()));
+    }
+}
+
+impl Default for PlaybackClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlaybackClock {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(ClockState {
+            anchor: None,
+            paused: false,
+            rate: 1.,
+        })))
+    }
+
+    pub fn seconds(&self) -> Option<f64> {
+        self.0.lock().unwrap().seconds()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.lock().unwrap().paused
+    }
+
+    pub fn is_anchored(&self) -> bool {
+        self.0.lock().unwrap().anchor.is_some()
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.0.lock().unwrap().rate
+    }
+
+    pub fn pause(&self) {
+        let mut state = self.0.lock().unwrap();
+        if !state.paused {
+            state.reanchor();
+            state.paused = true;
+        }
+    }
+
+    // picks up where it was paused, time spent paused doesn't count
+    pub fn resume(&self) {
+        let mut state = self.0.lock().unwrap();
+        if state.paused {
+            state.reanchor();
+            state.paused = false;
+        }
+    }
+
+    pub fn set_rate(&self, rate: f64) {
+        if rate <= 0. || !rate.is_finite() {
+            return;
+        }
+        let mut state = self.0.lock().unwrap();
+        state.reanchor();
+        state.rate = rate;
+    }
+
+    // after a seek nothing is due until the first frame at the target anchors it again
+    pub fn unanchor(&self) {
+        self.0.lock().unwrap().anchor = None;
+    }
+
+    // the moment at `seconds` is playing right now
+    pub fn anchor(&self, seconds: f64) {
+        self.0.lock().unwrap().anchor = Some((seconds, Instant::now()));
+    }
+
+    // anchors at `seconds` unless something already did, true if this call did
+    pub fn anchor_if_unset(&self, seconds: f64) -> bool {
+        let mut state = self.0.lock().unwrap();
+        if state.anchor.is_some() {
+            return false;
+        }
+        state.anchor = Some((seconds, Instant::now()));
+        true
+    }
+
+    // wall time until the moment at `seconds` is due, zero once it is. none while
+    // paused or unanchored, when nothing is ever due on its own
+    pub fn until(&self, seconds: f64) -> Option<Duration> {
+        let state = self.0.lock().unwrap();
+        if state.paused {
+            return None;
+        }
+        let ahead = (seconds - state.seconds()?) / state.rate;
+        Some(Duration::from_secs_f64(ahead.max(0.)))
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    num::NonZeroU32,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use cushy::{kludgine::image::RgbaImage, value::Generation};
+use softbuffer::{Context, Surface};
+use winit::{
+    application::ApplicationHandler,
+    event::{ElementState, KeyEvent, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::{Key, NamedKey},
+    window::{Window, WindowId},
+};
+
+use crate::format_timestamp;
+use crate::remote::{Remote, RemoteCommand, Status};
+
+// how often to look for a new frame, about as often as a display refreshes
+const POLL_INTERVAL: Duration = Duration::from_millis(16);
+const ARROW_SEEK_SECONDS: f64 = 5.;
+
+// the player on its own in a window filled by the cpu, for machines where wgpu can't make
+// a window at all, not even on a software rasterizer. there's no ui toolkit drawing here,
+// so it's driven by keys and says what's playing in the title. blocks until it's closed
+pub fn run(remote: Remote) -> Result<(), String> {
+    let event_loop = EventLoop::new().map_err(|err| err.to_string())?;
+    let mut window = CpuWindow {
+        remote,
+        surface: None,
+        shown: None,
+        title: String::new(),
+        error: None,
+    };
+    event_loop.run_app(&mut window).map_err(|err| err.to_string())?;
+
+    match window.error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+struct CpuWindow {
+    remote: Remote,
+    surface: Option<Surface<Rc<Window>, Rc<Window>>>,
+    // the frame in the window, converting it again when nothing changed would be wasted
+    shown: Option<Generation>,
+    title: String,
+    // why the window couldn't be made
+    error: Option<String>,
+}
+
+impl CpuWindow {
+    fn redraw(&mut self) -> Result<(), String> {
+        let Some(surface) = &mut self.surface else {
+            return Ok(());
+        };
+        let size = surface.window().inner_size();
+        // minimized
+        let (Some(width), Some(height)) =
+            (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+        else {
+            return Ok(());
+        };
+        surface.resize(width, height).map_err(|err| err.to_string())?;
+
+        // read before the frame, a newer one coming in between gets drawn next time
+        let generation = self.remote.frame_generation();
+        let frame = self.remote.displayed_frame();
+        let mut buffer = surface.buffer_mut().map_err(|err| err.to_string())?;
+        buffer.fill(0);
+        if let Some(frame) = &frame {
+            letterbox(frame, &mut buffer, size.width, size.height);
+        }
+        buffer.present().map_err(|err| err.to_string())?;
+        self.shown = frame.is_some().then_some(generation);
+        Ok(())
+    }
+
+    fn press(&self, event_loop: &ActiveEventLoop, key: &Key) {
+        let command = match key {
+            Key::Named(NamedKey::Escape) => return event_loop.exit(),
+            Key::Named(NamedKey::Space) => RemoteCommand::TogglePlay,
+            Key::Named(NamedKey::ArrowLeft) => RemoteCommand::SeekRelative(-ARROW_SEEK_SECONDS),
+            Key::Named(NamedKey::ArrowRight) => RemoteCommand::SeekRelative(ARROW_SEEK_SECONDS),
+            Key::Character(text) => match text.as_str() {
+                "," => RemoteCommand::StepBackward,
+                "." => RemoteCommand::StepForward,
+                "o" => match rfd::FileDialog::new().pick_file() {
+                    Some(path) => RemoteCommand::Open(path),
+                    None => return,
+                },
+                digit => match digit.parse::<usize>() {
+                    Ok(number @ 1..=9) => RemoteCommand::TagCategory(number - 1),
+                    _ => return,
+                },
+            },
+            _ => return,
+        };
+        if let Err(err) = self.remote.execute(command) {
+            tracing::warn!("{err}");
+        }
+    }
+}
+
+impl ApplicationHandler for CpuWindow {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.surface.is_some() {
+            return;
+        }
+        let attributes = Window::default_attributes()
+            .with_title("Video Tagger")
+            .with_maximized(true);
+        let surface = event_loop
+            .create_window(attributes)
+            .map_err(|err| err.to_string())
+            .and_then(|window| {
+                let window = Rc::new(window);
+                let context = Context::new(window.clone()).map_err(|err| err.to_string())?;
+                Surface::new(&context, window).map_err(|err| err.to_string())
+            });
+        match surface {
+            Ok(surface) => self.surface = Some(surface),
+            Err(err) => {
+                self.error = Some(err);
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(_) => self.shown = None,
+            WindowEvent::RedrawRequested => {
+                if let Err(err) = self.redraw() {
+                    tracing::error!("failed to draw the frame: {err}");
+                }
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key,
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => self.press(event_loop, &logical_key),
+            _ => {}
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(surface) = &self.surface else {
+            return;
+        };
+        let title = title(&self.remote.status(), &self.remote.categories());
+        if title != self.title {
+            surface.window().set_title(&title);
+            self.title = title;
+        }
+        if self.shown != Some(self.remote.frame_generation()) {
+            surface.window().request_redraw();
+        }
+        event_loop.set_control_flow(ControlFlow::WaitUntil(Instant::now() + POLL_INTERVAL));
+    }
+}
+
+// what a title bar can say in place of the ui: the video, where it is, and which
+// number key tags which category
+fn title(status: &Status, categories: &[String]) -> String {
+    let Some(name) = status.video.as_ref().and_then(|video| video.file_name()) else {
+        return String::from("Video Tagger - press o to open a video");
+    };
+    let state = if status.playing { "playing" } else { "paused" };
+    let keys = categories
+        .iter()
+        .take(9)
+        .enumerate()
+        .map(|(idx, category)| format!("{} {category}", idx + 1))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let position = Duration::try_from_secs_f64(status.position).unwrap_or_default();
+    format!(
+        "{} - {} {state} - {keys}",
+        name.to_string_lossy(),
+        format_timestamp(position)
+    )
+}
+
+// scaled to fit with black bars around it, nearest neighbour as it's the cheapest and
+// the frames are already small
+fn letterbox(frame: &RgbaImage, buffer: &mut [u32], width: u32, height: u32) {
+    if frame.width() == 0 || frame.height() == 0 {
+        return;
+    }
+    let scale = (width as f32 / frame.width() as f32).min(height as f32 / frame.height() as f32);
+    let scaled_width = ((frame.width() as f32 * scale) as u32).min(width);
+    let scaled_height = ((frame.height() as f32 * scale) as u32).min(height);
+    let (left, top) = ((width - scaled_width) / 2, (height - scaled_height) / 2);
+
+    for y in 0..scaled_height {
+        let source_y = ((y as f32 / scale) as u32).min(frame.height() - 1);
+        let start = ((top + y) * width + left) as usize;
+        for (x, pixel) in buffer[start..start + scaled_width as usize].iter_mut().enumerate() {
+            let source_x = ((x as f32 / scale) as u32).min(frame.width() - 1);
+            let [r, g, b, _] = frame.get_pixel(source_x, source_y).0;
+            // softbuffer wants 0RGB
+            *pixel = u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cushy::kludgine::image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn frames_are_letterboxed() {
+        let frame = RgbaImage::from_pixel(2, 1, Rgba([255, 128, 0, 255]));
+        let mut buffer = vec![0; 4 * 4];
+        letterbox(&frame, &mut buffer, 4, 4);
+
+        // twice as wide as it's tall, so it fills the middle two rows
+        let orange = 0xff8000;
+        assert_eq!(buffer, [[0; 4], [orange; 4], [orange; 4], [0; 4]].concat());
+    }
+}
@@ -0,0 +1,547 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+use cushy::value::{Dynamic, Source};
+use serde_json::Value;
+
+use crate::settings::config_dir;
+use crate::tags::{StoredPart, StoredRow, Tag, TagStore};
+
+const DATABASE_FILE: &str = "tags.json";
+const VERSION_KEY: &str = "schema_version";
+
+// bump together with a new entry in `MIGRATIONS`
+pub const SCHEMA_VERSION: u64 = 1;
+
+// upgrades a document from `to - 1` to `to`, in place
+struct Migration {
+    to: u64,
+    description: &'static str,
+    apply: fn(&mut Value) -> Result<(), String>,
+}
+
+// in order, each one builds on the previous. a released migration is never edited,
+// schema changes always go into a new one
+const MIGRATIONS: &[Migration] = &[Migration {
+    to: 1,
+    description: "number tags written without an id",
+    apply: number_tags,
+}];
+
+// documents without a version are hand-written or from scripts, which leave out the ids
+// the app gives every tag
+fn number_tags(document: &mut Value) -> Result<(), String> {
+    let fields = document.as_object_mut().ok_or("not a json object")?;
+    let tags = fields
+        .entry("tags")
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or("tags is not a list")?;
+
+    let mut next_id = tags
+        .iter()
+        .filter_map(|tag| tag.get("id")?.as_u64())
+        .max()
+        .map_or(0, |id| id + 1);
+    for tag in tags.iter_mut() {
+        let tag = tag.as_object_mut().ok_or("a tag is not a json object")?;
+        if !tag.contains_key("id") {
+            tag.insert("id".into(), next_id.into());
+            next_id += 1;
+        }
+    }
+
+    let stored = fields.get("next_id").and_then(Value::as_u64).unwrap_or(0);
+    fields.insert("next_id".into(), stored.max(next_id).into());
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum DatabaseError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    // written by a newer version of the app, opening it could lose what it added
+    TooNew { found: u64, known: u64 },
+    Migration { to: u64, message: String },
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Json(err) => write!(f, "{err}"),
+            Self::TooNew { found, known } => write!(
+                f,
+                "the tag database is at schema version {found}, this version of the app only knows up to {known}"
+            ),
+            Self::Migration { to, message } => {
+                write!(f, "migrating the tag database to version {to} failed: {message}")
+            }
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for DatabaseError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for DatabaseError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for DatabaseError {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::Sqlite(err)
+    }
+}
+
+// which tags a query wants, unset fields match every tag
+#[derive(Debug, Clone, Default)]
+pub struct TagQuery {
+    pub video: Option<PathBuf>,
+    pub label: Option<String>,
+    // tags overlapping from..to
+    pub from: Option<Duration>,
+    pub to: Option<Duration>,
+    pub limit: Option<usize>,
+}
+
+impl TagQuery {
+    pub fn matches(&self, tag: &Tag) -> bool {
+        self.video.as_ref().is_none_or(|video| tag.video == *video)
+            && self.label.as_ref().is_none_or(|label| tag.label == *label)
+            && self.from.is_none_or(|from| tag.end.unwrap_or(tag.time) >= from)
+            && self.to.is_none_or(|to| tag.time <= to)
+    }
+
+    // the matching ones of `tags`, in the order storage answers in
+    pub fn run(&self, tags: &[Tag]) -> Vec<Tag> {
+        let mut found = tags.iter().filter(|tag| self.matches(tag)).cloned().collect::<Vec<_>>();
+        found.sort_by(|a, b| a.video.cmp(&b.video).then(a.time.cmp(&b.time)));
+        found.truncate(self.limit.unwrap_or(usize::MAX));
+        found
+    }
+}
+
+// where tags live between sessions. queries go to the storage directly, so neither the
+// app nor other tools have to hold every tag to find some
+pub trait TagStorage: fmt::Debug + Send + Sync {
+    // storage that `keeps_rows` leaves the tags out, they're queried a video at a time
+    fn load(&self) -> Result<TagStore, DatabaseError>;
+
+    // replaces everything stored with `store`
+    fn save(&self, store: &TagStore) -> Result<(), DatabaseError>;
+
+    // storage that keeps a row per tag loads them a video at a time and is only handed
+    // the rows that changed, the rest get the whole store on every change
+    fn keeps_rows(&self) -> bool {
+        false
+    }
+
+    // only called when `keeps_rows`
+    fn write_rows(&self, rows: &[StoredRow]) -> Result<(), DatabaseError> {
+        let _ = rows;
+        unreachable!("{self:?} is saved whole")
+    }
+
+    // in order of video, then time. storage that can filter without reading every
+    // tag should
+    fn query(&self, query: &TagQuery) -> Result<Vec<Tag>, DatabaseError> {
+        Ok(query.run(self.load()?.tags()))
+    }
+
+    // every video with tags, in order
+    fn videos(&self) -> Result<Vec<PathBuf>, DatabaseError> {
+        Ok(tagged_videos(self.load()?.tags()))
+    }
+}
+
+fn tagged_videos(tags: &[Tag]) -> Vec<PathBuf> {
+    let videos = tags.iter().map(|tag| tag.video.clone()).collect::<BTreeSet<_>>();
+    videos.into_iter().collect()
+}
+
+// a versioned json document, written whole on every change
+#[derive(Debug)]
+struct JsonStorage {
+    path: PathBuf,
+}
+
+impl TagStorage for JsonStorage {
+    fn load(&self) -> Result<TagStore, DatabaseError> {
+        if !self.path.exists() {
+            return Ok(TagStore::new());
+        }
+        load(&self.path)
+    }
+
+    fn save(&self, store: &TagStore) -> Result<(), DatabaseError> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        write_document(store, &self.path)
+    }
+}
+
+// the storage in the config dir: the json document, or with the `sqlite` feature a
+// database that tags from the json one are moved into the first time
+#[derive(Debug, Clone)]
+pub struct TagDatabase {
+    storage: Option<Arc<dyn TagStorage>>,
+    // set when the storage couldn't be opened, so it is never overwritten with an empty store
+    read_only: bool,
+    writer: Arc<Writer>,
+}
+
+// saves are written on a thread of their own, a big library takes a while and the ui
+// shouldn't wait for it. ones arriving while a write is going on collapse into the newest
+#[derive(Debug, Default)]
+struct Writer {
+    pending: Mutex<Pending>,
+    changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Pending {
+    // the last change handed over, see `TagStore::changed_since`
+    seen: u64,
+    // of the store the last change came from, see `TagStore::read_from_document`
+    document: Option<u64>,
+    store: Option<TagStore>,
+    rows: BTreeMap<StoredPart, StoredRow>,
+    writing: bool,
+}
+
+impl Pending {
+    fn is_empty(&self) -> bool {
+        self.store.is_none() && self.rows.is_empty()
+    }
+}
+
+impl TagDatabase {
+    pub fn open() -> (Self, Result<TagStore, DatabaseError>) {
+        let (storage, store) = match config_dir().map(|dir| open_storage(&dir)) {
+            Some(Ok(storage)) => {
+                let store = storage.load();
+                (Some(storage), store)
+            }
+            Some(Err(err)) => (None, Err(err)),
+            None => (None, Ok(TagStore::new())),
+        };
+
+        let writer = Arc::new(Writer::default());
+        if let Some(storage) = storage.clone().filter(|_| store.is_ok()) {
+            let writer = writer.clone();
+            std::thread::Builder::new()
+                .name("Tag Database Thread".into())
+                .spawn(move || write_saves(&*storage, &writer))
+                .unwrap();
+        }
+
+        let database = Self {
+            storage,
+            read_only: store.is_err(),
+            writer,
+        };
+        (database, store)
+    }
+
+    // hands what changed since the last save to the writer thread, errors writing it
+    // are logged there
+    pub fn save(&self, store: &TagStore) {
+        let Some(storage) = self.storage.as_ref().filter(|_| !self.read_only) else {
+            return;
+        };
+        let mut pending = self.writer.pending.lock().unwrap();
+        let swapped = store.read_from_document() != pending.document;
+        let changed = store.changed_since(pending.seen);
+        if !swapped && changed.is_empty() {
+            return;
+        }
+        pending.document = store.read_from_document();
+        if let Some((last, _)) = changed.last() {
+            pending.seen = *last;
+        }
+
+        if swapped || !storage.keeps_rows() {
+            pending.rows.clear();
+            pending.store = Some(store.clone());
+        } else {
+            for (_, part) in changed {
+                pending.rows.insert(part.clone(), store.row(part));
+            }
+        }
+        drop(pending);
+        self.writer.changed.notify_all();
+    }
+
+    // waits for the saves handed over so far to be written
+    pub fn flush(&self) {
+        let pending = self.writer.pending.lock().unwrap();
+        let _written = self
+            .writer
+            .changed
+            .wait_while(pending, |pending| !pending.is_empty() || pending.writing)
+            .unwrap();
+    }
+
+    // what was saved last, which is every change made so far
+    pub fn query(&self, query: &TagQuery) -> Result<Vec<Tag>, DatabaseError> {
+        self.flush();
+        match &self.storage {
+            Some(storage) => storage.query(query),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // the tags matching `query` across the library: from `store` when it has every
+    // video's, from the storage when it only has the ones opened so far
+    pub fn find(
+        &self,
+        store: &Dynamic<TagStore>,
+        query: &TagQuery,
+    ) -> Result<Vec<Tag>, DatabaseError> {
+        let found = store.map_ref(|store| store.is_complete().then(|| query.run(store.tags())));
+        match found {
+            Some(tags) => Ok(tags),
+            None => self.query(query),
+        }
+    }
+
+    // every video with tags, the same way
+    pub fn videos(&self, store: &Dynamic<TagStore>) -> Result<Vec<PathBuf>, DatabaseError> {
+        let found = store.map_ref(|store| store.is_complete().then(|| tagged_videos(store.tags())));
+        match (found, &self.storage) {
+            (Some(videos), _) => Ok(videos),
+            (None, Some(storage)) => {
+                self.flush();
+                storage.videos()
+            }
+            (None, None) => Ok(Vec::new()),
+        }
+    }
+
+    // adds the tags of `video` to the store, for storage that loads them as videos are
+    // opened. everything after works on them in memory
+    pub fn load_video(&self, store: &Dynamic<TagStore>, video: &Path) {
+        if store.map_ref(|store| store.is_loaded(video)) {
+            return;
+        }
+        let query = TagQuery {
+            video: Some(video.to_path_buf()),
+            ..TagQuery::default()
+        };
+        // locked first, so nothing changes between the query and adding what it found
+        let mut store = store.lock();
+        match self.query(&query) {
+            Ok(tags) => store.load_video(video.to_path_buf(), tags),
+            Err(err) => tracing::error!("failed to load the tags of {}: {err}", video.display()),
+        }
+    }
+
+    // the store with every video's tags, for backups and projects
+    pub fn everything(&self, store: &TagStore) -> Result<TagStore, DatabaseError> {
+        let mut store = store.clone();
+        if !store.is_complete() {
+            store.load_everything(self.query(&TagQuery::default())?);
+        }
+        Ok(store)
+    }
+}
+
+fn write_saves(storage: &dyn TagStorage, writer: &Writer) {
+    loop {
+        let (store, rows) = {
+            let pending = writer.pending.lock().unwrap();
+            let mut pending = writer
+                .changed
+                .wait_while(pending, |pending| pending.is_empty())
+                .unwrap();
+            pending.writing = true;
+            (pending.store.take(), std::mem::take(&mut pending.rows))
+        };
+
+        if let Some(Err(err)) = store.map(|store| storage.save(&store)) {
+            tracing::error!("failed to save tags: {err}");
+        }
+        if !rows.is_empty() {
+            let rows = rows.into_values().collect::<Vec<_>>();
+            if let Err(err) = storage.write_rows(&rows) {
+                tracing::error!("failed to save tags: {err}");
+            }
+        }
+        writer.pending.lock().unwrap().writing = false;
+        writer.changed.notify_all();
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn open_storage(dir: &Path) -> Result<Arc<dyn TagStorage>, DatabaseError> {
+    Ok(Arc::new(JsonStorage {
+        path: dir.join(DATABASE_FILE),
+    }))
+}
+
+#[cfg(feature = "sqlite")]
+fn open_storage(dir: &Path) -> Result<Arc<dyn TagStorage>, DatabaseError> {
+    use crate::sqlite::{SqliteStorage, SQLITE_FILE};
+
+    let path = dir.join(SQLITE_FILE);
+    let fresh = !path.exists();
+    std::fs::create_dir_all(dir)?;
+    let storage = SqliteStorage::open(&path)?;
+
+    let json = JsonStorage {
+        path: dir.join(DATABASE_FILE),
+    };
+    if fresh && json.path.exists() {
+        tracing::info!(
+            "moving the tags in {} into {}",
+            json.path.display(),
+            path.display()
+        );
+        // a half imported database would hide the rest of the tags from the next start
+        if let Err(err) = json.load().and_then(|store| storage.save(&store)) {
+            drop(storage);
+            let _ = std::fs::remove_file(&path);
+            return Err(err);
+        }
+    }
+    Ok(Arc::new(storage))
+}
+
+// the tags as the database stores them, with the schema version they're at
+pub fn to_document(store: &TagStore) -> Result<Value, DatabaseError> {
+    let mut document = serde_json::to_value(store)?;
+    if let Value::Object(fields) = &mut document {
+        fields.insert(VERSION_KEY.into(), SCHEMA_VERSION.into());
+    }
+    Ok(document)
+}
+
+// the same format the database uses, for backups and anything else that stores a copy
+pub fn write_document(store: &TagStore, path: &Path) -> Result<(), DatabaseError> {
+    let document = to_document(store)?;
+
+    // written next to the target and renamed over it, a crash mid-write
+    // leaves the previous version intact
+    let temp = path.with_extension("json.tmp");
+    std::fs::write(&temp, serde_json::to_vec_pretty(&document)?)?;
+    std::fs::rename(temp, path)?;
+    Ok(())
+}
+
+// reads and, if needed, migrates any document written by `write_document`
+pub fn load(path: &Path) -> Result<TagStore, DatabaseError> {
+    let document: Value = serde_json::from_slice(&std::fs::read(path)?)?;
+    let version = version_of(&document);
+
+    if version < SCHEMA_VERSION {
+        let backup = path.with_extension(format!("v{version}.json.bak"));
+        std::fs::copy(path, &backup)?;
+        tracing::warn!(
+            "migrating the tag database from version {version} to {SCHEMA_VERSION}, backup at {}",
+            backup.display()
+        );
+    }
+
+    from_document(document)
+}
+
+fn version_of(document: &Value) -> u64 {
+    document.get(VERSION_KEY).and_then(Value::as_u64).unwrap_or(0)
+}
+
+// a document from `to_document`, migrated first when it's older
+pub fn from_document(mut document: Value) -> Result<TagStore, DatabaseError> {
+    let version = version_of(&document);
+    if version > SCHEMA_VERSION {
+        return Err(DatabaseError::TooNew {
+            found: version,
+            known: SCHEMA_VERSION,
+        });
+    }
+
+    for migration in MIGRATIONS.iter().filter(|migration| migration.to > version) {
+        tracing::warn!("  {}: {}", migration.to, migration.description);
+        (migration.apply)(&mut document).map_err(|message| DatabaseError::Migration {
+            to: migration.to,
+            message,
+        })?;
+    }
+
+    Ok(serde_json::from_value(document)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    // from before the version key, written by hand: no ids and no next_id
+    fn v0_document() -> Value {
+        json!({
+            "tags": [
+                {"video": "match.mp4", "time": {"secs": 3, "nanos": 0}, "label": "kickoff"},
+                {"id": 7, "video": "match.mp4", "time": {"secs": 9, "nanos": 0}, "label": "goal"},
+                {"video": "match.mp4", "time": {"secs": 5, "nanos": 0}, "label": "foul"}
+            ]
+        })
+    }
+
+    #[test]
+    fn upgrades_a_v0_document() {
+        let mut store = from_document(v0_document()).unwrap();
+        let mut ids = store.tags().iter().map(|tag| tag.id).collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+
+        let added = store
+            .add("match.mp4".into(), Duration::from_secs(1), "corner".into())
+            .unwrap();
+        assert!(!ids.contains(&added));
+
+        let document = to_document(&store).unwrap();
+        assert_eq!(version_of(&document), SCHEMA_VERSION);
+        assert_eq!(from_document(document).unwrap().tags(), store.tags());
+    }
+
+    #[test]
+    fn backs_up_before_migrating() {
+        let dir = std::env::temp_dir().join(format!("tag-database-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(DATABASE_FILE);
+        std::fs::write(&path, v0_document().to_string()).unwrap();
+
+        let store = load(&path);
+        let backup = std::fs::read(dir.join("tags.v0.json.bak"));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(store.unwrap().tags().len(), 3);
+        assert_eq!(serde_json::from_slice::<Value>(&backup.unwrap()).unwrap(), v0_document());
+    }
+
+    #[test]
+    fn refuses_newer_documents() {
+        let document = json!({"tags": [], "next_id": 0, VERSION_KEY: SCHEMA_VERSION + 1});
+        assert!(matches!(
+            from_document(document),
+            Err(DatabaseError::TooNew { found, .. }) if found == SCHEMA_VERSION + 1
+        ));
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use cushy::value::{Destination, Dynamic, Source};
+use ffmpeg_next as ffmpeg;
+
+use super::{export_clip, Clip, ExportProfile, Method};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum JobState {
+    Waiting,
+    // how far along each part is, 0 to 1
+    Running(Vec<f32>),
+    Finished(Method),
+    Failed(String),
+    Canceled,
+}
+
+impl JobState {
+    pub(super) fn label(&self, parts: &[&str]) -> String {
+        match self {
+            Self::Waiting => String::from("waiting"),
+            Self::Running(progress) => progress_label(parts, progress),
+            Self::Finished(method) => String::from(method.label()),
+            Self::Failed(err) => format!("failed: {err}"),
+            Self::Canceled => String::from("canceled"),
+        }
+    }
+
+    pub(super) fn is_over(&self) -> bool {
+        !matches!(self, Self::Waiting | Self::Running(_))
+    }
+}
+
+// how far along an export is, and each of its files when it makes more than one
+pub(super) fn progress_label(parts: &[&str], progress: &[f32]) -> String {
+    let overall = progress.iter().sum::<f32>() / progress.len().max(1) as f32;
+    if progress.len() < 2 {
+        return format!("{:.0}%", overall * 100.);
+    }
+    let each = parts
+        .iter()
+        .zip(progress)
+        .map(|(part, progress)| format!("{part} {:.0}%", progress * 100.))
+        .collect::<Vec<_>>();
+    format!("{:.0}% ({})", overall * 100., each.join(", "))
+}
+
+// one clip of a batch and how it's going
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Job {
+    pub clip: Clip,
+    // the files it makes, see `ExportProfile::parts`
+    pub parts: Vec<&'static str>,
+    pub state: JobState,
+}
+
+impl Job {
+    pub(super) fn new(clip: Clip, parts: Vec<&'static str>) -> Self {
+        Self {
+            clip,
+            parts,
+            state: JobState::Waiting,
+        }
+    }
+}
+
+// how many clips are made at once. x264 spreads each one over several cores already,
+// more workers than this mostly fight over the disk
+fn worker_count() -> usize {
+    std::thread::available_parallelism().map_or(1, |cores| cores.get() / 4).clamp(1, 4)
+}
+
+// exports every job on a pool of workers taking them in order, returning once all are
+// over. `cancel` stops the clips being made and leaves the rest canceled
+pub(super) fn run(jobs: &Dynamic<Vec<Job>>, profile: &ExportProfile, cancel: &AtomicBool) {
+    let count = jobs.map_ref(Vec::len);
+    let next = AtomicUsize::new(0);
+    let set = |idx: usize, state: JobState| {
+        if let Some(job) = jobs.lock().get_mut(idx) {
+            job.state = state;
+        }
+    };
+
+    std::thread::scope(|scope| {
+        for _ in 0..worker_count().min(count) {
+            let next = &next;
+            std::thread::Builder::new()
+                .name("Export Worker Thread".into())
+                .spawn_scoped(scope, move || loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let clip = jobs.map_ref(|jobs| jobs.get(idx).map(|job| job.clip.clone()));
+                    let Some(clip) = clip else {
+                        break;
+                    };
+                    if cancel.load(Ordering::Relaxed) {
+                        set(idx, JobState::Canceled);
+                        continue;
+                    }
+
+                    set(idx, JobState::Running(vec![0.; profile.parts().len()]));
+                    // every percent is plenty for the queue, and spares it a redraw per frame
+                    let mut shown = 0.;
+                    let exported = export_clip(&clip, profile, |progress| {
+                        let overall = progress.iter().sum::<f32>() / progress.len() as f32;
+                        if overall - shown >= 0.01 {
+                            shown = overall;
+                            set(idx, JobState::Running(progress.to_vec()));
+                        }
+                        !cancel.load(Ordering::Relaxed)
+                    });
+                    set(
+                        idx,
+                        match exported {
+                            Ok(exported) => JobState::Finished(exported.method),
+                            Err(ffmpeg::Error::Exit) => JobState::Canceled,
+                            Err(err) => JobState::Failed(err.to_string()),
+                        },
+                    );
+                })
+                .unwrap();
+        }
+    });
+}
@@ -0,0 +1,192 @@
+use std::sync::OnceLock;
+
+use ffmpeg_next as ffmpeg;
+use serde::{Deserialize, Serialize};
+
+use super::{Preset, Quality};
+use crate::media::init_ffmpeg;
+
+// which of them work on this machine, found out once
+static AVAILABLE: OnceLock<Vec<VideoEncoder>> = OnceLock::new();
+
+// what makes the h264 of re-encoded clips and previews. the gpu ones are several times
+// faster than x264 at a somewhat bigger file for the same picture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum VideoEncoder {
+    // x264
+    #[default]
+    Software,
+    // nvidia
+    Nvenc,
+    // intel
+    QuickSync,
+    // amd
+    Amf,
+}
+
+impl VideoEncoder {
+    pub const ALL: [Self; 4] = [Self::Software, Self::Nvenc, Self::QuickSync, Self::Amf];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Software => "x264",
+            Self::Nvenc => "nvenc",
+            Self::QuickSync => "quicksync",
+            Self::Amf => "amf",
+        }
+    }
+
+    fn codec_name(self) -> &'static str {
+        match self {
+            Self::Software => "libx264",
+            Self::Nvenc => "h264_nvenc",
+            Self::QuickSync => "h264_qsv",
+            Self::Amf => "h264_amf",
+        }
+    }
+
+    // the ones ffmpeg was built with and that open on this machine's hardware. the
+    // first call tries each, which takes a moment with a gpu driver to load
+    pub fn available() -> &'static [Self] {
+        AVAILABLE.get_or_init(|| {
+            Self::ALL
+                .into_iter()
+                .filter(|encoder| *encoder == Self::Software || encoder.opens())
+                .collect()
+        })
+    }
+
+    // the next one that works here, after the last back to x264
+    pub fn next(self) -> Self {
+        let available = Self::available();
+        let at = available.iter().position(|encoder| *encoder == self);
+        available[at.map_or(0, |at| (at + 1) % available.len())]
+    }
+
+    // a profile made on another machine may name a gpu this one doesn't have
+    pub(super) fn or_software(self) -> Self {
+        if Self::available().contains(&self) {
+            self
+        } else {
+            Self::Software
+        }
+    }
+
+    pub(super) fn find(self) -> Option<ffmpeg::Codec> {
+        match self {
+            Self::Software => ffmpeg::encoder::find_by_name(self.codec_name())
+                .or_else(|| ffmpeg::encoder::find(ffmpeg::codec::Id::H264)),
+            _ => ffmpeg::encoder::find_by_name(self.codec_name()),
+        }
+    }
+
+    // quicksync only takes nv12 from system memory
+    pub(super) fn pixel(self) -> ffmpeg::format::Pixel {
+        match self {
+            Self::QuickSync => ffmpeg::format::Pixel::NV12,
+            _ => ffmpeg::format::Pixel::YUV420P,
+        }
+    }
+
+    // the same, as the format filter calls it
+    pub(super) fn pixel_name(self) -> &'static str {
+        match self {
+            Self::QuickSync => "nv12",
+            _ => "yuv420p",
+        }
+    }
+
+    // the encoder's own names for the quality and preset. a bitrate is set on the
+    // encoder itself, see `Quality::bit_rate`
+    pub(super) fn options(self, quality: Quality, preset: Preset) -> ffmpeg::Dictionary<'static> {
+        let mut options = ffmpeg::Dictionary::new();
+        let crf = match quality {
+            Quality::Crf(crf) => Some(crf.to_string()),
+            Quality::Bitrate { .. } => None,
+        };
+        match self {
+            Self::Software => {
+                if let Some(crf) = &crf {
+                    options.set("crf", crf);
+                }
+                options.set(
+                    "preset",
+                    match preset {
+                        Preset::Fast => "veryfast",
+                        Preset::Medium => "medium",
+                        Preset::Slow => "slow",
+                    },
+                );
+            }
+            Self::Nvenc => {
+                options.set("rc", "vbr");
+                match &crf {
+                    Some(crf) => options.set("cq", crf),
+                    None => options.set("multipass", "fullres"),
+                }
+                options.set(
+                    "preset",
+                    match preset {
+                        Preset::Fast => "p2",
+                        Preset::Medium => "p5",
+                        Preset::Slow => "p7",
+                    },
+                );
+            }
+            Self::QuickSync => {
+                if let Some(crf) = &crf {
+                    options.set("global_quality", crf);
+                }
+                options.set(
+                    "preset",
+                    match preset {
+                        Preset::Fast => "veryfast",
+                        Preset::Medium => "medium",
+                        Preset::Slow => "veryslow",
+                    },
+                );
+            }
+            Self::Amf => {
+                match &crf {
+                    Some(crf) => {
+                        options.set("rc", "cqp");
+                        options.set("qp_i", crf);
+                        options.set("qp_p", crf);
+                    }
+                    None => options.set("rc", "vbr_peak"),
+                }
+                options.set(
+                    "quality",
+                    match preset {
+                        Preset::Fast => "speed",
+                        Preset::Medium => "balanced",
+                        Preset::Slow => "quality",
+                    },
+                );
+            }
+        }
+        options
+    }
+
+    // being built in doesn't mean there's a card and a driver for it, only opening an
+    // encoder tells
+    fn opens(self) -> bool {
+        if init_ffmpeg().is_err() {
+            return false;
+        }
+        let Some(codec) = self.find() else {
+            return false;
+        };
+        let Ok(mut encoder) = ffmpeg::codec::context::Context::new_with_codec(codec)
+            .encoder()
+            .video()
+        else {
+            return false;
+        };
+        encoder.set_width(256);
+        encoder.set_height(256);
+        encoder.set_format(self.pixel());
+        encoder.set_time_base(ffmpeg::Rational::new(1, 25));
+        encoder.open_as_with(codec, self.options(Quality::Crf(23), Preset::Fast)).is_ok()
+    }
+}
@@ -0,0 +1,449 @@
+// turning range tags into video files: stream copies where the range allows it,
+// re-encodes through the profile's filters otherwise
+mod batch;
+mod encoder;
+mod panel;
+mod remux;
+mod shared;
+mod transcode;
+mod watermark;
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use ffmpeg_next as ffmpeg;
+use serde::{Deserialize, Serialize};
+
+use crate::media::open_input;
+use crate::vertical_crop::{CropTrack, VERTICAL_SIZE};
+use encoder::VideoEncoder;
+use transcode::{Pass, Target};
+
+pub use panel::export_panel;
+pub use shared::ExportWorker;
+pub use watermark::{Corner, Watermark};
+
+// how the picture is framed in the exported file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Framing {
+    // as it was shot
+    #[default]
+    Original,
+    // an upright 9:16 window out of a landscape frame, following the tag's crop
+    Vertical,
+}
+
+// a further file made of the same range beside the profile's own, from the same decoded
+// frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Extra {
+    // a small mp4 that's quick to send around, 480 lines tall
+    Preview,
+    // a silent animated gif, 480 wide
+    Gif,
+}
+
+impl Extra {
+    pub const ALL: [Self; 2] = [Self::Preview, Self::Gif];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Preview => "preview",
+            Self::Gif => "gif",
+        }
+    }
+
+    fn output(self, clip: &Clip) -> PathBuf {
+        match self {
+            Self::Preview => clip.file("_preview", "mp4"),
+            Self::Gif => clip.file("", "gif"),
+        }
+    }
+}
+
+// how long the encoder takes over each frame, slower makes a smaller file of the same
+// picture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Preset {
+    Fast,
+    #[default]
+    Medium,
+    Slow,
+}
+
+impl Preset {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Fast => "fast",
+            Self::Medium => "medium",
+            Self::Slow => "slow",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Fast => Self::Medium,
+            Self::Medium => Self::Slow,
+            Self::Slow => Self::Fast,
+        }
+    }
+}
+
+// what a re-encoded clip's picture is held to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Quality {
+    // the same quality all along at whatever size that takes, lower is better. x264's
+    // crf scale, the gpu encoders get their nearest
+    Crf(u32),
+    // a size known ahead: an average in kbit/s, spread better over a second pass. only
+    // x264 makes two passes, nvenc does its own within one
+    Bitrate { kbps: u32, two_pass: bool },
+}
+
+impl Default for Quality {
+    // x264's defaults apart from a slightly better picture than its crf 23
+    fn default() -> Self {
+        Self::Crf(20)
+    }
+}
+
+impl Quality {
+    pub fn label(self) -> String {
+        match self {
+            Self::Crf(crf) => format!("crf {crf}"),
+            Self::Bitrate {
+                kbps,
+                two_pass: false,
+            } => format!("{kbps} kbit/s"),
+            Self::Bitrate {
+                kbps,
+                two_pass: true,
+            } => format!("{kbps} kbit/s, 2 passes"),
+        }
+    }
+
+    // bits a second and a ceiling above them for busy scenes, for encoders that want one
+    fn bit_rate(self) -> Option<(usize, usize)> {
+        let Self::Bitrate { kbps, .. } = self else {
+            return None;
+        };
+        let bit_rate = kbps as usize * 1000;
+        Some((bit_rate, bit_rate * 3 / 2))
+    }
+}
+
+// what an exported clip is made like
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportProfile {
+    pub name: String,
+    #[serde(default)]
+    pub framing: Framing,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watermark: Option<Watermark>,
+    // integrated loudness the sound is brought to, in LUFS. left as it is without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loudness: Option<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extras: Vec<Extra>,
+    // what re-encodes it, x264 on machines without the gpu it names
+    #[serde(default)]
+    pub encoder: VideoEncoder,
+    #[serde(default)]
+    pub quality: Quality,
+    #[serde(default)]
+    pub preset: Preset,
+}
+
+impl ExportProfile {
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self {
+                name: String::from("original"),
+                framing: Framing::Original,
+                watermark: None,
+                loudness: None,
+                extras: Vec::new(),
+                encoder: VideoEncoder::default(),
+                quality: Quality::default(),
+                preset: Preset::default(),
+            },
+            Self {
+                name: String::from("vertical 9:16"),
+                framing: Framing::Vertical,
+                watermark: None,
+                loudness: None,
+                extras: Vec::new(),
+                encoder: VideoEncoder::default(),
+                quality: Quality::default(),
+                preset: Preset::default(),
+            },
+        ]
+    }
+
+    // what the parts of an export's progress are, the profile's own file first
+    pub fn parts(&self) -> Vec<&'static str> {
+        std::iter::once("clip")
+            .chain(self.extras.iter().map(|extra| extra.label()))
+            .collect()
+    }
+
+    // a first pass only writes what the second needs to spread the bits
+    fn two_pass(&self) -> bool {
+        matches!(self.quality, Quality::Bitrate { two_pass: true, .. })
+            && self.encoder.or_software() == VideoEncoder::Software
+    }
+
+    // copying packets keeps picture and sound exactly as they are
+    fn changes_frames(&self) -> bool {
+        self.framing != Framing::Original || self.watermark.is_some() || self.loudness.is_some()
+    }
+}
+
+// what x264 at medium puts into a pixel of a typical clip at crf 23. every 6 crf
+// halves it or doubles it, how much really depends on what's filmed
+const CRF_23_BITS_PER_PIXEL: f64 = 0.07;
+
+// about how big the profile's own file of `length` from `video` comes out, none when
+// the video doesn't open. a copy keeps the video's bitrate, a bitrate is what it says
+// and a crf is a guess
+pub fn estimated_size(video: &Path, length: Duration, profile: &ExportProfile) -> Option<u64> {
+    let ictx = open_input(video).ok()?;
+    let seconds = length.as_secs_f64();
+    if !profile.changes_frames() {
+        return Some((ictx.bit_rate().max(0) as f64 * seconds / 8.) as u64);
+    }
+
+    let stream = ictx.streams().best(ffmpeg::media::Type::Video)?;
+    let decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+        .ok()?
+        .decoder()
+        .video()
+        .ok()?;
+    let (width, height) = match profile.framing {
+        Framing::Original => (decoder.width(), decoder.height()),
+        Framing::Vertical => VERTICAL_SIZE,
+    };
+    let frame_rate = match f64::from(stream.avg_frame_rate()) {
+        rate if rate.is_finite() && rate > 0. => rate,
+        _ => 30.,
+    };
+    let video_bits = match profile.quality {
+        Quality::Bitrate { kbps, .. } => f64::from(kbps) * 1000.,
+        Quality::Crf(crf) => {
+            let per_pixel = CRF_23_BITS_PER_PIXEL * 2_f64.powf((23. - f64::from(crf)) / 6.);
+            f64::from(width) * f64::from(height) * frame_rate * per_pixel
+        }
+    };
+    let audio_bits = match ictx.streams().best(ffmpeg::media::Type::Audio) {
+        Some(_) => transcode::AUDIO_BIT_RATE as f64,
+        None => 0.,
+    };
+    Some(((video_bits + audio_bits) * seconds / 8.) as u64)
+}
+
+// one range of one video into a file of its own
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clip {
+    // the original, never a proxy
+    pub video: PathBuf,
+    pub start: Duration,
+    pub end: Duration,
+    pub crop: CropTrack,
+    // where it goes, without an extension. that depends on how it's made
+    pub output: PathBuf,
+}
+
+impl Clip {
+    // named after the video, the tag's category and where the range starts
+    pub fn of_range(
+        video: PathBuf,
+        label: &str,
+        (start, end): (Duration, Duration),
+        crop: CropTrack,
+        folder: &Path,
+    ) -> Self {
+        let stem = video.file_stem().unwrap_or_default().to_string_lossy();
+        let label = label
+            .chars()
+            .map(|c| match c {
+                c if c.is_alphanumeric() || c == '-' => c,
+                _ => '_',
+            })
+            .collect::<String>();
+        let output = folder.join(format!("{stem}_{label}_{}ms", start.as_millis()));
+        Self {
+            video,
+            start,
+            end,
+            crop,
+            output,
+        }
+    }
+
+    // `output` with a suffix and an extension added. the video's name may have dots of
+    // its own, so they're never replaced
+    fn file(&self, suffix: &str, extension: &str) -> PathBuf {
+        let mut file = self.output.clone().into_os_string();
+        file.push(suffix);
+        file.push(".");
+        file.push(extension);
+        PathBuf::from(file)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    // packets as they are, in the source's container
+    Copy,
+    // decoded, filtered and encoded as h264 and aac in mp4
+    Encode,
+}
+
+impl Method {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Copy => "copied",
+            Self::Encode => "re-encoded",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exported {
+    // how the profile's own file was made, the extras are always encoded
+    pub method: Method,
+    // that file first, then one per extra
+    pub outputs: Vec<PathBuf>,
+}
+
+// copies the range when the profile leaves the frames alone and there's a keyframe
+// where it starts, and re-encodes it otherwise, or when copying doesn't work out. the
+// profile's extras come out of the same decode as a re-encoded clip, or one of their own
+// after a copy. `on_progress` hears how far along each part is, 0 to 1, in the order of
+// `ExportProfile::parts`, and cancels by returning false. a canceled or failed clip
+// leaves no files behind
+pub fn export_clip(
+    clip: &Clip,
+    profile: &ExportProfile,
+    mut on_progress: impl FnMut(&[f32]) -> bool,
+) -> Result<Exported, ffmpeg::Error> {
+    let extras = profile
+        .extras
+        .iter()
+        .map(|&extra| Target {
+            output: extra.output(clip),
+            extra: Some(extra),
+        })
+        .collect::<Vec<_>>();
+    let remove = |targets: &[Target]| {
+        for target in targets {
+            let _ = std::fs::remove_file(&target.output);
+        }
+    };
+
+    if !profile.changes_frames() {
+        if let Some(from) = remux::copy_start(clip)? {
+            let extension = clip.video.extension().unwrap_or_default().to_string_lossy();
+            let output = clip.file("", &extension);
+            let mut parts = vec![0.; extras.len() + 1];
+            let copied = remux::remux(clip, from, &output, &mut |progress| {
+                parts[0] = progress;
+                on_progress(&parts)
+            });
+            let encoded = copied.and_then(|()| {
+                if extras.is_empty() {
+                    return Ok(());
+                }
+                transcode::transcode(clip, &extras, profile, Pass::Only, &mut |progress| {
+                    parts[1..].copy_from_slice(progress);
+                    on_progress(&parts)
+                })
+            });
+            match encoded {
+                Ok(()) => {
+                    return Ok(Exported {
+                        method: Method::Copy,
+                        outputs: std::iter::once(output)
+                            .chain(extras.into_iter().map(|target| target.output))
+                            .collect(),
+                    })
+                }
+                Err(ffmpeg::Error::Exit) => {
+                    let _ = std::fs::remove_file(&output);
+                    remove(&extras);
+                    return Err(ffmpeg::Error::Exit);
+                }
+                // e.g. a stream the muxer won't take as it is
+                Err(err) => {
+                    let _ = std::fs::remove_file(&output);
+                    remove(&extras);
+                    tracing::warn!("failed to copy {}, re-encoding it: {err}", output.display());
+                }
+            }
+        }
+    }
+
+    let targets = std::iter::once(Target {
+        output: clip.file("", "mp4"),
+        extra: None,
+    })
+    .chain(extras)
+    .collect::<Vec<_>>();
+    let encoded = if profile.two_pass() {
+        two_passes(clip, &targets, profile, &mut on_progress)
+    } else {
+        transcode::transcode(clip, &targets, profile, Pass::Only, &mut on_progress)
+    };
+    match encoded {
+        Ok(()) => Ok(Exported {
+            method: Method::Encode,
+            outputs: targets.into_iter().map(|target| target.output).collect(),
+        }),
+        Err(err) => {
+            remove(&targets);
+            Err(err)
+        }
+    }
+}
+
+// the profile's own file twice, the first time only for x264's stats, which is half of
+// its progress. the extras come along on the second
+fn two_passes(
+    clip: &Clip,
+    targets: &[Target],
+    profile: &ExportProfile,
+    on_progress: &mut dyn FnMut(&[f32]) -> bool,
+) -> Result<(), ffmpeg::Error> {
+    let first = Target {
+        output: clip.file("_first_pass", "mp4"),
+        extra: None,
+    };
+    let stats = clip.file("_pass", "log");
+    let mut parts = vec![0.; targets.len()];
+    let analyzed = transcode::transcode(
+        clip,
+        std::slice::from_ref(&first),
+        profile,
+        Pass::First(&stats),
+        &mut |progress| {
+            parts[0] = progress[0] / 2.;
+            on_progress(&parts)
+        },
+    );
+    let _ = std::fs::remove_file(&first.output);
+    let encoded = analyzed.and_then(|()| {
+        transcode::transcode(clip, targets, profile, Pass::Second(&stats), &mut |progress| {
+            parts.copy_from_slice(progress);
+            parts[0] = 0.5 + progress[0] / 2.;
+            on_progress(&parts)
+        })
+    });
+    // x264 keeps its macroblock tree beside the stats
+    let _ = std::fs::remove_file(&stats);
+    let mut mbtree = stats.into_os_string();
+    mbtree.push(".mbtree");
+    let _ = std::fs::remove_file(mbtree);
+    encoded
+}
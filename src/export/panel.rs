@@ -0,0 +1,926 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use cushy::{
+    animation::ZeroToOne,
+    value::{Destination, Dynamic, MapEach, Source, Switchable},
+    widget::{MakeWidget, WidgetList},
+    widgets::{layers::Modal, progress::Progressable, slider::Slidable},
+};
+use ffmpeg_next as ffmpeg;
+
+use super::batch::{self, progress_label, Job, JobState};
+use super::shared::{self, SharedJob};
+use super::{
+    estimated_size, export_clip, Clip, Corner, ExportProfile, Extra, Method, Quality, VideoEncoder,
+    Watermark,
+};
+use crate::database::{TagDatabase, TagQuery};
+use crate::renditions::Renditions;
+use crate::tags::{Tag, TagId, TagStore};
+use crate::vertical_crop::CropTrack;
+
+// what the loudness button goes through, in LUFS: streaming sites, podcasts, broadcast
+const LOUDNESS_TARGETS: [f32; 3] = [-14., -16., -23.];
+// what the quality button goes through: crfs from nearly lossless to small, then
+// bitrates in kbit/s for 720p, 1080p and 4k
+const CRFS: [u32; 4] = [18, 20, 23, 28];
+const BITRATES: [u32; 3] = [2_500, 6_000, 20_000];
+
+// which range tags "export ranges" cuts out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    // the selected tag, if it is a range
+    Selected,
+    // every range on the open video
+    Video,
+    // every range in the selected tag's category, on any video
+    Category,
+}
+
+impl Scope {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Selected => "selected range",
+            Self::Video => "this video's ranges",
+            Self::Category => "category, all videos",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Self::Selected => Self::Video,
+            Self::Video => Self::Category,
+            Self::Category => Self::Selected,
+        }
+    }
+}
+
+// one range tag, copied out of the store so the export doesn't hold it
+#[derive(Debug, Clone)]
+struct Range {
+    video: PathBuf,
+    label: String,
+    start: Duration,
+    end: Duration,
+    crop: CropTrack,
+}
+
+// the range tags among `tags`, instants have nothing to cut
+fn ranges(tags: &[Tag]) -> Vec<Range> {
+    tags.iter()
+        .filter_map(|tag| {
+            Some(Range {
+                video: tag.video.clone(),
+                label: tag.label.clone(),
+                start: tag.time,
+                end: tag.end?,
+                crop: tag.crop.clone(),
+            })
+        })
+        .collect()
+}
+
+// what "export ranges" and "send to workers" go through
+#[derive(Debug, Clone)]
+struct Selection {
+    tags: Dynamic<TagStore>,
+    database: TagDatabase,
+    scope: Dynamic<Scope>,
+    selected_tag: Dynamic<Option<TagId>>,
+    video_source: Dynamic<Option<PathBuf>>,
+    renditions: Dynamic<Renditions>,
+}
+
+impl Selection {
+    // tags live on the originals, so the open video counts as its original
+    fn ranges(&self) -> Result<Vec<Range>, String> {
+        let video = self
+            .video_source
+            .get()
+            .map(|video| self.renditions.map_ref(|renditions| renditions.original_of(&video)));
+        let selected = self
+            .selected_tag
+            .get()
+            .and_then(|id| self.tags.map_ref(|tags| tags.get(id).cloned()));
+        let tags = match self.scope.get() {
+            Scope::Selected => vec![selected.ok_or("select a range tag first")?],
+            Scope::Video => {
+                let video = video.ok_or("open a video first")?;
+                self.tags.map_ref(|tags| {
+                    tags.tags().iter().filter(|tag| tag.video == video).cloned().collect()
+                })
+            }
+            // the other videos' tags aren't loaded, the database has them
+            Scope::Category => {
+                let query = TagQuery {
+                    label: Some(selected.ok_or("select a tag of the category first")?.label),
+                    ..TagQuery::default()
+                };
+                self.database.find(&self.tags, &query).map_err(|err| err.to_string())?
+            }
+        };
+        let ranges = ranges(&tags);
+        if ranges.is_empty() {
+            return Err(String::from("no range tags to export"));
+        }
+        Ok(ranges)
+    }
+}
+
+// how an export run went, for the dialog once it's over
+#[derive(Debug, Clone, Default)]
+struct Report {
+    copied: usize,
+    encoded: usize,
+    failed: Vec<String>,
+    canceled: bool,
+}
+
+impl Report {
+    fn summary(&self) -> String {
+        let mut summary = format!("{} copied, {} re-encoded", self.copied, self.encoded);
+        if !self.failed.is_empty() {
+            summary.push_str(&format!(", {} failed", self.failed.len()));
+        }
+        if self.canceled {
+            summary.push_str(", canceled");
+        }
+        summary
+    }
+}
+
+// the clip being made, how far along it and each of its files are, and a way to stop
+// the rest
+fn progress_dialog(
+    status: Dynamic<String>,
+    fraction: Dynamic<ZeroToOne>,
+    parts: Dynamic<String>,
+    report: Dynamic<Option<Report>>,
+    cancel: Arc<AtomicBool>,
+    modal: Modal,
+) -> impl MakeWidget {
+    let running = report.map_each(Option::is_none);
+    let details = report.map_each(|report| match report {
+        Some(report) => match report.failed.first() {
+            Some(first) => format!("{}\nfirst failure: {first}", report.summary()),
+            None => report.summary(),
+        },
+        None => String::new(),
+    });
+    let cancel_button = "cancel"
+        .into_button()
+        .on_click(move |_| cancel.store(true, Ordering::Relaxed))
+        .collapse_vertically(running.map_each(|running| !running));
+    let ok_button = "OK"
+        .into_button()
+        .on_click(move |_| modal.dismiss())
+        .collapse_vertically(running.clone());
+
+    "Exporting clips"
+        .and(status)
+        .and(fraction.progress_bar())
+        .and(parts)
+        .and(details.collapse_vertically(running))
+        .and(cancel_button)
+        .and(ok_button)
+        .into_rows()
+        .contain()
+}
+
+// has the selected profile make one more file of each range, or one less
+fn extra_toggle(
+    profiles: &Dynamic<Vec<ExportProfile>>,
+    profile: &Dynamic<usize>,
+    extra: Extra,
+) -> impl MakeWidget {
+    (profiles, profile)
+        .map_each(move |(profiles, idx)| {
+            if profiles[*idx].extras.contains(&extra) {
+                format!("[+{}]", extra.label())
+            } else {
+                format!("+{}", extra.label())
+            }
+        })
+        .into_button()
+        .on_click({
+            let profiles = profiles.clone();
+            let profile = profile.clone();
+            move |_| {
+                let idx = profile.get();
+                let mut profiles = profiles.lock();
+                let extras = &mut profiles[idx].extras;
+                match extras.iter().position(|made| *made == extra) {
+                    Some(at) => {
+                        extras.remove(at);
+                    }
+                    None => {
+                        extras.push(extra);
+                        extras.sort();
+                    }
+                }
+            }
+        })
+}
+
+// what comes after `quality` on the quality button, a bitrate keeps its passes
+fn next_quality(quality: Quality) -> Quality {
+    let two_pass = matches!(quality, Quality::Bitrate { two_pass: true, .. });
+    let choices = CRFS
+        .into_iter()
+        .map(Quality::Crf)
+        .chain(BITRATES.into_iter().map(|kbps| Quality::Bitrate { kbps, two_pass }))
+        .collect::<Vec<_>>();
+    let at = choices.iter().position(|choice| *choice == quality);
+    choices[at.map_or(0, |at| (at + 1) % choices.len())]
+}
+
+fn size_label(bytes: u64) -> String {
+    match bytes {
+        bytes if bytes >= 1 << 30 => format!("{:.1} GB", bytes as f64 / f64::from(1 << 30)),
+        bytes => format!("{:.0} MB", (bytes as f64 / f64::from(1 << 20)).max(1.)),
+    }
+}
+
+// the selected profile's crf or bitrate, two passes for a bitrate, the encoder's preset,
+// and how big the ranges in scope would come out, reckoned when asked since it opens
+// every video
+fn quality_row(
+    profiles: Dynamic<Vec<ExportProfile>>,
+    profile: Dynamic<usize>,
+    selection: Selection,
+) -> impl MakeWidget {
+    let selected = (&profiles, &profile).map_each(|(profiles, idx)| profiles[*idx].clone());
+    let change = {
+        let profiles = profiles.clone();
+        let profile = profile.clone();
+        move |change: &dyn Fn(&mut ExportProfile)| {
+            let idx = profile.get();
+            change(&mut profiles.lock()[idx]);
+        }
+    };
+
+    let quality_button = selected
+        .map_each(|profile| format!("quality: {}", profile.quality.label()))
+        .into_button()
+        .on_click({
+            let change = change.clone();
+            move |_| change(&|profile| profile.quality = next_quality(profile.quality))
+        });
+    let two_pass_button = selected
+        .map_each(|profile| match profile.quality {
+            Quality::Bitrate { two_pass: true, .. } => String::from("[two pass]"),
+            _ => String::from("two pass"),
+        })
+        .into_button()
+        .on_click({
+            let change = change.clone();
+            move |_| {
+                change(&|profile| {
+                    if let Quality::Bitrate { two_pass, .. } = &mut profile.quality {
+                        *two_pass = !*two_pass;
+                    }
+                })
+            }
+        })
+        .with_enabled(
+            selected.map_each(|profile| matches!(profile.quality, Quality::Bitrate { .. })),
+        );
+    let preset_button = selected
+        .map_each(|profile| format!("preset: {}", profile.preset.label()))
+        .into_button()
+        .on_click(move |_| change(&|profile| profile.preset = profile.preset.next()));
+
+    let estimate = Dynamic::new(String::new());
+    // a change of profile makes the last estimate stale
+    selected
+        .for_each({
+            let estimate = estimate.clone();
+            move |_| estimate.set(String::new())
+        })
+        .persist();
+    let estimate_button = "estimate size".into_button().on_click({
+        let estimate = estimate.clone();
+        move |_| {
+            let ranges = match selection.ranges() {
+                Ok(ranges) => ranges,
+                Err(why) => {
+                    estimate.set(String::from(why));
+                    return;
+                }
+            };
+            let profile = profiles.map_ref(|profiles| profiles[profile.get()].clone());
+            let estimate = estimate.clone();
+            estimate.set(String::from("estimating..."));
+            std::thread::Builder::new()
+                .name("Export Estimate Thread".into())
+                .spawn(move || {
+                    let count = ranges.len();
+                    let sizes = ranges
+                        .iter()
+                        .map(|range| {
+                            estimated_size(&range.video, range.end - range.start, &profile)
+                        })
+                        .collect::<Option<Vec<_>>>();
+                    estimate.set(match sizes {
+                        Some(sizes) => format!(
+                            "about {} for {count} clips",
+                            size_label(sizes.into_iter().sum())
+                        ),
+                        None => String::from("a video didn't open"),
+                    });
+                })
+                .unwrap();
+        }
+    });
+
+    quality_button
+        .and(two_pass_button)
+        .and(preset_button)
+        .and(estimate_button)
+        .and(estimate)
+        .into_columns()
+}
+
+// the selected profile's watermark: an image, the corner it sits in, how see-through
+// and how wide it is. changes are kept with the profile
+fn watermark_editor(
+    profiles: Dynamic<Vec<ExportProfile>>,
+    profile: Dynamic<usize>,
+) -> impl MakeWidget {
+    let watermark = (&profiles, &profile)
+        .map_each(|(profiles, idx)| profiles[*idx].watermark.clone());
+    let edit = {
+        let profiles = profiles.clone();
+        let profile = profile.clone();
+        move |change: &dyn Fn(&mut Watermark)| {
+            let idx = profile.get();
+            if let Some(watermark) = &mut profiles.lock()[idx].watermark {
+                change(watermark);
+            }
+        }
+    };
+
+    // the sliders show the profile picked, and write back into it
+    let opacity = Dynamic::new(0.8_f32);
+    let scale = Dynamic::new(0.15_f32);
+    profile
+        .for_each({
+            let profiles = profiles.clone();
+            let (opacity, scale) = (opacity.clone(), scale.clone());
+            move |idx| {
+                let picked = profiles.map_ref(|profiles| profiles[*idx].watermark.clone());
+                if let Some(watermark) = picked {
+                    opacity.set(watermark.opacity);
+                    scale.set(watermark.scale);
+                }
+            }
+        })
+        .persist();
+    opacity
+        .for_each({
+            let edit = edit.clone();
+            move |opacity| edit(&|watermark: &mut Watermark| watermark.opacity = *opacity)
+        })
+        .persist();
+    scale
+        .for_each({
+            let edit = edit.clone();
+            move |scale| edit(&|watermark: &mut Watermark| watermark.scale = *scale)
+        })
+        .persist();
+
+    let image_button = watermark
+        .map_each(|watermark| match watermark {
+            Some(watermark) => format!(
+                "watermark: {}",
+                watermark.image.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            None => String::from("add watermark"),
+        })
+        .into_button()
+        .on_click({
+            let profiles = profiles.clone();
+            let profile = profile.clone();
+            let (opacity, scale) = (opacity.clone(), scale.clone());
+            move |_| {
+                let profiles = profiles.clone();
+                let idx = profile.get();
+                let (opacity, scale) = (opacity.get(), scale.get());
+                std::thread::Builder::new()
+                    .name("File Picker Thread".into())
+                    .spawn(move || {
+                        let Some(image) = rfd::FileDialog::new()
+                            .add_filter("Image", &["png", "jpg", "jpeg", "webp", "bmp"])
+                            .pick_file()
+                        else {
+                            return;
+                        };
+                        let mut profiles = profiles.lock();
+                        let watermark = &mut profiles[idx].watermark;
+                        let corner = watermark.as_ref().map(|watermark| watermark.corner);
+                        *watermark = Some(Watermark {
+                            corner: corner.unwrap_or_default(),
+                            opacity,
+                            scale,
+                            ..Watermark::new(image)
+                        });
+                    })
+                    .unwrap();
+            }
+        });
+    let corner_button = watermark
+        .map_each(|watermark| {
+            watermark.as_ref().map_or(Corner::default(), |watermark| watermark.corner).label()
+        })
+        .into_button()
+        .on_click({
+            let edit = edit.clone();
+            move |_| edit(&|watermark: &mut Watermark| watermark.corner = watermark.corner.next())
+        });
+    let remove_button = "remove".into_button().on_click(move |_| {
+        let idx = profile.get();
+        profiles.lock()[idx].watermark = None;
+    });
+
+    let hidden = watermark.map_each(Option::is_none);
+    image_button.and(
+        corner_button
+            .and("opacity")
+            .and(opacity.slider_between(0., 1.).expand_horizontally())
+            .and("size")
+            .and(scale.slider_between(0.02, 0.5).expand_horizontally())
+            .and(remove_button)
+            .into_columns()
+            .collapse_vertically(hidden),
+    )
+    .into_rows()
+}
+
+// the clips of the last "export all", each with how it's going or what went wrong
+fn batch_queue(
+    jobs: Dynamic<Vec<Job>>,
+    exporting: Dynamic<bool>,
+    cancel: Arc<AtomicBool>,
+) -> impl MakeWidget {
+    let summary = jobs.map_each(|jobs| {
+        let over = jobs.iter().filter(|job| job.state.is_over()).count();
+        let failed = jobs
+            .iter()
+            .filter(|job| matches!(job.state, JobState::Failed(_)))
+            .count();
+        match failed {
+            0 => format!("batch: {over} of {} over", jobs.len()),
+            failed => format!("batch: {over} of {} over, {failed} failed", jobs.len()),
+        }
+    });
+    let listing = jobs.switcher(|jobs, _| {
+        jobs.iter()
+            .map(|job| {
+                let name = job.clip.output.file_name().unwrap_or_default().to_string_lossy();
+                format!("{name}: {}", job.state.label(&job.parts)).make_widget()
+            })
+            .collect::<WidgetList>()
+            .into_rows()
+            .make_widget()
+    });
+    let running = (&jobs, &exporting).map_each(|(jobs, exporting)| {
+        *exporting && jobs.iter().any(|job| !job.state.is_over())
+    });
+    let cancel_button = "cancel batch"
+        .into_button()
+        .on_click(move |_| cancel.store(true, Ordering::Relaxed))
+        .with_enabled(running.clone());
+    let clear_button = "clear"
+        .into_button()
+        .on_click({
+            let jobs = jobs.clone();
+            move |_| jobs.set(Vec::new())
+        })
+        .with_enabled(running.map_each(|running| !running));
+
+    summary
+        .and(cancel_button)
+        .and(clear_button)
+        .into_columns()
+        .and(listing.vertical_scroll())
+        .into_rows()
+        .collapse_vertically(jobs.map_each(Vec::is_empty))
+}
+
+// hands the selected ranges to export workers on other machines through a shared
+// folder, and says how far they've got
+fn workers_row(
+    shared_queue: Dynamic<Option<PathBuf>>,
+    selection: Selection,
+    profiles: Dynamic<Vec<ExportProfile>>,
+    profile: Dynamic<usize>,
+    announcement: Dynamic<String>,
+) -> impl MakeWidget {
+    let status = Dynamic::new(String::new());
+    // read on a thread, the folder may be across the network
+    let check = {
+        let status = status.clone();
+        move |queue: PathBuf| {
+            let status = status.clone();
+            std::thread::Builder::new()
+                .name("Export Queue Thread".into())
+                .spawn(move || status.set(shared::status(&queue).summary()))
+                .unwrap();
+        }
+    };
+
+    let folder_button = shared_queue
+        .map_each(|queue| match queue {
+            Some(queue) => format!("workers: {}", queue.display()),
+            None => String::from("workers: choose folder"),
+        })
+        .into_button()
+        .on_click({
+            let shared_queue = shared_queue.clone();
+            let check = check.clone();
+            move |_| {
+                let shared_queue = shared_queue.clone();
+                let check = check.clone();
+                std::thread::Builder::new()
+                    .name("File Picker Thread".into())
+                    .spawn(move || {
+                        if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                            shared_queue.set(Some(folder.clone()));
+                            check(folder);
+                        }
+                    })
+                    .unwrap();
+            }
+        });
+    let has_queue = shared_queue.map_each(Option::is_some);
+    let send = "send to workers"
+        .into_button()
+        .on_click({
+            let shared_queue = shared_queue.clone();
+            let check = check.clone();
+            move |_| {
+                let Some(queue) = shared_queue.get() else {
+                    return;
+                };
+                let ranges = match selection.ranges() {
+                    Ok(ranges) => ranges,
+                    Err(why) => {
+                        announcement.set(String::from(why));
+                        return;
+                    }
+                };
+                let profile = profiles.map_ref(|profiles| profiles[profile.get()].clone());
+                let jobs = ranges
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, range)| SharedJob {
+                        id: shared::job_id(idx),
+                        video: range.video,
+                        label: range.label,
+                        start: range.start,
+                        end: range.end,
+                        crop: range.crop,
+                        profile: profile.clone(),
+                    })
+                    .collect();
+                let announcement = announcement.clone();
+                let check = check.clone();
+                std::thread::Builder::new()
+                    .name("Export Queue Thread".into())
+                    .spawn(move || {
+                        announcement.set(match shared::submit(&queue, jobs) {
+                            Ok(count) => format!("sent {count} clips to {}", queue.display()),
+                            Err(err) => format!("failed to send clips to workers: {err}"),
+                        });
+                        check(queue);
+                    })
+                    .unwrap();
+            }
+        })
+        .with_enabled(has_queue.clone());
+    let check_button = "check"
+        .into_button()
+        .on_click(move |_| {
+            if let Some(queue) = shared_queue.get() {
+                check(queue);
+            }
+        })
+        .with_enabled(has_queue);
+
+    folder_button
+        .and(send)
+        .and(check_button)
+        .and(status)
+        .into_columns()
+}
+
+// cuts range tags out into files of their own, a stream copy where the range starts close
+// enough to a keyframe and the profile keeps the picture, a re-encode otherwise
+pub fn export_panel(
+    library: impl Fn() -> Vec<PathBuf> + Send + Sync + 'static,
+    profiles: Dynamic<Vec<ExportProfile>>,
+    tags: Dynamic<TagStore>,
+    database: TagDatabase,
+    selected_tag: Dynamic<Option<TagId>>,
+    video_source: Dynamic<Option<PathBuf>>,
+    renditions: Dynamic<Renditions>,
+    editing_crop: Dynamic<bool>,
+    modal: Modal,
+    shared_queue: Dynamic<Option<PathBuf>>,
+    announcement: Dynamic<String>,
+) -> impl MakeWidget {
+    let profile = Dynamic::new(0_usize);
+    let scope = Dynamic::new(Scope::Video);
+    let exporting = Dynamic::new(false);
+
+    let profile_button = (&profiles, &profile)
+        .map_each(|(profiles, idx)| format!("profile: {}", profiles[*idx].name))
+        .into_button()
+        .on_click({
+            let profiles = profiles.clone();
+            let profile = profile.clone();
+            move |_| {
+                let count = profiles.map_ref(Vec::len);
+                let mut profile = profile.lock();
+                *profile = (*profile + 1) % count;
+            }
+        });
+    let loudness_button = (&profiles, &profile)
+        .map_each(|(profiles, idx)| match profiles[*idx].loudness {
+            Some(target) => format!("loudness: {target} LUFS"),
+            None => String::from("loudness: as is"),
+        })
+        .into_button()
+        .on_click({
+            let profiles = profiles.clone();
+            let profile = profile.clone();
+            move |_| {
+                let idx = profile.get();
+                let mut profiles = profiles.lock();
+                let loudness = &mut profiles[idx].loudness;
+                // as is, then each target, then as is again
+                let next = match *loudness {
+                    None => 0,
+                    Some(target) => {
+                        LOUDNESS_TARGETS.iter().position(|t| *t == target).map_or(0, |i| i + 1)
+                    }
+                };
+                *loudness = LOUDNESS_TARGETS.get(next).copied();
+            }
+        });
+    // trying the gpu encoders takes a moment, better before the button is first pressed
+    std::thread::Builder::new()
+        .name("Encoder Probe Thread".into())
+        .spawn(|| {
+            VideoEncoder::available();
+        })
+        .unwrap();
+    let encoder_button = (&profiles, &profile)
+        .map_each(|(profiles, idx)| format!("encoder: {}", profiles[*idx].encoder.label()))
+        .into_button()
+        .on_click({
+            let profiles = profiles.clone();
+            let profile = profile.clone();
+            move |_| {
+                let idx = profile.get();
+                let mut profiles = profiles.lock();
+                profiles[idx].encoder = profiles[idx].encoder.next();
+            }
+        });
+    let extras = Extra::ALL
+        .into_iter()
+        .map(|extra| extra_toggle(&profiles, &profile, extra).make_widget())
+        .collect::<WidgetList>()
+        .into_columns();
+    let watermark = watermark_editor(profiles.clone(), profile.clone());
+
+    let crop_button = editing_crop
+        .map_each(|editing| {
+            if *editing {
+                String::from("[edit crop]")
+            } else {
+                String::from("edit crop")
+            }
+        })
+        .into_button()
+        .on_click({
+            let editing_crop = editing_crop.clone();
+            move |_| editing_crop.toggle()
+        });
+
+    let scope_button = scope.map_each(|scope| scope.label()).into_button().on_click({
+        let scope = scope.clone();
+        move |_| {
+            let mut scope = scope.lock();
+            *scope = scope.next();
+        }
+    });
+
+    let selection = Selection {
+        tags: tags.clone(),
+        database: database.clone(),
+        scope: scope.clone(),
+        selected_tag,
+        video_source,
+        renditions,
+    };
+    let quality = quality_row(profiles.clone(), profile.clone(), selection.clone());
+    let workers = workers_row(
+        shared_queue,
+        selection.clone(),
+        profiles.clone(),
+        profile.clone(),
+        announcement.clone(),
+    );
+    let export = "export ranges".into_button().on_click({
+        let profiles = profiles.clone();
+        let profile = profile.clone();
+        let tags = tags.clone();
+        let exporting = exporting.clone();
+        let announcement = announcement.clone();
+        let selection = selection.clone();
+        move |_| {
+            let ranges = match selection.ranges() {
+                Ok(ranges) => ranges,
+                Err(why) => {
+                    announcement.set(String::from(why));
+                    return;
+                }
+            };
+            let profile = profiles.map_ref(|profiles| profiles[profile.get()].clone());
+            let exporting = exporting.clone();
+            let modal = modal.clone();
+            let announcement = announcement.clone();
+            exporting.set(true);
+            std::thread::Builder::new()
+                .name("Export Thread".into())
+                .spawn(move || {
+                    let Some(folder) = rfd::FileDialog::new().pick_folder() else {
+                        exporting.set(false);
+                        return;
+                    };
+                    let status = Dynamic::new(String::new());
+                    let fraction = Dynamic::new(ZeroToOne::ZERO);
+                    let parts = Dynamic::new(String::new());
+                    let report: Dynamic<Option<Report>> = Dynamic::new(None);
+                    let cancel = Arc::new(AtomicBool::new(false));
+                    modal.present(progress_dialog(
+                        status.clone(),
+                        fraction.clone(),
+                        parts.clone(),
+                        report.clone(),
+                        cancel.clone(),
+                        modal.clone(),
+                    ));
+
+                    let count = ranges.len();
+                    let names = profile.parts();
+                    let mut done = Report::default();
+                    for (idx, range) in ranges.into_iter().enumerate() {
+                        let clip = Clip::of_range(
+                            range.video,
+                            &range.label,
+                            (range.start, range.end),
+                            range.crop,
+                            &folder,
+                        );
+                        let name = clip.output.file_name().unwrap_or_default().to_string_lossy();
+                        status.set(format!("{} of {count}: {name}", idx + 1));
+                        fraction.set(ZeroToOne::ZERO);
+                        let exported = export_clip(&clip, &profile, |progress| {
+                            let overall = progress.iter().sum::<f32>() / progress.len() as f32;
+                            fraction.set(ZeroToOne::new(overall));
+                            if names.len() > 1 {
+                                parts.set(progress_label(&names, progress));
+                            }
+                            !cancel.load(Ordering::Relaxed)
+                        });
+                        match exported {
+                            Ok(exported) => match exported.method {
+                                Method::Copy => done.copied += 1,
+                                Method::Encode => done.encoded += 1,
+                            },
+                            Err(ffmpeg::Error::Exit) => {
+                                done.canceled = true;
+                                break;
+                            }
+                            Err(err) => done.failed.push(format!("{name}: {err}")),
+                        }
+                    }
+
+                    announcement.set(format!(
+                        "exported to {}: {}",
+                        folder.display(),
+                        done.summary()
+                    ));
+                    status.set(format!("{count} ranges"));
+                    report.set(Some(done));
+                    exporting.set(false);
+                })
+                .unwrap();
+        }
+    });
+
+    let jobs: Dynamic<Vec<Job>> = Dynamic::new(Vec::new());
+    let cancel_batch = Arc::new(AtomicBool::new(false));
+    let export_all = "export all".into_button().on_click({
+        let jobs = jobs.clone();
+        let cancel_batch = cancel_batch.clone();
+        let exporting = exporting.clone();
+        move |_| {
+            let videos = library();
+            let mut found = Vec::new();
+            for video in videos {
+                let query = TagQuery {
+                    video: Some(video),
+                    ..TagQuery::default()
+                };
+                match database.find(&tags, &query) {
+                    Ok(tagged) => found.extend(ranges(&tagged)),
+                    Err(err) => {
+                        announcement.set(format!("couldn't read the tags: {err}"));
+                        return;
+                    }
+                }
+            }
+            let ranges = found;
+            if ranges.is_empty() {
+                announcement.set(String::from("no range tags in the project"));
+                return;
+            }
+            let profile = profiles.map_ref(|profiles| profiles[profile.get()].clone());
+            let jobs = jobs.clone();
+            let cancel = cancel_batch.clone();
+            let exporting = exporting.clone();
+            let announcement = announcement.clone();
+            exporting.set(true);
+            std::thread::Builder::new()
+                .name("Export Thread".into())
+                .spawn(move || {
+                    let Some(folder) = rfd::FileDialog::new().pick_folder() else {
+                        exporting.set(false);
+                        return;
+                    };
+                    cancel.store(false, Ordering::Relaxed);
+                    jobs.set(
+                        ranges
+                            .into_iter()
+                            .map(|range| {
+                                let clip = Clip::of_range(
+                                    range.video,
+                                    &range.label,
+                                    (range.start, range.end),
+                                    range.crop,
+                                    &folder,
+                                );
+                                Job::new(clip, profile.parts())
+                            })
+                            .collect(),
+                    );
+                    batch::run(&jobs, &profile, &cancel);
+
+                    let mut done = Report::default();
+                    for job in jobs.get() {
+                        match job.state {
+                            JobState::Finished(Method::Copy) => done.copied += 1,
+                            JobState::Finished(Method::Encode) => done.encoded += 1,
+                            JobState::Failed(err) => done.failed.push(err),
+                            _ => done.canceled = true,
+                        }
+                    }
+                    announcement.set(format!(
+                        "exported to {}: {}",
+                        folder.display(),
+                        done.summary()
+                    ));
+                    exporting.set(false);
+                })
+                .unwrap();
+        }
+    });
+
+    "Export"
+        .and(
+            profile_button
+                .and(loudness_button)
+                .and(encoder_button)
+                .and(extras)
+                .and(scope_button)
+                .and(crop_button)
+                .and(export.with_enabled(exporting.map_each(|exporting| !exporting)))
+                .and(export_all.with_enabled(exporting.map_each(|exporting| !exporting)))
+                .into_columns(),
+        )
+        .and(quality)
+        .and(watermark)
+        .and(workers)
+        .and(batch_queue(jobs, exporting, cancel_batch))
+        .into_rows()
+}
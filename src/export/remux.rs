@@ -0,0 +1,131 @@
+use std::{path::Path, time::Duration};
+
+use ffmpeg_next as ffmpeg;
+
+use super::Clip;
+use crate::media::open_input;
+
+// a copy may start this much before the range, the keyframe it has to start on. any
+// further and the clip is re-encoded instead
+const KEYFRAME_TOLERANCE: Duration = Duration::from_millis(250);
+
+// the keyframe a copy of the range would start on, if it's close enough to the start
+pub(super) fn copy_start(clip: &Clip) -> Result<Option<Duration>, ffmpeg::Error> {
+    let mut ictx = open_input(&clip.video)?;
+    let stream = ictx
+        .streams()
+        .best(ffmpeg::media::Type::Video)
+        .ok_or(ffmpeg::Error::StreamNotFound)?;
+    let (stream_index, time_base) = (stream.index(), f64::from(stream.time_base()));
+
+    let target = (clip.start.as_secs_f64() * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+    ictx.seek(target, ..=target)?;
+    let keyframe = ictx
+        .packets()
+        .find(|(stream, packet)| stream.index() == stream_index && packet.is_key())
+        .and_then(|(_, packet)| packet.pts().or(packet.dts()))
+        .map(|pts| Duration::from_secs_f64((pts as f64 * time_base).max(0.)));
+
+    Ok(keyframe.filter(|&keyframe| {
+        keyframe <= clip.start + KEYFRAME_TOLERANCE && clip.start <= keyframe + KEYFRAME_TOLERANCE
+    }))
+}
+
+// copies the packets of the range as they are, from the keyframe at `from`. nothing
+// is decoded, so it's as fast as the disk and loses nothing, but the streams have to
+// fit the container: the clip keeps the source's
+pub(super) fn remux(
+    clip: &Clip,
+    from: Duration,
+    output: &Path,
+    on_progress: &mut dyn FnMut(f32) -> bool,
+) -> Result<(), ffmpeg::Error> {
+    let mut ictx = open_input(&clip.video)?;
+    let mut octx = ffmpeg::format::output(output)?;
+    let video_index = ictx
+        .streams()
+        .best(ffmpeg::media::Type::Video)
+        .ok_or(ffmpeg::Error::StreamNotFound)?
+        .index();
+
+    // picture, sound and subtitles, data tracks rarely survive a different muxer
+    let mut streams = vec![None; ictx.nb_streams() as usize];
+    for stream in ictx.streams() {
+        let medium = stream.parameters().medium();
+        if !matches!(
+            medium,
+            ffmpeg::media::Type::Video | ffmpeg::media::Type::Audio | ffmpeg::media::Type::Subtitle
+        ) {
+            continue;
+        }
+        let mut copy = octx.add_stream(ffmpeg::encoder::find(ffmpeg::codec::Id::None))?;
+        copy.set_parameters(stream.parameters());
+        copy.set_metadata(stream.metadata().to_owned());
+        streams[stream.index()] = Some(Copied {
+            output_index: copy.index(),
+            time_base: stream.time_base(),
+            output_time_base: ffmpeg::Rational::new(0, 1),
+            sparse: medium == ffmpeg::media::Type::Subtitle,
+            done: false,
+        });
+    }
+    octx.set_metadata(ictx.metadata().to_owned());
+    octx.write_header()?;
+    for copied in streams.iter_mut().flatten() {
+        copied.output_time_base =
+            octx.stream(copied.output_index).expect("added above").time_base();
+    }
+
+    let target = (from.as_secs_f64() * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+    ictx.seek(target, ..=target)?;
+
+    let (from, end) = (from.as_secs_f64(), clip.end.as_secs_f64());
+    let length = (end - from).max(f64::EPSILON);
+    for (stream, mut packet) in ictx.packets() {
+        let Some(copied) = streams[stream.index()].as_mut() else {
+            continue;
+        };
+        let seconds = |timestamp: i64| timestamp as f64 * f64::from(copied.time_base);
+        let Some(time) = packet.pts().or(packet.dts()).map(seconds) else {
+            continue;
+        };
+        if time >= end {
+            copied.done = true;
+            if streams.iter().flatten().all(|copied| copied.done || copied.sparse) {
+                break;
+            }
+            continue;
+        }
+        // sound and subtitles from before the first picture
+        if copied.done || time < from {
+            continue;
+        }
+
+        if stream.index() == video_index && !on_progress(((time - from) / length) as f32) {
+            return Err(ffmpeg::Error::Exit);
+        }
+        // the clip's timeline starts at the keyframe
+        let offset = (from / f64::from(copied.time_base)).round() as i64;
+        packet.set_pts(packet.pts().map(|pts| pts - offset));
+        packet.set_dts(packet.dts().map(|dts| dts - offset));
+        packet.rescale_ts(copied.time_base, copied.output_time_base);
+        packet.set_position(-1);
+        packet.set_stream(copied.output_index);
+        packet.write_interleaved(&mut octx)?;
+    }
+
+    octx.write_trailer()?;
+    on_progress(1.);
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Copied {
+    output_index: usize,
+    time_base: ffmpeg::Rational,
+    output_time_base: ffmpeg::Rational,
+    // subtitles can go quiet for minutes, they don't hold up the end
+    sparse: bool,
+    // past the end of the range
+    done: bool,
+}
@@ -0,0 +1,380 @@
+// exports handed to other machines through a shared folder. the workstation writes a
+// json file per clip into "pending"; a worker started with `--export-worker <folder>`
+// claims one by moving it into "claimed", makes the clip into "done/<id>" and writes how
+// it went to "done/<id>.json". moving a file is atomic on one file system, so two workers
+// never make the same clip. a worker keeps touching its claim while it works, claims
+// nobody touched for a while are from a worker that died and go back to "pending"
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{export_clip, Clip, ExportProfile};
+use crate::vertical_crop::CropTrack;
+
+const WORKER_FLAG: &str = "--export-worker";
+// `--export-map <from>=<to>`, for workers that see the videos under another path
+const MAP_FLAG: &str = "--export-map";
+const PENDING: &str = "pending";
+const CLAIMED: &str = "claimed";
+const DONE: &str = "done";
+// how often an idle worker looks for new jobs
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+// how often a claim is touched while its clip is being made, and how long one that isn't
+// is left before it's made again
+const CLAIM_RENEWAL: Duration = Duration::from_secs(60);
+const CLAIM_LEASE: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedJob {
+    // also the file name, which is what workers go by
+    pub id: String,
+    // as the workstation sees it
+    pub video: PathBuf,
+    pub label: String,
+    pub start: Duration,
+    pub end: Duration,
+    #[serde(default)]
+    pub crop: CropTrack,
+    pub profile: ExportProfile,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedResult {
+    pub id: String,
+    pub worker: String,
+    // file names in "done/<id>"
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// how many jobs of a shared folder are where
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueStatus {
+    pub pending: usize,
+    pub claimed: usize,
+    pub done: usize,
+    pub failed: usize,
+}
+
+impl QueueStatus {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} waiting, {} being made, {} done, {} failed",
+            self.pending, self.claimed, self.done, self.failed
+        )
+    }
+}
+
+fn json_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect::<Vec<_>>();
+    // oldest first, ids start with when they were sent
+    files.sort();
+    files
+}
+
+// written under a temporary name first, so nobody reads half a job
+fn write_json(path: &Path, value: &impl Serialize) -> std::io::Result<()> {
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, serde_json::to_vec_pretty(value)?)?;
+    std::fs::rename(&partial, path)
+}
+
+pub fn submit(queue: &Path, jobs: Vec<SharedJob>) -> std::io::Result<usize> {
+    let pending = queue.join(PENDING);
+    std::fs::create_dir_all(&pending)?;
+    let count = jobs.len();
+    for job in jobs {
+        write_json(&pending.join(format!("{}.json", job.id)), &job)?;
+    }
+    Ok(count)
+}
+
+// what `job_id` makes, and nothing that could lead out of the queue's folders when
+// it's used as a file name
+fn is_job_id(id: &str) -> bool {
+    !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_digit() || byte == b'-')
+}
+
+// unique enough across machines sending to the same folder at once
+pub fn job_id(idx: usize) -> String {
+    let sent = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!("{sent}-{}-{idx}", std::process::id())
+}
+
+pub fn status(queue: &Path) -> QueueStatus {
+    let mut status = QueueStatus {
+        pending: json_files(&queue.join(PENDING)).len(),
+        claimed: json_files(&queue.join(CLAIMED)).len(),
+        ..QueueStatus::default()
+    };
+    for path in json_files(&queue.join(DONE)) {
+        let result = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<SharedResult>(&bytes).ok());
+        match result {
+            Some(SharedResult { error: None, .. }) => status.done += 1,
+            _ => status.failed += 1,
+        }
+    }
+    status
+}
+
+// a worker that keeps making clips from the folder until the process is stopped
+#[derive(Debug, Clone)]
+pub struct ExportWorker {
+    queue: PathBuf,
+    map: Option<(PathBuf, PathBuf)>,
+    name: String,
+}
+
+impl ExportWorker {
+    pub fn from_args() -> Option<Self> {
+        let mut queue = None;
+        let mut map = None;
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == WORKER_FLAG {
+                queue = args.next().map(PathBuf::from);
+            } else if arg == MAP_FLAG {
+                map = args.next().and_then(|map| {
+                    let (from, to) = map.split_once('=')?;
+                    Some((PathBuf::from(from), PathBuf::from(to)))
+                });
+            }
+        }
+        let name = std::env::var("HOSTNAME")
+            .or_else(|_| std::env::var("COMPUTERNAME"))
+            .unwrap_or_else(|_| String::from("worker"));
+        Some(Self {
+            queue: queue?,
+            map,
+            name: format!("{name}-{}", std::process::id()),
+        })
+    }
+
+    // only fails when the folders can't be made, a job that fails is logged and the
+    // worker carries on with the next
+    pub fn run(&self) -> std::io::Result<()> {
+        for dir in [PENDING, CLAIMED, DONE] {
+            std::fs::create_dir_all(self.queue.join(dir))?;
+        }
+        tracing::info!("{} making exports from {}", self.name, self.queue.display());
+        loop {
+            self.requeue_stale();
+            match self.claim() {
+                Ok(Some((claimed, job))) => {
+                    if let Err(err) = self.make(&claimed, job) {
+                        let claimed = claimed.display();
+                        tracing::error!("{} failed to make {claimed}: {err}", self.name);
+                    }
+                }
+                Ok(None) => std::thread::sleep(POLL_INTERVAL),
+                Err(err) => {
+                    tracing::error!("{} failed to claim a job: {err}", self.name);
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+            }
+        }
+    }
+
+    // the oldest pending job nobody else got to first
+    fn claim(&self) -> std::io::Result<Option<(PathBuf, SharedJob)>> {
+        for pending in json_files(&self.queue.join(PENDING)) {
+            let id = pending.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+            if !is_job_id(id) {
+                tracing::warn!("skipping job with an invalid name {}", pending.display());
+                std::fs::remove_file(&pending)?;
+                continue;
+            }
+            let id = id.to_string();
+
+            // fresh before it's moved, or someone could take it for a stale claim
+            renew(&pending);
+            let claimed = self.queue.join(CLAIMED).join(format!("{id}.json"));
+            match std::fs::rename(&pending, &claimed) {
+                Ok(()) => {}
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            }
+            let job = std::fs::read(&claimed)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<SharedJob>(&bytes).ok());
+            match job {
+                Some(job) => return Ok(Some((claimed, SharedJob { id, ..job }))),
+                None => {
+                    tracing::warn!("skipping unreadable job {}", claimed.display());
+                    std::fs::remove_file(&claimed)?;
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // puts claims back that haven't been touched within the lease
+    fn requeue_stale(&self) {
+        for claimed in json_files(&self.queue.join(CLAIMED)) {
+            let age = std::fs::metadata(&claimed)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok());
+            if !age.is_some_and(|age| age >= CLAIM_LEASE) {
+                continue;
+            }
+            let pending = self.queue.join(PENDING).join(claimed.file_name().unwrap_or_default());
+            match std::fs::rename(&claimed, &pending) {
+                Ok(()) => tracing::info!("{} requeued stale {}", self.name, pending.display()),
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => tracing::error!("failed to requeue {}: {err}", claimed.display()),
+            }
+        }
+    }
+
+    fn make(&self, claimed: &Path, job: SharedJob) -> std::io::Result<()> {
+        let video = match &self.map {
+            Some((from, to)) => match job.video.strip_prefix(from) {
+                Ok(rest) => to.join(rest),
+                Err(_) => job.video.clone(),
+            },
+            None => job.video.clone(),
+        };
+        let folder = self.queue.join(DONE).join(&job.id);
+        std::fs::create_dir_all(&folder)?;
+        let clip = Clip::of_range(video, &job.label, (job.start, job.end), job.crop, &folder);
+
+        tracing::info!("{} making {}", self.name, clip.output.display());
+        let mut renewed = Instant::now();
+        let exported = export_clip(&clip, &job.profile, |_| {
+            if renewed.elapsed() >= CLAIM_RENEWAL {
+                renew(claimed);
+                renewed = Instant::now();
+            }
+            true
+        });
+        let result = SharedResult {
+            id: job.id.clone(),
+            worker: self.name.clone(),
+            outputs: exported
+                .as_ref()
+                .map(|exported| {
+                    exported
+                        .outputs
+                        .iter()
+                        .filter_map(|output| output.file_name())
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            error: exported.err().map(|err| err.to_string()),
+        };
+        if let Some(err) = &result.error {
+            tracing::error!("{} failed to make {}: {err}", self.name, job.id);
+        }
+        write_json(&self.queue.join(DONE).join(format!("{}.json", job.id)), &result)?;
+        std::fs::remove_file(claimed)
+    }
+}
+
+// marks a claim as still being worked on
+fn renew(path: &Path) {
+    let touched = std::fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+    match touched {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => tracing::error!("failed to renew the claim on {}: {err}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn worker(name: &str) -> ExportWorker {
+        let queue = std::env::temp_dir()
+            .join(format!("export-queue-{name}-{}", std::process::id()));
+        for dir in [PENDING, CLAIMED, DONE] {
+            std::fs::create_dir_all(queue.join(dir)).unwrap();
+        }
+        ExportWorker {
+            queue,
+            map: None,
+            name: String::from("test"),
+        }
+    }
+
+    fn job(id: &str) -> SharedJob {
+        SharedJob {
+            id: id.to_string(),
+            video: PathBuf::from("match.mp4"),
+            label: String::from("goal"),
+            start: Duration::from_secs(1),
+            end: Duration::from_secs(2),
+            crop: CropTrack::default(),
+            profile: ExportProfile::defaults().remove(0),
+        }
+    }
+
+    #[test]
+    fn job_ids() {
+        assert!(is_job_id(&job_id(3)));
+        for id in ["", "..", "../../etc", "1-2/3", "12.json"] {
+            assert!(!is_job_id(id), "{id}");
+        }
+    }
+
+    #[test]
+    fn the_file_name_is_the_id() {
+        let worker = worker("claim");
+        let pending = worker.queue.join(PENDING);
+        write_json(&pending.join("17-1-0.json"), &job("../../outside")).unwrap();
+        write_json(&pending.join("..json"), &job("2-1-0")).unwrap();
+
+        let claimed = worker.claim().unwrap();
+        let leftover = json_files(&pending);
+        std::fs::remove_dir_all(&worker.queue).unwrap();
+        let (path, job) = claimed.unwrap();
+        assert_eq!(job.id, "17-1-0");
+        assert_eq!(path.file_name().unwrap(), "17-1-0.json");
+        assert!(leftover.is_empty());
+    }
+
+    #[test]
+    fn stale_claims_go_back() {
+        let worker = worker("stale");
+        let stale = worker.queue.join(CLAIMED).join("1-1-0.json");
+        let fresh = worker.queue.join(CLAIMED).join("2-1-0.json");
+        write_json(&stale, &job("1-1-0")).unwrap();
+        write_json(&fresh, &job("2-1-0")).unwrap();
+        let long_ago = SystemTime::now() - CLAIM_LEASE - Duration::from_secs(1);
+        std::fs::File::options()
+            .write(true)
+            .open(&stale)
+            .and_then(|file| file.set_modified(long_ago))
+            .unwrap();
+
+        worker.requeue_stale();
+        let pending = json_files(&worker.queue.join(PENDING));
+        let claimed = json_files(&worker.queue.join(CLAIMED));
+        std::fs::remove_dir_all(&worker.queue).unwrap();
+        assert_eq!(pending, [worker.queue.join(PENDING).join("1-1-0.json")]);
+        assert_eq!(claimed, [fresh]);
+    }
+}
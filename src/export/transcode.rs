@@ -0,0 +1,644 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use ffmpeg::format::context::{Input, Output};
+use ffmpeg_next as ffmpeg;
+
+use super::{Clip, ExportProfile, Extra, Framing, Preset, Quality};
+use crate::media::open_input;
+use crate::vertical_crop::VERTICAL_SIZE;
+
+const AUDIO_RATE: i32 = 48_000;
+pub(super) const AUDIO_BIT_RATE: usize = 192_000;
+// what loudnorm holds peaks under and how much the loudness may swing, the usual for
+// streaming
+const TRUE_PEAK: f32 = -1.5;
+const LOUDNESS_RANGE: f32 = 11.;
+// previews are for a look, not for keeping
+const PREVIEW_HEIGHT: u32 = 480;
+const PREVIEW_QUALITY: Quality = Quality::Crf(28);
+const GIF_WIDTH: u32 = 480;
+const GIF_RATE: i32 = 12;
+
+// one file made out of the decoded range, the profile's own or one of its extras
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Target {
+    pub output: PathBuf,
+    pub extra: Option<Extra>,
+}
+
+// which run of the profile's own file this is. the first of two writes what x264 made
+// of the frames to `stats`, for the second to spread the bitrate by. extras always
+// come out of a single pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Pass<'a> {
+    Only,
+    First(&'a Path),
+    Second(&'a Path),
+}
+
+// decodes the range once and puts the frames through every target's filters and
+// encoders: h264 with aac sound, or a gif. `on_progress` hears how far along each
+// target is
+pub(super) fn transcode(
+    clip: &Clip,
+    targets: &[Target],
+    profile: &ExportProfile,
+    pass: Pass<'_>,
+    on_progress: &mut dyn FnMut(&[f32]) -> bool,
+) -> Result<(), ffmpeg::Error> {
+    let mut ictx = open_input(&clip.video)?;
+    let mut video = VideoDecode::new(&ictx)?;
+    let mut audio = match ictx.streams().best(ffmpeg::media::Type::Audio) {
+        Some(stream) => Some(AudioDecode::new(&stream)?),
+        None => None,
+    };
+    let mut files = targets
+        .iter()
+        .map(|target| File::new(target, clip, profile, pass, &video, audio.is_some()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let target = (clip.start.as_secs_f64() * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+    ictx.seek(target, ..=target)?;
+
+    let length = (clip.end - clip.start).as_secs_f64().max(f64::EPSILON);
+    for (stream, packet) in ictx.packets() {
+        if stream.index() == video.input_index {
+            video.decoder.send_packet(&packet)?;
+            video.receive(clip, &mut files)?;
+            let progress = files
+                .iter()
+                .map(|file| (file.video.written.as_secs_f64() / length).min(1.) as f32)
+                .collect::<Vec<_>>();
+            if !on_progress(&progress) {
+                return Err(ffmpeg::Error::Exit);
+            }
+            if video.done && audio.as_ref().is_none_or(|audio| audio.done) {
+                break;
+            }
+        } else if let Some(audio) = audio
+            .as_mut()
+            .filter(|audio| audio.input_index == stream.index())
+        {
+            audio.decoder.send_packet(&packet)?;
+            audio.receive(clip, &mut files)?;
+            if video.done && audio.done {
+                break;
+            }
+        }
+    }
+
+    if !video.done {
+        video.decoder.send_eof()?;
+        video.receive(clip, &mut files)?;
+    }
+    if let Some(audio) = audio.as_mut().filter(|audio| !audio.done) {
+        audio.decoder.send_eof()?;
+        audio.receive(clip, &mut files)?;
+    }
+    for file in &mut files {
+        file.finish()?;
+    }
+    on_progress(&vec![1.; files.len()]);
+    Ok(())
+}
+
+// from where a frame was in the source to where it goes in the clip, none outside of it
+fn clip_timestamp(
+    timestamp: Option<i64>,
+    time_base: ffmpeg::Rational,
+    clip: &Clip,
+) -> (Option<i64>, bool) {
+    let Some(timestamp) = timestamp else {
+        return (None, false);
+    };
+    let seconds = timestamp as f64 * f64::from(time_base);
+    if seconds >= clip.end.as_secs_f64() {
+        return (None, true);
+    }
+    let start = (clip.start.as_secs_f64() / f64::from(time_base)).round() as i64;
+    ((timestamp >= start).then_some(timestamp - start), false)
+}
+
+// one target being written
+struct File {
+    octx: Output,
+    video: VideoEncode,
+    // gifs have no sound
+    audio: Option<AudioEncode>,
+}
+
+impl File {
+    fn new(
+        target: &Target,
+        clip: &Clip,
+        profile: &ExportProfile,
+        pass: Pass<'_>,
+        video: &VideoDecode,
+        has_audio: bool,
+    ) -> Result<Self, ffmpeg::Error> {
+        let mut octx = ffmpeg::format::output(&target.output)?;
+        let global_header = octx
+            .format()
+            .flags()
+            .contains(ffmpeg::format::Flags::GLOBAL_HEADER);
+
+        let is_gif = target.extra == Some(Extra::Gif);
+        let pass = if target.extra.is_none() { pass } else { Pass::Only };
+        let mut video = VideoEncode::new(
+            &mut octx,
+            target.extra,
+            clip,
+            profile,
+            pass,
+            video,
+            global_header,
+        )?;
+        let mut audio = if has_audio && !is_gif {
+            Some(AudioEncode::new(&mut octx, profile.loudness, global_header)?)
+        } else {
+            None
+        };
+
+        // the phone has the file playing before it's all downloaded
+        let mut options = ffmpeg::Dictionary::new();
+        if !is_gif {
+            options.set("movflags", "+faststart");
+        }
+        octx.write_header_with(options)?;
+        video.output_time_base = octx
+            .stream(video.output_index)
+            .expect("added above")
+            .time_base();
+        if let Some(audio) = &mut audio {
+            audio.output_time_base = octx
+                .stream(audio.output_index)
+                .expect("added above")
+                .time_base();
+        }
+        Ok(Self { octx, video, audio })
+    }
+
+    fn finish(&mut self) -> Result<(), ffmpeg::Error> {
+        self.video.finish(&mut self.octx)?;
+        if let Some(audio) = &mut self.audio {
+            audio.finish(&mut self.octx)?;
+        }
+        self.octx.write_trailer()
+    }
+}
+
+struct VideoDecode {
+    input_index: usize,
+    decoder: ffmpeg::decoder::Video,
+    time_base: ffmpeg::Rational,
+    frame_rate: ffmpeg::Rational,
+    done: bool,
+}
+
+impl VideoDecode {
+    fn new(ictx: &Input) -> Result<Self, ffmpeg::Error> {
+        let stream = ictx
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .ok_or(ffmpeg::Error::StreamNotFound)?;
+        let frame_rate = match stream.avg_frame_rate() {
+            rate if rate.numerator() > 0 && rate.denominator() > 0 => rate,
+            _ => ffmpeg::Rational::new(30, 1),
+        };
+        let decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+            .decoder()
+            .video()?;
+        Ok(Self {
+            input_index: stream.index(),
+            decoder,
+            time_base: stream.time_base(),
+            frame_rate,
+            done: false,
+        })
+    }
+
+    // hands every frame of the range to every file
+    fn receive(&mut self, clip: &Clip, files: &mut [File]) -> Result<(), ffmpeg::Error> {
+        let mut frame = ffmpeg::util::frame::Video::empty();
+        while !self.done && self.decoder.receive_frame(&mut frame).is_ok() {
+            let (timestamp, past) = clip_timestamp(frame.timestamp(), self.time_base, clip);
+            self.done = past;
+            let Some(timestamp) = timestamp else {
+                continue;
+            };
+            frame.set_pts(Some(timestamp));
+            for file in files.iter_mut() {
+                file.video.add(&frame, &mut file.octx)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct VideoEncode {
+    output_index: usize,
+    graph: ffmpeg::filter::Graph,
+    encoder: ffmpeg::encoder::Video,
+    time_base: ffmpeg::Rational,
+    output_time_base: ffmpeg::Rational,
+    // how much of the clip is encoded
+    written: Duration,
+}
+
+impl VideoEncode {
+    fn new(
+        octx: &mut Output,
+        extra: Option<Extra>,
+        clip: &Clip,
+        profile: &ExportProfile,
+        pass: Pass<'_>,
+        decode: &VideoDecode,
+        global_header: bool,
+    ) -> Result<Self, ffmpeg::Error> {
+        let decoder = &decode.decoder;
+        // h264 wants even sizes
+        let (width, height) = match profile.framing {
+            Framing::Original => (decoder.width() & !1, decoder.height() & !1),
+            Framing::Vertical => VERTICAL_SIZE,
+        };
+        let picture = match profile.framing {
+            Framing::Original => format!("scale={width}:{height},setsar=1"),
+            Framing::Vertical => format!(
+                "{},scale={width}:{height},setsar=1",
+                clip.crop.filter(clip.start)
+            ),
+        };
+        let picture = match &profile.watermark {
+            Some(watermark) => watermark.overlay(&picture, width),
+            None => format!("[in]{picture}"),
+        };
+
+        // the rest of the graph, the size it comes out at and what encodes it
+        let h264 = profile.encoder.or_software();
+        let h264_format = h264.pixel_name();
+        let (rest, (width, height), codec, quality) = match extra {
+            None => (
+                format!("format={h264_format}"),
+                (width, height),
+                h264.find(),
+                Some((profile.quality, profile.preset)),
+            ),
+            Some(Extra::Preview) => {
+                let preview_height = PREVIEW_HEIGHT.min(height);
+                let preview_width = (width * preview_height / height).max(2) & !1;
+                (
+                    format!("scale={preview_width}:{preview_height},format={h264_format}"),
+                    (preview_width, preview_height),
+                    h264.find(),
+                    Some((PREVIEW_QUALITY, Preset::Fast)),
+                )
+            }
+            // a palette made for the clip's own colors, from all of its frames at the end.
+            // select drops frames without retiming the rest like fps would
+            Some(Extra::Gif) => {
+                let gif_width = GIF_WIDTH.min(width);
+                let gif_height = (height * gif_width / width).max(1);
+                (
+                    format!(
+                        "select='isnan(prev_selected_t)+gte(t-prev_selected_t,{:.4})',\
+                         scale={gif_width}:{gif_height}:flags=lanczos,split[frames][colors];\
+                         [colors]palettegen=stats_mode=diff[palette];\
+                         [frames][palette]paletteuse=dither=bayer",
+                        1. / f64::from(GIF_RATE)
+                    ),
+                    (gif_width, gif_height),
+                    ffmpeg::encoder::find(ffmpeg::codec::Id::GIF),
+                    None,
+                )
+            }
+        };
+        let codec = codec.ok_or(ffmpeg::Error::EncoderNotFound)?;
+        let graph = video_graph(decoder, decode.time_base, &format!("{picture},{rest}[out]"))?;
+
+        let mut output = octx.add_stream(codec)?;
+        let mut encoder = ffmpeg::codec::context::Context::new_with_codec(codec)
+            .encoder()
+            .video()?;
+        encoder.set_width(width);
+        encoder.set_height(height);
+        encoder.set_time_base(decode.time_base);
+        let mut flags = ffmpeg::codec::Flags::empty();
+        let options = match quality {
+            Some((quality, preset)) => {
+                encoder.set_format(h264.pixel());
+                encoder.set_frame_rate(Some(decode.frame_rate));
+                if let Some((bit_rate, max_bit_rate)) = quality.bit_rate() {
+                    encoder.set_bit_rate(bit_rate);
+                    encoder.set_max_bit_rate(max_bit_rate);
+                }
+                let mut options = h264.options(quality, preset);
+                match pass {
+                    Pass::Only => {}
+                    Pass::First(stats) => {
+                        flags |= ffmpeg::codec::Flags::PASS1;
+                        options.set("stats", &stats.to_string_lossy());
+                    }
+                    Pass::Second(stats) => {
+                        flags |= ffmpeg::codec::Flags::PASS2;
+                        options.set("stats", &stats.to_string_lossy());
+                    }
+                }
+                options
+            }
+            None => {
+                encoder.set_format(ffmpeg::format::Pixel::PAL8);
+                encoder.set_frame_rate(Some(ffmpeg::Rational::new(GIF_RATE, 1)));
+                ffmpeg::Dictionary::new()
+            }
+        };
+        if global_header {
+            flags |= ffmpeg::codec::Flags::GLOBAL_HEADER;
+        }
+        encoder.set_flags(flags);
+        let encoder = encoder.open_as_with(codec, options)?;
+        output.set_parameters(&encoder);
+
+        Ok(Self {
+            output_index: output.index(),
+            graph,
+            encoder,
+            time_base: decode.time_base,
+            output_time_base: decode.time_base,
+            written: Duration::ZERO,
+        })
+    }
+
+    fn add(
+        &mut self,
+        frame: &ffmpeg::util::frame::Video,
+        octx: &mut Output,
+    ) -> Result<(), ffmpeg::Error> {
+        self.graph
+            .get("in")
+            .expect("added in build")
+            .source()
+            .add(frame)?;
+        self.drain(octx)
+    }
+
+    // a gif's frames only come out once the palette is made, at the end
+    fn drain(&mut self, octx: &mut Output) -> Result<(), ffmpeg::Error> {
+        let mut filtered = ffmpeg::util::frame::Video::empty();
+        while self
+            .graph
+            .get("out")
+            .expect("added in build")
+            .sink()
+            .frame(&mut filtered)
+            .is_ok()
+        {
+            let pts = filtered.pts().unwrap_or_default();
+            self.written =
+                Duration::from_secs_f64((pts as f64 * f64::from(self.time_base)).max(0.));
+            filtered.set_kind(ffmpeg::picture::Type::None);
+            self.encoder.send_frame(&filtered)?;
+            self.write_packets(octx)?;
+        }
+        Ok(())
+    }
+
+    fn write_packets(&mut self, octx: &mut Output) -> Result<(), ffmpeg::Error> {
+        let mut packet = ffmpeg::Packet::empty();
+        while self.encoder.receive_packet(&mut packet).is_ok() {
+            packet.set_stream(self.output_index);
+            packet.rescale_ts(self.time_base, self.output_time_base);
+            packet.write_interleaved(octx)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, octx: &mut Output) -> Result<(), ffmpeg::Error> {
+        self.graph
+            .get("in")
+            .expect("added in build")
+            .source()
+            .flush()?;
+        self.drain(octx)?;
+        self.encoder.send_eof()?;
+        self.write_packets(octx)
+    }
+}
+
+fn video_graph(
+    decoder: &ffmpeg::decoder::Video,
+    time_base: ffmpeg::Rational,
+    filters: &str,
+) -> Result<ffmpeg::filter::Graph, ffmpeg::Error> {
+    let aspect = match decoder.aspect_ratio() {
+        aspect if aspect.numerator() > 0 => aspect,
+        _ => ffmpeg::Rational::new(1, 1),
+    };
+    let args = format!(
+        "video_size={}x{}:pix_fmt={}:time_base={}/{}:pixel_aspect={}/{}",
+        decoder.width(),
+        decoder.height(),
+        ffmpeg::ffi::AVPixelFormat::from(decoder.format()) as i32,
+        time_base.numerator(),
+        time_base.denominator(),
+        aspect.numerator(),
+        aspect.denominator(),
+    );
+
+    let mut graph = ffmpeg::filter::Graph::new();
+    let buffer = ffmpeg::filter::find("buffer").ok_or(ffmpeg::Error::FilterNotFound)?;
+    let sink = ffmpeg::filter::find("buffersink").ok_or(ffmpeg::Error::FilterNotFound)?;
+    graph.add(&buffer, "in", &args)?;
+    graph.add(&sink, "out", "")?;
+    graph.output("in", 0)?.input("out", 0)?.parse(filters)?;
+    graph.validate()?;
+    Ok(graph)
+}
+
+struct AudioDecode {
+    input_index: usize,
+    decoder: ffmpeg::decoder::Audio,
+    time_base: ffmpeg::Rational,
+    done: bool,
+}
+
+impl AudioDecode {
+    fn new(stream: &ffmpeg::format::stream::Stream<'_>) -> Result<Self, ffmpeg::Error> {
+        let decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+            .decoder()
+            .audio()?;
+        Ok(Self {
+            input_index: stream.index(),
+            decoder,
+            time_base: stream.time_base(),
+            done: false,
+        })
+    }
+
+    fn receive(&mut self, clip: &Clip, files: &mut [File]) -> Result<(), ffmpeg::Error> {
+        let mut frame = ffmpeg::util::frame::Audio::empty();
+        while !self.done && self.decoder.receive_frame(&mut frame).is_ok() {
+            let (timestamp, past) = clip_timestamp(frame.timestamp(), self.time_base, clip);
+            self.done = past;
+            let Some(timestamp) = timestamp else {
+                continue;
+            };
+            // the graph counts in samples
+            let seconds = timestamp as f64 * f64::from(self.time_base);
+            frame.set_pts(Some((seconds * f64::from(frame.rate())).round() as i64));
+            for file in files.iter_mut() {
+                if let Some(audio) = &mut file.audio {
+                    audio.add(&frame, &mut file.octx)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct AudioEncode {
+    output_index: usize,
+    // built from the first frame, whose layout the stream's parameters may not have
+    graph: Option<ffmpeg::filter::Graph>,
+    encoder: ffmpeg::encoder::Audio,
+    output_time_base: ffmpeg::Rational,
+    loudness: Option<f32>,
+}
+
+impl AudioEncode {
+    fn new(
+        octx: &mut Output,
+        loudness: Option<f32>,
+        global_header: bool,
+    ) -> Result<Self, ffmpeg::Error> {
+        let codec =
+            ffmpeg::encoder::find(ffmpeg::codec::Id::AAC).ok_or(ffmpeg::Error::EncoderNotFound)?;
+        let mut output = octx.add_stream(codec)?;
+        let mut encoder = ffmpeg::codec::context::Context::new_with_codec(codec)
+            .encoder()
+            .audio()?;
+        encoder.set_rate(AUDIO_RATE);
+        encoder.set_channel_layout(ffmpeg::ChannelLayout::STEREO);
+        encoder.set_format(ffmpeg::format::Sample::F32(
+            ffmpeg::format::sample::Type::Planar,
+        ));
+        encoder.set_bit_rate(AUDIO_BIT_RATE);
+        encoder.set_time_base(ffmpeg::Rational::new(1, AUDIO_RATE));
+        if global_header {
+            encoder.set_flags(ffmpeg::codec::Flags::GLOBAL_HEADER);
+        }
+        let encoder = encoder.open_as(codec)?;
+        output.set_parameters(&encoder);
+
+        Ok(Self {
+            output_index: output.index(),
+            graph: None,
+            encoder,
+            output_time_base: ffmpeg::Rational::new(1, AUDIO_RATE),
+            loudness,
+        })
+    }
+
+    fn add(
+        &mut self,
+        frame: &ffmpeg::util::frame::Audio,
+        octx: &mut Output,
+    ) -> Result<(), ffmpeg::Error> {
+        if self.graph.is_none() {
+            let frame_size = self.encoder.frame_size();
+            self.graph = Some(audio_graph(frame, frame_size, self.loudness)?);
+        }
+        let graph = self.graph.as_mut().expect("built above");
+        graph
+            .get("in")
+            .expect("added in build")
+            .source()
+            .add(frame)?;
+        self.drain(octx)
+    }
+
+    fn drain(&mut self, octx: &mut Output) -> Result<(), ffmpeg::Error> {
+        let Some(graph) = self.graph.as_mut() else {
+            return Ok(());
+        };
+        let mut filtered = ffmpeg::util::frame::Audio::empty();
+        while graph
+            .get("out")
+            .expect("added in build")
+            .sink()
+            .frame(&mut filtered)
+            .is_ok()
+        {
+            self.encoder.send_frame(&filtered)?;
+            self.write_packets(octx)?;
+        }
+        Ok(())
+    }
+
+    fn write_packets(&mut self, octx: &mut Output) -> Result<(), ffmpeg::Error> {
+        let mut packet = ffmpeg::Packet::empty();
+        while self.encoder.receive_packet(&mut packet).is_ok() {
+            packet.set_stream(self.output_index);
+            packet.rescale_ts(ffmpeg::Rational::new(1, AUDIO_RATE), self.output_time_base);
+            packet.write_interleaved(octx)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, octx: &mut Output) -> Result<(), ffmpeg::Error> {
+        if let Some(graph) = self.graph.as_mut() {
+            graph.get("in").expect("added in build").source().flush()?;
+        }
+        self.drain(octx)?;
+        self.encoder.send_eof()?;
+        self.write_packets(octx)
+    }
+}
+
+// whatever the source has, as the stereo 48khz the encoder takes, in frames of the
+// size it wants. with a `loudness` target loudnorm evens it out first, in one pass: it
+// works out the loudness as it goes rather than measuring the whole clip beforehand
+fn audio_graph(
+    frame: &ffmpeg::util::frame::Audio,
+    frame_size: u32,
+    loudness: Option<f32>,
+) -> Result<ffmpeg::filter::Graph, ffmpeg::Error> {
+    let layout = if frame.channel_layout().is_empty() {
+        ffmpeg::ChannelLayout::default(i32::from(frame.channels()))
+    } else {
+        frame.channel_layout()
+    };
+    let args = format!(
+        "time_base=1/{rate}:sample_rate={rate}:sample_fmt={}:channel_layout=0x{:x}",
+        frame.format().name(),
+        layout.bits(),
+        rate = frame.rate(),
+    );
+
+    let mut graph = ffmpeg::filter::Graph::new();
+    let buffer = ffmpeg::filter::find("abuffer").ok_or(ffmpeg::Error::FilterNotFound)?;
+    let sink = ffmpeg::filter::find("abuffersink").ok_or(ffmpeg::Error::FilterNotFound)?;
+    graph.add(&buffer, "in", &args)?;
+    graph.add(&sink, "out", "")?;
+    // loudnorm takes targets from -70 to -5 and puts out 192khz, aresample brings it back
+    let normalize = loudness.map_or(String::new(), |target| {
+        format!(
+            "loudnorm=I={:.1}:TP={TRUE_PEAK:.1}:LRA={LOUDNESS_RANGE:.1},",
+            target.clamp(-70., -5.)
+        )
+    });
+    graph.output("in", 0)?.input("out", 0)?.parse(&format!(
+        "{normalize}aresample={AUDIO_RATE},aformat=sample_fmts=fltp:channel_layouts=stereo"
+    ))?;
+    graph.validate()?;
+    if frame_size > 0 {
+        graph
+            .get("out")
+            .expect("added above")
+            .sink()
+            .set_frame_size(frame_size);
+    }
+    Ok(graph)
+}
@@ -0,0 +1,105 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+// space between the mark and the edges of the picture, of its width
+const MARGIN: f32 = 0.025;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+impl Corner {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::TopLeft => "top left",
+            Self::TopRight => "top right",
+            Self::BottomLeft => "bottom left",
+            Self::BottomRight => "bottom right",
+            Self::Center => "center",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::TopLeft => Self::TopRight,
+            Self::TopRight => Self::BottomRight,
+            Self::BottomRight => Self::BottomLeft,
+            Self::BottomLeft => Self::Center,
+            Self::Center => Self::TopLeft,
+        }
+    }
+
+    // overlay's x and y, W and H the picture's size, w and h the mark's
+    fn position(self, margin: u32) -> String {
+        match self {
+            Self::TopLeft => format!("{margin}:{margin}"),
+            Self::TopRight => format!("W-w-{margin}:{margin}"),
+            Self::BottomLeft => format!("{margin}:H-h-{margin}"),
+            Self::BottomRight => format!("W-w-{margin}:H-h-{margin}"),
+            Self::Center => String::from("(W-w)/2:(H-h)/2"),
+        }
+    }
+}
+
+// an image burned into every exported frame, a logo or a channel name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Watermark {
+    pub image: PathBuf,
+    #[serde(default)]
+    pub corner: Corner,
+    // 0 leaves it out, 1 covers what's under it
+    pub opacity: f32,
+    // its width over the clip's, the height follows the image
+    pub scale: f32,
+}
+
+impl Watermark {
+    pub fn new(image: PathBuf) -> Self {
+        Self {
+            image,
+            corner: Corner::default(),
+            opacity: 0.8,
+            scale: 0.15,
+        }
+    }
+
+    // a graph putting the mark over what `picture` makes of the frames, for a clip
+    // `width` wide. its last filter is left open for more to follow. the image is read
+    // by the movie source, a still comes out as one frame that overlay keeps showing
+    pub(super) fn overlay(&self, picture: &str, width: u32) -> String {
+        let mark_width = ((width as f32 * self.scale.clamp(0.01, 1.)).round() as u32).max(1);
+        let margin = (width as f32 * MARGIN).round() as u32;
+        format!(
+            "[in]{picture}[clip];\
+             movie={image},format=rgba,scale={mark_width}:-1,\
+             colorchannelmixer=aa={opacity:.3}[mark];\
+             [clip][mark]overlay={position}:format=auto",
+            image = escape(&self.image),
+            opacity = self.opacity.clamp(0., 1.),
+            position = self.corner.position(margin),
+        )
+    }
+}
+
+// a path escaped once as the movie source's option and again for the graph around it
+fn escape(path: &Path) -> String {
+    let escape_with = |value: &str, special: &[char]| {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            if c == '\\' || special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    let option = escape_with(&path.to_string_lossy(), &['\'', ':']);
+    escape_with(&option, &['\'', '[', ']', ',', ';'])
+}
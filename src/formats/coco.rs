@@ -0,0 +1,200 @@
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+use serde_json::{json, Value};
+
+use crate::regions::Polygon;
+use crate::skeletons::Skeleton;
+use crate::tags::Tag;
+
+// outlines in the COCO instance segmentation layout. every keyframed outline is an
+// annotation on the image of its frame, <video stem>-<frame>.png with frames
+// counted from 0. categories count from 1, labels without a category come last
+pub fn write_coco(
+    tags: &[Tag],
+    categories: &[String],
+    video: &Path,
+    fps: f64,
+    (width, height): (u32, u32),
+) -> String {
+    let frame_of = |time: Duration| (time.as_secs_f64() * fps).round() as u64;
+    let stem = video
+        .file_stem()
+        .map_or_else(|| String::from("video"), |stem| stem.to_string_lossy().into_owned());
+    let (width_px, height_px) = (width as f32, height as f32);
+
+    let mut names = categories.to_vec();
+    for tag in tags {
+        if !tag.polygons.is_empty() && !names.contains(&tag.label) {
+            names.push(tag.label.clone());
+        }
+    }
+
+    // image ids by frame, in frame order
+    let mut images = BTreeMap::new();
+    let mut annotations = Vec::new();
+    for tag in tags {
+        let Some(category) = names.iter().position(|name| *name == tag.label) else {
+            continue;
+        };
+        for keyframe in tag.polygons.keyframes() {
+            let polygon = &keyframe.polygon;
+            if !polygon.is_valid() {
+                continue;
+            }
+            let frame = frame_of(keyframe.time);
+            let next_id = images.len() + 1;
+            let image = *images.entry(frame).or_insert(next_id);
+
+            let segmentation = polygon
+                .points
+                .iter()
+                .flat_map(|&(x, y)| [x * width_px, y * height_px])
+                .collect::<Vec<_>>();
+            let bounds = polygon.bounds();
+            annotations.push(json!({
+                "id": annotations.len() + 1,
+                "image_id": image,
+                "category_id": category + 1,
+                "segmentation": [segmentation],
+                "bbox": [
+                    bounds.x * width_px,
+                    bounds.y * height_px,
+                    bounds.width * width_px,
+                    bounds.height * height_px,
+                ],
+                "area": polygon.area() * width_px * height_px,
+                "iscrowd": 0,
+            }));
+        }
+    }
+
+    let images = images
+        .into_iter()
+        .map(|(frame, id)| {
+            json!({
+                "id": id,
+                "file_name": format!("{stem}-{frame:06}.png"),
+                "width": width,
+                "height": height,
+            })
+        })
+        .collect::<Vec<_>>();
+    let categories = names
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| json!({ "id": idx + 1, "name": name, "supercategory": "" }))
+        .collect::<Vec<_>>();
+
+    let coco: Value = json!({
+        "info": { "description": format!("outlines of {stem}") },
+        "images": images,
+        "annotations": annotations,
+        "categories": categories,
+    });
+    coco.to_string()
+}
+
+// poses in the COCO keypoints layout, one category per skeleton with its keypoint
+// names and 1 based bones. images are named like `write_coco` names them. keypoints
+// that weren't placed are (0, 0, 0), placed ones are visible (2)
+pub fn write_coco_keypoints(
+    tags: &[Tag],
+    skeletons: &[Skeleton],
+    video: &Path,
+    fps: f64,
+    (width, height): (u32, u32),
+) -> String {
+    let frame_of = |time: Duration| (time.as_secs_f64() * fps).round() as u64;
+    let stem = video
+        .file_stem()
+        .map_or_else(|| String::from("video"), |stem| stem.to_string_lossy().into_owned());
+    let (width_px, height_px) = (width as f32, height as f32);
+
+    let mut images = BTreeMap::new();
+    let mut annotations = Vec::new();
+    for (track, tag) in tags.iter().enumerate() {
+        for keyframe in tag.poses.keyframes() {
+            let pose = &keyframe.pose;
+            let Some(category) = skeletons
+                .iter()
+                .position(|skeleton| skeleton.name == pose.skeleton)
+            else {
+                continue;
+            };
+            if pose.is_empty() {
+                continue;
+            }
+            let frame = frame_of(keyframe.time);
+            let next_id = images.len() + 1;
+            let image = *images.entry(frame).or_insert(next_id);
+
+            let keypoints = pose
+                .points
+                .iter()
+                .flat_map(|point| match point {
+                    Some((x, y)) => [x * width_px, y * height_px, 2.],
+                    None => [0., 0., 0.],
+                })
+                .collect::<Vec<_>>();
+            let bounds = Polygon {
+                points: pose.placed().collect(),
+            }
+            .bounds();
+            let bbox = [
+                bounds.x * width_px,
+                bounds.y * height_px,
+                bounds.width * width_px,
+                bounds.height * height_px,
+            ];
+            annotations.push(json!({
+                "id": annotations.len() + 1,
+                "image_id": image,
+                "category_id": category + 1,
+                "keypoints": keypoints,
+                "num_keypoints": pose.placed().count(),
+                "bbox": bbox,
+                "area": bbox[2] * bbox[3],
+                "iscrowd": 0,
+                // the same tag across frames, for tools that track poses
+                "track_id": track + 1,
+            }));
+        }
+    }
+
+    let images = images
+        .into_iter()
+        .map(|(frame, id)| {
+            json!({
+                "id": id,
+                "file_name": format!("{stem}-{frame:06}.png"),
+                "width": width,
+                "height": height,
+            })
+        })
+        .collect::<Vec<_>>();
+    let categories = skeletons
+        .iter()
+        .enumerate()
+        .map(|(idx, skeleton)| {
+            json!({
+                "id": idx + 1,
+                "name": skeleton.name,
+                "supercategory": skeleton.name,
+                "keypoints": skeleton.keypoints,
+                "skeleton": skeleton
+                    .edges
+                    .iter()
+                    .map(|(a, b)| [a + 1, b + 1])
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let coco: Value = json!({
+        "info": { "description": format!("poses in {stem}") },
+        "images": images,
+        "annotations": annotations,
+        "categories": categories,
+    });
+    coco.to_string()
+}
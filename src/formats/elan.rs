@@ -0,0 +1,226 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::{file_url, invalid_data, resolve_location, xml_escape, Marker};
+use crate::activity::format_day;
+use crate::tags::Tag;
+
+// elan won't take zero length annotations, instant tags get one frame at 25 fps.
+// annotations this short come back as instant tags
+const INSTANT_LENGTH: Duration = Duration::from_millis(40);
+
+// every time-aligned annotation of a .eaf, labelled with its tier and noted with its
+// value. the media the document describes gets the tags if it exists here, otherwise
+// the open video
+pub fn read_eaf(contents: &str, base: &Path) -> std::io::Result<Vec<Marker>> {
+    let document = roxmltree::Document::parse(contents).map_err(invalid_data)?;
+
+    // documents moved between machines usually only have a working relative url
+    let video = document
+        .descendants()
+        .filter(|node| node.has_tag_name("MEDIA_DESCRIPTOR"))
+        .flat_map(|node| [node.attribute("MEDIA_URL"), node.attribute("RELATIVE_MEDIA_URL")])
+        .flatten()
+        .map(|url| resolve_location(url, base))
+        .find(|path| path.exists());
+
+    // unaligned slots have no TIME_VALUE, annotations using them are skipped
+    let slots = document
+        .descendants()
+        .filter(|node| node.has_tag_name("TIME_SLOT"))
+        .filter_map(|slot| {
+            let millis = slot.attribute("TIME_VALUE")?.parse::<u64>().ok()?;
+            Some((slot.attribute("TIME_SLOT_ID")?, Duration::from_millis(millis)))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut markers = Vec::new();
+    for tier in document.descendants().filter(|node| node.has_tag_name("TIER")) {
+        let Some(label) = tier.attribute("TIER_ID") else {
+            continue;
+        };
+        for annotation in tier
+            .descendants()
+            .filter(|node| node.has_tag_name("ALIGNABLE_ANNOTATION"))
+        {
+            let slot = |name: &str| {
+                annotation
+                    .attribute(name)
+                    .and_then(|id| slots.get(id))
+                    .copied()
+            };
+            let (Some(start), Some(end)) = (slot("TIME_SLOT_REF1"), slot("TIME_SLOT_REF2")) else {
+                continue;
+            };
+            // tags without a note are written with their label as the value
+            let note = annotation
+                .children()
+                .find(|node| node.has_tag_name("ANNOTATION_VALUE"))
+                .and_then(|value| value.text())
+                .map(str::trim)
+                .filter(|value| *value != label)
+                .unwrap_or_default();
+            markers.push(Marker {
+                video: video.clone(),
+                time: start.min(end),
+                end: (start.abs_diff(end) > INSTANT_LENGTH).then(|| start.max(end)),
+                label: label.to_string(),
+                note: note.to_string(),
+            });
+        }
+    }
+
+    Ok(markers)
+}
+
+fn mime_type(video: &Path) -> &'static str {
+    let extension = video
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("mp4" | "m4v") => "video/mp4",
+        Some("mpg" | "mpeg") => "video/mpeg",
+        Some("mov") => "video/quicktime",
+        Some("wav") => "audio/x-wav",
+        _ => "unknown",
+    }
+}
+
+// one tier per category, in the ui's order, then one per label without a category.
+// annotations carry the note, or the label when there is none, so they read sensibly
+// in elan's annotation view
+pub fn write_eaf(tags: &[Tag], categories: &[String], video: &Path) -> String {
+    let mut tiers = categories.to_vec();
+    for tag in tags {
+        if !tiers.contains(&tag.label) {
+            tiers.push(tag.label.clone());
+        }
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let seconds_of_day = now % (24 * 60 * 60);
+    let date = format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        format_day(now / (24 * 60 * 60)),
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    );
+
+    let mut eaf = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<ANNOTATION_DOCUMENT AUTHOR=\"\" DATE=\"{date}\" FORMAT=\"3.0\" VERSION=\"3.0\">\n  <HEADER MEDIA_FILE=\"\" TIME_UNITS=\"milliseconds\">\n    <MEDIA_DESCRIPTOR MEDIA_URL=\"{}\" MIME_TYPE=\"{}\"/>\n  </HEADER>\n",
+        xml_escape(&file_url(video)),
+        mime_type(video),
+    );
+
+    // two slots per annotation, slot n of tag i is ts{2i+n}
+    eaf.push_str("  <TIME_ORDER>\n");
+    for (idx, tag) in tags.iter().enumerate() {
+        let end = tag.end.unwrap_or(tag.time + INSTANT_LENGTH);
+        for (slot, time) in [(idx * 2 + 1, tag.time), (idx * 2 + 2, end)] {
+            eaf.push_str(&format!(
+                "    <TIME_SLOT TIME_SLOT_ID=\"ts{slot}\" TIME_VALUE=\"{}\"/>\n",
+                time.as_millis()
+            ));
+        }
+    }
+    eaf.push_str("  </TIME_ORDER>\n");
+
+    for tier in &tiers {
+        eaf.push_str(&format!(
+            "  <TIER LINGUISTIC_TYPE_REF=\"default-lt\" TIER_ID=\"{}\">\n",
+            xml_escape(tier)
+        ));
+        for (idx, tag) in tags.iter().enumerate().filter(|(_, tag)| tag.label == *tier) {
+            eaf.push_str(&format!(
+                "    <ANNOTATION>\n      <ALIGNABLE_ANNOTATION ANNOTATION_ID=\"a{}\" TIME_SLOT_REF1=\"ts{}\" TIME_SLOT_REF2=\"ts{}\">\n        <ANNOTATION_VALUE>{}</ANNOTATION_VALUE>\n      </ALIGNABLE_ANNOTATION>\n    </ANNOTATION>\n",
+                idx + 1,
+                idx * 2 + 1,
+                idx * 2 + 2,
+                xml_escape(if tag.note.is_empty() { &tag.label } else { &tag.note }),
+            ));
+        }
+        eaf.push_str("  </TIER>\n");
+    }
+
+    eaf.push_str("  <LINGUISTIC_TYPE GRAPHIC_REFERENCES=\"false\" LINGUISTIC_TYPE_ID=\"default-lt\" TIME_ALIGNABLE=\"true\"/>\n</ANNOTATION_DOCUMENT>\n");
+    eaf
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::tags::TagStore;
+
+    #[test]
+    fn notes_round_trip() {
+        let mut store = TagStore::new();
+        let video = PathBuf::from("/videos/match.mp4");
+        let noted = store
+            .add_range(video.clone(), Duration::from_secs(2), Duration::from_secs(5), "goal".into())
+            .unwrap();
+        store.set_note(noted, "header & <rebound>".into());
+        store.add(video.clone(), Duration::from_secs(9), "foul".into()).unwrap();
+        let eaf = write_eaf(store.tags(), &["goal".into()], &video);
+
+        let markers = read_eaf(&eaf, Path::new("/videos")).unwrap();
+        assert_eq!(
+            markers,
+            [
+                Marker {
+                    video: None,
+                    time: Duration::from_secs(2),
+                    end: Some(Duration::from_secs(5)),
+                    label: "goal".into(),
+                    note: "header & <rebound>".into(),
+                },
+                Marker {
+                    video: None,
+                    time: Duration::from_secs(9),
+                    end: None,
+                    label: "foul".into(),
+                    note: String::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn annotation_values_become_notes() {
+        let eaf = r#"<ANNOTATION_DOCUMENT>
+            <TIME_ORDER>
+                <TIME_SLOT TIME_SLOT_ID="ts1" TIME_VALUE="1000"/>
+                <TIME_SLOT TIME_SLOT_ID="ts2" TIME_VALUE="2500"/>
+                <TIME_SLOT TIME_SLOT_ID="ts3"/>
+            </TIME_ORDER>
+            <TIER TIER_ID="gesture">
+                <ANNOTATION>
+                    <ALIGNABLE_ANNOTATION
+                        ANNOTATION_ID="a1" TIME_SLOT_REF1="ts1" TIME_SLOT_REF2="ts2">
+                        <ANNOTATION_VALUE>points left</ANNOTATION_VALUE>
+                    </ALIGNABLE_ANNOTATION>
+                </ANNOTATION>
+                <ANNOTATION>
+                    <ALIGNABLE_ANNOTATION
+                        ANNOTATION_ID="a2" TIME_SLOT_REF1="ts1" TIME_SLOT_REF2="ts3">
+                        <ANNOTATION_VALUE>unaligned</ANNOTATION_VALUE>
+                    </ALIGNABLE_ANNOTATION>
+                </ANNOTATION>
+            </TIER>
+        </ANNOTATION_DOCUMENT>"#;
+
+        let markers = read_eaf(eaf, Path::new(".")).unwrap();
+        assert_eq!(markers.len(), 1);
+        assert_eq!(markers[0].label, "gesture");
+        assert_eq!(markers[0].note, "points left");
+        assert_eq!(markers[0].time, Duration::from_millis(1000));
+        assert_eq!(markers[0].end, Some(Duration::from_millis(2500)));
+    }
+}
@@ -0,0 +1,156 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::{from_seconds, invalid_data, mlt_frame_rate, mlt_property, resolve_location, Marker};
+
+// kdenlive keeps guides and clip markers as json inside mlt properties, in frames
+#[derive(Debug, Deserialize)]
+struct KdenliveMarker {
+    #[serde(default)]
+    comment: String,
+    pos: u64,
+}
+
+fn parse_marker_json(json: &str, fps: f64, video: Option<&Path>) -> Vec<Marker> {
+    serde_json::from_str::<Vec<KdenliveMarker>>(json)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|marker| {
+            Some(Marker {
+                video: video.map(Path::to_path_buf),
+                time: from_seconds(marker.pos as f64 / fps)?,
+                end: None,
+                label: if marker.comment.trim().is_empty() {
+                    String::from("marker")
+                } else {
+                    marker.comment
+                },
+                note: String::new(),
+            })
+        })
+        .collect()
+}
+
+// timeline guides and the markers placed on clips of a .kdenlive project
+pub fn read_kdenlive(contents: &str, base: &Path) -> std::io::Result<Vec<Marker>> {
+    let document = roxmltree::Document::parse(contents).map_err(invalid_data)?;
+    let fps = mlt_frame_rate(&document);
+    // clip paths are relative to the project root unless absolute
+    let root = document
+        .root_element()
+        .attribute("root")
+        .map_or_else(|| base.to_path_buf(), |root| resolve_location(root, base));
+
+    let mut markers = Vec::new();
+    // kdenlive 23.08 and later keep guides per sequence, the project-wide ones are
+    // from older versions and only count when there are no others
+    let mut project_guides = Vec::new();
+    let mut sequence_guides = Vec::new();
+    for node in document.descendants() {
+        if let Some(guides) = mlt_property(node, "kdenlive:docproperties.guides") {
+            project_guides.extend(parse_marker_json(guides, fps, None));
+        }
+        if let Some(guides) = mlt_property(node, "kdenlive:sequenceproperties.guides") {
+            sequence_guides.extend(parse_marker_json(guides, fps, None));
+        }
+
+        if node.has_tag_name("producer") || node.has_tag_name("chain") {
+            if let (Some(clip_markers), Some(resource)) = (
+                mlt_property(node, "kdenlive:markers"),
+                mlt_property(node, "resource"),
+            ) {
+                let video = resolve_location(resource, &root);
+                markers.extend(parse_marker_json(clip_markers, fps, Some(&video)));
+            }
+        }
+
+        // projects from before guides moved into properties, times in seconds
+        if node.has_tag_name("guide") {
+            let time = node.attribute("time").and_then(|time| time.parse::<f64>().ok());
+            if let Some(time) = time.and_then(from_seconds) {
+                markers.push(Marker {
+                    video: None,
+                    time,
+                    end: None,
+                    label: node
+                        .attribute("comment")
+                        .filter(|comment| !comment.trim().is_empty())
+                        .unwrap_or("marker")
+                        .to_string(),
+                    note: String::new(),
+                });
+            }
+        }
+    }
+
+    if sequence_guides.is_empty() {
+        markers.extend(project_guides);
+    } else {
+        markers.extend(sequence_guides);
+    }
+    Ok(markers)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    const GUIDES: &str = r#"[{"comment": "kickoff", "pos": 50, "type": 0}]"#;
+
+    fn project(properties: &[(&str, &str)]) -> String {
+        let properties = properties
+            .iter()
+            .map(|(name, value)| format!(r#"<property name="{name}">{value}</property>"#))
+            .collect::<String>();
+        format!(
+            r#"<mlt root="/videos"><profile frame_rate_num="25" frame_rate_den="1"/>
+            <tractor id="sequence">{properties}</tractor></mlt>"#
+        )
+    }
+
+    #[test]
+    fn sequence_guides() {
+        let contents = project(&[("kdenlive:sequenceproperties.guides", GUIDES)]);
+        let markers = read_kdenlive(&contents, Path::new("/videos")).unwrap();
+        assert_eq!(
+            markers,
+            [Marker {
+                video: None,
+                time: Duration::from_secs(2),
+                end: None,
+                label: "kickoff".into(),
+                note: String::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn project_guides_only_without_sequence_ones() {
+        let old = r#"[{"comment": "old", "pos": 25}]"#;
+        let contents = project(&[
+            ("kdenlive:docproperties.guides", old),
+            ("kdenlive:sequenceproperties.guides", GUIDES),
+        ]);
+        let markers = read_kdenlive(&contents, Path::new("/videos")).unwrap();
+        assert_eq!(markers.len(), 1);
+        assert_eq!(markers[0].label, "kickoff");
+
+        let contents = project(&[("kdenlive:docproperties.guides", old)]);
+        let markers = read_kdenlive(&contents, Path::new("/videos")).unwrap();
+        assert_eq!(markers[0].time, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn guides_past_any_duration_are_skipped() {
+        let contents = r#"<mlt><profile frame_rate_num="1" frame_rate_den="1000000000000000000000"/>
+            <guide time="1e300" comment="far"/><guide time="4" comment="near"/>
+            <tractor><property name="kdenlive:sequenceproperties.guides">[{"pos": 9}]</property>
+            </tractor></mlt>"#;
+        let markers = read_kdenlive(contents, Path::new("/")).unwrap();
+        let labels = markers.iter().map(|marker| marker.label.as_str()).collect::<Vec<_>>();
+        assert_eq!(labels, ["near"]);
+    }
+}
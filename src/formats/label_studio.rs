@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use super::{percent_encode, xml_escape};
+use crate::tags::Tag;
+
+// what label studio shows as the source of pre-annotations
+const MODEL_VERSION: &str = "video-tagger";
+
+// label studio serves local media as /data/local-files/?d=<path under its document root>
+pub fn local_file_url(relative_path: &str) -> String {
+    format!("/data/local-files/?d={}", percent_encode(relative_path))
+}
+
+// labeling config for the tasks below, paste it into the project's settings. label
+// studio refuses predictions with labels the config doesn't list, so the labels of
+// `tags` outside the categories are added after them
+pub fn label_studio_config(categories: &[String], tags: &[Tag], frames: bool) -> String {
+    let mut labels = categories.to_vec();
+    for tag in tags {
+        if !labels.contains(&tag.label) {
+            labels.push(tag.label.clone());
+        }
+    }
+
+    let mut config = String::from("<View>\n");
+    if frames {
+        config.push_str("  <Image name=\"image\" value=\"$image\"/>\n");
+        config.push_str("  <Choices name=\"label\" toName=\"image\" choice=\"multiple\">\n");
+    } else {
+        config.push_str("  <Video name=\"video\" value=\"$video\"/>\n");
+        config.push_str("  <TimelineLabels name=\"label\" toName=\"video\">\n");
+    }
+    let item = if frames { "Choice" } else { "Label" };
+    for label in &labels {
+        config.push_str(&format!("    <{item} value=\"{}\"/>\n", xml_escape(label)));
+    }
+    config.push_str(if frames {
+        "  </Choices>\n</View>\n"
+    } else {
+        "  </TimelineLabels>\n</View>\n"
+    });
+    config
+}
+
+// one video task, every tag a timeline label prediction. label studio counts
+// frames from 1 and instant tags cover their one frame
+pub fn label_studio_video_task(video_url: &str, tags: &[Tag], fps: f64) -> Value {
+    let frame = |time: Duration| (time.as_secs_f64() * fps).round() as u64 + 1;
+    let result = tags
+        .iter()
+        .enumerate()
+        .map(|(idx, tag)| {
+            let start = frame(tag.time);
+            let end = tag.end.map_or(start, |end| frame(end).max(start));
+            json!({
+                "id": format!("tag{}", idx + 1),
+                "type": "timelinelabels",
+                "from_name": "label",
+                "to_name": "video",
+                "value": {
+                    "ranges": [{ "start": start, "end": end }],
+                    "timelinelabels": [tag.label],
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "data": { "video": video_url },
+        "predictions": [{ "model_version": MODEL_VERSION, "result": result }],
+    })
+}
+
+// one image task for a frame, with the labels of the tags at that moment as choices
+pub fn label_studio_frame_task(image_url: &str, time: Duration, labels: &[String]) -> Value {
+    json!({
+        "data": { "image": image_url, "time": time.as_secs_f64() },
+        "predictions": [{
+            "model_version": MODEL_VERSION,
+            "result": [{
+                "type": "choices",
+                "from_name": "label",
+                "to_name": "image",
+                "value": { "choices": labels },
+            }],
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::tags::TagStore;
+
+    #[test]
+    fn local_file_urls_are_encoded() {
+        assert_eq!(
+            local_file_url("cup final/a&b #2.mp4"),
+            "/data/local-files/?d=cup%20final/a%26b%20%232.mp4"
+        );
+        assert_eq!(local_file_url("plain.mp4"), "/data/local-files/?d=plain.mp4");
+    }
+
+    #[test]
+    fn the_config_lists_every_exported_label() {
+        let mut store = TagStore::new();
+        let video = PathBuf::from("match.mp4");
+        store.add(video.clone(), Duration::from_secs(1), "goal".into()).unwrap();
+        store.add(video.clone(), Duration::from_secs(2), "offside".into()).unwrap();
+        store.add(video, Duration::from_secs(3), "offside".into()).unwrap();
+
+        let config = label_studio_config(&["goal".into(), "foul".into()], store.tags(), false);
+        let labels = config
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("<Label value=\"")?.strip_suffix("\"/>"))
+            .collect::<Vec<_>>();
+        assert_eq!(labels, ["goal", "foul", "offside"]);
+    }
+}
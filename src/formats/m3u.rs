@@ -0,0 +1,33 @@
+use std::path::Path;
+
+use super::{apply_vlc_option, resolve_location, QueueEntry};
+
+// plain and extended m3u, including the #EXTVLCOPT lines vlc writes
+pub fn read_m3u(contents: &str, base: &Path) -> Vec<QueueEntry> {
+    let mut entries = Vec::new();
+    let mut pending = QueueEntry::new(Default::default());
+
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            // #EXTINF:<duration>,<title>
+            pending.title = info
+                .split_once(',')
+                .map(|(_, title)| title.trim().to_string())
+                .filter(|title| !title.is_empty());
+        } else if let Some(option) = line.strip_prefix("#EXTVLCOPT:") {
+            apply_vlc_option(&mut pending, option);
+        } else if !line.starts_with('#') {
+            pending.path = resolve_location(line, base);
+            entries.push(std::mem::replace(
+                &mut pending,
+                QueueEntry::new(Default::default()),
+            ));
+        }
+    }
+
+    entries
+}
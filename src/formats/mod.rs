@@ -0,0 +1,353 @@
+// reading and writing other tools' files, every format gets its own module
+mod coco;
+mod elan;
+mod kdenlive;
+mod label_studio;
+mod m3u;
+mod mpv;
+mod resolve;
+mod shotcut;
+mod sportscode;
+mod tracks;
+mod xspf;
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+pub use coco::{write_coco, write_coco_keypoints};
+pub use elan::{read_eaf, write_eaf};
+pub use kdenlive::read_kdenlive;
+pub use label_studio::{
+    label_studio_config, label_studio_frame_task, label_studio_video_task, local_file_url,
+};
+pub use m3u::read_m3u;
+pub use mpv::read_watch_later;
+pub use resolve::{read_resolve_csv, write_resolve_csv, ResolveMarker};
+pub use shotcut::read_shotcut;
+pub use sportscode::write_sportscode_xml;
+pub use tracks::write_mot_csv;
+pub use xspf::read_xspf;
+
+// a marker or guide from an editing project or annotation file, becomes a tag
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    // the clip a marker sits on, none for timeline guides, which go to the open video
+    pub video: Option<PathBuf>,
+    pub time: Duration,
+    pub end: Option<Duration>,
+    pub label: String,
+    // becomes the tag's note, empty for formats that only have the label
+    pub note: String,
+}
+
+// reads markers from any of the supported project formats, by extension
+pub fn read_markers(path: &Path) -> std::io::Result<Vec<Marker>> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    let contents = std::fs::read_to_string(path)?;
+    let base = path.parent().unwrap_or(Path::new("."));
+
+    match extension.as_deref() {
+        Some("kdenlive") => read_kdenlive(&contents, base),
+        Some("mlt") => read_shotcut(&contents, base),
+        Some("eaf") => read_eaf(&contents, base),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} is not a kdenlive, shotcut or elan file", path.display()),
+        )),
+    }
+}
+
+fn invalid_data(err: impl std::fmt::Display) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// frames per second from an mlt <profile>, 25 when there is none
+fn mlt_frame_rate(document: &roxmltree::Document<'_>) -> f64 {
+    document
+        .descendants()
+        .find(|node| node.has_tag_name("profile"))
+        .and_then(|profile| {
+            let numerator = profile.attribute("frame_rate_num")?.parse::<f64>().ok()?;
+            let denominator = profile.attribute("frame_rate_den")?.parse::<f64>().ok()?;
+            (numerator > 0. && denominator > 0.).then_some(numerator / denominator)
+        })
+        .unwrap_or(25.)
+}
+
+// mlt times are frame counts, clock times (hh:mm:ss.mmm) or smpte (hh:mm:ss:ff)
+fn parse_mlt_time(text: &str, fps: f64) -> Option<Duration> {
+    let text = text.trim();
+    let seconds = if let Ok(frames) = text.parse::<u64>() {
+        frames as f64 / fps
+    } else if text.matches(':').count() == 3 && !text.contains('.') {
+        let (clock, frames) = text.rsplit_once(':')?;
+        crate::tag_tools::parse_timestamp(clock)?.as_secs_f64() + frames.parse::<f64>().ok()? / fps
+    } else {
+        crate::tag_tools::parse_timestamp(text)?.as_secs_f64()
+    };
+    from_seconds(seconds)
+}
+
+// a time from someone else's file. before the start is the start, but nan, infinity and
+// anything further than a duration goes make the entry unusable
+pub fn from_seconds(seconds: f64) -> Option<Duration> {
+    if seconds.is_nan() {
+        return None;
+    }
+    Duration::try_from_secs_f64(seconds.max(0.)).ok()
+}
+
+// the value of a <property name="..."> child
+fn mlt_property<'a>(node: roxmltree::Node<'a, 'a>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|child| child.has_tag_name("property") && child.attribute("name") == Some(name))
+        .and_then(|property| property.text())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub time: Duration,
+}
+
+// one video waiting in the queue, with where the previous player left off
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueueEntry {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub start: Option<Duration>,
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl QueueEntry {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            title: None,
+            start: None,
+            bookmarks: Vec::new(),
+        }
+    }
+}
+
+// picks the reader by extension
+pub fn read_playlist(path: &Path) -> std::io::Result<Vec<QueueEntry>> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    let contents = std::fs::read_to_string(path)?;
+    let base = path.parent().unwrap_or(Path::new("."));
+
+    match extension.as_deref() {
+        Some("xspf") => read_xspf(&contents, base),
+        Some("m3u" | "m3u8") => Ok(read_m3u(&contents, base)),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} is not an m3u or xspf playlist", path.display()),
+        )),
+    }
+}
+
+// playlist and project locations can be relative to the playlist, absolute, or file:// urls.
+// other urls (streams) are kept as they are
+fn resolve_location(location: &str, base: &Path) -> PathBuf {
+    if let Some(path) = location.strip_prefix("file://") {
+        return PathBuf::from(percent_decode(path));
+    }
+    if location.contains("://") {
+        return PathBuf::from(location);
+    }
+
+    let path = PathBuf::from(location);
+    if path.is_absolute() {
+        path
+    } else {
+        base.join(path)
+    }
+}
+
+// file:// url for an absolute path, escaping what would end or confuse the url
+fn file_url(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut path = path.to_string_lossy().replace('\\', "/");
+    // windows drive letters still need the slash of an empty host
+    if !path.starts_with('/') {
+        path.insert(0, '/');
+    }
+    format!("file://{}", percent_encode(&path))
+}
+
+// everything but path separators and characters urls never give a meaning to
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                encoded.push(char::from(byte));
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+fn percent_decode(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' && tail.len() >= 2 {
+            if let Ok(decoded) = u8::from_str_radix(&String::from_utf8_lossy(&tail[..2]), 16) {
+                bytes.push(decoded);
+                rest = &tail[2..];
+                continue;
+            }
+        }
+        bytes.push(byte);
+        rest = tail;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// vlc's `bookmarks` option: {name=intro,time=12.5},{name=goal,time=301}
+fn parse_vlc_bookmarks(value: &str) -> Vec<Bookmark> {
+    value
+        .split('}')
+        .filter_map(|bookmark| {
+            let fields = bookmark.trim_start_matches([',', '{']);
+            let mut name = None;
+            let mut time = None;
+            for field in fields.split(',') {
+                match field.split_once('=') {
+                    Some(("name", value)) => name = Some(value.to_string()),
+                    Some(("time", value)) => time = value.parse::<f64>().ok(),
+                    _ => {}
+                }
+            }
+            Some(Bookmark {
+                name: name.unwrap_or_else(|| String::from("bookmark")),
+                time: from_seconds(time?)?,
+            })
+        })
+        .collect()
+}
+
+// the options vlc stores per item, in both m3u and xspf
+fn apply_vlc_option(entry: &mut QueueEntry, option: &str) {
+    match option.split_once('=') {
+        Some(("start-time", value)) => {
+            entry.start = value.parse::<f64>().ok().and_then(from_seconds);
+        }
+        Some(("bookmarks", value)) => entry.bookmarks.extend(parse_vlc_bookmarks(value)),
+        _ => {}
+    }
+}
+
+// rfc 4180 csv, quoted fields may contain commas, quotes and line breaks
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (c, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows.retain(|row| row.iter().any(|field| !field.trim().is_empty()));
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vlc_bookmarks() {
+        let bookmarks = parse_vlc_bookmarks("{name=intro,time=12.5},{name=goal,time=301}");
+        assert_eq!(
+            bookmarks,
+            [
+                Bookmark {
+                    name: "intro".into(),
+                    time: Duration::from_millis(12_500),
+                },
+                Bookmark {
+                    name: "goal".into(),
+                    time: Duration::from_secs(301),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn vlc_bookmarks_that_arent_times_are_skipped() {
+        let bookmarks = parse_vlc_bookmarks(
+            "{name=a,time=1e300},{name=b,time=nan},{name=c,time=inf},{name=d,time=-4}",
+        );
+        assert_eq!(
+            bookmarks,
+            [Bookmark {
+                name: "d".into(),
+                time: Duration::ZERO,
+            }]
+        );
+    }
+
+    #[test]
+    fn m3u_start_times() {
+        let m3u = "#EXTM3U\n\
+            #EXTINF:0,first\n#EXTVLCOPT:start-time=42\nfirst.mp4\n\
+            #EXTVLCOPT:start-time=1e300\nsecond.mp4\n\
+            #EXTVLCOPT:start-time=nan\nthird.mp4\n";
+        let entries = read_m3u(m3u, Path::new("/videos"));
+        let starts = entries.iter().map(|entry| entry.start).collect::<Vec<_>>();
+        assert_eq!(starts, [Some(Duration::from_secs(42)), None, None]);
+        assert_eq!(entries[0].title.as_deref(), Some("first"));
+        assert_eq!(entries[2].path, Path::new("/videos/third.mp4"));
+    }
+
+    #[test]
+    fn mlt_times() {
+        assert_eq!(parse_mlt_time("50", 25.), Some(Duration::from_secs(2)));
+        assert_eq!(parse_mlt_time("00:00:02.500", 25.), Some(Duration::from_millis(2500)));
+        assert_eq!(parse_mlt_time("00:00:02:02", 4.), Some(Duration::from_millis(2500)));
+        assert_eq!(parse_mlt_time("18446744073709551615", 1e-300), None);
+        assert_eq!(parse_mlt_time("1e20", 25.), None);
+    }
+}
@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use super::{from_seconds, QueueEntry};
+
+// mpv's watch_later directory. only files written with
+// `write-filename-in-watch-later-config` name their video, the rest are skipped, and so
+// are ones whose position isn't a time
+pub fn read_watch_later(dir: &Path) -> std::io::Result<Vec<QueueEntry>> {
+    let mut entries = Vec::new();
+
+    'files: for file in std::fs::read_dir(dir)?.filter_map(Result::ok) {
+        let Ok(contents) = std::fs::read_to_string(file.path()) else {
+            continue;
+        };
+
+        let mut lines = contents.lines();
+        let Some(path) = lines.next().and_then(|first| first.strip_prefix("# ")) else {
+            continue;
+        };
+
+        let mut entry = QueueEntry::new(path.into());
+        for line in lines {
+            if let Some(start) = line.strip_prefix("start=") {
+                let Some(start) = start.parse::<f64>().ok().and_then(from_seconds) else {
+                    continue 'files;
+                };
+                entry.start = Some(start);
+            }
+        }
+        entries.push(entry);
+    }
+
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn watch_later_files() {
+        let dir = std::env::temp_dir().join(format!("watch-later-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let files = [
+            ("a", "# /videos/a.mp4\nstart=12.500000\nvolume=80\n"),
+            ("b", "start=3.000000\n"),
+            ("c", "# /videos/c.mp4\nstart=1e300\n"),
+            ("d", "# /videos/d.mp4\nstart=nan\n"),
+            ("e", "# /videos/e.mp4\n"),
+        ];
+        for (name, contents) in files {
+            std::fs::write(dir.join(name), contents).unwrap();
+        }
+
+        let entries = read_watch_later(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let entries = entries
+            .into_iter()
+            .map(|entry| (entry.path, entry.start))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                ("/videos/a.mp4".into(), Some(Duration::from_millis(12_500))),
+                ("/videos/e.mp4".into(), None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use super::{from_seconds, invalid_data, parse_csv};
+use crate::tag_tools::csv_field;
+use crate::tags::Tag;
+
+// the marker colors resolve offers, in its menu order. category n gets color n
+pub const RESOLVE_COLORS: [&str; 16] = [
+    "Blue", "Cyan", "Green", "Yellow", "Red", "Pink", "Purple", "Fuchsia", "Rose", "Lavender",
+    "Sky", "Mint", "Lemon", "Sand", "Cocoa", "Cream",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolveMarker {
+    pub frame: u64,
+    pub color: String,
+    pub name: String,
+    pub notes: String,
+    // in frames, 1 for a marker on a single frame
+    pub duration: u64,
+}
+
+impl ResolveMarker {
+    // none when the frame is too far out to be a time
+    pub fn time(&self, fps: f64) -> Option<Duration> {
+        from_seconds(self.frame as f64 / fps)
+    }
+
+    pub fn end(&self, fps: f64) -> Option<Duration> {
+        let end = self.frame.saturating_add(self.duration);
+        (self.duration > 1).then(|| from_seconds(end as f64 / fps)).flatten()
+    }
+
+    // a category named like the marker wins, then the one its color stands for
+    pub fn category(&self, categories: &[String]) -> String {
+        if categories.iter().any(|category| *category == self.name) {
+            return self.name.clone();
+        }
+
+        RESOLVE_COLORS
+            .iter()
+            .position(|color| color.eq_ignore_ascii_case(self.color.trim()))
+            .and_then(|idx| categories.get(idx))
+            .cloned()
+            .or_else(|| (!self.name.trim().is_empty()).then(|| self.name.clone()))
+            .unwrap_or_else(|| String::from("marker"))
+    }
+}
+
+fn color_for(label: &str, categories: &[String]) -> &'static str {
+    categories
+        .iter()
+        .position(|category| category == label)
+        .map_or(RESOLVE_COLORS[0], |idx| RESOLVE_COLORS[idx % RESOLVE_COLORS.len()])
+}
+
+// columns are found by header name, so files with extra columns or another order work
+pub fn read_resolve_csv(text: &str) -> std::io::Result<Vec<ResolveMarker>> {
+    let mut rows = parse_csv(text).into_iter();
+    let header = rows
+        .next()
+        .ok_or_else(|| invalid_data("the file is empty"))?
+        .into_iter()
+        .map(|name| name.trim().to_lowercase())
+        .collect::<Vec<_>>();
+    let column = |name: &str| header.iter().position(|header| header == name);
+
+    let frame = column("frame").ok_or_else(|| invalid_data("no Frame column"))?;
+    let (color, name, notes, duration) = (
+        column("color"),
+        column("name"),
+        column("notes"),
+        column("duration"),
+    );
+
+    let markers = rows
+        .filter_map(|row| {
+            let field = |idx: Option<usize>| {
+                idx.and_then(|idx| row.get(idx))
+                    .map(|value| value.trim().to_string())
+                    .unwrap_or_default()
+            };
+            Some(ResolveMarker {
+                frame: row.get(frame)?.trim().parse().ok()?,
+                color: field(color),
+                name: field(name),
+                notes: field(notes),
+                duration: field(duration).parse().unwrap_or(1).max(1),
+            })
+        })
+        .collect();
+
+    Ok(markers)
+}
+
+pub fn write_resolve_csv(tags: &[Tag], categories: &[String], fps: f64) -> String {
+    let to_frames = |time: Duration| (time.as_secs_f64() * fps).round() as u64;
+
+    let mut csv = String::from("Frame,Color,Name,Notes,Duration\n");
+    for tag in tags {
+        let frame = to_frames(tag.time);
+        let duration = tag
+            .end
+            .map_or(1, |end| to_frames(end).saturating_sub(frame).max(1));
+        csv.push_str(&format!(
+            "{frame},{},{},{},{duration}\n",
+            color_for(&tag.label, categories),
+            csv_field(&tag.label),
+            csv_field(&tag.note),
+        ));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::tags::TagStore;
+
+    #[test]
+    fn notes_round_trip() {
+        let mut store = TagStore::new();
+        let id = store
+            .add(PathBuf::from("match.mp4"), Duration::from_secs(2), "goal".into())
+            .unwrap();
+        store.set_note(id, "top corner, \"unstoppable\"".into());
+        let csv = write_resolve_csv(store.tags(), &["goal".into()], 25.);
+
+        let markers = read_resolve_csv(&csv).unwrap();
+        assert_eq!(markers.len(), 1);
+        assert_eq!(markers[0].frame, 50);
+        assert_eq!(markers[0].notes, "top corner, \"unstoppable\"");
+    }
+
+    #[test]
+    fn frames_past_any_duration() {
+        let csv = "Frame,Color,Name,Notes,Duration\n18446744073709551615,Blue,far,,5\n";
+        let marker = &read_resolve_csv(csv).unwrap()[0];
+        assert_eq!(marker.time(1e-300), None);
+        assert_eq!(marker.end(1e-300), None);
+        assert_eq!(marker.time(25.).map(|time| time.as_secs()), Some(737_869_762_948_382));
+    }
+}
@@ -0,0 +1,36 @@
+use std::path::Path;
+
+use super::{invalid_data, mlt_frame_rate, mlt_property, parse_mlt_time, Marker};
+
+// the timeline markers of a shotcut .mlt project, ranges keep their end
+pub fn read_shotcut(contents: &str, base: &Path) -> std::io::Result<Vec<Marker>> {
+    let document = roxmltree::Document::parse(contents).map_err(invalid_data)?;
+    let fps = mlt_frame_rate(&document);
+
+    let markers = document
+        .descendants()
+        .filter(|node| node.has_tag_name("properties") && node.attribute("name") == Some("shotcut:markers"))
+        .flat_map(|list| list.children().filter(|node| node.has_tag_name("properties")))
+        .filter_map(|marker| {
+            let time = parse_mlt_time(mlt_property(marker, "start")?, fps)?;
+            let end = mlt_property(marker, "end")
+                .and_then(|end| parse_mlt_time(end, fps))
+                .filter(|end| *end > time);
+            let label = mlt_property(marker, "text")
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .unwrap_or("marker")
+                .to_string();
+
+            Some(Marker {
+                video: None,
+                time,
+                end,
+                label,
+                note: String::new(),
+            })
+        })
+        .collect();
+
+    Ok(markers)
+}
@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use cushy::styles::Color;
+
+use super::xml_escape;
+use crate::tags::Tag;
+
+// sportscode's default lead and lag for a code button, instant tags become a
+// clip of this much around the moment
+const INSTANT_LEAD: Duration = Duration::from_secs(5);
+const INSTANT_LAG: Duration = Duration::from_secs(5);
+
+// one <instance> per tag and one <row> per category, in the layout sportscode
+// and hudl import. instances are labelled with their video's file name, and with
+// their note in a "Notes" group. `color` picks the row color of a category
+pub fn write_sportscode_xml(
+    tags: &[Tag],
+    categories: &[String],
+    color: impl Fn(usize) -> Color,
+) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<file>\n");
+
+    xml.push_str("  <ALL_INSTANCES>\n");
+    for (idx, tag) in tags.iter().enumerate() {
+        let (start, end) = match tag.end {
+            Some(end) => (tag.time, end),
+            None => (tag.time.saturating_sub(INSTANT_LEAD), tag.time + INSTANT_LAG),
+        };
+        let video = tag
+            .video
+            .file_name()
+            .map_or_else(|| tag.video.to_string_lossy(), |name| name.to_string_lossy());
+        xml.push_str(&format!(
+            "    <instance>\n      <ID>{}</ID>\n      <start>{:.3}</start>\n      <end>{:.3}</end>\n      <code>{}</code>\n      <label>\n        <group>Video</group>\n        <text>{}</text>\n      </label>\n",
+            idx + 1,
+            start.as_secs_f64(),
+            end.as_secs_f64(),
+            xml_escape(&tag.label),
+            xml_escape(&video),
+        ));
+        if !tag.note.is_empty() {
+            xml.push_str(&format!(
+                "      <label>\n        <group>Notes</group>\n        <text>{}</text>\n      </label>\n",
+                xml_escape(&tag.note),
+            ));
+        }
+        xml.push_str("    </instance>\n");
+    }
+    xml.push_str("  </ALL_INSTANCES>\n");
+
+    // categories first so the rows keep the ui's order, then labels without a category
+    let mut codes = categories.to_vec();
+    for tag in tags {
+        if !codes.contains(&tag.label) {
+            codes.push(tag.label.clone());
+        }
+    }
+
+    xml.push_str("  <ROWS>\n");
+    for (idx, code) in codes.iter().enumerate() {
+        let color = color(idx);
+        // sportscode wants 16 bit channels
+        let channel = |value: u8| u16::from(value) * 257;
+        xml.push_str(&format!(
+            "    <row>\n      <code>{}</code>\n      <R>{}</R>\n      <G>{}</G>\n      <B>{}</B>\n    </row>\n",
+            xml_escape(code),
+            channel(color.red()),
+            channel(color.green()),
+            channel(color.blue()),
+        ));
+    }
+    xml.push_str("  </ROWS>\n</file>\n");
+
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::tags::TagStore;
+
+    #[test]
+    fn instances_and_rows() {
+        let mut store = TagStore::new();
+        let video = PathBuf::from("/videos/match.mp4");
+        let (start, end) = (Duration::from_secs(12), Duration::from_secs(20));
+        let id = store.add_range(video.clone(), start, end, "attack".into()).unwrap();
+        store.set_note(id, "overload on the left & a cross".into());
+        store.add(video, Duration::from_secs(3), "foul".into()).unwrap();
+
+        let xml = write_sportscode_xml(store.tags(), &["attack".into()], |idx| {
+            [Color::new(0xff, 0, 0, 0xff), Color::new(0, 0, 0xff, 0xff)][idx]
+        });
+        assert_eq!(
+            xml,
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<file>
+  <ALL_INSTANCES>
+    <instance>
+      <ID>1</ID>
+      <start>0.000</start>
+      <end>8.000</end>
+      <code>foul</code>
+      <label>
+        <group>Video</group>
+        <text>match.mp4</text>
+      </label>
+    </instance>
+    <instance>
+      <ID>2</ID>
+      <start>12.000</start>
+      <end>20.000</end>
+      <code>attack</code>
+      <label>
+        <group>Video</group>
+        <text>match.mp4</text>
+      </label>
+      <label>
+        <group>Notes</group>
+        <text>overload on the left &amp; a cross</text>
+      </label>
+    </instance>
+  </ALL_INSTANCES>
+  <ROWS>
+    <row>
+      <code>attack</code>
+      <R>65535</R>
+      <G>0</G>
+      <B>0</B>
+    </row>
+    <row>
+      <code>foul</code>
+      <R>0</R>
+      <G>0</G>
+      <B>65535</B>
+    </row>
+  </ROWS>
+</file>
+"#
+        );
+    }
+}
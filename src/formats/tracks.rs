@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use crate::tags::Tag;
+
+// region tracks in the MOTChallenge layout most tracking tools read:
+// frame,id,left,top,width,height,confidence,x,y,z with frames counted from 1.
+// every frame a box is on screen gets a row, interpolated between keyframes
+pub fn write_mot_csv(tags: &[Tag], fps: f64, (width, height): (u32, u32)) -> String {
+    let frame_of = |time: Duration| (time.as_secs_f64() * fps).round() as u64;
+
+    let mut rows = Vec::new();
+    for (id, tag) in tags.iter().filter(|tag| !tag.track.is_empty()).enumerate() {
+        let Some((start, end)) = tag.region_span() else {
+            continue;
+        };
+        for frame in frame_of(start)..=frame_of(end) {
+            let Some(region) = tag.track.at(Duration::from_secs_f64(frame as f64 / fps)) else {
+                continue;
+            };
+            rows.push((
+                frame + 1,
+                id + 1,
+                format!(
+                    "{:.1},{:.1},{:.1},{:.1}",
+                    region.x * width as f32,
+                    region.y * height as f32,
+                    region.width * width as f32,
+                    region.height * height as f32,
+                ),
+            ));
+        }
+    }
+    rows.sort_by_key(|(frame, id, _)| (*frame, *id));
+
+    let mut csv = String::new();
+    for (frame, id, bbox) in rows {
+        csv.push_str(&format!("{frame},{id},{bbox},1,-1,-1,-1\n"));
+    }
+    csv
+}
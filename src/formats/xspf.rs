@@ -0,0 +1,37 @@
+use std::path::Path;
+
+use super::{apply_vlc_option, invalid_data, resolve_location, QueueEntry};
+
+// xspf tracks, with vlc's <vlc:option> extension for start times and bookmarks
+pub fn read_xspf(contents: &str, base: &Path) -> std::io::Result<Vec<QueueEntry>> {
+    let document = roxmltree::Document::parse(contents).map_err(invalid_data)?;
+
+    let entries = document
+        .descendants()
+        .filter(|node| node.has_tag_name("track"))
+        .filter_map(|track| {
+            let child_text = |name: &str| {
+                track
+                    .children()
+                    .find(|child| child.has_tag_name(name))
+                    .and_then(|child| child.text())
+                    .map(str::trim)
+            };
+
+            let mut entry = QueueEntry::new(resolve_location(child_text("location")?, base));
+            entry.title = child_text("title").map(str::to_string);
+
+            for option in track
+                .descendants()
+                .filter(|node| node.tag_name().name() == "option")
+                .filter_map(|node| node.text())
+            {
+                apply_vlc_option(&mut entry, option.trim());
+            }
+
+            Some(entry)
+        })
+        .collect();
+
+    Ok(entries)
+}
@@ -0,0 +1,88 @@
+use std::time::{Duration, Instant};
+
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+use crate::remote::{Remote, RemoteCommand};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(16);
+// stick travel below this is drift, not intent
+const SHUTTLE_DEADZONE: f32 = 0.15;
+// a fully deflected stick moves this many seconds of footage per second
+const MAX_SHUTTLE_SPEED: f64 = 8.;
+// each seek restarts decoding at a keyframe, so the stick's travel is gathered up and
+// sent this often instead of every poll
+const SHUTTLE_SEEK_INTERVAL: Duration = Duration::from_millis(250);
+const BUMPER_SEEK_SECONDS: f64 = 5.;
+
+// face buttons tag the first four categories, south (A / cross) being the first
+const QUICK_TAG_BUTTONS: [Button; 4] = [Button::South, Button::East, Button::West, Button::North];
+
+// polls every connected controller for as long as the app runs
+pub fn listen(remote: Remote) {
+    std::thread::Builder::new()
+        .name("Gamepad Thread".into())
+        .spawn(move || {
+            let mut gilrs = match Gilrs::new() {
+                Ok(gilrs) => gilrs,
+                Err(err) => {
+                    tracing::error!("gamepad support unavailable: {err}");
+                    return;
+                }
+            };
+
+            let mut shuttle = 0.;
+            let mut last_tick = Instant::now();
+            let mut last_seek = Instant::now();
+            let mut travel = 0.;
+            loop {
+                while let Some(event) = gilrs.next_event() {
+                    match event.event {
+                        EventType::ButtonPressed(button, _) => press(&remote, button),
+                        EventType::AxisChanged(Axis::LeftStickX, value, _) => {
+                            shuttle = if value.abs() < SHUTTLE_DEADZONE { 0. } else { value };
+                        }
+                        EventType::Disconnected => shuttle = 0.,
+                        _ => {}
+                    }
+                }
+
+                let elapsed = last_tick.elapsed();
+                last_tick = Instant::now();
+                // squared so small deflections give fine control
+                let speed = f64::from(shuttle * shuttle.abs()) * MAX_SHUTTLE_SPEED;
+                travel += speed * elapsed.as_secs_f64();
+                // what's left goes out as soon as the stick is let go
+                if travel != 0. && (shuttle == 0. || last_seek.elapsed() >= SHUTTLE_SEEK_INTERVAL)
+                {
+                    let _ = remote.execute(RemoteCommand::SeekRelative(travel));
+                    travel = 0.;
+                    last_seek = Instant::now();
+                }
+
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        })
+        .unwrap();
+}
+
+fn press(remote: &Remote, button: Button) {
+    let commands = match button {
+        Button::Start => vec![RemoteCommand::TogglePlay],
+        Button::Select => vec![RemoteCommand::Stop],
+        Button::DPadLeft => vec![RemoteCommand::StepBackward],
+        Button::DPadRight => vec![RemoteCommand::StepForward],
+        Button::LeftTrigger => vec![RemoteCommand::SeekRelative(-BUMPER_SEEK_SECONDS)],
+        Button::RightTrigger => vec![RemoteCommand::SeekRelative(BUMPER_SEEK_SECONDS)],
+        button => match QUICK_TAG_BUTTONS.iter().position(|quick| *quick == button) {
+            Some(idx) => vec![RemoteCommand::TagCategory(idx)],
+            None => return,
+        },
+    };
+
+    for command in commands {
+        if let Err(err) = remote.execute(command) {
+            tracing::error!("gamepad command failed: {err}");
+            break;
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::tags::{FrameLabels, StoredPart, Tag};
+
+// how many operations back undo can go
+pub const UNDO_LIMIT: usize = 200;
+
+// one step of a tag store change, carrying everything needed to take it back
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Inserted(Tag),
+    // gone for good, only the undo of an insert does this
+    Discarded(Tag),
+    Edited { before: Tag, after: Tag },
+    Trashed(Tag),
+    Restored(Tag),
+    // one frame's labels, none before when it had none yet and none after when the
+    // last one came off. moving a frame is one of these too
+    FrameEdited {
+        before: Option<FrameLabels>,
+        after: Option<FrameLabels>,
+    },
+    // a tag in the trash, moved along with its video
+    TrashEdited { before: Tag, after: Tag },
+}
+
+impl Change {
+    pub fn inverse(self) -> Self {
+        match self {
+            Self::Inserted(tag) => Self::Discarded(tag),
+            Self::Discarded(tag) => Self::Inserted(tag),
+            Self::Edited { before, after } => Self::Edited {
+                before: after,
+                after: before,
+            },
+            Self::Trashed(tag) => Self::Restored(tag),
+            Self::Restored(tag) => Self::Trashed(tag),
+            Self::FrameEdited { before, after } => Self::FrameEdited {
+                before: after,
+                after: before,
+            },
+            Self::TrashEdited { before, after } => Self::TrashEdited {
+                before: after,
+                after: before,
+            },
+        }
+    }
+
+    // the tags as they are before and after, for lock checks
+    pub fn tags(&self) -> impl Iterator<Item = &Tag> {
+        let (first, second) = match self {
+            Self::Inserted(tag)
+            | Self::Discarded(tag)
+            | Self::Trashed(tag)
+            | Self::Restored(tag) => (Some(tag), None),
+            Self::Edited { before, after } | Self::TrashEdited { before, after } => {
+                (Some(before), Some(after))
+            }
+            Self::FrameEdited { .. } => (None, None),
+        };
+        first.into_iter().chain(second)
+    }
+
+    // the same for frame labels
+    pub fn frames(&self) -> impl Iterator<Item = &FrameLabels> {
+        let (before, after) = match self {
+            Self::FrameEdited { before, after } => (before.as_ref(), after.as_ref()),
+            _ => (None, None),
+        };
+        before.into_iter().chain(after)
+    }
+
+    // what storage has to write again after it
+    pub fn parts(&self) -> Vec<StoredPart> {
+        let frame = |frame: &FrameLabels| StoredPart::Frame(frame.video.clone(), frame.time);
+        match self {
+            Self::Inserted(tag) | Self::Discarded(tag) => vec![StoredPart::Tag(tag.id)],
+            Self::Edited { after, .. } => vec![StoredPart::Tag(after.id)],
+            Self::Trashed(tag) | Self::Restored(tag) => {
+                vec![StoredPart::Tag(tag.id), StoredPart::Trashed(tag.id)]
+            }
+            Self::FrameEdited { .. } => self.frames().map(frame).collect(),
+            Self::TrashEdited { after, .. } => vec![StoredPart::Trashed(after.id)],
+        }
+    }
+}
+
+// what one user action did to the tag store, undone and redone as a whole
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Operation {
+    pub changes: Vec<Change>,
+}
+
+impl Operation {
+    pub fn inverse(self) -> Self {
+        Self {
+            changes: self.changes.into_iter().rev().map(Change::inverse).collect(),
+        }
+    }
+
+    // "undo tag", "undo 4 changes"
+    pub fn describe(&self) -> String {
+        match self.changes.as_slice() {
+            [Change::FrameEdited { .. }] => String::from("frame labels"),
+            [change] => change
+                .tags()
+                .last()
+                .map_or_else(String::new, |tag| format!("\"{}\"", tag.label)),
+            changes => format!("{} changes", changes.len()),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+use cushy::{
+    animation::ZeroToOne,
+    context::GraphicsContext,
+    figures::{units::Px, FloatConversion, IntoSigned, Point, Rect, Size},
+    kludgine::{
+        image::{DynamicImage, RgbaImage},
+        shapes::Shape,
+        text::Text,
+        wgpu::FilterMode,
+        DrawableExt, LazyTexture,
+    },
+    styles::Color,
+    value::{Destination, Dynamic, Source},
+    widget::Widget,
+};
+use ffmpeg_next as ffmpeg;
+
+use crate::format_timestamp;
+use crate::media::open_input;
+use crate::Rescaler;
+
+// longest side of the popup's frame
+const PREVIEW_SIZE: u32 = 240;
+// between the popup and the bottom of the player, and around the timestamp
+const MARGIN: i32 = 6;
+const LABEL_HEIGHT: i32 = 20;
+const BACKDROP: Color = Color::new(0, 0, 0, 0xc0);
+
+// where the pointer is over the timeline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hover {
+    // across the timeline's width, 0 to 1
+    pub fraction: f32,
+    pub time: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct Preview {
+    // width over height
+    pub aspect: f32,
+    pub texture: LazyTexture,
+}
+
+impl Preview {
+    fn new(image: RgbaImage) -> Self {
+        Self {
+            aspect: image.width() as f32 / image.height().max(1) as f32,
+            texture: LazyTexture::from_image(DynamicImage::from(image), FilterMode::Linear),
+        }
+    }
+}
+
+// decodes the frame under the pointer while it hovers the timeline, on an input of its
+// own that only ever seeks. requests that pile up while one decodes are skipped, only
+// the newest matters
+pub fn follow(
+    video_source: &Dynamic<Option<PathBuf>>,
+    hover: &Dynamic<Option<Hover>>,
+    preview: Dynamic<Option<Preview>>,
+) {
+    let (requests, received) = mpsc::channel::<(PathBuf, Duration)>();
+    std::thread::Builder::new()
+        .name("Preview Thread".into())
+        .spawn({
+            let preview = preview.clone();
+            move || {
+                let mut seeker: Option<Seeker> = None;
+                while let Ok(mut request) = received.recv() {
+                    while let Ok(newer) = received.try_recv() {
+                        request = newer;
+                    }
+                    let (video, time) = request;
+
+                    if seeker.as_ref().is_none_or(|seeker| seeker.video != video) {
+                        seeker = Seeker::open(&video)
+                            .map_err(|err| {
+                                tracing::error!("failed to preview {}: {err}", video.display());
+                            })
+                            .ok();
+                    }
+                    let Some(seeker) = seeker.as_mut() else {
+                        continue;
+                    };
+                    match seeker.frame_at(time) {
+                        Ok(Some(image)) => *preview.lock() = Some(Preview::new(image)),
+                        Ok(None) => {}
+                        Err(err) => tracing::error!("failed to preview {}: {err}", video.display()),
+                    }
+                }
+            }
+        })
+        .unwrap();
+
+    // a new video's frames aren't the old one's
+    video_source
+        .for_each({
+            let preview = preview.clone();
+            move |_| *preview.lock() = None
+        })
+        .persist();
+
+    let video_source = video_source.clone();
+    hover
+        .for_each(move |hover| {
+            if let (Some(hover), Some(video)) = (hover, video_source.get()) {
+                let _ = requests.send((video, hover.time));
+            }
+        })
+        .persist();
+}
+
+struct Seeker {
+    video: PathBuf,
+    ictx: ffmpeg::format::context::Input,
+    decoder: ffmpeg::decoder::Video,
+    stream_index: usize,
+    time_base: f64,
+    rescaler: Option<Rescaler>,
+}
+
+impl Seeker {
+    fn open(video: &Path) -> Result<Self, ffmpeg::Error> {
+        let ictx = open_input(video)?;
+        let stream = ictx
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .ok_or(ffmpeg::Error::StreamNotFound)?;
+        let stream_index = stream.index();
+        let time_base = f64::from(stream.time_base());
+        let decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+            .decoder()
+            .video()?;
+        Ok(Self {
+            video: video.to_path_buf(),
+            ictx,
+            decoder,
+            stream_index,
+            time_base,
+            rescaler: None,
+        })
+    }
+
+    // the frame on screen at `time`: from the keyframe before it, decoded forward
+    fn frame_at(&mut self, time: Duration) -> Result<Option<RgbaImage>, ffmpeg::Error> {
+        let target = (time.as_secs_f64() * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+        self.ictx.seek(target, ..=target)?;
+        self.decoder.flush();
+
+        let limit = time.as_secs_f64() + 0.0005;
+        let mut frame = ffmpeg::util::frame::Video::empty();
+        let mut shown = None;
+        'packets: for (stream, packet) in self.ictx.packets() {
+            if stream.index() != self.stream_index || self.decoder.send_packet(&packet).is_err() {
+                continue;
+            }
+            while self.decoder.receive_frame(&mut frame).is_ok() {
+                let past = frame.timestamp().unwrap_or(0) as f64 * self.time_base > limit;
+                if !past || shown.is_none() {
+                    shown = Some(frame.clone());
+                }
+                if past {
+                    break 'packets;
+                }
+            }
+        }
+
+        Ok(shown.map(|shown| {
+            crate::frame_to_image(&shown, Some(PREVIEW_SIZE), &mut self.rescaler)
+        }))
+    }
+}
+
+// sits over the player and shows the frame under the pointer above the timeline, with
+// its timestamp, while the pointer hovers it. clicks go through to the player
+#[derive(Debug)]
+pub struct HoverPopup {
+    hover: Dynamic<Option<Hover>>,
+    preview: Dynamic<Option<Preview>>,
+}
+
+impl HoverPopup {
+    pub fn new(hover: Dynamic<Option<Hover>>, preview: Dynamic<Option<Preview>>) -> Self {
+        Self { hover, preview }
+    }
+}
+
+impl Widget for HoverPopup {
+    fn redraw(&mut self, context: &mut GraphicsContext<'_, '_, '_, '_>) {
+        use cushy::context::Trackable;
+
+        let Some(hover) = self.hover.get_tracking_redraw(context) else {
+            return;
+        };
+        let preview = self.preview.get_tracking_redraw(context);
+
+        let size = context.gfx.size().into_signed();
+        let margin = Px::new(MARGIN);
+        let label_height = Px::new(LABEL_HEIGHT);
+        let (width, height) = match &preview {
+            Some(preview) => {
+                let longest = PREVIEW_SIZE as f32;
+                if preview.aspect >= 1. {
+                    (Px::from(longest), Px::from(longest / preview.aspect))
+                } else {
+                    (Px::from(longest * preview.aspect), Px::from(longest))
+                }
+            }
+            // the timestamp alone until the first frame is decoded
+            None => (Px::new(100), Px::ZERO),
+        };
+
+        // centered on the pointer, kept inside the player
+        let center = Px::from(hover.fraction * size.width.into_float());
+        let left = (center - width / 2).max(Px::ZERO).min(size.width - width);
+        let top = size.height - height - label_height - margin;
+        context.gfx.draw_shape(&Shape::filled_rect(
+            Rect::new(Point::new(left, top), Size::new(width, height + label_height)),
+            BACKDROP,
+        ));
+        if let Some(preview) = &preview {
+            context.gfx.draw_texture(
+                &preview.texture,
+                Rect::new(Point::new(left, top), Size::new(width, height)),
+                ZeroToOne::new(1.),
+            );
+        }
+        let label = format_timestamp(hover.time);
+        context.gfx.draw_text(
+            Text::new(&label, Color::WHITE)
+                .translate_by(Point::new(left + margin, top + height + margin / 2)),
+        );
+    }
+}
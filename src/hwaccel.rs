@@ -0,0 +1,76 @@
+use std::ptr;
+
+use ffmpeg_next as ffmpeg;
+use ffmpeg::ffi::{
+    av_frame_copy_props, av_hwdevice_ctx_create, av_hwframe_transfer_data, avcodec_find_decoder,
+    avcodec_get_hw_config, AVHWDeviceType, AV_CODEC_HW_CONFIG_METHOD_HW_DEVICE_CTX,
+};
+
+// the decoding api every machine of the platform has
+#[cfg(target_os = "macos")]
+const DEVICE_TYPE: Option<AVHWDeviceType> = Some(AVHWDeviceType::AV_HWDEVICE_TYPE_VIDEOTOOLBOX);
+#[cfg(target_os = "linux")]
+const DEVICE_TYPE: Option<AVHWDeviceType> = Some(AVHWDeviceType::AV_HWDEVICE_TYPE_VAAPI);
+#[cfg(target_os = "windows")]
+const DEVICE_TYPE: Option<AVHWDeviceType> = Some(AVHWDeviceType::AV_HWDEVICE_TYPE_D3D11VA);
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+const DEVICE_TYPE: Option<AVHWDeviceType> = None;
+
+// hands decoding of `context` to the gpu, false when the codec or the machine can't
+// and it stays in software. frames can then live in gpu memory, see `download`
+pub fn attach(context: &mut ffmpeg::codec::Context) -> bool {
+    let Some(device_type) = DEVICE_TYPE else {
+        return false;
+    };
+
+    unsafe {
+        let raw = context.as_mut_ptr();
+        let codec = avcodec_find_decoder((*raw).codec_id);
+        if codec.is_null() || !supports(codec, device_type) {
+            return false;
+        }
+
+        let mut device = ptr::null_mut();
+        if av_hwdevice_ctx_create(&mut device, device_type, ptr::null(), ptr::null_mut(), 0) < 0 {
+            return false;
+        }
+        // the codec context owns the reference from here and unrefs it when freed.
+        // ffmpeg's default format negotiation picks the device's format when it can
+        (*raw).hw_device_ctx = device;
+    }
+    true
+}
+
+unsafe fn supports(codec: *const ffmpeg::ffi::AVCodec, device_type: AVHWDeviceType) -> bool {
+    (0..)
+        .map(|idx| avcodec_get_hw_config(codec, idx))
+        .take_while(|config| !config.is_null())
+        .any(|config| {
+            (*config).device_type == device_type
+                && (*config).methods & AV_CODEC_HW_CONFIG_METHOD_HW_DEVICE_CTX as i32 != 0
+        })
+}
+
+// a copy in system memory of a frame decoded on the gpu, for the rescaler. none for
+// frames that already are there
+pub fn download(
+    frame: &ffmpeg::util::frame::Video,
+) -> Result<Option<ffmpeg::util::frame::Video>, ffmpeg::Error> {
+    unsafe {
+        if (*frame.as_ptr()).hw_frames_ctx.is_null() {
+            return Ok(None);
+        }
+
+        let mut software = ffmpeg::util::frame::Video::empty();
+        let err = av_hwframe_transfer_data(software.as_mut_ptr(), frame.as_ptr(), 0);
+        if err < 0 {
+            return Err(ffmpeg::Error::from(err));
+        }
+        // timestamps and the like
+        let err = av_frame_copy_props(software.as_mut_ptr(), frame.as_ptr());
+        if err < 0 {
+            return Err(ffmpeg::Error::from(err));
+        }
+        Ok(Some(software))
+    }
+}
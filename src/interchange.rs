@@ -0,0 +1,425 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use cushy::{
+    value::{Destination, Dynamic, Source},
+    widget::MakeWidget,
+};
+
+use crate::format_timestamp;
+use crate::formats::{
+    label_studio_config, label_studio_frame_task, label_studio_video_task, local_file_url,
+    read_markers, read_resolve_csv, write_coco, write_coco_keypoints, write_eaf, write_mot_csv,
+    write_resolve_csv, write_sportscode_xml,
+};
+use crate::media::extract_frames;
+use crate::palette::Palette;
+use crate::renditions::{probe_frame_rate, probe_frame_size, Renditions};
+use crate::skeletons::Skeleton;
+use crate::tags::{Tag, TagStore};
+
+// what resolve and most editors assume when a file doesn't say
+const FALLBACK_FRAME_RATE: f64 = 25.;
+
+// everything an import or export needs, cloned into its worker thread
+#[derive(Debug, Clone)]
+struct Interchange {
+    tags: Dynamic<TagStore>,
+    video_source: Dynamic<Option<PathBuf>>,
+    renditions: Dynamic<Renditions>,
+    categories: Dynamic<Vec<String>>,
+    palette: Dynamic<Palette>,
+    skeletons: Vec<Skeleton>,
+    status: Dynamic<String>,
+}
+
+impl Interchange {
+    // tags always live on the original's timeline
+    fn open_original(&self) -> Option<PathBuf> {
+        let video = self.video_source.get()?;
+        Some(self.renditions.map_ref(|renditions| renditions.original_of(&video)))
+    }
+
+    fn tags_of(&self, video: &Path) -> Vec<Tag> {
+        self.tags.map_ref(|tags| {
+            tags.tags()
+                .iter()
+                .filter(|tag| tag.video == video)
+                .cloned()
+                .collect()
+        })
+    }
+
+    // adds (video, time, end, label, note) tags, counting the ones refused because of locks
+    fn add_tags(
+        &self,
+        tags: impl IntoIterator<Item = (PathBuf, Duration, Option<Duration>, String, String)>,
+    ) -> (usize, usize) {
+        let mut imported = 0;
+        let mut skipped = 0;
+        // one undo takes the whole import back
+        self.tags.lock().group(|store| {
+            for (video, time, end, label, note) in tags {
+                let added = match end {
+                    Some(end) => store.add_range(video, time, end, label),
+                    None => store.add(video, time, label),
+                };
+                match added {
+                    Some(id) => {
+                        if !note.is_empty() {
+                            store.set_note(id, note);
+                        }
+                        imported += 1;
+                    }
+                    None => skipped += 1,
+                }
+            }
+        });
+        (imported, skipped)
+    }
+
+    fn report(&self, imported: usize, skipped: usize) {
+        self.status.set(if skipped > 0 {
+            format!("imported {imported} markers, skipped {skipped} (locked, out of range, or no video open for guides)")
+        } else {
+            format!("imported {imported} markers")
+        });
+    }
+
+    // runs `work` on a picker thread with what `pick` (pick_file, save_file...) chose
+    fn with_file(
+        &self,
+        dialog: rfd::FileDialog,
+        pick: fn(rfd::FileDialog) -> Option<PathBuf>,
+        work: impl FnOnce(&Self, PathBuf) + Send + 'static,
+    ) {
+        let this = self.clone();
+        std::thread::Builder::new()
+            .name("File Picker Thread".into())
+            .spawn(move || {
+                if let Some(path) = pick(dialog) {
+                    work(&this, path);
+                }
+            })
+            .unwrap();
+    }
+
+    fn import_markers(&self) {
+        // guides belong to the timeline rather than a clip, they go to the open video
+        let open_video = self.open_original();
+        let dialog = rfd::FileDialog::new()
+            .add_filter("editing project", &["kdenlive", "mlt"])
+            .add_filter("elan annotations", &["eaf"]);
+        self.with_file(dialog, rfd::FileDialog::pick_file, move |this, path| {
+            let markers = match read_markers(&path) {
+                Ok(markers) => markers,
+                Err(err) => {
+                    this.status.set(format!("failed to import {}: {err}", path.display()));
+                    return;
+                }
+            };
+
+            let total = markers.len();
+            let tags = markers
+                .into_iter()
+                .filter_map(|marker| {
+                    let video = marker.video.or_else(|| open_video.clone())?;
+                    Some((video, marker.time, marker.end, marker.label, marker.note))
+                })
+                .collect::<Vec<_>>();
+            let without_video = total - tags.len();
+            let (imported, skipped) = this.add_tags(tags);
+            this.report(imported, skipped + without_video);
+        });
+    }
+
+    fn import_resolve(&self) {
+        let Some(video) = self.open_original() else {
+            self.status.set(String::from("open the video the markers belong to first"));
+            return;
+        };
+        let dialog = rfd::FileDialog::new().add_filter("resolve markers", &["csv"]);
+        self.with_file(dialog, rfd::FileDialog::pick_file, move |this, path| {
+            let markers = match std::fs::read_to_string(&path)
+                .and_then(|text| read_resolve_csv(&text))
+            {
+                Ok(markers) => markers,
+                Err(err) => {
+                    this.status.set(format!("failed to import {}: {err}", path.display()));
+                    return;
+                }
+            };
+
+            let fps = probe_frame_rate(&video).unwrap_or(FALLBACK_FRAME_RATE);
+            let categories = this.categories.get();
+            let tags = markers
+                .iter()
+                .filter_map(|marker| {
+                    Some((
+                        video.clone(),
+                        marker.time(fps)?,
+                        marker.end(fps),
+                        marker.category(&categories),
+                        marker.notes.clone(),
+                    ))
+                })
+                .collect::<Vec<_>>();
+            let out_of_range = markers.len() - tags.len();
+            let (imported, skipped) = this.add_tags(tags);
+            this.report(imported, skipped + out_of_range);
+        });
+    }
+
+    fn export_resolve(&self) {
+        let Some(video) = self.open_original() else {
+            self.status.set(String::from("no video open"));
+            return;
+        };
+        let dialog = rfd::FileDialog::new()
+            .add_filter("resolve markers", &["csv"])
+            .set_file_name(export_name(&video, "markers", "csv"));
+        self.with_file(dialog, rfd::FileDialog::save_file, move |this, path| {
+            let fps = probe_frame_rate(&video).unwrap_or(FALLBACK_FRAME_RATE);
+            let csv = write_resolve_csv(&this.tags_of(&video), &this.categories.get(), fps);
+            this.write(&path, csv);
+        });
+    }
+
+    fn export_sportscode(&self) {
+        let Some(video) = self.open_original() else {
+            self.status.set(String::from("no video open"));
+            return;
+        };
+        let dialog = rfd::FileDialog::new()
+            .add_filter("sportscode xml", &["xml"])
+            .set_file_name(export_name(&video, "instances", "xml"));
+        self.with_file(dialog, rfd::FileDialog::save_file, move |this, path| {
+            let palette = this.palette.get();
+            let xml = write_sportscode_xml(&this.tags_of(&video), &this.categories.get(), |idx| {
+                palette.color(idx)
+            });
+            this.write(&path, xml);
+        });
+    }
+
+    fn export_elan(&self) {
+        let Some(video) = self.open_original() else {
+            self.status.set(String::from("no video open"));
+            return;
+        };
+        let dialog = rfd::FileDialog::new()
+            .add_filter("elan annotations", &["eaf"])
+            .set_file_name(export_name(&video, "annotations", "eaf"));
+        self.with_file(dialog, rfd::FileDialog::save_file, move |this, path| {
+            let eaf = write_eaf(&this.tags_of(&video), &this.categories.get(), &video);
+            this.write(&path, eaf);
+        });
+    }
+
+    fn export_tracks(&self) {
+        let Some(video) = self.open_original() else {
+            self.status.set(String::from("no video open"));
+            return;
+        };
+        let dialog = rfd::FileDialog::new()
+            .add_filter("mot tracks", &["txt", "csv"])
+            .set_file_name(export_name(&video, "tracks", "txt"));
+        self.with_file(dialog, rfd::FileDialog::save_file, move |this, path| {
+            let Some(size) = probe_frame_size(&video) else {
+                this.status.set(format!("failed to read the frame size of {}", video.display()));
+                return;
+            };
+            let fps = probe_frame_rate(&video).unwrap_or(FALLBACK_FRAME_RATE);
+            this.write(&path, write_mot_csv(&this.tags_of(&video), fps, size));
+        });
+    }
+
+    fn export_coco(&self) {
+        let Some(video) = self.open_original() else {
+            self.status.set(String::from("no video open"));
+            return;
+        };
+        let dialog = rfd::FileDialog::new()
+            .add_filter("coco annotations", &["json"])
+            .set_file_name(export_name(&video, "coco", "json"));
+        self.with_file(dialog, rfd::FileDialog::save_file, move |this, path| {
+            let Some(size) = probe_frame_size(&video) else {
+                this.status.set(format!("failed to read the frame size of {}", video.display()));
+                return;
+            };
+            let fps = probe_frame_rate(&video).unwrap_or(FALLBACK_FRAME_RATE);
+            let coco = write_coco(&this.tags_of(&video), &this.categories.get(), &video, fps, size);
+            this.write(&path, coco);
+        });
+    }
+
+    fn export_coco_keypoints(&self) {
+        let Some(video) = self.open_original() else {
+            self.status.set(String::from("no video open"));
+            return;
+        };
+        let dialog = rfd::FileDialog::new()
+            .add_filter("coco keypoints", &["json"])
+            .set_file_name(export_name(&video, "keypoints", "json"));
+        self.with_file(dialog, rfd::FileDialog::save_file, move |this, path| {
+            let Some(size) = probe_frame_size(&video) else {
+                this.status.set(format!("failed to read the frame size of {}", video.display()));
+                return;
+            };
+            let fps = probe_frame_rate(&video).unwrap_or(FALLBACK_FRAME_RATE);
+            let tags = this.tags_of(&video);
+            let coco = write_coco_keypoints(&tags, &this.skeletons, &video, fps, size);
+            this.write(&path, coco);
+        });
+    }
+
+    // the tasks and the labeling config for them, side by side
+    fn export_label_studio_video(&self) {
+        let Some(video) = self.open_original() else {
+            self.status.set(String::from("no video open"));
+            return;
+        };
+        let dialog = rfd::FileDialog::new()
+            .add_filter("label studio tasks", &["json"])
+            .set_file_name(export_name(&video, "tasks", "json"));
+        self.with_file(dialog, rfd::FileDialog::save_file, move |this, path| {
+            let fps = probe_frame_rate(&video).unwrap_or(FALLBACK_FRAME_RATE);
+            let name = video.file_name().unwrap_or_default().to_string_lossy();
+            let tags = this.tags_of(&video);
+            let task = label_studio_video_task(&local_file_url(&name), &tags, fps);
+            let config = label_studio_config(&this.categories.get(), &tags, false);
+            if let Err(err) = std::fs::write(path.with_extension("xml"), config) {
+                this.status.set(format!("failed to write the labeling config: {err}"));
+                return;
+            }
+            this.write(&path, serde_json::Value::Array(vec![task]).to_string());
+        });
+    }
+
+    // a png and an image task for every tagged moment, all in one folder
+    fn export_label_studio_frames(&self) {
+        let Some(video) = self.open_original() else {
+            self.status.set(String::from("no video open"));
+            return;
+        };
+        let dialog = rfd::FileDialog::new();
+        self.with_file(dialog, rfd::FileDialog::pick_folder, move |this, folder| {
+            let tags = this.tags_of(&video);
+            let mut times = tags.iter().map(|tag| tag.time).collect::<Vec<_>>();
+            times.sort();
+            times.dedup();
+
+            let mut tasks = Vec::new();
+            let mut failed = None;
+            let extracted = extract_frames(&video, &times, |time, image| {
+                this.status
+                    .set(format!("extracting frame {} of {}", tasks.len() + 1, times.len()));
+                let timestamp = format_timestamp(time).replace([':', '.'], "-");
+                let name = export_name(&video, &timestamp, "png");
+                if let Err(err) = image.save(folder.join(&name)) {
+                    failed = Some(format!("failed to save {name}: {err}"));
+                    return;
+                }
+                let labels = tags
+                    .iter()
+                    .filter(|tag| tag.time == time)
+                    .map(|tag| tag.label.clone())
+                    .collect::<Vec<_>>();
+                tasks.push(label_studio_frame_task(&local_file_url(&name), time, &labels));
+            });
+            if let Err(err) = extracted {
+                this.status.set(format!("failed to extract frames: {err}"));
+                return;
+            }
+            if let Some(failed) = failed {
+                this.status.set(failed);
+                return;
+            }
+
+            let config = label_studio_config(&this.categories.get(), &tags, true);
+            if let Err(err) = std::fs::write(folder.join("label-config.xml"), config) {
+                this.status.set(format!("failed to write the labeling config: {err}"));
+                return;
+            }
+            this.write(
+                &folder.join("tasks.json"),
+                serde_json::Value::Array(tasks).to_string(),
+            );
+        });
+    }
+
+    fn write(&self, path: &Path, contents: String) {
+        self.status.set(match std::fs::write(path, contents) {
+            Ok(()) => format!("exported to {}", path.display()),
+            Err(err) => format!("failed to write {}: {err}", path.display()),
+        });
+    }
+}
+
+// <video stem>-<what>.<extension>
+fn export_name(video: &Path, what: &str, extension: &str) -> String {
+    let stem = video
+        .file_stem()
+        .map_or_else(|| String::from("video"), |stem| stem.to_string_lossy().into_owned());
+    format!("{stem}-{what}.{extension}")
+}
+
+// importing and exporting tags in other tools' formats
+pub fn interchange_panel(
+    tags: Dynamic<TagStore>,
+    video_source: Dynamic<Option<PathBuf>>,
+    renditions: Dynamic<Renditions>,
+    categories: Dynamic<Vec<String>>,
+    palette: Dynamic<Palette>,
+    skeletons: Vec<Skeleton>,
+) -> impl MakeWidget {
+    let status = Dynamic::new(String::new());
+    let interchange = Interchange {
+        tags,
+        video_source,
+        renditions,
+        categories,
+        palette,
+        skeletons,
+        status: status.clone(),
+    };
+
+    let button = |label: &str, action: fn(&Interchange)| {
+        let interchange = interchange.clone();
+        label.into_button().on_click(move |_| action(&interchange))
+    };
+
+    "Import / export"
+        .and(button(
+            "import kdenlive/shotcut/elan markers",
+            Interchange::import_markers,
+        ))
+        .and(
+            button("import resolve markers", Interchange::import_resolve)
+                .and(button("export resolve markers", Interchange::export_resolve))
+                .into_columns(),
+        )
+        .and(
+            button("export sportscode xml", Interchange::export_sportscode)
+                .and(button("export elan eaf", Interchange::export_elan))
+                .and(button("export region tracks", Interchange::export_tracks))
+                .and(button("export coco outlines", Interchange::export_coco))
+                .and(button("export coco keypoints", Interchange::export_coco_keypoints))
+                .into_columns(),
+        )
+        .and(
+            button(
+                "export label studio video tasks",
+                Interchange::export_label_studio_video,
+            )
+            .and(button(
+                "export label studio frames",
+                Interchange::export_label_studio_frames,
+            ))
+            .into_columns(),
+        )
+        .and(status)
+        .into_rows()
+}
@@ -0,0 +1,403 @@
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use cushy::context::{EventContext, GraphicsContext};
+use cushy::figures::units::Px;
+use cushy::figures::Point;
+use cushy::kludgine::app::winit::event::{DeviceId, MouseButton};
+use cushy::kludgine::app::winit::keyboard::{Key, ModifiersState, NamedKey};
+use cushy::value::{Destination, Dynamic, Source};
+use cushy::widget::{
+    EventHandling, MakeWidget, SharedCallback, WidgetRef, WrapperWidget, HANDLED, IGNORED,
+};
+use cushy::window::KeyEvent;
+use serde::{Deserialize, Serialize};
+
+use crate::safe_mode::is_safe_mode;
+use crate::settings::config_dir;
+use crate::tags::{TagEdge, TagId, TagStore};
+
+const KEYBINDINGS_FILE: &str = "keybindings.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct KeyCombo {
+    pub key: String,
+    #[serde(default)]
+    pub ctrl: bool,
+    #[serde(default)]
+    pub shift: bool,
+    #[serde(default)]
+    pub alt: bool,
+    #[serde(default)]
+    pub logo: bool,
+}
+
+impl KeyCombo {
+    // modifier keys alone don't make a combo, recording waits for the real key
+    pub fn from_key(key: &Key, modifiers: ModifiersState) -> Option<Self> {
+        let key = match key {
+            Key::Character(text) => text.to_uppercase(),
+            Key::Named(
+                NamedKey::Control
+                | NamedKey::Shift
+                | NamedKey::Alt
+                | NamedKey::Super
+                | NamedKey::Meta
+                | NamedKey::Hyper,
+            ) => return None,
+            Key::Named(named) => format!("{named:?}"),
+            _ => return None,
+        };
+
+        Some(Self {
+            key,
+            ctrl: modifiers.control_key(),
+            shift: modifiers.shift_key(),
+            alt: modifiers.alt_key(),
+            logo: modifiers.super_key(),
+        })
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "Ctrl+"),
+            (self.alt, "Alt+"),
+            (self.shift, "Shift+"),
+            (self.logo, "Super+"),
+        ] {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+        f.write_str(&self.key)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Keybindings {
+    #[serde(default)]
+    pub categories: BTreeMap<String, KeyCombo>,
+}
+
+impl Keybindings {
+    pub fn load() -> Self {
+        if is_safe_mode() {
+            return Self::default();
+        }
+
+        config_dir()
+            .map(|dir| dir.join(KEYBINDINGS_FILE))
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(dir) = config_dir().filter(|_| !is_safe_mode()) else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(&dir)?;
+
+        let json = serde_json::to_vec_pretty(self)?;
+        std::fs::write(dir.join(KEYBINDINGS_FILE), json)
+    }
+
+    // a combo belongs to at most one category, binding it again steals it
+    pub fn bind_category(&mut self, category: &str, combo: KeyCombo) {
+        self.categories.retain(|_, bound| *bound != combo);
+        self.categories.insert(category.to_string(), combo);
+    }
+
+    pub fn category_for(&self, combo: &KeyCombo) -> Option<&str> {
+        self.categories
+            .iter()
+            .find(|(_, bound)| *bound == combo)
+            .map(|(category, _)| category.as_str())
+    }
+
+    pub fn shortcut_for(&self, category: &str) -> Option<&KeyCombo> {
+        self.categories.get(category)
+    }
+}
+
+// click to start recording, the next key combination pressed gets stored
+#[derive(Debug)]
+pub struct KeyRecorder {
+    child: WidgetRef,
+    recording: Dynamic<bool>,
+    on_recorded: SharedCallback<KeyCombo>,
+}
+
+impl KeyRecorder {
+    pub fn new(current: Option<KeyCombo>, on_recorded: SharedCallback<KeyCombo>) -> Self {
+        let recording = Dynamic::new(false);
+        let label = recording.map_each(move |recording| {
+            if *recording {
+                String::from("press keys...")
+            } else {
+                current
+                    .as_ref()
+                    .map_or_else(|| String::from("record shortcut"), KeyCombo::to_string)
+            }
+        });
+
+        Self {
+            child: WidgetRef::new(label.contain()),
+            recording,
+            on_recorded,
+        }
+    }
+}
+
+impl WrapperWidget for KeyRecorder {
+    fn child_mut(&mut self) -> &mut WidgetRef {
+        &mut self.child
+    }
+
+    fn hit_test(&mut self, location: Point<Px>, context: &mut EventContext<'_>) -> bool {
+        true
+    }
+
+    fn accept_focus(&mut self, context: &mut EventContext<'_>) -> bool {
+        true
+    }
+
+    fn mouse_down(
+        &mut self,
+        location: Point<Px>,
+        device_id: DeviceId,
+        button: MouseButton,
+        context: &mut EventContext<'_>,
+    ) -> EventHandling {
+        self.recording.set(true);
+        context.focus();
+        HANDLED
+    }
+
+    fn blur(&mut self, context: &mut EventContext<'_>) {
+        self.recording.set(false);
+        context.set_needs_redraw();
+    }
+
+    fn focus(&mut self, context: &mut EventContext<'_>) {
+        context.set_needs_redraw();
+    }
+
+    fn redraw_foreground(&mut self, context: &mut GraphicsContext<'_, '_, '_, '_>) {
+        if context.focused(true) {
+            context.draw_focus_ring();
+        }
+    }
+
+    fn keyboard_input(
+        &mut self,
+        device_id: DeviceId,
+        input: KeyEvent,
+        is_synthetic: bool,
+        context: &mut EventContext<'_>,
+    ) -> EventHandling {
+        if !input.state.is_pressed() {
+            return IGNORED;
+        }
+
+        // reached with tab, enter or space starts recording like a click would
+        if !self.recording.get() {
+            return match input.logical_key {
+                Key::Named(NamedKey::Enter | NamedKey::Space) => {
+                    self.recording.set(true);
+                    HANDLED
+                }
+                _ => IGNORED,
+            };
+        }
+
+        if input.logical_key == Key::Named(NamedKey::Escape) {
+            self.recording.set(false);
+            return HANDLED;
+        }
+
+        let Some(combo) = KeyCombo::from_key(&input.logical_key, context.modifiers().state())
+        else {
+            return HANDLED;
+        };
+
+        self.recording.set(false);
+        self.on_recorded.invoke(combo);
+        HANDLED
+    }
+}
+
+// sits at the root and turns bound key combinations into category triggers
+#[derive(Debug)]
+pub struct CategoryShortcuts {
+    child: WidgetRef,
+    keybindings: Dynamic<Keybindings>,
+    on_category: SharedCallback<String>,
+}
+
+impl CategoryShortcuts {
+    pub fn new(
+        child: impl MakeWidget,
+        keybindings: Dynamic<Keybindings>,
+        on_category: SharedCallback<String>,
+    ) -> Self {
+        Self {
+            child: WidgetRef::new(child),
+            keybindings,
+            on_category,
+        }
+    }
+}
+
+impl WrapperWidget for CategoryShortcuts {
+    fn child_mut(&mut self) -> &mut WidgetRef {
+        &mut self.child
+    }
+
+    fn keyboard_input(
+        &mut self,
+        device_id: DeviceId,
+        input: KeyEvent,
+        is_synthetic: bool,
+        context: &mut EventContext<'_>,
+    ) -> EventHandling {
+        if !input.state.is_pressed() || input.repeat {
+            return IGNORED;
+        }
+
+        let Some(combo) = KeyCombo::from_key(&input.logical_key, context.modifiers().state())
+        else {
+            return IGNORED;
+        };
+
+        let category = self
+            .keybindings
+            .map_ref(|keybindings| keybindings.category_for(&combo).map(str::to_string));
+
+        match category {
+            Some(category) => {
+                self.on_category.invoke(category);
+                HANDLED
+            }
+            None => IGNORED,
+        }
+    }
+}
+
+// alt+arrows nudge the selected tag's start, ctrl+alt+arrows its end,
+// by one frame or by a whole second with shift held
+#[derive(Debug)]
+pub struct TagNudging {
+    child: WidgetRef,
+    tags: Dynamic<TagStore>,
+    selected: Dynamic<Option<TagId>>,
+    frame_duration: Dynamic<Duration>,
+}
+
+impl TagNudging {
+    pub fn new(
+        child: impl MakeWidget,
+        tags: Dynamic<TagStore>,
+        selected: Dynamic<Option<TagId>>,
+        frame_duration: Dynamic<Duration>,
+    ) -> Self {
+        Self {
+            child: WidgetRef::new(child),
+            tags,
+            selected,
+            frame_duration,
+        }
+    }
+}
+
+impl WrapperWidget for TagNudging {
+    fn child_mut(&mut self) -> &mut WidgetRef {
+        &mut self.child
+    }
+
+    fn keyboard_input(
+        &mut self,
+        device_id: DeviceId,
+        input: KeyEvent,
+        is_synthetic: bool,
+        context: &mut EventContext<'_>,
+    ) -> EventHandling {
+        let modifiers = context.modifiers().state();
+        if !input.state.is_pressed() || !modifiers.alt_key() {
+            return IGNORED;
+        }
+
+        let direction = match input.logical_key {
+            Key::Named(NamedKey::ArrowLeft) => -1.,
+            Key::Named(NamedKey::ArrowRight) => 1.,
+            _ => return IGNORED,
+        };
+
+        let Some(id) = self.selected.get() else {
+            return IGNORED;
+        };
+
+        let step = if modifiers.shift_key() {
+            1.
+        } else {
+            self.frame_duration.get().as_secs_f64()
+        };
+        let edge = if modifiers.control_key() {
+            TagEdge::End
+        } else {
+            TagEdge::Start
+        };
+
+        self.tags.lock().nudge(id, edge, direction * step);
+        HANDLED
+    }
+}
+
+// i marks where a range tag starts and o where it ends, bound categories come first
+#[derive(Debug)]
+pub struct RangeMarking {
+    child: WidgetRef,
+    on_mark: SharedCallback<TagEdge>,
+}
+
+impl RangeMarking {
+    pub fn new(child: impl MakeWidget, on_mark: SharedCallback<TagEdge>) -> Self {
+        Self {
+            child: WidgetRef::new(child),
+            on_mark,
+        }
+    }
+}
+
+impl WrapperWidget for RangeMarking {
+    fn child_mut(&mut self) -> &mut WidgetRef {
+        &mut self.child
+    }
+
+    fn keyboard_input(
+        &mut self,
+        device_id: DeviceId,
+        input: KeyEvent,
+        is_synthetic: bool,
+        context: &mut EventContext<'_>,
+    ) -> EventHandling {
+        let modifiers = context.modifiers().state();
+        if !input.state.is_pressed()
+            || input.repeat
+            || modifiers.control_key()
+            || modifiers.alt_key()
+            || modifiers.super_key()
+        {
+            return IGNORED;
+        }
+
+        let edge = match &input.logical_key {
+            Key::Character(text) if text.eq_ignore_ascii_case("i") => TagEdge::Start,
+            Key::Character(text) if text.eq_ignore_ascii_case("o") => TagEdge::End,
+            _ => return IGNORED,
+        };
+        self.on_mark.invoke(edge);
+        HANDLED
+    }
+}
@@ -1,11 +1,77 @@
 #![allow(dead_code, unused_variables)]
 
-mod counter;
+mod active_learning;
+mod activity;
+mod appearance;
+mod audio;
+mod backups;
+mod categories;
+mod chapters;
+mod classify;
+mod clock;
+#[cfg(feature = "cpu-window")]
+mod cpu_window;
+mod database;
+mod export;
+mod formats;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod history;
+mod hover_preview;
+mod hwaccel;
+mod interchange;
+mod keybindings;
+mod media;
 mod menu;
+#[cfg(feature = "midi")]
+mod midi;
+mod mosaic;
+#[cfg(feature = "onnx")]
+mod onnx;
+mod organize;
+mod osc;
+mod palette;
+mod pipelines;
+mod project;
+mod qc;
+mod qc_rules;
+mod queue;
+mod quick_tags;
+mod regions;
+mod render_mode;
+mod remote;
+mod renditions;
+mod replay;
+mod review_later;
+mod rpc;
+mod safe_mode;
+mod settings;
+mod shortcuts;
+mod similarity;
+mod skeletons;
+mod spherical;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod stabilize;
+mod stereo;
+mod subtitles;
+mod tag_list;
+mod tag_spans;
+mod tag_tools;
+mod tags;
+mod telemetry;
+mod thumbnails;
+mod timeline;
+mod timings;
+mod tracking;
+mod trash;
+mod vertical_crop;
 mod video_player;
+mod webhooks;
+mod yuv;
 
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     pin::Pin,
     sync::{Arc, Mutex},
     task::Poll,
@@ -14,48 +80,99 @@ use std::{
 use ffmpeg_next as ffmpeg;
 
 use cushy::figures::units::Lp;
-use cushy::kludgine::image::{DynamicImage, ImageBuffer, ImageReader, Rgb};
-use cushy::kludgine::wgpu::{FilterMode, TextureFormat, TextureUsages};
-use cushy::kludgine::{AnyTexture, LazyTexture};
-use cushy::value::{Destination, Dynamic, Switchable};
-use cushy::widget::{MakeWidget, SharedCallback};
-use cushy::widgets::{layers::Modal, Image};
+use cushy::kludgine::image::{DynamicImage, ImageBuffer, ImageReader, Rgb, RgbaImage};
+use cushy::kludgine::wgpu::FilterMode;
+use cushy::kludgine::LazyTexture;
+use cushy::styles::Color;
+use cushy::value::{Destination, Dynamic, MapEach, Source, Switchable};
+use cushy::widget::{EventHandling, MakeWidget, SharedCallback, WidgetList, HANDLED, IGNORED};
+use cushy::widgets::{
+    image::ImageScaling,
+    layers::{Modal, OverlayLayer},
+    progress::ProgressBar,
+    Image,
+};
 use cushy::WithClone;
 
-use futures::{future::OptionFuture, Future, FutureExt};
-
-use counter::Counter;
-use menu::MainMenu;
-use video_player::{ControlCommand, VideoPlayer};
-
-pub struct StreamClock {
-    time_base_seconds: f64,
-    start_time: std::time::Instant,
-}
-
-impl StreamClock {
-    pub fn new(stream: &ffmpeg::format::stream::Stream) -> Self {
-        let time_base_seconds = stream.time_base();
-        let time_base_seconds =
-            time_base_seconds.numerator() as f64 / time_base_seconds.denominator() as f64;
-        let start_time = std::time::Instant::now();
-
-        Self {
-            time_base_seconds,
-            start_time,
-        }
-    }
-
-    pub fn convert_pts_to_instant(&self, pts: Option<i64>) -> Option<std::time::Duration> {
-        pts.and_then(|pts| {
-            let pts_since_start =
-                std::time::Duration::from_secs_f64(pts as f64 * self.time_base_seconds);
-
-            self.start_time.checked_add(pts_since_start)
-        })
-        .map(|absolute_pts| absolute_pts.duration_since(std::time::Instant::now()))
-    }
-}
+use futures::Future;
+
+use activity::{activity_chart, ActivityLog};
+use appearance::{appearance_controls, Appearance};
+use audio::AudioDecoder;
+use clock::PlaybackClock;
+use backups::{backup_panel, Backup, BackupSettings};
+use categories::{category_buttons, category_editor};
+use chapters::{chapter_list_panel, Chapter};
+use classify::{Classifier, FrameClassifier};
+use database::TagDatabase;
+use export::{export_panel, ExportProfile};
+use formats::QueueEntry;
+use hover_preview::{Hover, HoverPopup, Preview};
+use interchange::interchange_panel;
+use keybindings::{CategoryShortcuts, Keybindings, RangeMarking, TagNudging};
+use menu::{video_context_menu, MainMenu, MainMenuOptions, VideoMenuOptions};
+use mosaic::Mosaic;
+use organize::organize_panel;
+use palette::Palette;
+use pipelines::{pipelines_panel, Denoise, FrameFilter, PipelinePreset};
+use project::Workspace;
+use qc::QcScanner;
+use qc_rules::{qc_rules_panel, QcRules};
+use queue::{library, load_queue, queue_panel, save_queue};
+use quick_tags::quick_tags_panel;
+use regions::{DrawTool, Keyframe, Outline, Polygon, Region, RegionOverlay};
+use skeletons::{skeleton_templates, Pose, Skeleton};
+use renditions::{probe_offset, Renditions};
+use replay::Recorder;
+use review_later::{load_review_queue, review_later_panel, ReviewItem};
+use settings::{ScalingMode, Settings};
+use shortcuts::{Action, Shortcuts};
+use similarity::similarity_panel;
+use spherical::{is_equirectangular, ViewDirection};
+use stabilize::Stabilizer;
+use stereo::StereoLayout;
+use subtitles::{SubtitleSource, Subtitles, Track};
+use tag_tools::{gap_report_tool, ripple_shift_tool};
+use tag_list::tag_list_panel;
+use tag_spans::{Span, TagSpans};
+use timeline::Timeline;
+use tags::{TagEdge, TagId, TagStore};
+use telemetry::{telemetry_bar, Telemetry};
+use thumbnails::Thumbnail;
+use timings::{timing_overlay, FrameTiming};
+use trash::trash_panel;
+pub use export::ExportWorker;
+#[cfg(feature = "gamepad")]
+pub use gamepad::listen as listen_for_gamepads;
+pub use media::warm_up as warm_up_media;
+use media::MediaStream;
+#[cfg(feature = "midi")]
+pub use midi::{enabled_from_args as midi_enabled_from_args, MidiControl};
+pub use osc::{address_from_args as osc_address_from_args, serve_udp as serve_osc};
+pub use remote::{Remote, RemoteCommand};
+pub use replay::{replay, script_from_args as replay_script_from_args};
+#[cfg(feature = "cpu-window")]
+pub use render_mode::relaunch_with_cpu_window;
+pub use render_mode::{probe_gpu_from_args, relaunch_in_software_mode, RenderMode};
+pub use rpc::RpcTransport;
+pub use safe_mode::is_safe_mode;
+use video_player::{
+    draw_tool_controls, scaling_controls, transport_bar, CancelToken, ControlCommand, PipelineContext, VideoFrame,
+    VideoPlayer, WHEEL_SEEK_SECONDS,
+};
+use webhooks::{webhook_editor, Webhooks};
+use yuv::YuvFrame;
+
+const CANCEL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+// stream seconds behind the clock a frame can be and still be shown
+const LATE_FRAME_SECONDS: f64 = 0.05;
+const MAX_DROPPED_IN_ROW: usize = 5;
+// unfiltered frames kept for the comparison wipe until their filtered ones come out
+const COMPARED_FRAMES: usize = 8;
+// how often the packet reader looks for commands while the decoder's queue is full
+const CONTROL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+// packets read ahead of the decoder, a paused decoder stops the reader once it fills
+const PACKET_QUEUE_LENGTH: usize = 64;
 
 pub fn yield_now() -> YieldNow {
     YieldNow(false)
@@ -77,213 +194,2073 @@ impl Future for YieldNow {
     }
 }
 
+// what the packet reader hands the decoder thread
+enum DecoderInput {
+    Packet(ffmpeg::codec::packet::Packet),
+    // the reader moved to the keyframe before this time, decode up to it
+    Seek(std::time::Duration),
+    EndOfStream,
+}
+
+enum FrameDue {
+    Show,
+    // decoded on the way to a seek target, or queued before a seek
+    Skip,
+    // its moment passed a while ago, decoding fell behind
+    Drop,
+    Stop,
+}
+
+// the decoder thread's side of the transport controls
+struct Playback {
+    clock: PlaybackClock,
+    time_base_seconds: f64,
+    controls: std::sync::mpsc::Receiver<ControlCommand>,
+    stopped: bool,
+    // seeks sent by the reader that the decoder hasn't reached yet
+    pending_seeks: Arc<std::sync::atomic::AtomicUsize>,
+    seek_target: Option<f64>,
+    // frames to show while paused, one per step forward
+    steps: usize,
+    // late frames dropped since the last one shown
+    dropped_in_row: usize,
+}
+
+impl Playback {
+    // `VideoDecoder` already paused, resumed or sped up the shared clock, the
+    // commands only wake the decoder up here
+    fn apply(&mut self, command: ControlCommand) {
+        match command {
+            ControlCommand::Stop => self.stopped = true,
+            ControlCommand::Play => self.steps = 0,
+            ControlCommand::StepForward => self.steps += 1,
+            _ => {}
+        }
+    }
+
+    fn seeking(&self) -> bool {
+        self.pending_seeks.load(std::sync::atomic::Ordering::Acquire) > 0
+    }
+
+    fn seek(&mut self, target: std::time::Duration) {
+        self.seek_target = Some(target.as_secs_f64());
+    }
+
+    fn seconds(&self, pts: Option<i64>) -> Option<f64> {
+        pts.map(|pts| pts as f64 * self.time_base_seconds)
+    }
+
+    // blocks until the frame at `pts` is due, staying responsive to commands while
+    // paused. the first frame at a seek target shows right away, even when paused
+    // a frame whose moment came already. ones too late to matter are dropped so
+    // playback catches up, but when decoding can't keep up at all every few still
+    // make it to the screen
+    fn due_late(&mut self, seconds: f64) -> FrameDue {
+        let late = self.clock.seconds().map_or(0., |now| now - seconds);
+        if late > LATE_FRAME_SECONDS && self.dropped_in_row < MAX_DROPPED_IN_ROW {
+            self.dropped_in_row += 1;
+            return FrameDue::Drop;
+        }
+        self.dropped_in_row = 0;
+        FrameDue::Show
+    }
+
+    fn wait_for(&mut self, pts: Option<i64>, cancel: &CancelToken) -> FrameDue {
+        while let Ok(command) = self.controls.try_recv() {
+            self.apply(command);
+        }
+
+        if let Some(target) = self.seek_target {
+            // half a millisecond covers targets rounded to a tag's precision
+            match self.seconds(pts) {
+                Some(seconds) if seconds + 0.0005 >= target => {
+                    self.seek_target = None;
+                    self.clock.anchor(seconds);
+                    return FrameDue::Show;
+                }
+                _ => return FrameDue::Skip,
+            }
+        }
+
+        // frames without a timestamp can't be paced
+        let Some(seconds) = self.seconds(pts) else {
+            return FrameDue::Show;
+        };
+
+        loop {
+            if self.stopped || cancel.is_cancelled() {
+                return FrameDue::Stop;
+            }
+            if self.seeking() {
+                return FrameDue::Skip;
+            }
+            // the first frame after opening decides where the clock starts
+            if self.clock.anchor_if_unset(seconds) {
+                return FrameDue::Show;
+            }
+
+            if self.steps > 0 && self.clock.is_paused() {
+                self.steps -= 1;
+                // playing on continues from the stepped to frame
+                self.clock.anchor(seconds);
+                return FrameDue::Show;
+            }
+
+            let timeout = match self.clock.until(seconds) {
+                Some(delay) if !delay.is_zero() => delay.min(CANCEL_POLL_INTERVAL),
+                Some(_) => return self.due_late(seconds),
+                None => CANCEL_POLL_INTERVAL,
+            };
+
+            match self.controls.recv_timeout(timeout) {
+                Ok(command) => self.apply(command),
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => std::thread::sleep(timeout),
+            }
+        }
+    }
+}
+
+// a shown frame, the unfiltered one of the same moment while filtered frames are
+// being compared to them, and how long decoding took
+type FrameCallback = Box<
+    dyn FnMut(&ffmpeg::util::frame::Video, Option<&ffmpeg::util::frame::Video>, std::time::Duration)
+        + Send,
+>;
+
 struct VideoDecoder {
     control_sender: std::sync::mpsc::Sender<ControlCommand>,
-    packet_sender: std::sync::mpsc::Sender<ffmpeg::codec::packet::Packet>,
+    clock: PlaybackClock,
+    input_sender: Option<std::sync::mpsc::SyncSender<DecoderInput>>,
+    pending_seeks: Arc<std::sync::atomic::AtomicUsize>,
+    // every frame up to the end of the stream was handed on
+    drained: Arc<std::sync::atomic::AtomicBool>,
+    // frames left out because they were late, since opening
+    dropped: Arc<std::sync::atomic::AtomicUsize>,
     receiver_thread: Option<std::thread::JoinHandle<()>>,
+    cancel: CancelToken,
 }
 
 impl Drop for VideoDecoder {
     fn drop(&mut self) {
+        self.cancel.cancel();
         if let Some(handle) = self.receiver_thread.take() {
-            drop(handle)
+            let _ = handle.join();
         }
     }
 }
 
+// on the gpu when asked for and it opens there, in software otherwise
+fn open_video_decoder(
+    stream: &ffmpeg::format::stream::Stream,
+    hardware: bool,
+) -> Result<ffmpeg::decoder::Video, ffmpeg::Error> {
+    let codec = stream.parameters().id();
+    if hardware {
+        let mut context = ffmpeg::codec::Context::from_parameters(stream.parameters())?;
+        if hwaccel::attach(&mut context) {
+            match context.decoder().video() {
+                Ok(decoder) => return Ok(decoder),
+                Err(err) => tracing::warn!(
+                    "failed to open the hardware decoder for {codec:?}, decoding in software: {err}"
+                ),
+            }
+        } else {
+            tracing::warn!("no hardware decoding for {codec:?} here, decoding in software");
+        }
+    }
+    ffmpeg::codec::Context::from_parameters(stream.parameters())?
+        .decoder()
+        .video()
+}
+
 impl VideoDecoder {
     fn start(
         stream: &ffmpeg::format::stream::Stream,
-        mut frame_callback: Box<dyn FnMut(&ffmpeg::util::frame::Video) + Send>,
-    ) -> Self {
+        cancel: CancelToken,
+        clock: PlaybackClock,
+        hardware: bool,
+        mut filter: Option<FrameFilter>,
+        compare: Dynamic<Option<f32>>,
+        mut frame_callback: FrameCallback,
+    ) -> Result<Self, ffmpeg::Error> {
         let (control_sender, control_receiver) = std::sync::mpsc::channel::<ControlCommand>();
-        let (packet_sender, packet_receiver) =
-            std::sync::mpsc::channel::<ffmpeg::codec::packet::Packet>();
-
-        let decoder_ctx = ffmpeg::codec::Context::from_parameters(stream.parameters()).unwrap();
-        let mut packet_decoder = decoder_ctx.decoder().video().unwrap();
-
-        let clock = StreamClock::new(stream);
+        let (input_sender, input_receiver) =
+            std::sync::mpsc::sync_channel::<DecoderInput>(PACKET_QUEUE_LENGTH);
+        let pending_seeks = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let drained = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let dropped = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let mut packet_decoder = open_video_decoder(stream, hardware)?;
+
+        let mut playback = Playback {
+            clock: clock.clone(),
+            time_base_seconds: f64::from(stream.time_base()),
+            controls: control_receiver,
+            stopped: false,
+            pending_seeks: pending_seeks.clone(),
+            seek_target: None,
+            steps: 0,
+            dropped_in_row: 0,
+        };
+        let receiver_cancel = cancel.clone();
+        let receiver_drained = drained.clone();
+        let receiver_dropped = dropped.clone();
+        // unfiltered frames waiting for the filters to hand out theirs
+        let mut originals = std::collections::VecDeque::new();
 
         let receiver_thread = std::thread::Builder::new()
             .name("Receiver Thread".into())
-            .spawn(move || {
-                futures::executor::block_on(async move {
-                    let packet_receiver_impl = async {
-                        loop {
-                            let Ok(packet) = packet_receiver.recv() else {
-                                break;
-                            };
+            .spawn(move || loop {
+                if receiver_cancel.is_cancelled() {
+                    break;
+                }
 
-                            yield_now().await;
+                let input = match input_receiver.recv_timeout(CANCEL_POLL_INTERVAL) {
+                    Ok(input) => input,
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => continue,
+                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+                };
+
+                let mut decode_started = std::time::Instant::now();
+                let end_of_stream = matches!(input, DecoderInput::EndOfStream);
+                match input {
+                    // queued before a seek that is still on its way
+                    DecoderInput::Packet(_) if playback.seeking() => continue,
+                    DecoderInput::Packet(packet) => {
+                        // a damaged packet costs a frame or two, not the playback
+                        if let Err(err) = packet_decoder.send_packet(&packet) {
+                            tracing::warn!("skipping a packet the decoder refused: {err}");
+                            continue;
+                        }
+                    }
+                    DecoderInput::Seek(target) => {
+                        let remaining = playback
+                            .pending_seeks
+                            .fetch_sub(1, std::sync::atomic::Ordering::AcqRel)
+                            - 1;
+                        if remaining == 0 {
+                            packet_decoder.flush();
+                            if let Some(filter) = &mut filter {
+                                filter.reset();
+                            }
+                            originals.clear();
+                            playback.seek(target);
+                        }
+                        continue;
+                    }
+                    // frames the decoder held back come out after this
+                    DecoderInput::EndOfStream => {
+                        let _ = packet_decoder.send_eof();
+                    }
+                }
+                let mut decoded_frame = ffmpeg::util::frame::Video::empty();
+
+                while packet_decoder.receive_frame(&mut decoded_frame).is_ok() {
+                    let decode_time = decode_started.elapsed();
+
+                    match playback.wait_for(decoded_frame.pts(), &receiver_cancel) {
+                        // frames decoded on the gpu come back for the rescaler, only the
+                        // shown ones
+                        FrameDue::Show => match hwaccel::download(&decoded_frame) {
+                            Ok(downloaded) => {
+                                let shown = downloaded.as_ref().unwrap_or(&decoded_frame);
+                                if filter.is_some() && compare.get().is_some() {
+                                    if originals.len() == COMPARED_FRAMES {
+                                        originals.pop_front();
+                                    }
+                                    originals.push_back(shown.clone());
+                                }
+                                let filtered = filter.as_mut().map(|filter| {
+                                    filter.process(shown, |filtered| {
+                                        // filters that hold frames back hand them out later
+                                        let original = originals
+                                            .iter()
+                                            .find(|original| original.pts() == filtered.pts());
+                                        frame_callback(filtered, original, decode_time)
+                                    })
+                                });
+                                match filtered {
+                                    Some(Ok(())) => {}
+                                    // a preset the video doesn't agree with plays it unfiltered
+                                    Some(Err(err)) => {
+                                        tracing::warn!(
+                                            "failed to filter a frame, playing unfiltered: {err}"
+                                        );
+                                        filter = None;
+                                        frame_callback(shown, None, decode_time);
+                                    }
+                                    None => frame_callback(shown, None, decode_time),
+                                }
+                            }
+                            Err(err) => {
+                                tracing::error!("failed to download a decoded frame: {err}")
+                            }
+                        },
+                        FrameDue::Skip => {}
+                        FrameDue::Drop => {
+                            receiver_dropped.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        }
+                        // dropping the input receiver on the way out also stops the reader
+                        FrameDue::Stop => return,
+                    }
+                    decode_started = std::time::Instant::now();
+                }
+                if end_of_stream {
+                    receiver_drained.store(true, std::sync::atomic::Ordering::Release);
+                }
+            })
+            .unwrap();
+
+        Ok(Self {
+            control_sender,
+            clock,
+            input_sender: Some(input_sender),
+            pending_seeks,
+            drained,
+            dropped,
+            receiver_thread: Some(receiver_thread),
+            cancel,
+        })
+    }
+
+    // hands the packet back when the queue is full, so the reader can keep
+    // forwarding commands to a paused decoder instead of blocking
+    pub fn try_get_packet(
+        &self,
+        packet: ffmpeg::codec::packet::packet::Packet,
+    ) -> Result<(), std::sync::mpsc::TrySendError<ffmpeg::codec::packet::packet::Packet>> {
+        let Some(sender) = &self.input_sender else {
+            return Err(std::sync::mpsc::TrySendError::Disconnected(packet));
+        };
+        sender.try_send(DecoderInput::Packet(packet)).map_err(|err| match err {
+            std::sync::mpsc::TrySendError::Full(DecoderInput::Packet(packet)) => {
+                std::sync::mpsc::TrySendError::Full(packet)
+            }
+            std::sync::mpsc::TrySendError::Disconnected(DecoderInput::Packet(packet)) => {
+                std::sync::mpsc::TrySendError::Disconnected(packet)
+            }
+            _ => unreachable!("only packets are sent here"),
+        })
+    }
+
+    // the reader already moved to the keyframe before `target`. everything queued
+    // until now gets dropped and decoding continues up to the target
+    pub fn seek(&self, target: std::time::Duration) -> bool {
+        let Some(sender) = &self.input_sender else {
+            return false;
+        };
+        self.pending_seeks
+            .fetch_add(1, std::sync::atomic::Ordering::AcqRel);
+        self.drained.store(false, std::sync::atomic::Ordering::Release);
+        sender.send(DecoderInput::Seek(target)).is_ok()
+    }
+
+    // the last frame was shown, or skipped, since `end_of_stream`
+    pub fn drained(&self) -> bool {
+        self.drained.load(std::sync::atomic::Ordering::Acquire)
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    pub fn end_of_stream(&self) -> bool {
+        match &self.input_sender {
+            Some(sender) => sender.send(DecoderInput::EndOfStream).is_ok(),
+            None => false,
+        }
+    }
+
+    // no more input is coming, let the receiver drain what is queued and wait for
+    // it. commands keep flowing meanwhile, the last queued frames can still be paused
+    pub fn finish(mut self, controls: &std::sync::mpsc::Receiver<ControlCommand>) {
+        self.input_sender.take();
+        if let Some(handle) = self.receiver_thread.take() {
+            while !handle.is_finished() {
+                while let Ok(command) = controls.try_recv() {
+                    self.send_control_message(command);
+                }
+                std::thread::sleep(CONTROL_POLL_INTERVAL);
+            }
+            let _ = handle.join();
+        }
+    }
+
+    // the clock is shared with the audio, it changes here rather than on the decoder thread
+    pub fn send_control_message(&self, message: ControlCommand) {
+        match message {
+            ControlCommand::Play => self.clock.resume(),
+            ControlCommand::Pause => self.clock.pause(),
+            ControlCommand::SetSpeed(speed) => self.clock.set_rate(speed),
+            _ => {}
+        }
+        let _ = self.control_sender.send(message);
+    }
+}
+
+// where to tag on the player's timeline, where the range ends for range tags, and the label
+type TagRequest = (std::time::Duration, Option<std::time::Duration>, String);
+
+pub struct App {
+    image_source: Dynamic<Option<PathBuf>>,
+    video_source: Dynamic<Option<PathBuf>>,
+    video_player: VideoPlayer,
+    tags: Dynamic<TagStore>,
+    // saves every change of `tags`, and answers remote queries from what it saved
+    database: TagDatabase,
+    selected_tag: Dynamic<Option<TagId>>,
+    // the playhead "tag moment" caught, or the range marked with in and out, while
+    // its label is being picked
+    pending_moment: Dynamic<Option<(std::time::Duration, Option<std::time::Duration>)>>,
+    // where the range being marked starts, on the player's timeline
+    mark_in: Dynamic<Option<std::time::Duration>>,
+    draw_tool: Dynamic<DrawTool>,
+    // keypoint templates to pick from, and the one the keypoints tool places
+    skeletons: Vec<Skeleton>,
+    skeleton: Dynamic<Skeleton>,
+    renditions: Dynamic<Renditions>,
+    mosaic: Mosaic,
+    render_mode: RenderMode,
+    settings: Arc<Mutex<Settings>>,
+    scaling_mode: Dynamic<ScalingMode>,
+    categories: Dynamic<Vec<String>>,
+    keybindings: Dynamic<Keybindings>,
+    show_category_editor: Dynamic<bool>,
+    activity: Dynamic<ActivityLog>,
+    show_activity: Dynamic<bool>,
+    webhook_urls: Dynamic<Vec<String>>,
+    tablet_layout: Dynamic<bool>,
+    hardware_decoding: Dynamic<bool>,
+    palette: Dynamic<Palette>,
+    appearance: Dynamic<Appearance>,
+    backup_settings: Dynamic<BackupSettings>,
+    backups: Dynamic<Vec<Backup>>,
+    show_backups: Dynamic<bool>,
+    show_trash: Dynamic<bool>,
+    show_tag_list: Dynamic<bool>,
+    // the open video's chapters, from its container
+    chapters: Dynamic<Vec<Chapter>>,
+    show_chapters: Dynamic<bool>,
+    show_quick_tags: Dynamic<bool>,
+    queue: Dynamic<Vec<QueueEntry>>,
+    show_queue: Dynamic<bool>,
+    // moments to come back to, shared by every project
+    review_later: Dynamic<Vec<ReviewItem>>,
+    show_review_later: Dynamic<bool>,
+    show_interchange: Dynamic<bool>,
+    show_similarity: Dynamic<bool>,
+    show_pipelines: Dynamic<bool>,
+    // for watching noisy footage, not kept between sessions
+    denoise: Dynamic<Denoise>,
+    // same for shaky footage
+    stabilizer: Stabilizer,
+    qc: QcScanner,
+    qc_rules: Dynamic<QcRules>,
+    show_qc_rules: Dynamic<bool>,
+    show_organize: Dynamic<bool>,
+    show_export: Dynamic<bool>,
+    export_profiles: Dynamic<Vec<ExportProfile>>,
+    export_queue: Dynamic<Option<PathBuf>>,
+    // the selected range tag's upright export window is shown over the player
+    editing_crop: Dynamic<bool>,
+    // cues shown over the player, found next to the video or picked from its menu
+    subtitles: Dynamic<Option<Subtitles>>,
+    // where they come from: a file, one of the video's own tracks or nowhere
+    subtitle_source: Dynamic<SubtitleSource>,
+    // the text subtitle streams of the open video
+    subtitle_tracks: Dynamic<Vec<Track>>,
+    // the open video's video streams when it has several, the default first
+    video_streams: Dynamic<Vec<MediaStream>>,
+    // one picked from the menu instead, and for which video
+    video_stream: Dynamic<Option<(PathBuf, usize)>>,
+    // the same for sound, languages or a commentary. switching doesn't restart playback
+    audio_streams: Dynamic<Vec<MediaStream>>,
+    audio_stream: Dynamic<Option<(PathBuf, usize)>>,
+    // gps of the open video from a dji sidecar or gopro's metadata track
+    telemetry: Dynamic<Option<Telemetry>>,
+    // keyframes along the playing file, for the timeline's filmstrip
+    thumbnails: Dynamic<Vec<Thumbnail>>,
+    // where the pointer is over the timeline, and the frame there
+    timeline_hover: Dynamic<Option<Hover>>,
+    hover_preview: Dynamic<Option<Preview>>,
+    // new tags get the telemetry of their moment in their note
+    stamp_telemetry: Dynamic<bool>,
+    // whole-frame labels, its panel shows while it's active
+    classifier: Classifier,
+    // set with `--record`, see `replay`
+    recorder: Option<Recorder>,
+    // the latest thing worth telling the user, shown in the status line
+    announcement: Dynamic<String>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new(RenderMode::Hardware)
+    }
+}
+
+impl App {
+    pub fn new(render_mode: RenderMode) -> Self {
+        let _span = tracing::info_span!("app_new").entered();
+        let settings = Settings::load();
+        let scaling_mode = Dynamic::new(settings.default_scaling);
+        let categories = Dynamic::new(settings.tag_categories.clone());
+        let settings = Arc::new(Mutex::new(settings));
+
+        categories
+            .for_each({
+                let settings = settings.clone();
+                move |categories| {
+                    let mut settings = settings.lock().unwrap();
+                    settings.tag_categories = categories.clone();
+                    let _ = settings.save();
+                }
+            })
+            .persist();
+
+        let webhook_urls = Dynamic::new(settings.lock().unwrap().webhooks.clone());
+        webhook_urls
+            .for_each({
+                let settings = settings.clone();
+                move |urls| {
+                    let mut settings = settings.lock().unwrap();
+                    settings.webhooks = urls.clone();
+                    let _ = settings.save();
+                }
+            })
+            .persist();
+
+        let tablet_layout = Dynamic::new(settings.lock().unwrap().tablet_layout);
+        tablet_layout
+            .for_each({
+                let settings = settings.clone();
+                move |tablet_layout| {
+                    let mut settings = settings.lock().unwrap();
+                    settings.tablet_layout = *tablet_layout;
+                    let _ = settings.save();
+                }
+            })
+            .persist();
+
+        let hardware_decoding = Dynamic::new(settings.lock().unwrap().hardware_decoding);
+        hardware_decoding
+            .for_each({
+                let settings = settings.clone();
+                move |hardware_decoding| {
+                    let mut settings = settings.lock().unwrap();
+                    settings.hardware_decoding = *hardware_decoding;
+                    let _ = settings.save();
+                }
+            })
+            .persist();
+
+        let palette = Dynamic::new(settings.lock().unwrap().palette);
+        palette
+            .for_each({
+                let settings = settings.clone();
+                move |palette| {
+                    let mut settings = settings.lock().unwrap();
+                    settings.palette = *palette;
+                    let _ = settings.save();
+                }
+            })
+            .persist();
+
+        let qc_rules = Dynamic::new(settings.lock().unwrap().qc_rules.clone());
+        qc_rules
+            .for_each({
+                let settings = settings.clone();
+                move |qc_rules| {
+                    let mut settings = settings.lock().unwrap();
+                    settings.qc_rules = qc_rules.clone();
+                    let _ = settings.save();
+                }
+            })
+            .persist();
+
+        let export_profiles = match settings.lock().unwrap().export_profiles.clone() {
+            profiles if profiles.is_empty() => ExportProfile::defaults(),
+            profiles => profiles,
+        };
+        let export_profiles = Dynamic::new(export_profiles);
+        export_profiles
+            .for_each({
+                let settings = settings.clone();
+                move |profiles| {
+                    let mut settings = settings.lock().unwrap();
+                    settings.export_profiles = profiles.clone();
+                    let _ = settings.save();
+                }
+            })
+            .persist();
+
+        let export_queue = Dynamic::new(settings.lock().unwrap().export_queue.clone());
+        export_queue
+            .for_each({
+                let settings = settings.clone();
+                move |queue| {
+                    let mut settings = settings.lock().unwrap();
+                    settings.export_queue = queue.clone();
+                    let _ = settings.save();
+                }
+            })
+            .persist();
+
+        let skeletons = skeleton_templates(&settings.lock().unwrap().skeletons);
+        let skeleton = Dynamic::new(skeletons[0].clone());
+
+        let appearance = Dynamic::new(settings.lock().unwrap().appearance);
+        appearance
+            .for_each({
+                let settings = settings.clone();
+                move |appearance| {
+                    let mut settings = settings.lock().unwrap();
+                    settings.appearance = *appearance;
+                    let _ = settings.save();
+                }
+            })
+            .persist();
+
+        let (database, store) = TagDatabase::open();
+        let database_error = store.as_ref().err().map(|err| format!("tags not loaded: {err}"));
+        if let Some(err) = &database_error {
+            tracing::error!("{err}");
+        }
+        let mut store = store.unwrap_or_default();
+        store.purge_trash();
+        let tags = Dynamic::new(store);
+        let backup_settings = Dynamic::new(settings.lock().unwrap().backups.clone());
+        backup_settings
+            .for_each({
+                let settings = settings.clone();
+                move |backups| {
+                    let mut settings = settings.lock().unwrap();
+                    settings.backups = backups.clone();
+                    let _ = settings.save();
+                }
+            })
+            .persist();
+        let backups = Dynamic::new(backups::list(&backup_settings.get()));
+        backups::start(
+            tags.clone(),
+            database.clone(),
+            backup_settings.clone(),
+            backups.clone(),
+        );
+
+        let queue = Dynamic::new(load_queue());
+        queue
+            .for_each(|queue| {
+                if let Err(err) = save_queue(queue) {
+                    tracing::error!("failed to save the queue: {err}");
+                }
+            })
+            .persist();
+
+        let activity = Dynamic::new(ActivityLog::default());
+        ActivityLog::load_in_background(activity.clone());
+        let webhooks = Webhooks::start();
+        let mut seen = 0;
+        tags.for_each({
+            let database = database.clone();
+            let activity = activity.clone();
+            let webhook_urls = webhook_urls.clone();
+            let categories = categories.clone();
+            move |tags: &TagStore| {
+                database.save(tags);
+
+                let new_events = tags.activity_since(seen);
+                let Some(last) = new_events.last() else {
+                    return;
+                };
+                seen = last.sequence;
+                webhooks.notify(webhook_urls.get(), categories.get(), new_events);
+                if let Err(err) = activity.lock().append(new_events) {
+                    tracing::error!("failed to record tagging activity: {err}");
+                }
+            }
+        })
+        .persist();
+
+        let keybindings = Dynamic::new(Keybindings::load());
+        keybindings
+            .for_each(|keybindings| {
+                let _ = keybindings.save();
+            })
+            .persist();
+
+        let video_source = Dynamic::new(None);
+        let recorder = Recorder::from_args();
+        let video_player = VideoPlayer::new().with_recorder(recorder.clone());
+        if let Some(recorder) = &recorder {
+            record_opens(&video_source, recorder.clone());
+        }
+        let announcement = Dynamic::new(String::new());
+        announce_playback(&video_source, &video_player, &announcement);
+        if let Some(err) = database_error {
+            announcement.set(err);
+        } else if is_safe_mode() {
+            announcement.set(String::from(
+                "safe mode: default settings, software rendering, nothing is saved to the config",
+            ));
+        }
+
+        let stabilizer = Stabilizer::new(announcement.clone());
+        stabilizer.follow(&video_source);
+
+        let selected_tag = Dynamic::new(None);
+        look_at_selected(&selected_tag, &tags, &video_player);
+
+        let renditions = Dynamic::new(Renditions::default());
+        // storage that keeps rows hands over a video's tags once it's opened
+        (&video_source, &renditions)
+            .for_each({
+                let database = database.clone();
+                let tags = tags.clone();
+                move |(video, renditions)| {
+                    if let Some(video) = video {
+                        database.load_video(&tags, &renditions.original_of(video));
+                    }
+                }
+            })
+            .persist();
+        let chapters = Dynamic::new(Vec::new());
+        chapters::follow(&video_source, chapters.clone());
+        let telemetry = Dynamic::new(None);
+        telemetry::follow(&video_source, renditions.clone(), telemetry.clone());
+        let thumbnails = Dynamic::default();
+        thumbnails::follow(&video_source, thumbnails.clone());
+        let timeline_hover = Dynamic::new(None);
+        let hover_preview = Dynamic::new(None);
+        hover_preview::follow(&video_source, &timeline_hover, hover_preview.clone());
+        let qc = QcScanner::new(
+            tags.clone(),
+            database.clone(),
+            renditions.clone(),
+            announcement.clone(),
+        );
+        let classifier = Classifier::new(
+            tags.clone(),
+            video_source.clone(),
+            renditions.clone(),
+            categories.clone(),
+            video_player.clone(),
+        );
+
+        Self {
+            image_source: Dynamic::new(None),
+            video_source,
+            video_player,
+            tags,
+            database,
+            selected_tag,
+            pending_moment: Dynamic::new(None),
+            mark_in: Dynamic::new(None),
+            draw_tool: Dynamic::default(),
+            skeletons,
+            skeleton,
+            renditions,
+            mosaic: Mosaic::new(),
+            render_mode,
+            settings,
+            scaling_mode,
+            categories,
+            keybindings,
+            show_category_editor: Dynamic::new(false),
+            activity,
+            show_activity: Dynamic::new(false),
+            webhook_urls,
+            tablet_layout,
+            hardware_decoding,
+            palette,
+            appearance,
+            backup_settings,
+            backups,
+            show_backups: Dynamic::new(false),
+            show_trash: Dynamic::new(false),
+            show_tag_list: Dynamic::new(false),
+            chapters,
+            show_chapters: Dynamic::new(false),
+            show_quick_tags: Dynamic::new(false),
+            queue,
+            show_queue: Dynamic::new(false),
+            review_later: Dynamic::new(load_review_queue()),
+            show_review_later: Dynamic::new(false),
+            show_interchange: Dynamic::new(false),
+            show_similarity: Dynamic::new(false),
+            show_pipelines: Dynamic::new(false),
+            denoise: Dynamic::default(),
+            classifier,
+            recorder,
+            stabilizer,
+            qc,
+            qc_rules,
+            show_qc_rules: Dynamic::new(false),
+            show_organize: Dynamic::new(false),
+            show_export: Dynamic::new(false),
+            export_profiles,
+            export_queue,
+            editing_crop: Dynamic::new(false),
+            subtitles: Dynamic::new(None),
+            subtitle_source: Dynamic::default(),
+            subtitle_tracks: Dynamic::default(),
+            video_streams: Dynamic::default(),
+            video_stream: Dynamic::default(),
+            audio_streams: Dynamic::default(),
+            audio_stream: Dynamic::default(),
+            telemetry,
+            thumbnails,
+            timeline_hover,
+            hover_preview,
+            stamp_telemetry: Dynamic::new(false),
+            announcement,
+        }
+    }
+
+    // tags the moment currently shown in the player with the given category
+    fn tag_current_moment(&self) -> SharedCallback<String> {
+        let position = self.video_player.position();
+        let recorder = self.recorder.clone();
+        let tag_at = self.tag_at();
+        SharedCallback::new(move |label: String| {
+            if let Some(recorder) = &recorder {
+                recorder.record(RemoteCommand::Tag(label.clone()), position.get());
+            }
+            tag_at.invoke((position.get(), None, label));
+        })
+    }
+
+    // tags the playing video at a position of the player, or over a range of them
+    fn tag_at(&self) -> SharedCallback<TagRequest> {
+        let video_source = self.video_source.clone();
+        let tags = self.tags.clone();
+        let selected_tag = self.selected_tag.clone();
+        let renditions = self.renditions.clone();
+        let announcement = self.announcement.clone();
+        let telemetry = self.telemetry.clone();
+        let stamp_telemetry = self.stamp_telemetry.clone();
+        let view = self.video_player.view();
+        SharedCallback::new(move |(position, end, label): TagRequest| {
+            let Some(video) = video_source.get() else {
+                announcement.set(String::from("open a video before tagging"));
+                return;
+            };
+            // tags made while a proxy plays are stored against the original
+            let (video, time, end) = renditions.map_ref(|renditions| {
+                (
+                    renditions.original_of(&video),
+                    renditions.to_original(&video, position),
+                    end.map(|end| renditions.to_original(&video, end)),
+                )
+            });
+            let message = match end {
+                Some(end) => format!("{label} {} - {}", format_timestamp(time), format_timestamp(end)),
+                None => format!("{label} at {}", format_timestamp(time)),
+            };
+            let note = stamp_telemetry
+                .get()
+                .then(|| telemetry.map_ref(|telemetry| telemetry.as_ref()?.at(time).copied()))
+                .flatten();
+            let view = view.get();
+            // the note and view are part of making the tag, one undo takes it all back
+            let id = tags.lock().group(|tags| {
+                let id = match end {
+                    Some(end) => tags.add_range(video, time, end, label),
+                    None => tags.add(video, time, label),
+                }?;
+                if let Some(sample) = note {
+                    tags.set_note(id, sample.describe());
+                }
+                if view.is_some() {
+                    tags.set_view(id, view);
+                }
+                Some(id)
+            });
+            match id {
+                Some(id) => {
+                    selected_tag.set(Some(id));
+                    announcement.set(format!("tagged {message}"));
+                }
+                None => announcement.set(format!("locked, not tagged: {message}")),
+            }
+        })
+    }
+
+    // "tag moment" keeps the playhead where it was clicked while playback goes on, and
+    // tags it with whatever label is typed or picked from the categories
+    fn moment_tagger(&self) -> impl MakeWidget {
+        let moment = self.pending_moment.clone();
+        let label = Dynamic::new(String::new());
+        moment
+            .for_each({
+                let label = label.clone();
+                move |moment| {
+                    if moment.is_some() {
+                        label.set(String::new());
+                    }
+                }
+            })
+            .persist();
+        // the moment belongs to the video it was caught in
+        self.video_source
+            .for_each({
+                let moment = moment.clone();
+                let mark_in = self.mark_in.clone();
+                move |_| {
+                    moment.set(None);
+                    mark_in.set(None);
+                }
+            })
+            .persist();
+
+        let has_video = self.video_source.map_each(Option::is_some);
+        let start = "tag moment"
+            .into_button()
+            .on_click({
+                let moment = moment.clone();
+                let position = self.video_player.position();
+                move |_| moment.set(Some((position.get(), None)))
+            })
+            .with_enabled(has_video.clone());
+        let mark_range = self.mark_range();
+        let mark_in = self
+            .mark_in
+            .map_each(|mark_in| match mark_in {
+                Some(time) => format!("[in {}]", format_timestamp(*time)),
+                None => String::from("mark in"),
+            })
+            .into_button()
+            .on_click({
+                let mark_range = mark_range.clone();
+                move |_| mark_range.invoke(TagEdge::Start)
+            })
+            .with_enabled(has_video);
+        let mark_out = "mark out"
+            .into_button()
+            .on_click(move |_| mark_range.invoke(TagEdge::End))
+            .with_enabled(self.mark_in.map_each(Option::is_some));
+
+        let finish = SharedCallback::new({
+            let moment = moment.clone();
+            let tag_at = self.tag_at();
+            move |label: String| {
+                let label = label.trim().to_string();
+                if label.is_empty() {
+                    return;
+                }
+                let moment = moment.lock().take();
+                if let Some((time, end)) = moment {
+                    tag_at.invoke((time, end, label));
+                }
+            }
+        });
+        let add = "add".into_button().on_click({
+            let label = label.clone();
+            let finish = finish.clone();
+            move |_| finish.invoke(label.get())
+        });
+        let picks = self.categories.switcher(move |categories, _| {
+            categories
+                .iter()
+                .map(|category| {
+                    category.clone().into_button().on_click({
+                        let category = category.clone();
+                        let finish = finish.clone();
+                        move |_| finish.invoke(category.clone())
+                    })
+                })
+                .collect::<WidgetList>()
+                .into_columns()
+                .make_widget()
+        });
+        let cancel = "cancel".into_button().on_click({
+            let moment = moment.clone();
+            move |_| moment.set(None)
+        });
+
+        let editor = moment
+            .map_each(|moment| match moment {
+                Some((time, Some(end))) => {
+                    format!("tag {} - {}", format_timestamp(*time), format_timestamp(*end))
+                }
+                Some((time, None)) => format!("tag at {}", format_timestamp(*time)),
+                None => String::new(),
+            })
+            .and(label.into_input().placeholder("label"))
+            .and(add)
+            .and(picks)
+            .and(cancel)
+            .into_columns()
+            .collapse_vertically(moment.map_each(Option::is_none));
+
+        start
+            .and(mark_in)
+            .and(mark_out)
+            .and(editor)
+            .into_columns()
+    }
+
+    // i holds on to the playhead, o opens the tag editor for the range from there
+    fn mark_range(&self) -> SharedCallback<TagEdge> {
+        let mark_in = self.mark_in.clone();
+        let pending_moment = self.pending_moment.clone();
+        let position = self.video_player.position();
+        let video_source = self.video_source.clone();
+        let announcement = self.announcement.clone();
+        SharedCallback::new(move |edge: TagEdge| {
+            if video_source.get().is_none() {
+                return;
+            }
+            let position = position.get();
+            match edge {
+                TagEdge::Start => {
+                    mark_in.set(Some(position));
+                    announcement.set(format!("in at {}", format_timestamp(position)));
+                }
+                TagEdge::End => {
+                    let start = mark_in.lock().take();
+                    match start {
+                        Some(start) if start != position => pending_moment
+                            .set(Some((start.min(position), Some(start.max(position))))),
+                        Some(_) => announcement.set(String::from("in and out are the same moment")),
+                        None => announcement.set(String::from("mark in first")),
+                    }
+                }
+            }
+        })
+    }
+
+    // every tag of the open video in its category's color along the player's timeline,
+    // with the playhead. clicking or dragging on it seeks
+    fn timeline(&self) -> impl MakeWidget {
+        let spans = (
+            &self.tags,
+            &self.video_source,
+            &self.renditions,
+            &self.categories,
+            &self.palette,
+        )
+            .map_each(|(tags, video, renditions, categories, palette)| {
+                let Some(video) = video else {
+                    return Vec::new();
+                };
+                let original = renditions.original_of(video);
+                tags.tags()
+                    .iter()
+                    .filter(|tag| tag.video == original)
+                    .map(|tag| Span {
+                        start: renditions.from_original(video, tag.time),
+                        end: tag.end.map(|end| renditions.from_original(video, end)),
+                        color: categories
+                            .iter()
+                            .position(|category| *category == tag.label)
+                            .map_or(Color::WHITE, |idx| palette.color(idx)),
+                    })
+                    .collect()
+            });
+        let video_player = self.video_player.clone();
+        Timeline::new(
+            self.thumbnails.clone(),
+            spans,
+            self.mark_in.clone(),
+            self.video_player.position(),
+            self.video_player.duration(),
+            SharedCallback::new(move |time| video_player.seek(time)),
+            self.timeline_hover.clone(),
+        )
+        .with_chapters(self.chapters.clone())
+    }
+
+    // what the last quality check of the playing file found, in the strip under the tags
+    fn qc_spans(&self) -> impl MakeWidget {
+        let spans = (&self.qc.results, &self.video_source).map_each(|(results, video)| {
+            video
+                .as_ref()
+                .and_then(|video| results.get(video))
+                .map(|defects| {
+                    defects
+                        .iter()
+                        .map(|defect| Span {
+                            start: defect.start,
+                            end: Some(defect.end),
+                            color: defect.kind.color(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        });
+        TagSpans::new(spans, self.video_player.duration())
+    }
+
+    fn telemetry_bar(&self) -> impl MakeWidget {
+        // telemetry is read from the original, a proxy's playhead is moved onto its timeline
+        let time = (
+            &self.video_player.position(),
+            &self.video_source,
+            &self.renditions,
+        )
+            .map_each(|(position, video, renditions)| match video {
+                Some(video) => renditions.to_original(video, *position),
+                None => *position,
+            });
+        // the map seeks on the original's timeline too
+        let on_seek = SharedCallback::new({
+            let video_player = self.video_player.clone();
+            let video_source = self.video_source.clone();
+            let renditions = self.renditions.clone();
+            move |time: std::time::Duration| {
+                if let Some(video) = video_source.get() {
+                    let time = renditions.map_ref(|renditions| renditions.from_original(&video, time));
+                    video_player.seek(time);
+                }
+            }
+        });
+        telemetry_bar(
+            &self.telemetry,
+            &time,
+            self.stamp_telemetry.clone(),
+            on_seek,
+        )
+    }
+
+    fn playback_shortcuts(&self) -> impl FnMut(Action) -> EventHandling + Send + 'static {
+        let remote = self.remote();
+        let announcement = self.announcement.clone();
+        move |action| {
+            let command = match action {
+                Action::TogglePlay => RemoteCommand::TogglePlay,
+                Action::SeekBackward => RemoteCommand::SeekRelative(-WHEEL_SEEK_SECONDS),
+                Action::SeekForward => RemoteCommand::SeekRelative(WHEEL_SEEK_SECONDS),
+                Action::StepBackward => RemoteCommand::StepBackward,
+                Action::StepForward => RemoteCommand::StepForward,
+                Action::PreviousChapter
+                | Action::NextChapter
+                | Action::QuickTag(_)
+                | Action::Undo
+                | Action::Redo => return IGNORED,
+            };
+            if let Err(err) = remote.execute(command) {
+                announcement.set(err);
+            }
+            HANDLED
+        }
+    }
+
+    // 1 to 9 tag with their quick tag labels, or the categories in the order they are
+    // listed where there is none
+    fn quick_tag_shortcuts(&self) -> impl FnMut(Action) -> EventHandling + Send + 'static {
+        let remote = self.remote();
+        let tags = self.tags.clone();
+        let announcement = self.announcement.clone();
+        move |action| {
+            let Action::QuickTag(idx) = action else {
+                return IGNORED;
+            };
+            let command = match tags.map_ref(|tags| tags.quick_tag(idx).map(str::to_string)) {
+                Some(label) => RemoteCommand::Tag(label),
+                None => RemoteCommand::TagCategory(idx),
+            };
+            if let Err(err) = remote.execute(command) {
+                announcement.set(err);
+            }
+            HANDLED
+        }
+    }
+
+    // page up and down, nothing happens before the first chapter or after the last
+    fn chapter_shortcuts(&self) -> impl FnMut(Action) -> EventHandling + Send + 'static {
+        let chapters = self.chapters.clone();
+        let video_player = self.video_player.clone();
+        move |action| {
+            let position = video_player.position().get();
+            let start = match action {
+                Action::PreviousChapter => {
+                    chapters.map_ref(|chapters| chapters::previous(chapters, position))
+                }
+                Action::NextChapter => {
+                    chapters.map_ref(|chapters| chapters::next(chapters, position))
+                }
+                _ => return IGNORED,
+            };
+            if let Some(start) = start {
+                video_player.seek(start);
+            }
+            HANDLED
+        }
+    }
+
+    // ctrl+z and ctrl+shift+z, after anything focused had its chance
+    fn history_shortcuts(&self) -> impl FnMut(Action) -> EventHandling + Send + 'static {
+        let tags = self.tags.clone();
+        let announcement = self.announcement.clone();
+        move |action| match action {
+            Action::Undo => {
+                step_history(&tags, &announcement, false);
+                HANDLED
+            }
+            Action::Redo => {
+                step_history(&tags, &announcement, true);
+                HANDLED
+            }
+            _ => IGNORED,
+        }
+    }
+
+    fn history_buttons(&self) -> impl MakeWidget {
+        let undo = "undo"
+            .into_button()
+            .on_click({
+                let tags = self.tags.clone();
+                let announcement = self.announcement.clone();
+                move |_| step_history(&tags, &announcement, false)
+            })
+            .with_enabled(self.tags.map_each(TagStore::can_undo));
+        let redo = "redo"
+            .into_button()
+            .on_click({
+                let tags = self.tags.clone();
+                let announcement = self.announcement.clone();
+                move |_| step_history(&tags, &announcement, true)
+            })
+            .with_enabled(self.tags.map_each(TagStore::can_redo));
+        undo.and(redo).into_columns()
+    }
+
+    // what a project file saves and restores, see `project`
+    fn workspace(&self) -> Workspace {
+        Workspace {
+            video_source: self.video_source.clone(),
+            video_player: self.video_player.clone(),
+            tags: self.tags.clone(),
+            database: self.database.clone(),
+            selected_tag: self.selected_tag.clone(),
+            scaling_mode: self.scaling_mode.clone(),
+            panels: vec![
+                ("categories", self.show_category_editor.clone()),
+                ("queue", self.show_queue.clone()),
+                ("review later", self.show_review_later.clone()),
+                ("interchange", self.show_interchange.clone()),
+                ("similarity", self.show_similarity.clone()),
+                ("pipelines", self.show_pipelines.clone()),
+                ("qc rules", self.show_qc_rules.clone()),
+                ("organize", self.show_organize.clone()),
+                ("export", self.show_export.clone()),
+                ("activity", self.show_activity.clone()),
+                ("backups", self.show_backups.clone()),
+                ("trash", self.show_trash.clone()),
+                ("tag list", self.show_tag_list.clone()),
+                ("chapters", self.show_chapters.clone()),
+                ("quick tags", self.show_quick_tags.clone()),
+            ],
+            backup_settings: self.backup_settings.clone(),
+            announcement: self.announcement.clone(),
+        }
+    }
+
+    // blocks until every change to the tags is on disk, for shutting down
+    pub fn flush_tags(&self) {
+        self.database.flush();
+    }
+
+    // handle for driving the app from outside the ui
+    pub fn remote(&self) -> Remote {
+        Remote::new(
+            self.video_source.clone(),
+            self.video_player.clone(),
+            self.categories.clone(),
+            self.tag_current_moment(),
+            self.database.clone(),
+        )
+    }
+
+    // the player without the ui, drawn by the cpu. the ui is still built, it's what starts
+    // playback when a video opens, it just never goes into a window
+    #[cfg(feature = "cpu-window")]
+    pub fn run_cpu_window(&self) -> Result<(), String> {
+        let _view = self.view().make_widget();
+        cpu_window::run(self.remote())
+    }
+
+    fn lock_video_button(&self) -> impl MakeWidget {
+        let original = (&self.video_source, &self.renditions).map_each(|(source, renditions)| {
+            source.as_ref().map(|source| renditions.original_of(source))
+        });
+        let locked = (&original, &self.tags).map_each(|(original, tags)| {
+            original
+                .as_ref()
+                .is_some_and(|original| tags.is_video_locked(original))
+        });
+        let label = locked.map_each(|locked| {
+            if *locked {
+                String::from("video locked, unlock")
+            } else {
+                String::from("lock video")
+            }
+        });
+
+        let tags = self.tags.clone();
+        label.into_button().on_click(move |_| {
+            if let Some(original) = original.get() {
+                tags.lock().set_video_locked(original, !locked.get());
+            }
+        })
+    }
+
+    // closes the video and moves its tags to the trash
+    fn remove_video_button(&self) -> impl MakeWidget {
+        let video_source = self.video_source.clone();
+        let renditions = self.renditions.clone();
+        let tags = self.tags.clone();
+        let announcement = self.announcement.clone();
+        "remove video".into_button().on_click(move |_| {
+            let Some(current) = video_source.get() else {
+                return;
+            };
+            let original = renditions.map_ref(|renditions| renditions.original_of(&current));
+            let removed = tags.lock().remove_video(&original);
+            video_source.set(None);
+            announcement.set(format!("moved {removed} tags to the trash"));
+        })
+    }
+
+    // the selected tag's note, loaded again whenever another tag gets selected
+    fn note_editor(&self) -> impl MakeWidget {
+        let note = Dynamic::new(String::new());
+        self.selected_tag
+            .for_each({
+                let tags = self.tags.clone();
+                let note = note.clone();
+                move |selected| {
+                    let text = selected
+                        .and_then(|id| tags.map_ref(|tags| tags.get(id).map(|tag| tag.note.clone())))
+                        .unwrap_or_default();
+                    note.set(text);
+                }
+            })
+            .persist();
+
+        let tags = self.tags.clone();
+        let selected_tag = self.selected_tag.clone();
+        let announcement = self.announcement.clone();
+        let save = "save note"
+            .into_button()
+            .on_click({
+                let note = note.clone();
+                move |_| {
+                    let Some(id) = selected_tag.get() else {
+                        return;
+                    };
+                    if !tags.lock().set_note(id, note.get().trim().to_string()) {
+                        announcement.set(String::from("locked, note not changed"));
+                    }
+                }
+            })
+            .with_enabled(self.selected_tag.map_each(Option::is_some));
+
+        note.into_input()
+            .placeholder("note")
+            .and(save)
+            .into_columns()
+    }
+
+    fn delete_tag_button(&self) -> impl MakeWidget {
+        let tags = self.tags.clone();
+        let selected_tag = self.selected_tag.clone();
+        "delete tag"
+            .into_button()
+            .on_click(move |_| {
+                let Some(id) = selected_tag.get() else {
+                    return;
+                };
+                let removed = tags.lock().remove(id);
+                if removed {
+                    selected_tag.set(None);
+                }
+            })
+            .with_enabled(self.selected_tag.map_each(Option::is_some))
+    }
+
+    // takes the selected tag's box and outline off the current moment
+    fn remove_shape_button(&self) -> impl MakeWidget {
+        let tags = self.tags.clone();
+        let selected_tag = self.selected_tag.clone();
+        let video_source = self.video_source.clone();
+        let renditions = self.renditions.clone();
+        let position = self.video_player.position();
+        "remove shape"
+            .into_button()
+            .on_click(move |_| {
+                let (Some(id), Some(video)) = (selected_tag.get(), video_source.get()) else {
+                    return;
+                };
+                let time = renditions
+                    .map_ref(|renditions| renditions.to_original(&video, position.get()));
+                tags.lock().remove_keyframe(id, time);
+            })
+            .with_enabled(self.selected_tag.map_each(Option::is_some))
+    }
+
+    // follows the selected tag's box from the current frame on, keyframing it every
+    // few frames until the subject is lost or `TRACK_LENGTH` is covered
+    fn track_forward_button(&self) -> impl MakeWidget {
+        let tags = self.tags.clone();
+        let selected_tag = self.selected_tag.clone();
+        let video_source = self.video_source.clone();
+        let renditions = self.renditions.clone();
+        let position = self.video_player.position();
+        let announcement = self.announcement.clone();
+        let tracking = Dynamic::new(false);
+        "track forward"
+            .into_button()
+            .on_click({
+                let tracking = tracking.clone();
+                move |_| {
+                    let (Some(id), Some(video)) = (selected_tag.get(), video_source.get()) else {
+                        return;
+                    };
+                    let from = position.get();
+                    let renditions = renditions.get();
+                    let region = tags.map_ref(|tags| {
+                        let tag = tags.get(id)?;
+                        let (start, end) = tag.region_span()?;
+                        let time = renditions.to_original(&video, from);
+                        (start..=end).contains(&time).then(|| tag.track.at(time))?
+                    });
+                    let Some(region) = region else {
+                        announcement.set(String::from("draw a box on this frame to track it"));
+                        return;
+                    };
+
+                    tracking.set(true);
+                    let tags = tags.clone();
+                    let announcement = announcement.clone();
+                    let tracking = tracking.clone();
+                    std::thread::Builder::new()
+                        .name("Tracking Thread".into())
+                        .spawn(move || {
+                            let tracked = tracking::track_forward(&video, from, region, |time| {
+                                announcement.set(format!("tracking at {}", format_timestamp(time)));
+                            });
+                            tracking.set(false);
+                            let tracked = match tracked {
+                                Ok(tracked) => tracked,
+                                Err(err) => {
+                                    announcement.set(format!("tracking failed: {err}"));
+                                    return;
+                                }
+                            };
+                            let keyframes = tracked
+                                .keyframes
+                                .iter()
+                                .map(|keyframe| Keyframe {
+                                    time: renditions.to_original(&video, keyframe.time),
+                                    region: keyframe.region,
+                                })
+                                .collect::<Vec<_>>();
+                            let Some(last) = keyframes.last() else {
+                                announcement.set(String::from("lost the box right away"));
+                                return;
+                            };
+                            let until = format_timestamp(last.time);
+                            if !tags.lock().set_keyframes(id, &keyframes) {
+                                announcement.set(String::from("locked, box not tracked"));
+                            } else if tracked.lost {
+                                announcement.set(format!("lost the box, tracked up to {until}"));
+                            } else {
+                                announcement.set(format!("tracked up to {until}"));
+                            }
+                        })
+                        .unwrap();
+                }
+            })
+            .with_enabled(
+                (&self.selected_tag, &tracking)
+                    .map_each(|(selected, tracking)| selected.is_some() && !tracking),
+            )
+    }
 
-                            packet_decoder.send_packet(&packet).unwrap();
-                            let mut decoded_frame = ffmpeg::util::frame::Video::empty();
+    // boxes and outlines of the region tags on screen at the current moment, plus
+    // the selected tag's wherever it is, so it can be edited anywhere
+    fn region_overlays(&self) -> Dynamic<Vec<RegionOverlay>> {
+        let video_source = self.video_source.clone();
+        let renditions = self.renditions.clone();
+        let categories = self.categories.clone();
+        let palette = self.palette.clone();
+        let skeletons = self.skeletons.clone();
+        let position = self.video_player.position();
+        (&self.tags, &self.selected_tag, &position).map_each(move |(tags, selected, position)| {
+            let Some(video) = video_source.get() else {
+                return Vec::new();
+            };
+            let (video, time) = renditions.map_ref(|renditions| {
+                (
+                    renditions.original_of(&video),
+                    renditions.to_original(&video, *position),
+                )
+            });
+            let palette = palette.get();
+            let categories = categories.get();
+
+            tags.tags()
+                .iter()
+                .filter(|tag| tag.video == video)
+                .flat_map(|tag| {
+                    let Some((start, end)) = tag.region_span() else {
+                        return Vec::new();
+                    };
+                    let selected = *selected == Some(tag.id);
+                    if !selected && (time < start || time > end) {
+                        return Vec::new();
+                    }
+                    let color = categories
+                        .iter()
+                        .position(|category| *category == tag.label)
+                        .map_or(Color::WHITE, |idx| palette.color(idx));
+
+                    let region = tag.track.at(time).map(Outline::Box);
+                    let polygon = tag.polygons.at(time).cloned().map(Outline::Polygon);
+                    let pose = tag.poses.at(time).map(|pose| Outline::Pose {
+                        pose: pose.clone(),
+                        edges: skeletons
+                            .iter()
+                            .find(|skeleton| skeleton.name == pose.skeleton)
+                            .map(|skeleton| skeleton.edges.clone())
+                            .unwrap_or_default(),
+                    });
+                    region
+                        .into_iter()
+                        .chain(polygon)
+                        .chain(pose)
+                        .map(|outline| RegionOverlay {
+                            outline,
+                            color,
+                            selected,
+                        })
+                        .collect()
+                })
+                .collect()
+        })
+    }
 
-                            while packet_decoder.receive_frame(&mut decoded_frame).is_ok() {
-                                if let Some(delay) =
-                                    clock.convert_pts_to_instant(decoded_frame.pts())
-                                {
-                                    std::thread::sleep(delay)
-                                }
+    // while editing the crop, where the selected range tag's upright window is now
+    fn crop_window(&self) -> Dynamic<Option<f32>> {
+        let video_source = self.video_source.clone();
+        let renditions = self.renditions.clone();
+        let position = self.video_player.position();
+        (&self.tags, &self.selected_tag, &position, &self.editing_crop).map_each(
+            move |(tags, selected, position, editing)| {
+                let (id, video) = (selected.filter(|_| *editing)?, video_source.get()?);
+                let time =
+                    renditions.map_ref(|renditions| renditions.to_original(&video, *position));
+                tags.tags()
+                    .iter()
+                    .find(|tag| tag.id == id && tag.end.is_some())
+                    .map(|tag| tag.crop.at(time))
+            },
+        )
+    }
 
-                                frame_callback(&decoded_frame);
-                            }
-                        }
-                    }
-                    .fuse()
-                    .shared();
+    // dragging the upright window keyframes it where it was let go
+    fn move_crop(&self) -> SharedCallback<f32> {
+        let tags = self.tags.clone();
+        let selected_tag = self.selected_tag.clone();
+        let video_source = self.video_source.clone();
+        let renditions = self.renditions.clone();
+        let position = self.video_player.position();
+        let announcement = self.announcement.clone();
+        SharedCallback::new(move |center: f32| {
+            let (Some(id), Some(video)) = (selected_tag.get(), video_source.get()) else {
+                return;
+            };
+            let time =
+                renditions.map_ref(|renditions| renditions.to_original(&video, position.get()));
+            if tags.lock().set_crop(id, time, center) {
+                announcement.set(format!("crop keyframed at {}", format_timestamp(time)));
+            } else {
+                announcement.set(String::from("locked, crop not changed"));
+            }
+        })
+    }
 
-                    let playing = true;
+    // shift+dragging on the video boxes the selected tag at the current moment,
+    // boxes at other moments make it a track
+    fn draw_region(&self) -> SharedCallback<Region> {
+        let tags = self.tags.clone();
+        let selected_tag = self.selected_tag.clone();
+        let video_source = self.video_source.clone();
+        let renditions = self.renditions.clone();
+        let position = self.video_player.position();
+        let announcement = self.announcement.clone();
+        SharedCallback::new(move |region: Region| {
+            let Some(id) = selected_tag.get() else {
+                announcement.set(String::from("select a tag to draw its box"));
+                return;
+            };
+            let Some(video) = video_source.get() else {
+                return;
+            };
+            let time =
+                renditions.map_ref(|renditions| renditions.to_original(&video, position.get()));
+            if tags.lock().set_keyframe(id, time, region) {
+                announcement.set(format!("boxed at {}", format_timestamp(time)));
+            } else {
+                announcement.set(String::from("locked, box not changed"));
+            }
+        })
+    }
 
-                    loop {
-                        let packet_receiver: OptionFuture<_> = if playing {
-                            Some(packet_receiver_impl.clone())
-                        } else {
-                            None
-                        }
-                        .into();
+    // polygons and freehand outlines for the selected tag at the current moment,
+    // also what comes back when a point of one was moved
+    fn draw_polygon(&self) -> SharedCallback<Polygon> {
+        let tags = self.tags.clone();
+        let selected_tag = self.selected_tag.clone();
+        let video_source = self.video_source.clone();
+        let renditions = self.renditions.clone();
+        let position = self.video_player.position();
+        let announcement = self.announcement.clone();
+        SharedCallback::new(move |polygon: Polygon| {
+            let Some(id) = selected_tag.get() else {
+                announcement.set(String::from("select a tag to outline it"));
+                return;
+            };
+            let Some(video) = video_source.get() else {
+                return;
+            };
+            let time =
+                renditions.map_ref(|renditions| renditions.to_original(&video, position.get()));
+            if tags.lock().set_polygon(id, time, polygon) {
+                announcement.set(format!("outlined at {}", format_timestamp(time)));
+            } else {
+                announcement.set(String::from("locked, outline not changed"));
+            }
+        })
+    }
 
-                        futures::pin_mut!(packet_receiver);
+    fn draw_pose(&self) -> SharedCallback<Pose> {
+        let tags = self.tags.clone();
+        let selected_tag = self.selected_tag.clone();
+        let video_source = self.video_source.clone();
+        let renditions = self.renditions.clone();
+        let position = self.video_player.position();
+        let announcement = self.announcement.clone();
+        SharedCallback::new(move |pose: Pose| {
+            let Some(id) = selected_tag.get() else {
+                announcement.set(String::from("select a tag to place its keypoints"));
+                return;
+            };
+            let Some(video) = video_source.get() else {
+                return;
+            };
+            let time =
+                renditions.map_ref(|renditions| renditions.to_original(&video, position.get()));
+            if tags.lock().set_pose(id, time, pose) {
+                announcement.set(format!("posed at {}", format_timestamp(time)));
+            } else {
+                announcement.set(String::from("locked, pose not changed"));
+            }
+        })
+    }
 
-                        futures::select! {
-                            _ = packet_receiver => {}
-                        }
+    // which template the keypoints tool places, and what to click next
+    fn skeleton_controls(&self) -> impl MakeWidget {
+        let templates = self
+            .skeletons
+            .iter()
+            .map(|template| {
+                let name = template.name.clone();
+                let label = self.skeleton.map_each(move |skeleton| {
+                    if skeleton.name == name {
+                        format!("[{name}]")
+                    } else {
+                        name.clone()
                     }
+                });
+                label.into_button().on_click({
+                    let skeleton = self.skeleton.clone();
+                    let template = template.clone();
+                    move |_| skeleton.set(template.clone())
                 })
             })
-            .unwrap();
-
-        Self {
-            control_sender,
-            packet_sender,
-            receiver_thread: Some(receiver_thread),
-        }
-    }
+            .collect::<WidgetList>();
 
-    pub fn get_packet(&self, packet: ffmpeg::codec::packet::packet::Packet) -> bool {
-        match self.packet_sender.send(packet) {
-            Ok(_) => true,
-            Err(_) => false,
-        }
+        let hint = (
+            &self.draw_tool,
+            &self.skeleton,
+            &self.video_player.pose_progress(),
+        )
+            .map_each(|(tool, skeleton, progress)| {
+                if *tool != DrawTool::Keypoints {
+                    return String::new();
+                }
+                skeleton
+                    .keypoints
+                    .get(*progress)
+                    .map(|keypoint| format!("shift+click {keypoint}, x skips it"))
+                    .unwrap_or_default()
+            });
+
+        templates
+            .into_columns()
+            .and(hint)
+            .into_columns()
+            .collapse_horizontally(self.draw_tool.map_each(|tool| *tool != DrawTool::Keypoints))
     }
 
-    pub fn send_control_message(&self, message: ControlCommand) {
-        self.control_sender.send(message).unwrap();
+    fn open_original_button(&self) -> impl MakeWidget {
+        let video_source = self.video_source.clone();
+        let renditions = self.renditions.clone();
+        "open original".into_button().on_click(move |_| {
+            let Some(current) = video_source.get() else {
+                return;
+            };
+            let original = renditions.map_ref(|renditions| renditions.original_of(&current));
+            if original != current {
+                video_source.set(Some(original));
+            }
+        })
     }
-}
-
-pub struct App {
-    image_source: Dynamic<Option<PathBuf>>,
-    video_source: Dynamic<Option<PathBuf>>,
-    counter: Arc<Mutex<Counter>>,
-    main_menu: MainMenu,
-}
 
-impl Default for App {
-    fn default() -> Self {
-        Self {
-            image_source: Dynamic::new(None),
-            video_source: Dynamic::new(None),
-            counter: Arc::new(Mutex::new(Counter::new())),
-            main_menu: MainMenu::new(),
-        }
+    // picks a proxy for the open video, links their timelines and switches playback to it
+    fn open_proxy_button(&self) -> impl MakeWidget {
+        let video_source = self.video_source.clone();
+        let renditions = self.renditions.clone();
+        "open proxy".into_button().on_click(move |_| {
+            let Some(current) = video_source.get() else {
+                return;
+            };
+            let original = renditions.map_ref(|renditions| renditions.original_of(&current));
+
+            let video_source = video_source.clone();
+            let renditions = renditions.clone();
+            std::thread::Builder::new()
+                .name("File Picker Thread".into())
+                .spawn(move || {
+                    let Some(proxy) = rfd::FileDialog::new().pick_file() else {
+                        return;
+                    };
+                    let offset = probe_offset(&proxy, &original).unwrap_or_default();
+                    renditions.lock().link(proxy.clone(), original, offset);
+                    video_source.set(Some(proxy));
+                })
+                .unwrap();
+        })
     }
-}
 
-impl App {
     fn handle_video_source(&self) -> impl MakeWidget {
-        self.video_source.clone().switcher(move |source, _| {
-            let mut video_player = VideoPlayer::new();
-
-            if let Some(source) = source {
-                let path = source.clone();
-
-                video_player.start(move |content| {
-                    let path = path.clone();
-
-                    futures::executor::block_on(async move {
-                        let mut ictx = ffmpeg::format::input(&path).unwrap();
-                        let stream = ictx.streams().best(ffmpeg::media::Type::Video).unwrap();
-                        let vs_idx = stream.index();
-
-                        let video_decoder = VideoDecoder::start(
-                            &stream,
-                            Box::new(move |yuv_frame| {
-                                let mut rgb_frame = ffmpeg::util::frame::Video::empty();
-                                let mut rescaler = rescaler(yuv_frame);
-                                rescaler.0.run(yuv_frame, &mut rgb_frame).unwrap();
-
-                                // do something with the rgb_frame
-                                let mut pixel_buffer = ImageBuffer::<Rgb<u8>, Vec<u8>>::new(
-                                    rgb_frame.width(),
-                                    rgb_frame.height(),
-                                );
-
-                                let pixel_line_iter = pixel_buffer.chunks_mut(
-                                    rgb_frame.width() as usize * std::mem::size_of::<Rgb<u8>>(),
-                                );
-
-                                let source_line_iter =
-                                    rgb_frame.data(0).chunks_exact(rgb_frame.stride(0));
-
-                                for (source, dest) in source_line_iter.zip(pixel_line_iter) {
-                                    dest.copy_from_slice(&source[..dest.len()]);
-                                }
+        let options = DecodeOptions {
+            max_dimension: self.render_mode.max_frame_dimension(),
+            audio: true,
+            ..DecodeOptions::default()
+        };
+
+        let settings = self.settings.clone();
+        let scaling_mode = self.scaling_mode.clone();
+        self.video_source
+            .for_each(move |source| {
+                if let Some(source) = source {
+                    scaling_mode.set(settings.lock().unwrap().scaling_for(source));
+                }
+            })
+            .persist();
 
-                                let pixel_buffer = DynamicImage::from(pixel_buffer).into_rgba8();
-
-                                let texture = LazyTexture::from_data(
-                                    cushy::figures::Size::new(
-                                        pixel_buffer.width().into(),
-                                        pixel_buffer.height().into(),
-                                    ),
-                                    TextureFormat::Rgba8UnormSrgb,
-                                    TextureUsages::TEXTURE_BINDING,
-                                    FilterMode::Nearest,
-                                    pixel_buffer.into_raw(),
-                                );
-                                let texture = AnyTexture::from(texture);
-
-                                content.set(texture);
-                            }),
-                        );
-
-                        let playing = true;
-
-                        let packet_forwarder_impl = async {
-                            for (stream, packet) in ictx.packets() {
-                                if stream.index() == vs_idx {
-                                    video_decoder.get_packet(packet);
+        let scaling = self.video_player.scaling();
+        scaling.set(ImageScaling::from(self.scaling_mode.get()));
+        let settings = self.settings.clone();
+        let video_source = self.video_source.clone();
+        self.scaling_mode
+            .for_each(move |mode| {
+                scaling.set(ImageScaling::from(*mode));
+
+                let Some(source) = video_source.get() else {
+                    return;
+                };
+                let mut settings = settings.lock().unwrap();
+                if settings.scaling_for(&source) != *mode {
+                    settings.video_scaling.insert(source, *mode);
+                    let _ = settings.save();
+                }
+            })
+            .persist();
+
+        // the open video with the filters of its preset, stabilization and denoise, and
+        // the video stream picked for it, the pipeline restarts when any changes. editing
+        // unrelated tags maps to the same value and doesn't
+        let playback = (
+            &self.video_source,
+            &self.tags,
+            &self.renditions,
+            &self.stabilizer.state,
+            &self.denoise,
+            &self.video_stream,
+        )
+            .map_each(|(source, tags, renditions, stabilization, denoise, stream)| {
+                source.clone().map(|source| {
+                    let filters = tags
+                        .pipeline_for(&renditions.original_of(&source))
+                        .and_then(PipelinePreset::filter_spec);
+                    let (filters, transforms) = stabilization.apply(&source, filters);
+                    let stream = stream
+                        .as_ref()
+                        .filter(|(video, _)| *video == source)
+                        .map(|(_, stream)| *stream);
+                    (source, denoise.apply(filters), transforms, stream)
+                })
+            });
+
+        // one player for the whole session, only the pipeline underneath is swapped
+        let video_player = self.video_player.clone();
+        let hardware_decoding = self.hardware_decoding.clone();
+        let audio_stream = self.audio_stream.clone();
+        let mut playing: Option<PathBuf> = None;
+        playback
+            .for_each(move |playback| match playback {
+                Some((source, filters, transforms, stream)) => {
+                    // new filters or another stream of the same video carry on where it was
+                    let resume = (playing.as_ref() == Some(source))
+                        .then(|| video_player.position().get());
+                    playing = Some(source.clone());
+
+                    let path = source.clone();
+                    // switching applies from the next video on
+                    let options = DecodeOptions {
+                        hardware: hardware_decoding.get(),
+                        filters: filters.clone(),
+                        stabilization: transforms.clone(),
+                        video_stream: *stream,
+                        // a restarted pipeline keeps the sound it had
+                        audio_stream: audio_stream
+                            .get()
+                            .filter(|(video, _)| video == source)
+                            .map(|(_, stream)| stream),
+                        ..options.clone()
+                    };
+                    video_player.start(move |context| decode_video(&path, options, context));
+                    if let Some(resume) = resume {
+                        video_player.seek(resume);
+                    }
+                }
+                None => {
+                    playing = None;
+                    video_player.stop();
+                }
+            })
+            .persist();
+        // what the menu can switch between, for files with more than one
+        self.video_source
+            .for_each({
+                let video_streams = self.video_streams.clone();
+                let audio_streams = self.audio_streams.clone();
+                let video_source = self.video_source.clone();
+                move |video| {
+                    video_streams.set(Vec::new());
+                    audio_streams.set(Vec::new());
+                    let Some(video) = video.clone() else {
+                        return;
+                    };
+                    let video_streams = video_streams.clone();
+                    let audio_streams = audio_streams.clone();
+                    let video_source = video_source.clone();
+                    std::thread::Builder::new()
+                        .name("Stream Thread".into())
+                        .spawn(move || {
+                            let video_found = media::streams(&video, ffmpeg::media::Type::Video);
+                            let audio_found = media::streams(&video, ffmpeg::media::Type::Audio);
+                            // another video may have opened in the meantime
+                            if video_source.get().as_ref() == Some(&video) {
+                                video_streams.set(video_found);
+                                audio_streams.set(audio_found);
+                            }
+                        })
+                        .unwrap();
+                }
+            })
+            .persist();
+        // an .srt next to the video comes up with it, one picked for another goes away.
+        // without one the video's own tracks are listed but stay off until picked
+        self.video_source
+            .for_each({
+                let subtitle_source = self.subtitle_source.clone();
+                let subtitle_tracks = self.subtitle_tracks.clone();
+                let video_source = self.video_source.clone();
+                move |video| {
+                    subtitle_tracks.set(Vec::new());
+                    subtitle_source.set(SubtitleSource::Off);
+                    let Some(video) = video.clone() else {
+                        return;
+                    };
+                    let subtitle_source = subtitle_source.clone();
+                    let subtitle_tracks = subtitle_tracks.clone();
+                    let video_source = video_source.clone();
+                    std::thread::Builder::new()
+                        .name("Subtitle Thread".into())
+                        .spawn(move || {
+                            let tracks = subtitles::tracks(&video);
+                            let beside = subtitles::beside(&video);
+                            if video_source.get().as_ref() != Some(&video) {
+                                return;
+                            }
+                            subtitle_tracks.set(tracks);
+                            if let Some(srt) = beside {
+                                subtitle_source.set(SubtitleSource::File(srt));
+                            }
+                        })
+                        .unwrap();
+                }
+            })
+            .persist();
+        self.subtitle_source
+            .for_each({
+                let subtitles = self.subtitles.clone();
+                let video_source = self.video_source.clone();
+                let subtitle_source = self.subtitle_source.clone();
+                let announcement = self.announcement.clone();
+                move |source| {
+                    subtitles.set(None);
+                    let Some(video) = video_source.get() else {
+                        return;
+                    };
+                    let source = source.clone();
+                    let subtitles = subtitles.clone();
+                    let video_source = video_source.clone();
+                    let subtitle_source = subtitle_source.clone();
+                    let announcement = announcement.clone();
+                    // embedded tracks are decoded from the whole file
+                    std::thread::Builder::new()
+                        .name("Subtitle Thread".into())
+                        .spawn(move || {
+                            let loaded = source.load(&video);
+                            let current = video_source.get().as_ref() == Some(&video)
+                                && subtitle_source.get() == source;
+                            if !current {
+                                return;
+                            }
+                            match loaded {
+                                Ok(Some(loaded)) if loaded.is_empty() => {
+                                    announcement.set(format!("no cues in {}", source.label()));
                                 }
+                                Ok(loaded) => subtitles.set(loaded),
+                                Err(err) => announcement.set(err),
                             }
+                        })
+                        .unwrap();
+                }
+            })
+            .persist();
+
+        let settings = self.settings.clone();
+        let scaling_mode = self.scaling_mode.clone();
+        let controls = scaling_controls(self.scaling_mode.clone(), move || {
+            let mut settings = settings.lock().unwrap();
+            settings.default_scaling = scaling_mode.get();
+            let _ = settings.save();
+        });
+
+        let overlay = OverlayLayer::default();
+        let context_menu = video_context_menu({
+            let video_player = self.video_player.clone();
+            let video_source = self.video_source.clone();
+            let scaling_mode = self.scaling_mode.clone();
+            let pending_moment = self.pending_moment.clone();
+            let subtitle_source = self.subtitle_source.clone();
+            let subtitle_tracks = self.subtitle_tracks.clone();
+            let video_streams = self.video_streams.clone();
+            let video_stream = self.video_stream.clone();
+            let audio_streams = self.audio_streams.clone();
+            let audio_stream = self.audio_stream.clone();
+            let announcement = self.announcement.clone();
+            move |selected| {
+                let Some(video) = video_source.get() else {
+                    return;
+                };
+                let position = video_player.position().get();
+
+                match selected {
+                    VideoMenuOptions::CaptureFrame => {
+                        video_player.capture_frame(capture_path(&video, position))
+                    }
+                    VideoMenuOptions::SaveFrame => video_player.save_frame(&video),
+                    VideoMenuOptions::AddTag => pending_moment.set(Some((position, None))),
+                    VideoMenuOptions::CopyTimestamp => {
+                        let copied = arboard::Clipboard::new()
+                            .and_then(|mut clipboard| clipboard.set_text(format_timestamp(position)));
+                        if let Err(err) = copied {
+                            tracing::error!("failed to copy timestamp: {err}");
                         }
-                        .fuse()
-                        .shared();
-
-                        loop {
-                            let packet_forwarder: OptionFuture<_> = if playing {
-                                Some(packet_forwarder_impl.clone())
-                            } else {
-                                None
-                            }
-                            .into();
+                    }
+                    VideoMenuOptions::Speed(speed) => {
+                        video_player.send_control_message(ControlCommand::SetSpeed(speed))
+                    }
+                    VideoMenuOptions::Scaling(mode) => scaling_mode.set(mode),
+                    VideoMenuOptions::FrameTimings => video_player.show_timings().toggle(),
+                    VideoMenuOptions::LoadSubtitles => {
+                        load_subtitles(&video, subtitle_source.clone())
+                    }
+                    VideoMenuOptions::NextSubtitleTrack => {
+                        let beside = subtitles::beside(&video);
+                        let next = subtitle_source
+                            .get()
+                            .next(beside.as_deref(), &subtitle_tracks.get());
+                        announcement.set(format!("subtitles: {}", next.label()));
+                        subtitle_source.set(next);
+                    }
+                    VideoMenuOptions::HideSubtitles => subtitle_source.set(SubtitleSource::Off),
+                    VideoMenuOptions::NextVideoStream => {
+                        let Some(next) = next_stream(&video_streams, &video_stream, &video) else {
+                            announcement.set(String::from("no other video stream in this file"));
+                            return;
+                        };
+                        announcement.set(format!("video: {}", next.label));
+                        video_stream.set(Some((video, next.index)));
+                    }
+                    VideoMenuOptions::NextAudioTrack => {
+                        let Some(next) = next_stream(&audio_streams, &audio_stream, &video) else {
+                            announcement.set(String::from("no other audio track in this file"));
+                            return;
+                        };
+                        announcement.set(format!("audio: {}", next.label));
+                        video_player
+                            .send_control_message(ControlCommand::SetAudioStream(next.index));
+                        audio_stream.set(Some((video, next.index)));
+                    }
+                }
+            }
+        });
 
-                            futures::pin_mut!(packet_forwarder);
+        let loading = self.video_player.loading();
+        let spinner = ProgressBar::indeterminate()
+            .spinner()
+            .centered()
+            .collapse_vertically(loading.map_each(|loading| !loading));
 
-                            futures::select! {
-                                _ = packet_forwarder => {}
-                            }
-                        }
-                    })
-                });
+        let timings = timing_overlay(self.video_player.timings())
+            .align_top()
+            .align_left()
+            .collapse_vertically(self.video_player.show_timings().map_each(|show| !show));
 
-                video_player.make_widget()
-            } else {
-                // VideoPlayer::new().make_widget()
-                video_player.make_widget()
-            }
-        })
+        let fullscreen = self.video_player.fullscreen();
+        self.video_player
+            .clone()
+            .with_context_menu(context_menu, overlay.clone())
+            .with_regions(self.region_overlays(), self.draw_region())
+            .with_polygons(self.draw_tool.clone(), self.draw_polygon())
+            .with_poses(self.skeleton.clone(), self.draw_pose())
+            .with_crop(self.crop_window(), self.move_crop())
+            .with_subtitles(self.subtitles.clone())
+            .and(spinner)
+            .and(timings)
+            .and(HoverPopup::new(
+                self.timeline_hover.clone(),
+                self.hover_preview.clone(),
+            ))
+            .into_layers()
+            .and(transport_bar(&self.video_player))
+            .and(self.timeline())
+            .and(self.qc_spans())
+            .and(controls.collapse_vertically(fullscreen))
+            .into_rows()
+            .and(overlay)
+            .into_layers()
     }
 
     fn handle_image_source(&self, on_error: SharedCallback<String>) -> impl MakeWidget {
@@ -313,36 +2290,560 @@ impl App {
     }
 
     pub fn view(&self) -> impl MakeWidget {
+        let _span = tracing::info_span!("build_view").entered();
         let image_source = self.image_source.clone();
         let open_image_button = file_picker("open image", image_source);
 
         let video_source = self.video_source.clone();
         let open_video_button = file_picker("open video", video_source);
 
+        let mosaic_visible = self.mosaic.visible();
+        let open_mosaic_button = "mosaic".into_button().on_click({
+            let mosaic_visible = mosaic_visible.clone();
+            move |_| mosaic_visible.set(true)
+        });
+        let mosaic = self
+            .mosaic
+            .view(self.video_source.clone())
+            .expand()
+            .collapse_vertically(mosaic_visible.map_each(|visible| !visible));
+
         let modal = Modal::new();
         let on_error = error_callback(modal.clone());
 
         let image = self.handle_image_source(on_error);
         let video = self.handle_video_source().centered().pad_by(Lp::new(10));
 
-        let counter = self.counter.clone();
-        let counter = counter::counter(counter);
+        let main_menu = MainMenu::new({
+            let video_source = self.video_source.clone();
+            let image_source = self.image_source.clone();
+            let workspace = self.workspace();
+            move |selected| match selected {
+                MainMenuOptions::OpenVideo => pick_file(video_source.clone()),
+                MainMenuOptions::OpenImage => pick_file(image_source.clone()),
+                MainMenuOptions::OpenProject => workspace.open_dialog(),
+                MainMenuOptions::SaveProject => workspace.save_dialog(),
+            }
+        })
+        .view();
+
+        let tooltips = OverlayLayer::default();
+        let tag_current_moment = self.tag_current_moment();
+        let category_buttons = category_buttons(
+            self.categories.clone(),
+            self.keybindings.clone(),
+            self.tags.clone(),
+            self.palette.clone(),
+            self.tablet_layout.clone(),
+            tooltips.clone(),
+            tag_current_moment.clone(),
+        );
+        let edit_categories_button = "categories".into_button().on_click({
+            let show_category_editor = self.show_category_editor.clone();
+            move |_| show_category_editor.toggle()
+        });
+        let selected_tag = (&self.tags, &self.selected_tag).map_each(|(tags, selected)| {
+            let Some(tag) = selected.and_then(|id| tags.get(id)) else {
+                return String::from("no tag selected");
+            };
+            let lock = if tags.is_locked(tag) { " (locked)" } else { "" };
+            match tag.end {
+                Some(end) => format!(
+                    "selected: {} {} - {}{lock}",
+                    tag.label,
+                    format_timestamp(tag.time),
+                    format_timestamp(end)
+                ),
+                None => format!(
+                    "selected: {} {}{lock}",
+                    tag.label,
+                    format_timestamp(tag.time)
+                ),
+            }
+        });
+
+        let tablet_button = self
+            .tablet_layout
+            .map_each(|tablet| {
+                if *tablet {
+                    String::from("[tablet layout]")
+                } else {
+                    String::from("tablet layout")
+                }
+            })
+            .into_button()
+            .on_click({
+                let tablet_layout = self.tablet_layout.clone();
+                move |_| tablet_layout.toggle()
+            });
+
+        let hardware_decoding_button = self
+            .hardware_decoding
+            .map_each(|hardware| {
+                if *hardware {
+                    String::from("[hw decoding]")
+                } else {
+                    String::from("hw decoding")
+                }
+            })
+            .into_button()
+            .on_click({
+                let hardware_decoding = self.hardware_decoding.clone();
+                move |_| hardware_decoding.toggle()
+            });
+
+        // announcements are plain text in a fixed place, easy to follow under a magnifier
+        let status_line = self
+            .announcement
+            .clone()
+            .align_left()
+            .collapse_vertically(self.announcement.map_each(String::is_empty));
+
+        let backups_button = "backups".into_button().on_click({
+            let show_backups = self.show_backups.clone();
+            move |_| show_backups.toggle()
+        });
+        let backups = backup_panel(
+            self.tags.clone(),
+            self.database.clone(),
+            self.backup_settings.clone(),
+            self.backups.clone(),
+        )
+        .collapse_vertically(self.show_backups.map_each(|show| !show));
+
+        let trash_button = "trash".into_button().on_click({
+            let show_trash = self.show_trash.clone();
+            move |_| show_trash.toggle()
+        });
+        let trash = trash_panel(self.tags.clone(), &self.show_trash)
+            .collapse_vertically(self.show_trash.map_each(|show| !show));
+
+        let tag_list_button = "tag list".into_button().on_click({
+            let show_tag_list = self.show_tag_list.clone();
+            move |_| show_tag_list.toggle()
+        });
+        let tag_list = tag_list_panel(
+            self.tags.clone(),
+            self.selected_tag.clone(),
+            self.video_source.clone(),
+            self.renditions.clone(),
+            self.video_player.clone(),
+        )
+        .width(Lp::points(280))
+        .collapse_horizontally(
+            (&self.show_tag_list, &self.video_player.fullscreen())
+                .map_each(|(show, fullscreen)| !show || *fullscreen),
+        );
+        let chapters_button = "chapters".into_button().on_click({
+            let show_chapters = self.show_chapters.clone();
+            move |_| show_chapters.toggle()
+        });
+        let chapter_list = chapter_list_panel(self.chapters.clone(), self.video_player.clone())
+            .width(Lp::points(240))
+            .collapse_horizontally(
+                (&self.show_chapters, &self.video_player.fullscreen())
+                    .map_each(|(show, fullscreen)| !show || *fullscreen),
+            );
 
-        let _main_menu = self.main_menu.view();
+        let quick_tags_button = "quick tags".into_button().on_click({
+            let show_quick_tags = self.show_quick_tags.clone();
+            move |_| show_quick_tags.toggle()
+        });
+        let quick_tags = quick_tags_panel(self.tags.clone(), self.announcement.clone())
+            .collapse_vertically(self.show_quick_tags.map_each(|show| !show));
+
+        let queue_button = "queue".into_button().on_click({
+            let show_queue = self.show_queue.clone();
+            move |_| show_queue.toggle()
+        });
+        let queue = queue_panel(
+            self.queue.clone(),
+            self.tags.clone(),
+            self.video_source.clone(),
+            self.video_player.clone(),
+        )
+        .collapse_vertically(self.show_queue.map_each(|show| !show));
+
+        let review_later_button = "review later".into_button().on_click({
+            let show_review_later = self.show_review_later.clone();
+            move |_| show_review_later.toggle()
+        });
+        let review_later = review_later_panel(
+            self.review_later.clone(),
+            self.video_source.clone(),
+            self.renditions.clone(),
+            self.video_player.clone(),
+            self.announcement.clone(),
+        )
+        .collapse_vertically(self.show_review_later.map_each(|show| !show));
+
+        let interchange_button = "import/export".into_button().on_click({
+            let show_interchange = self.show_interchange.clone();
+            move |_| show_interchange.toggle()
+        });
+        let interchange = interchange_panel(
+            self.tags.clone(),
+            self.video_source.clone(),
+            self.renditions.clone(),
+            self.categories.clone(),
+            self.palette.clone(),
+            self.skeletons.clone(),
+        )
+        .collapse_vertically(self.show_interchange.map_each(|show| !show));
+
+        let denoise_button = self
+            .denoise
+            .map_each(|denoise| denoise.label())
+            .into_button()
+            .on_click({
+                let denoise = self.denoise.clone();
+                let announcement = self.announcement.clone();
+                move |_| {
+                    let next = denoise.get().next();
+                    denoise.set(next);
+                    announcement.set(
+                        next.warning()
+                            .map_or_else(|| String::from("denoise off"), String::from),
+                    );
+                }
+            });
+
+        let pipelines_button = "pipelines".into_button().on_click({
+            let show_pipelines = self.show_pipelines.clone();
+            move |_| show_pipelines.toggle()
+        });
+        let pipelines = pipelines_panel(
+            self.tags.clone(),
+            self.video_source.clone(),
+            self.renditions.clone(),
+        )
+        .collapse_vertically(self.show_pipelines.map_each(|show| !show));
+
+        let similarity_button = "similar".into_button().on_click({
+            let show_similarity = self.show_similarity.clone();
+            move |_| show_similarity.toggle()
+        });
+        let similarity = similarity_panel(
+            self.tags.clone(),
+            self.database.clone(),
+            self.queue.clone(),
+            self.selected_tag.clone(),
+            self.video_source.clone(),
+            self.renditions.clone(),
+            self.video_player.clone(),
+        )
+        .collapse_vertically(self.show_similarity.map_each(|show| !show));
+
+        let qc_rules_button = "QC rules".into_button().on_click({
+            let show_qc_rules = self.show_qc_rules.clone();
+            move |_| show_qc_rules.toggle()
+        });
+        let qc_rules = qc_rules_panel(
+            self.qc_rules.clone(),
+            {
+                let database = self.database.clone();
+                let tags = self.tags.clone();
+                let queue = self.queue.clone();
+                move || library(&database, &tags, &queue)
+            },
+            self.qc.clone(),
+            self.announcement.clone(),
+        )
+        .collapse_vertically(self.show_qc_rules.map_each(|show| !show));
+
+        let organize_button = "organize".into_button().on_click({
+            let show_organize = self.show_organize.clone();
+            move |_| show_organize.toggle()
+        });
+        let organize = organize_panel(
+            {
+                let database = self.database.clone();
+                let tags = self.tags.clone();
+                let queue = self.queue.clone();
+                move || library(&database, &tags, &queue)
+            },
+            self.tags.clone(),
+            self.database.clone(),
+            self.renditions.clone(),
+            self.queue.clone(),
+            self.video_source.clone(),
+            self.announcement.clone(),
+        )
+        .collapse_vertically(self.show_organize.map_each(|show| !show));
+
+        let export_button = "export".into_button().on_click({
+            let show_export = self.show_export.clone();
+            move |_| show_export.toggle()
+        });
+        let export = export_panel(
+            {
+                let database = self.database.clone();
+                let tags = self.tags.clone();
+                let queue = self.queue.clone();
+                move || library(&database, &tags, &queue)
+            },
+            self.export_profiles.clone(),
+            self.tags.clone(),
+            self.database.clone(),
+            self.selected_tag.clone(),
+            self.video_source.clone(),
+            self.renditions.clone(),
+            self.editing_crop.clone(),
+            modal.clone(),
+            self.export_queue.clone(),
+            self.announcement.clone(),
+        )
+        .collapse_vertically(self.show_export.map_each(|show| !show));
+
+        let classify_button = "classify".into_button().on_click({
+            let active = self.classifier.active.clone();
+            move |_| active.toggle()
+        });
+        let classify = self
+            .classifier
+            .panel()
+            .collapse_vertically(self.classifier.active.map_each(|active| !active));
+
+        let activity_button = "activity".into_button().on_click({
+            let show_activity = self.show_activity.clone();
+            move |_| show_activity.toggle()
+        });
+        let activity = activity_chart(self.activity.clone())
+            .collapse_vertically(self.show_activity.map_each(|show| !show));
+
+        let ripple_shift = ripple_shift_tool(
+            self.tags.clone(),
+            self.video_source.clone(),
+            self.video_player.position(),
+            self.renditions.clone(),
+        );
+
+        let gap_report = gap_report_tool(
+            self.tags.clone(),
+            self.video_source.clone(),
+            self.renditions.clone(),
+        );
+
+        let category_editor = category_editor(
+            self.categories.clone(),
+            self.keybindings.clone(),
+            self.tags.clone(),
+            self.palette.clone(),
+        )
+            .collapse_vertically(self.show_category_editor.map_each(|show| !show));
 
         // open_image_button
         //     .and(open_video_button)
         //     .into_rows()
-        open_video_button
+        // double clicking the video hides everything around it
+        let fullscreen = self.video_player.fullscreen();
+
+        let player = main_menu
+            .and(open_video_button)
             .and(open_image_button)
-            // .and(counter)
+            .and(open_mosaic_button)
+            .and(self.open_proxy_button())
+            .and(self.open_original_button())
+            .and(self.lock_video_button())
+            .and(self.remove_video_button())
+            .and(self.history_buttons())
+            .and(edit_categories_button)
+            .and(quick_tags_button)
+            .and(queue_button)
+            .and(review_later_button)
+            .and(interchange_button)
+            .and(classify_button)
+            .and(similarity_button)
+            .and(pipelines_button)
+            .and(denoise_button)
+            .and(self.stabilizer.button())
+            .and(self.qc.controls(self.video_source.clone()))
+            .and(qc_rules_button)
+            .and(organize_button)
+            .and(export_button)
+            .and(activity_button)
+            .and(backups_button)
+            .and(trash_button)
+            .and(tag_list_button)
+            .and(chapters_button)
+            .and(tablet_button)
+            .and(hardware_decoding_button)
+            .and(appearance_controls(self.appearance.clone()))
             .into_columns()
-            .and(image)
-            .and(video)
+            .collapse_vertically(fullscreen.clone())
+            .and(
+                category_editor
+                    .and(quick_tags)
+                    .and(queue)
+                    .and(review_later)
+                    .and(interchange)
+                    .and(classify)
+                    .and(similarity)
+                    .and(pipelines)
+                    .and(qc_rules)
+                    .and(organize)
+                    .and(export)
+                    .and(activity)
+                    .and(backups)
+                    .and(trash)
+                    .and(ripple_shift)
+                    .and(gap_report)
+                    .and(webhook_editor(self.webhook_urls.clone()))
+                    .into_rows()
+                    .collapse_vertically(fullscreen.clone()),
+            )
+            .and(image.collapse_vertically(fullscreen.clone()))
+            .and(
+                chapter_list
+                    .and(video.expand_horizontally())
+                    .and(tag_list)
+                    .into_columns(),
+            )
+            .and(self.moment_tagger().collapse_vertically(fullscreen.clone()))
+            .and(self.telemetry_bar().collapse_vertically(fullscreen.clone()))
+            .and(
+                category_buttons
+                    .expand_horizontally()
+                    .and(selected_tag)
+                    .and(self.note_editor())
+                    .and(draw_tool_controls(self.draw_tool.clone()))
+                    .and(self.skeleton_controls())
+                    .and(self.remove_shape_button())
+                    .and(self.track_forward_button())
+                    .and(self.delete_tag_button())
+                    .into_columns()
+                    .collapse_vertically(fullscreen),
+            )
+            .and(status_line)
             .into_rows()
+            .collapse_vertically(mosaic_visible);
+
+        let root = player.and(mosaic).into_rows();
+
+        let root = TagNudging::new(
+            root,
+            self.tags.clone(),
+            self.selected_tag.clone(),
+            self.video_player.frame_duration(),
+        );
+
+        // inside the category shortcuts, digits classify while the panel is open
+        let root = FrameClassifier::new(root, self.classifier.clone());
+
+        let root = CategoryShortcuts::new(root, self.keybindings.clone(), tag_current_moment);
+        // outside the category shortcuts, so i and o can still be bound to categories
+        let root = RangeMarking::new(root, self.mark_range());
+        // outside everything, keys only get here when nothing else wanted them
+        let root = Shortcuts::new(root)
+            .on_action(self.playback_shortcuts())
+            .on_action(self.quick_tag_shortcuts())
+            .on_action(self.chapter_shortcuts())
+            .on_action(self.history_shortcuts())
+            .and(tooltips)
             .and(modal)
-            .into_layers()
+            .into_layers();
+
+        appearance::apply(root, &self.appearance)
+    }
+}
+
+fn record_opens(video_source: &Dynamic<Option<PathBuf>>, recorder: Recorder) {
+    video_source
+        .for_each(move |source| {
+            if let Some(source) = source {
+                recorder.record(RemoteCommand::Open(source.clone()), std::time::Duration::ZERO);
+            }
+        })
+        .persist();
+}
+
+// a 360 video turns to where its selected tag was made
+fn look_at_selected(
+    selected_tag: &Dynamic<Option<TagId>>,
+    tags: &Dynamic<TagStore>,
+    video_player: &VideoPlayer,
+) {
+    let tags = tags.clone();
+    let view = video_player.view();
+    selected_tag
+        .for_each(move |selected| {
+            let Some(tagged) = selected.and_then(|id| tags.map_ref(|tags| tags.get(id)?.view)) else {
+                return;
+            };
+            if let Some(view) = &mut *view.lock() {
+                *view = tagged;
+            }
+        })
+        .persist();
+}
+
+// what's playing and where, for the status line
+fn announce_playback(
+    video_source: &Dynamic<Option<PathBuf>>,
+    video_player: &VideoPlayer,
+    announcement: &Dynamic<String>,
+) {
+    video_source
+        .for_each({
+            let announcement = announcement.clone();
+            move |source| {
+                let Some(source) = source else {
+                    return;
+                };
+                let name = source
+                    .file_name()
+                    .map_or_else(|| source.to_string_lossy(), |name| name.to_string_lossy());
+                announcement.set(format!("opened {name}"));
+            }
+        })
+        .persist();
+
+    let position = video_player.position();
+    video_player
+        .playing()
+        .for_each({
+            let announcement = announcement.clone();
+            move |playing| {
+                let state = if *playing { "playing" } else { "paused" };
+                announcement.set(format!("{state} at {}", format_timestamp(position.get())));
+            }
+        })
+        .persist();
+}
+
+// the stream after the one playing, none when the file has only one
+fn next_stream(
+    streams: &Dynamic<Vec<MediaStream>>,
+    chosen: &Dynamic<Option<(PathBuf, usize)>>,
+    video: &Path,
+) -> Option<MediaStream> {
+    let streams = streams.get();
+    if streams.len() < 2 {
+        return None;
     }
+    let playing = chosen
+        .get()
+        .filter(|(chosen, _)| chosen == video)
+        .map_or(streams[0].index, |(_, stream)| stream);
+    let at = streams.iter().position(|stream| stream.index == playing);
+    Some(streams[at.map_or(0, |at| (at + 1) % streams.len())].clone())
+}
+
+// an .srt for the open video, from a file picker
+fn load_subtitles(video: &Path, subtitle_source: Dynamic<SubtitleSource>) {
+    let folder = video.parent().map(Path::to_path_buf);
+    std::thread::Builder::new()
+        .name("File Picker Thread".into())
+        .spawn(move || {
+            let mut dialog = rfd::FileDialog::new().add_filter("SubRip", &["srt"]);
+            if let Some(folder) = folder {
+                dialog = dialog.set_directory(folder);
+            }
+            if let Some(path) = dialog.pick_file() {
+                subtitle_source.set(SubtitleSource::File(path));
+            }
+        })
+        .unwrap();
 }
 
 fn error_callback(modal: Modal) -> SharedCallback<String> {
@@ -362,36 +2863,500 @@ fn error_callback(modal: Modal) -> SharedCallback<String> {
     })
 }
 
+// undoes the last tag change, or redoes the last undone one, saying what on the status line
+fn step_history(tags: &Dynamic<TagStore>, announcement: &Dynamic<String>, redo: bool) {
+    let stepped = if redo {
+        tags.lock().redo().map(|what| format!("redid {what}"))
+    } else {
+        tags.lock().undo().map(|what| format!("undid {what}"))
+    };
+    announcement.set(stepped.unwrap_or_else(|err| err));
+}
+
+pub fn format_timestamp(time: std::time::Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1_000 % 60,
+        millis % 1_000
+    )
+}
+
+// stills land next to the video, named after it and the moment they were taken at
+fn capture_path(video: &Path, position: std::time::Duration) -> PathBuf {
+    let stem = video
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("frame"));
+    let timestamp = format_timestamp(position).replace([':', '.'], "-");
+
+    video.with_file_name(format!("{stem}_{timestamp}.png"))
+}
+
 fn file_picker(label: &str, source: Dynamic<Option<PathBuf>>) -> impl MakeWidget {
-    label.into_button().on_click(move |_| {
-        let source = source.clone();
-        std::thread::Builder::new()
-            .name("File Picker Thread".into())
-            .spawn(move || {
-                let pick_file = rfd::FileDialog::new().pick_file();
-                if let Some(path) = pick_file {
-                    source.set(Some(path));
+    label.into_button().on_click(move |_| pick_file(source.clone()))
+}
+
+fn pick_file(source: Dynamic<Option<PathBuf>>) {
+    std::thread::Builder::new()
+        .name("File Picker Thread".into())
+        .spawn(move || {
+            let pick_file = rfd::FileDialog::new().pick_file();
+            if let Some(path) = pick_file {
+                source.set(Some(path));
+            }
+        })
+        .unwrap();
+}
+
+#[derive(Debug, Clone, Default)]
+struct DecodeOptions {
+    min_frame_interval: Option<std::time::Duration>,
+    max_dimension: Option<u32>,
+    // previews stay silent
+    audio: bool,
+    // decode on the gpu where the platform and codec allow
+    hardware: bool,
+    // ffmpeg filtergraph the shown frames go through, see `pipelines`
+    filters: Option<String>,
+    // vid.stab's detected motion to smooth out after them, see `stabilize`
+    stabilization: Option<PathBuf>,
+    // which of the file's video streams, its best one without
+    video_stream: Option<usize>,
+    // and the same for sound
+    audio_stream: Option<usize>,
+}
+
+fn decode_video(path: &Path, options: DecodeOptions, context: PipelineContext) {
+    let PipelineContext {
+        contents: content,
+        position,
+        frame_duration,
+        duration,
+        loading,
+        controls,
+        cancel,
+        timings,
+        show_timings,
+        history,
+        repeat,
+        filtered,
+        wipe,
+        view,
+        stereo,
+    } = context;
+
+    futures::executor::block_on(async move {
+        // opening can take a while for big or remote files, this only ever
+        // runs on the playback thread while the player shows a spinner
+        let mut ictx = match media::open_input(path) {
+            Ok(ictx) => ictx,
+            Err(err) => {
+                tracing::error!("failed to open {}: {err}", path.display());
+                loading.set(false);
+                return;
+            }
+        };
+        let chosen = options
+            .video_stream
+            .and_then(|idx| ictx.stream(idx))
+            .filter(|stream| stream.parameters().medium() == ffmpeg::media::Type::Video);
+        let Some(stream) = chosen.or_else(|| ictx.streams().best(ffmpeg::media::Type::Video))
+        else {
+            tracing::warn!("{} has no video stream", path.display());
+            loading.set(false);
+            return;
+        };
+        let vs_idx = stream.index();
+        if is_equirectangular(&stream) {
+            view.set(Some(ViewDirection::default()));
+        }
+        stereo.set(StereoLayout::detect(&stream, path));
+
+        if ictx.duration() > 0 {
+            duration.set(std::time::Duration::from_secs_f64(
+                ictx.duration() as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE),
+            ));
+        }
+
+        let frame_rate = stream.avg_frame_rate();
+        if frame_rate.numerator() > 0 && frame_rate.denominator() > 0 {
+            frame_duration.set(std::time::Duration::from_secs_f64(
+                frame_rate.denominator() as f64 / frame_rate.numerator() as f64,
+            ));
+        }
+
+        let time_base = stream.time_base();
+        let time_base_seconds = time_base.numerator() as f64 / time_base.denominator() as f64;
+        let mut last_presented: Option<f64> = None;
+        // made on the first frame swscale converts, kept while the frames match it
+        let mut rescaler = None;
+        let mut original_rescaler = None;
+        let seek_position = position.clone();
+
+        // the reader moves it on commands, the audio device keeps it honest
+        let clock = PlaybackClock::new();
+        let mut audio = if options.audio {
+            let chosen = options
+                .audio_stream
+                .and_then(|idx| ictx.stream(idx))
+                .filter(|stream| stream.parameters().medium() == ffmpeg::media::Type::Audio);
+            chosen
+                .or_else(|| ictx.streams().best(ffmpeg::media::Type::Audio))
+                .and_then(|stream| {
+                    Some((
+                        stream.index(),
+                        AudioDecoder::start(&stream, cancel.clone(), clock.clone())?,
+                    ))
+                })
+        } else {
+            None
+        };
+
+        // the frame callback takes the timings, the reader reports drops into them
+        let reader_timings = timings.clone();
+        let filter = (options.filters.is_some() || options.stabilization.is_some()).then(|| {
+            let filter = FrameFilter::new(options.filters.clone().unwrap_or_default(), time_base);
+            match &options.stabilization {
+                Some(transforms) if frame_rate.numerator() > 0 => {
+                    filter.stabilized(transforms.clone(), f64::from(frame_rate))
                 }
-            })
-            .unwrap();
+                _ => filter,
+            }
+        });
+        filtered.set(filter.is_some());
+        let video_decoder = VideoDecoder::start(
+            &stream,
+            cancel.clone(),
+            clock.clone(),
+            options.hardware,
+            filter,
+            wipe,
+            Box::new(move |yuv_frame, original, decode| {
+                // previews only need a few frames per second, skip the rest before converting
+                if let (Some(interval), Some(pts)) = (options.min_frame_interval, yuv_frame.pts()) {
+                    let seconds = pts as f64 * time_base_seconds;
+                    if let Some(last) = last_presented {
+                        // seeking backwards lands more than an interval before the last one
+                        if (seconds - last).abs() < interval.as_secs_f64() {
+                            return;
+                        }
+                    }
+                    last_presented = Some(seconds);
+                }
+
+                if let Some(pts) = yuv_frame.pts() {
+                    position.set(std::time::Duration::from_secs_f64(
+                        (pts as f64 * time_base_seconds).max(0.),
+                    ));
+                }
+
+                // the player converts yuv in a shader while drawing. formats it doesn't
+                // take and downscaled software rendering still go through swscale
+                let convert_started = std::time::Instant::now();
+                let planes = YuvFrame::from_frame(yuv_frame).filter(|_| options.max_dimension.is_none());
+                let image = planes
+                    .is_none()
+                    .then(|| frame_to_image(yuv_frame, options.max_dimension, &mut rescaler));
+                let original = original
+                    .map(|original| frame_planes(original, options.max_dimension, &mut original_rescaler));
+                let convert = convert_started.elapsed();
+
+                // the renderer writes the planes into textures it already has when it
+                // draws them, uploading happens there
+                let upload_started = std::time::Instant::now();
+                let planes = match (planes, image) {
+                    (Some(planes), _) => planes,
+                    (None, Some(image)) => YuvFrame::from_rgba(image),
+                    (None, None) => unreachable!("frames without planes are converted above"),
+                };
+                let frame = match original {
+                    Some(original) => VideoFrame::Compared {
+                        filtered: Arc::new(planes),
+                        original: Arc::new(original),
+                    },
+                    None => VideoFrame::Decoded(Arc::new(planes)),
+                };
+                history.push(position.get(), frame.clone());
+                if show_timings.get() {
+                    timings.lock().push(FrameTiming {
+                        decode,
+                        convert,
+                        upload: upload_started.elapsed(),
+                        draw: None,
+                    });
+                }
+                content.set(frame);
+                if loading.get() {
+                    loading.set(false);
+                }
+            }),
+        );
+        let video_decoder = match video_decoder {
+            Ok(video_decoder) => video_decoder,
+            Err(err) => {
+                tracing::error!("failed to open a decoder for {}: {err}", path.display());
+                loading.set(false);
+                return;
+            }
+        };
+
+        // the reader owns the input, so it carries out seeks. it stays around after
+        // the last packet, seeking back is still possible at the end of a video
+        let mut pending: Option<ffmpeg::codec::packet::Packet> = None;
+        let mut at_end = false;
+        let mut reported_dropped = 0;
+        'reader: loop {
+            if cancel.is_cancelled() {
+                break;
+            }
+
+            let dropped = video_decoder.dropped();
+            if dropped != reported_dropped {
+                reported_dropped = dropped;
+                reader_timings.lock().set_dropped(dropped);
+            }
+
+            // looping and a-b repeat go back the same way a seek does
+            let mut restart = repeat.map_ref(|repeat| {
+                if clock.is_paused() {
+                    return None;
+                }
+                if let Some((a, b)) = repeat.segment() {
+                    if seek_position.get() >= b {
+                        return Some(a);
+                    }
+                }
+                let looping = repeat.looping || repeat.segment().is_some();
+                (looping && at_end && video_decoder.drained()).then(|| repeat.restart())
+            });
+
+            loop {
+                let command = match controls.try_recv() {
+                    Ok(command) => command,
+                    Err(_) => match restart.take() {
+                        Some(target) => {
+                            // keeps the check above from firing again before the
+                            // first frame at the target is shown
+                            seek_position.set(target);
+                            ControlCommand::Seek(target)
+                        }
+                        None => break,
+                    },
+                };
+                let target = match command {
+                    ControlCommand::Seek(target) => target,
+                    // stays inside the video however far a remote asks to go
+                    ControlCommand::SeekRelative(seconds) if seconds.is_nan() => continue,
+                    ControlCommand::SeekRelative(seconds) => {
+                        let end = duration.get();
+                        let target = seek_position.get().as_secs_f64() + seconds;
+                        match std::time::Duration::try_from_secs_f64(target.max(0.)) {
+                            Ok(target) if end.is_zero() => target,
+                            Ok(target) => target.min(end),
+                            // further than a duration goes, only the end is that far
+                            Err(_) if !end.is_zero() => end,
+                            Err(_) => continue,
+                        }
+                    }
+                    ControlCommand::Stop => {
+                        audio = None;
+                        video_decoder.send_control_message(command);
+                        break 'reader;
+                    }
+                    // the new track has to start where playback is, the reader already
+                    // read past it, so this ends in a seek there. the old decoder gives up
+                    // the output device first
+                    ControlCommand::SetAudioStream(idx) => {
+                        if !options.audio {
+                            continue;
+                        }
+                        drop(audio.take());
+                        audio = ictx
+                            .stream(idx)
+                            .filter(|stream| {
+                                stream.parameters().medium() == ffmpeg::media::Type::Audio
+                            })
+                            .and_then(|stream| {
+                                Some((
+                                    stream.index(),
+                                    AudioDecoder::start(&stream, cancel.clone(), clock.clone())?,
+                                ))
+                            });
+                        seek_position.get()
+                    }
+                    command => {
+                        video_decoder.send_control_message(command);
+                        continue;
+                    }
+                };
+
+                // lands on the keyframe at or before the target, the decoder goes on from there
+                let timestamp =
+                    (target.as_secs_f64() * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+                if let Err(err) = ictx.seek(timestamp, ..=timestamp) {
+                    tracing::error!("failed to seek {} to {target:?}: {err}", path.display());
+                    continue;
+                }
+                pending = None;
+                at_end = false;
+                clock.unanchor();
+                if !video_decoder.seek(target) {
+                    break 'reader;
+                }
+                if audio.as_ref().is_some_and(|(_, decoder)| !decoder.seek(target)) {
+                    audio = None;
+                }
+            }
+
+            if at_end {
+                std::thread::sleep(CONTROL_POLL_INTERVAL);
+                continue;
+            }
+
+            let packet = match pending.take() {
+                Some(packet) => packet,
+                None => {
+                    let mut packet = ffmpeg::codec::packet::Packet::empty();
+                    match packet.read(&mut ictx) {
+                        Ok(()) if packet.stream() == vs_idx => packet,
+                        Ok(()) if audio.as_ref().is_some_and(|(idx, _)| packet.stream() == *idx) => {
+                            packet
+                        }
+                        Ok(()) => continue,
+                        Err(ffmpeg::Error::Eof) => {
+                            at_end = true;
+                            if audio.as_ref().is_some_and(|(_, decoder)| !decoder.end_of_stream()) {
+                                audio = None;
+                            }
+                            if !video_decoder.end_of_stream() {
+                                break 'reader;
+                            }
+                            continue;
+                        }
+                        // damaged packets are skipped, like the demuxer iterator does
+                        Err(_) => continue,
+                    }
+                }
+            };
+
+            if packet.stream() != vs_idx {
+                let Some((_, decoder)) = &audio else {
+                    continue;
+                };
+                match decoder.try_get_packet(packet) {
+                    Ok(()) => {}
+                    Err(std::sync::mpsc::TrySendError::Full(returned)) => {
+                        pending = Some(returned);
+                        std::thread::sleep(CONTROL_POLL_INTERVAL);
+                    }
+                    // no output device, or it went away. the video plays on by itself
+                    Err(std::sync::mpsc::TrySendError::Disconnected(_)) => audio = None,
+                }
+                continue;
+            }
+
+            match video_decoder.try_get_packet(packet) {
+                Ok(()) => {}
+                Err(std::sync::mpsc::TrySendError::Full(returned)) => {
+                    pending = Some(returned);
+                    std::thread::sleep(CONTROL_POLL_INTERVAL);
+                }
+                // the decoder was stopped
+                Err(std::sync::mpsc::TrySendError::Disconnected(_)) => break 'reader,
+            }
+        }
+
+        if !cancel.is_cancelled() {
+            video_decoder.finish(&controls);
+        }
     })
 }
 
-pub struct Rescaler(ffmpeg::software::scaling::Context);
+// what the player draws for `frame`, through swscale when the shader doesn't take its
+// format or it has to be downscaled
+fn frame_planes(
+    frame: &ffmpeg::util::frame::Video,
+    max_dimension: Option<u32>,
+    rescaler: &mut Option<Rescaler>,
+) -> YuvFrame {
+    YuvFrame::from_frame(frame)
+        .filter(|_| max_dimension.is_none())
+        .unwrap_or_else(|| YuvFrame::from_rgba(frame_to_image(frame, max_dimension, rescaler)))
+}
+
+// `rescaler` is kept by the caller for the next frame, it's only made again when the
+// frames change format or size
+fn frame_to_image(
+    yuv_frame: &ffmpeg::util::frame::Video,
+    max_dimension: Option<u32>,
+    rescaler: &mut Option<Rescaler>,
+) -> RgbaImage {
+    let mut rgb_frame = ffmpeg::util::frame::Video::empty();
+    let (width, height) = output_size(yuv_frame.width(), yuv_frame.height(), max_dimension);
+    let rescaler = match rescaler {
+        Some(rescaler) if rescaler.fits(yuv_frame, width, height) => rescaler,
+        _ => rescaler.insert(Rescaler::new(yuv_frame, width, height)),
+    };
+    rescaler.context.run(yuv_frame, &mut rgb_frame).unwrap();
+
+    // do something with the rgb_frame
+    let mut pixel_buffer = ImageBuffer::<Rgb<u8>, Vec<u8>>::new(rgb_frame.width(), rgb_frame.height());
+
+    let pixel_line_iter =
+        pixel_buffer.chunks_mut(rgb_frame.width() as usize * std::mem::size_of::<Rgb<u8>>());
+
+    let source_line_iter = rgb_frame.data(0).chunks_exact(rgb_frame.stride(0));
+
+    for (source, dest) in source_line_iter.zip(pixel_line_iter) {
+        dest.copy_from_slice(&source[..dest.len()]);
+    }
+
+    DynamicImage::from(pixel_buffer).into_rgba8()
+}
+
+pub struct Rescaler {
+    context: ffmpeg::software::scaling::Context,
+    // input format and size, then output size
+    made_for: (ffmpeg::format::Pixel, u32, u32, u32, u32),
+}
 
 unsafe impl std::marker::Send for Rescaler {}
 
-fn rescaler(frame: &ffmpeg::util::frame::Video) -> Rescaler {
-    Rescaler(
-        ffmpeg::software::scaling::Context::get(
+impl Rescaler {
+    fn new(frame: &ffmpeg::util::frame::Video, width: u32, height: u32) -> Self {
+        let context = ffmpeg::software::scaling::Context::get(
             frame.format(),
             frame.width(),
             frame.height(),
             ffmpeg::format::Pixel::RGB24,
-            frame.width(),
-            frame.height(),
+            width,
+            height,
             ffmpeg::software::scaling::Flags::BILINEAR,
         )
-        .unwrap(),
-    )
+        .unwrap();
+        Self {
+            context,
+            made_for: (frame.format(), frame.width(), frame.height(), width, height),
+        }
+    }
+
+    fn fits(&self, frame: &ffmpeg::util::frame::Video, width: u32, height: u32) -> bool {
+        self.made_for == (frame.format(), frame.width(), frame.height(), width, height)
+    }
+}
+
+fn output_size(width: u32, height: u32, max_dimension: Option<u32>) -> (u32, u32) {
+    match max_dimension {
+        Some(max) if width.max(height) > max => {
+            let scale = max as f64 / width.max(height) as f64;
+            (
+                ((width as f64 * scale) as u32).max(1),
+                ((height as f64 * scale) as u32).max(1),
+            )
+        }
+        _ => (width, height),
+    }
 }
@@ -1,8 +1,112 @@
 use cushy::{value::Dynamic, widget::MakeWidget, Run};
+use tracing_subscriber::{filter::LevelFilter, fmt::format::FmtSpan, EnvFilter};
 
-use gui_cushy::App;
+use gui_cushy::{
+    osc_address_from_args, probe_gpu_from_args, relaunch_in_software_mode, replay,
+    replay_script_from_args, serve_osc, warm_up_media, App, ExportWorker, RenderMode, RpcTransport,
+};
 
 fn main() -> cushy::Result {
-    let app = App::default();
-    app.view().into_window().maximized(Dynamic::new(true)).run()
+    probe_gpu_from_args();
+    // warnings and errors by default, RUST_LOG=info also prints how long each startup
+    // step took. stdout belongs to `--rpc`, so this goes to stderr
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::WARN.into())
+                .from_env_lossy(),
+        )
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .init();
+    // a machine making exports for others runs without a window
+    if let Some(worker) = ExportWorker::from_args() {
+        if let Err(err) = worker.run() {
+            tracing::error!("export worker stopped: {err}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let startup = tracing::info_span!("startup").entered();
+
+    let render_mode = RenderMode::from_env().probed();
+    render_mode.apply();
+    warm_up_media();
+
+    let app = App::new(render_mode);
+    if let Some(transport) = RpcTransport::from_args() {
+        if let Err(err) = transport.serve(app.remote()) {
+            tracing::error!("failed to start rpc control: {err}");
+        }
+    }
+    if let Some(address) = osc_address_from_args() {
+        if let Err(err) = serve_osc(app.remote(), address) {
+            tracing::error!("failed to listen for osc on {address}: {err}");
+        }
+    }
+    #[cfg(feature = "gamepad")]
+    if !gui_cushy::is_safe_mode() {
+        gui_cushy::listen_for_gamepads(app.remote());
+    }
+    if let Some(script) = replay_script_from_args() {
+        if let Err(err) = replay(app.remote(), script.clone()) {
+            tracing::error!("failed to replay {}: {err}", script.display());
+        }
+    }
+    // dropping the connections would stop listening, so they live as long as main
+    #[cfg(feature = "midi")]
+    let _midi = (gui_cushy::midi_enabled_from_args() && !gui_cushy::is_safe_mode())
+        .then(|| connect_midi(&app))
+        .flatten();
+
+    #[cfg(feature = "cpu-window")]
+    if render_mode == RenderMode::Cpu {
+        drop(startup);
+        let result = app.run_cpu_window();
+        app.flush_tags();
+        if let Err(err) = result {
+            tracing::error!("failed to open a window: {err}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let window = app.view().into_window().maximized(Dynamic::new(true));
+    drop(startup);
+    let result = window.run();
+    // the last edits may still be on their way to disk
+    app.flush_tags();
+
+    if result.is_err() && render_mode == RenderMode::Hardware {
+        tracing::warn!("failed to start with gpu rendering, retrying with software rendering");
+        if let Ok(status) = relaunch_in_software_mode() {
+            std::process::exit(status.code().unwrap_or_default());
+        }
+    }
+    #[cfg(feature = "cpu-window")]
+    if result.is_err() && render_mode == RenderMode::Software {
+        tracing::warn!("failed to start with software rendering, retrying without wgpu");
+        if let Ok(status) = gui_cushy::relaunch_with_cpu_window() {
+            std::process::exit(status.code().unwrap_or_default());
+        }
+    }
+
+    result
+}
+
+#[cfg(feature = "midi")]
+fn connect_midi(app: &App) -> Option<gui_cushy::MidiControl> {
+    match gui_cushy::MidiControl::connect(app.remote()) {
+        Ok(midi) => {
+            if midi.is_empty() {
+                tracing::warn!("no midi inputs found");
+            }
+            Some(midi)
+        }
+        Err(err) => {
+            tracing::error!("failed to start midi input: {err}");
+            None
+        }
+    }
 }
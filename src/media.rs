@@ -0,0 +1,212 @@
+use std::{path::Path, sync::OnceLock, time::Duration};
+
+use cushy::kludgine::image::RgbaImage;
+use ffmpeg_next as ffmpeg;
+
+static FFMPEG_INIT: OnceLock<Result<(), ffmpeg::Error>> = OnceLock::new();
+
+// registers devices and brings up networking for stream urls. nothing on the ui
+// path needs ffmpeg, so this runs on first use or in the background after startup
+pub fn init_ffmpeg() -> Result<(), ffmpeg::Error> {
+    *FFMPEG_INIT.get_or_init(|| {
+        let _span = tracing::info_span!("ffmpeg_init").entered();
+        ffmpeg::init()
+    })
+}
+
+// gets initialization out of the way before the first video is opened
+pub fn warm_up() {
+    std::thread::Builder::new()
+        .name("Media Warm-up Thread".into())
+        .spawn(|| {
+            if let Err(err) = init_ffmpeg() {
+                tracing::error!("failed to initialize ffmpeg: {err}");
+            }
+        })
+        .unwrap();
+}
+
+// every file and stream is opened through here
+pub fn open_input(path: &Path) -> Result<ffmpeg::format::context::Input, ffmpeg::Error> {
+    init_ffmpeg()?;
+    let _span = tracing::info_span!("open_input", path = %path.display()).entered();
+    ffmpeg::format::input(path)
+}
+
+// the frame on screen at each of `times`, at full size. every time seeks to the
+// keyframe before it and decodes forward, so `times` can be in any order
+pub fn extract_frames(
+    path: &Path,
+    times: &[Duration],
+    mut on_frame: impl FnMut(Duration, RgbaImage),
+) -> Result<(), ffmpeg::Error> {
+    let mut ictx = open_input(path)?;
+    let stream = ictx
+        .streams()
+        .best(ffmpeg::media::Type::Video)
+        .ok_or(ffmpeg::Error::StreamNotFound)?;
+    let stream_index = stream.index();
+    let time_base = f64::from(stream.time_base());
+    let mut decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+        .decoder()
+        .video()?;
+    let mut frame = ffmpeg::util::frame::Video::empty();
+    let mut shown = ffmpeg::util::frame::Video::empty();
+    let mut rescaler = None;
+
+    for &time in times {
+        let target = (time.as_secs_f64() * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+        ictx.seek(target, ..=target)?;
+        decoder.flush();
+
+        // half a millisecond covers timestamps rounded to the tag's precision
+        let limit = time.as_secs_f64() + 0.0005;
+        let mut have_shown = false;
+        'packets: for (stream, packet) in ictx.packets() {
+            if stream.index() != stream_index || decoder.send_packet(&packet).is_err() {
+                continue;
+            }
+            while decoder.receive_frame(&mut frame).is_ok() {
+                // a frame stays on screen until the next one starts
+                let past = frame.timestamp().unwrap_or(0) as f64 * time_base > limit;
+                if !past || !have_shown {
+                    std::mem::swap(&mut frame, &mut shown);
+                    have_shown = true;
+                }
+                if past {
+                    break 'packets;
+                }
+            }
+        }
+
+        if have_shown {
+            on_frame(time, crate::frame_to_image(&shown, None, &mut rescaler));
+        }
+    }
+
+    Ok(())
+}
+
+// every frame from the one on screen at `from` onwards, in order, until `on_frame`
+// returns false or the video ends. seeks once, unlike `extract_frames`
+pub fn decode_forward(
+    path: &Path,
+    from: Duration,
+    mut on_frame: impl FnMut(Duration, RgbaImage) -> bool,
+) -> Result<(), ffmpeg::Error> {
+    let mut ictx = open_input(path)?;
+    let stream = ictx
+        .streams()
+        .best(ffmpeg::media::Type::Video)
+        .ok_or(ffmpeg::Error::StreamNotFound)?;
+    let stream_index = stream.index();
+    let time_base = f64::from(stream.time_base());
+    let mut decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+        .decoder()
+        .video()?;
+    let mut frame = ffmpeg::util::frame::Video::empty();
+    let mut shown: Option<(f64, ffmpeg::util::frame::Video)> = None;
+
+    let target = (from.as_secs_f64() * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+    ictx.seek(target, ..=target)?;
+    let limit = from.as_secs_f64() + 0.0005;
+
+    let mut rescaler = None;
+    let mut emit = |seconds: f64, frame: &ffmpeg::util::frame::Video| {
+        on_frame(
+            Duration::from_secs_f64(seconds.max(0.)),
+            crate::frame_to_image(frame, None, &mut rescaler),
+        )
+    };
+
+    // a none at the end drains what the decoder still holds
+    for packet in ictx.packets().map(Some).chain(std::iter::once(None)) {
+        match packet {
+            Some((stream, packet)) => {
+                if stream.index() != stream_index || decoder.send_packet(&packet).is_err() {
+                    continue;
+                }
+            }
+            None => {
+                let _ = decoder.send_eof();
+            }
+        }
+        while decoder.receive_frame(&mut frame).is_ok() {
+            let seconds = frame.timestamp().unwrap_or(0) as f64 * time_base;
+            // frames up to `from` only matter for the last of them, the one on screen
+            if seconds <= limit {
+                shown = Some((seconds, frame.clone()));
+                continue;
+            }
+            if let Some((shown_seconds, shown)) = shown.take() {
+                if !emit(shown_seconds, &shown) {
+                    return Ok(());
+                }
+            }
+            if !emit(seconds, &frame) {
+                return Ok(());
+            }
+        }
+    }
+
+    if let Some((seconds, shown)) = shown {
+        emit(seconds, &shown);
+    }
+    Ok(())
+}
+
+// one of the streams of a kind when a file has more than one: angles of a multi-camera
+// recording or a screen and a camera, languages or a commentary
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaStream {
+    pub index: usize,
+    pub label: String,
+}
+
+// the file's streams of `kind`, the one played by default first. cover art doesn't
+// count as video
+pub fn streams(path: &Path, kind: ffmpeg::media::Type) -> Vec<MediaStream> {
+    let Ok(ictx) = open_input(path) else {
+        return Vec::new();
+    };
+    let best = ictx.streams().best(kind).map(|stream| stream.index());
+    let mut streams = ictx
+        .streams()
+        .filter(|stream| stream.parameters().medium() == kind)
+        .filter(|stream| {
+            !stream
+                .disposition()
+                .contains(ffmpeg::format::stream::Disposition::ATTACHED_PIC)
+        })
+        .map(|stream| {
+            let context = ffmpeg::codec::context::Context::from_parameters(stream.parameters());
+            let shape = match kind {
+                ffmpeg::media::Type::Video => context
+                    .and_then(|context| context.decoder().video())
+                    .map(|decoder| format!("{}x{}", decoder.width(), decoder.height()))
+                    .ok(),
+                ffmpeg::media::Type::Audio => context
+                    .and_then(|context| context.decoder().audio())
+                    .map(|decoder| format!("{} ch", decoder.channels()))
+                    .ok(),
+                _ => None,
+            };
+            let metadata = stream.metadata();
+            let language = metadata.get("language").filter(|language| *language != "und");
+            let title = metadata.get("title");
+            let described = [language, title, shape.as_deref()]
+                .into_iter()
+                .flatten()
+                .chain(std::iter::once(stream.parameters().id().name()))
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join(", ");
+            MediaStream {
+                index: stream.index(),
+                label: format!("stream {}: {described}", stream.index()),
+            }
+        })
+        .collect::<Vec<_>>();
+    streams.sort_by_key(|stream| Some(stream.index) != best);
+    streams
+}
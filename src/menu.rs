@@ -7,24 +7,78 @@ use cushy::{
     },
 };
 
+use crate::settings::ScalingMode;
+
 #[derive(Debug, Clone)]
-enum MainMenuOptions {
+pub enum MainMenuOptions {
     OpenVideo,
     OpenImage,
-    Third,
-    Fourth,
+    OpenProject,
+    SaveProject,
 }
 
 pub struct MainMenu(Menu<MainMenuOptions>);
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum VideoMenuOptions {
+    CaptureFrame,
+    SaveFrame,
+    AddTag,
+    CopyTimestamp,
+    Speed(f64),
+    Scaling(ScalingMode),
+    FrameTimings,
+    LoadSubtitles,
+    NextSubtitleTrack,
+    HideSubtitles,
+    NextVideoStream,
+    NextAudioTrack,
+}
+
+pub fn video_context_menu(
+    on_selected: impl FnMut(VideoMenuOptions) + Send + 'static,
+) -> Menu<VideoMenuOptions> {
+    let mut on_selected = on_selected;
+    let mut menu = Menu::new()
+        .on_selected(move |selected| on_selected(selected))
+        .with(MenuItem::new(VideoMenuOptions::CaptureFrame, "Capture Frame"))
+        .with(MenuItem::new(VideoMenuOptions::SaveFrame, "Save Frame..."))
+        .with(MenuItem::new(VideoMenuOptions::AddTag, "Add Tag Here"))
+        .with(MenuItem::new(VideoMenuOptions::CopyTimestamp, "Copy Timestamp"));
+
+    for speed in [0.5, 1., 1.5, 2.] {
+        menu = menu.with(MenuItem::new(
+            VideoMenuOptions::Speed(speed),
+            format!("Speed {speed}x"),
+        ));
+    }
+
+    for (mode, label) in [
+        (ScalingMode::Fit, "Scale: Fit"),
+        (ScalingMode::Fill, "Scale: Fill"),
+        (ScalingMode::Stretch, "Scale: Stretch"),
+        (ScalingMode::Scale(1.), "Scale: 1:1"),
+    ] {
+        menu = menu.with(MenuItem::new(VideoMenuOptions::Scaling(mode), label));
+    }
+
+    menu.with(MenuItem::new(VideoMenuOptions::FrameTimings, "Frame Timings"))
+        .with(MenuItem::new(VideoMenuOptions::LoadSubtitles, "Load Subtitles..."))
+        .with(MenuItem::new(VideoMenuOptions::NextSubtitleTrack, "Next Subtitle Track"))
+        .with(MenuItem::new(VideoMenuOptions::HideSubtitles, "Hide Subtitles"))
+        .with(MenuItem::new(VideoMenuOptions::NextVideoStream, "Next Video Stream"))
+        .with(MenuItem::new(VideoMenuOptions::NextAudioTrack, "Next Audio Track"))
+}
+
 impl MainMenu {
-    pub fn new() -> Self {
+    pub fn new(on_selected: impl FnMut(MainMenuOptions) + Send + 'static) -> Self {
+        let mut on_selected = on_selected;
         let menu = Menu::new()
-            .on_selected(|selected| {})
+            .on_selected(move |selected| on_selected(selected))
             .with(MenuItem::new(MainMenuOptions::OpenVideo, "Open Video"))
             .with(MenuItem::new(MainMenuOptions::OpenImage, "Open Image"))
-            .with(MenuItem::new(MainMenuOptions::Third, "Third"))
-            .with(MenuItem::new(MainMenuOptions::Fourth, "Fourth"));
+            .with(MenuItem::new(MainMenuOptions::OpenProject, "Open Project..."))
+            .with(MenuItem::new(MainMenuOptions::SaveProject, "Save Project..."));
 
         Self(menu)
     }
@@ -0,0 +1,92 @@
+use midir::{MidiInput, MidiInputConnection};
+
+use crate::remote::{Remote, RemoteCommand};
+
+const MIDI_FLAG: &str = "--midi";
+const CLIENT_NAME: &str = "video-tagger";
+
+const NOTE_ON: u8 = 0x90;
+const CONTROL_CHANGE: u8 = 0xb0;
+
+// most cheap surfaces send their jog wheel as a relative encoder on this cc
+const JOG_CONTROLLER: u8 = 60;
+const PLAY_CONTROLLER: u8 = 41;
+const STOP_CONTROLLER: u8 = 42;
+// pads start at the general midi kick drum, C1
+const FIRST_PAD_NOTE: u8 = 36;
+const PAD_COUNT: u8 = 16;
+
+pub fn enabled_from_args() -> bool {
+    std::env::args().any(|arg| arg == MIDI_FLAG)
+}
+
+// listens on every midi input present at startup for as long as this is alive
+pub struct MidiControl {
+    connections: Vec<MidiInputConnection<()>>,
+}
+
+impl MidiControl {
+    pub fn connect(remote: Remote) -> Result<Self, midir::InitError> {
+        let ports = MidiInput::new(CLIENT_NAME)?.ports();
+
+        let mut connections = Vec::new();
+        for port in ports {
+            // every connection consumes its own input handle
+            let input = MidiInput::new(CLIENT_NAME)?;
+            let name = input.port_name(&port).unwrap_or_default();
+            let remote = remote.clone();
+            match input.connect(
+                &port,
+                CLIENT_NAME,
+                move |_, message, _| handle_message(&remote, message),
+                (),
+            ) {
+                Ok(connection) => connections.push(connection),
+                Err(err) => tracing::error!("failed to connect to midi input {name}: {err}"),
+            }
+        }
+
+        Ok(Self { connections })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+}
+
+fn handle_message(remote: &Remote, message: &[u8]) {
+    let Some(command) = command_for(remote, message) else {
+        return;
+    };
+    if let Err(err) = remote.execute(command) {
+        tracing::error!("midi command failed: {err}");
+    }
+}
+
+fn command_for(remote: &Remote, message: &[u8]) -> Option<RemoteCommand> {
+    let [status, data1, data2] = *message else {
+        return None;
+    };
+
+    match (status & 0xf0, data1, data2) {
+        // note on with zero velocity is a note off
+        (NOTE_ON, note, velocity)
+            if velocity > 0 && (FIRST_PAD_NOTE..FIRST_PAD_NOTE + PAD_COUNT).contains(&note) =>
+        {
+            Some(RemoteCommand::TagCategory(usize::from(note - FIRST_PAD_NOTE)))
+        }
+        (CONTROL_CHANGE, JOG_CONTROLLER, value) => {
+            // two's complement style relative values, 1 is one step forward, 127 one back
+            let ticks = if value < 64 {
+                f64::from(value)
+            } else {
+                f64::from(value) - 128.
+            };
+            let step = remote.frame_duration().as_secs_f64();
+            Some(RemoteCommand::SeekRelative(ticks * step))
+        }
+        (CONTROL_CHANGE, PLAY_CONTROLLER, value) if value > 0 => Some(RemoteCommand::TogglePlay),
+        (CONTROL_CHANGE, STOP_CONTROLLER, value) if value > 0 => Some(RemoteCommand::Stop),
+        _ => None,
+    }
+}
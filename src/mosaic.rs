@@ -0,0 +1,142 @@
+use std::{path::PathBuf, time::Duration};
+
+use cushy::{
+    value::{Destination, Dynamic, MapEach, Source, Switchable},
+    widget::{MakeWidget, WidgetList},
+};
+
+use crate::{video_player::VideoPlayer, DecodeOptions};
+
+const PREVIEW_FPS: u32 = 5;
+const PREVIEW_MAX_DIMENSION: u32 = 480;
+const COLUMNS: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct Mosaic {
+    sources: Dynamic<Vec<PathBuf>>,
+    visible: Dynamic<bool>,
+    stream_url: Dynamic<String>,
+}
+
+impl Mosaic {
+    pub fn new() -> Self {
+        Self {
+            sources: Dynamic::new(Vec::new()),
+            visible: Dynamic::new(false),
+            stream_url: Dynamic::new(String::new()),
+        }
+    }
+
+    pub fn visible(&self) -> Dynamic<bool> {
+        self.visible.clone()
+    }
+
+    // `focused` is the main player's source, clicking a tile hands the source over to it
+    pub fn view(&self, focused: Dynamic<Option<PathBuf>>) -> impl MakeWidget {
+        let add_files = "add files".into_button().on_click({
+            let sources = self.sources.clone();
+            move |_| {
+                let sources = sources.clone();
+                std::thread::Builder::new()
+                    .name("File Picker Thread".into())
+                    .spawn(move || {
+                        if let Some(paths) = rfd::FileDialog::new().pick_files() {
+                            sources.lock().extend(paths);
+                        }
+                    })
+                    .unwrap();
+            }
+        });
+
+        // anything ffmpeg can open works here: rtsp://, http://, udp://...
+        let add_stream = "add stream".into_button().on_click({
+            let sources = self.sources.clone();
+            let stream_url = self.stream_url.clone();
+            move |_| {
+                let url = stream_url.take();
+                if !url.trim().is_empty() {
+                    sources.lock().push(PathBuf::from(url.trim()));
+                }
+            }
+        });
+
+        let clear = "clear".into_button().on_click({
+            let sources = self.sources.clone();
+            move |_| sources.set(Vec::new())
+        });
+
+        let close = "close".into_button().on_click({
+            let visible = self.visible.clone();
+            move |_| visible.set(false)
+        });
+
+        let toolbar = add_files
+            .and(self.stream_url.clone().into_input().expand_horizontally())
+            .and(add_stream)
+            .and(clear)
+            .and(close)
+            .into_columns();
+
+        // tiles only exist (and decode) while the mosaic is shown
+        let shown_sources = (&self.sources, &self.visible)
+            .map_each(|(sources, visible)| visible.then(|| sources.clone()));
+
+        let visible = self.visible.clone();
+        let grid = shown_sources.switcher(move |sources, _| match sources {
+            Some(sources) if !sources.is_empty() => sources
+                .chunks(COLUMNS)
+                .map(|row| {
+                    row.iter()
+                        .map(|source| tile(source.clone(), focused.clone(), visible.clone()))
+                        .collect::<WidgetList>()
+                        .into_columns()
+                        .expand()
+                })
+                .collect::<WidgetList>()
+                .into_rows()
+                .make_widget(),
+            _ => "No sources".make_widget(),
+        });
+
+        toolbar.and(grid.expand()).into_rows()
+    }
+}
+
+fn tile(
+    source: PathBuf,
+    focused: Dynamic<Option<PathBuf>>,
+    visible: Dynamic<bool>,
+) -> impl MakeWidget {
+    let video_player = VideoPlayer::new().non_interactive();
+
+    let path = source.clone();
+    video_player.start(move |context| {
+        let options = DecodeOptions {
+            min_frame_interval: Some(Duration::from_secs(1) / PREVIEW_FPS),
+            max_dimension: Some(PREVIEW_MAX_DIMENSION),
+            audio: false,
+            hardware: false,
+            filters: None,
+            stabilization: None,
+            video_stream: None,
+            audio_stream: None,
+        };
+        crate::decode_video(&path, options, context)
+    });
+
+    let label = source
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| source.to_string_lossy().into_owned());
+
+    video_player
+        .expand()
+        .and(label)
+        .into_rows()
+        .into_button()
+        .on_click(move |_| {
+            focused.set(Some(source.clone()));
+            visible.set(false);
+        })
+        .expand()
+}
@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use cushy::kludgine::image::{imageops, imageops::FilterType, RgbaImage};
+use tract_onnx::prelude::*;
+
+// frames go into models as 1x3xHxW rgb at this size, scaled to 0-1 and normalized
+const INPUT_SIZE: usize = 224;
+
+// per channel mean and deviation a model was trained with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Normalization {
+    pub mean: [f32; 3],
+    pub deviation: [f32; 3],
+}
+
+// what most image classifiers expect
+pub const IMAGENET: Normalization = Normalization {
+    mean: [0.485, 0.456, 0.406],
+    deviation: [0.229, 0.224, 0.225],
+};
+
+// what the image encoders of CLIP and its descendants expect
+pub const CLIP: Normalization = Normalization {
+    mean: [0.481_454_66, 0.457_827_5, 0.408_210_73],
+    deviation: [0.268_629_54, 0.261_302_6, 0.275_777_1],
+};
+
+// an onnx model taking one frame and handing back a flat list of numbers
+pub struct ImageModel {
+    plan: TypedSimplePlan<TypedModel>,
+    normalization: Normalization,
+}
+
+impl ImageModel {
+    pub fn load(path: &Path, normalization: Normalization) -> Result<Self, String> {
+        let input = f32::fact([1, 3, INPUT_SIZE, INPUT_SIZE]).into();
+        let plan = tract_onnx::onnx()
+            .model_for_path(path)
+            .and_then(|model| model.with_input_fact(0, input))
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .map_err(|err| format!("failed to load {}: {err}", path.display()))?;
+        Ok(Self { plan, normalization })
+    }
+
+    pub fn run(&self, image: &RgbaImage) -> Result<Vec<f32>, String> {
+        let size = INPUT_SIZE as u32;
+        let image = imageops::resize(image, size, size, FilterType::Triangle);
+        let Normalization { mean, deviation } = self.normalization;
+        let input = tract_ndarray::Array4::from_shape_fn(
+            (1, 3, INPUT_SIZE, INPUT_SIZE),
+            |(_, channel, y, x)| {
+                let value = f32::from(image.get_pixel(x as u32, y as u32)[channel]) / 255.;
+                (value - mean[channel]) / deviation[channel]
+            },
+        )
+        .into_tensor();
+
+        let outputs = self
+            .plan
+            .run(tvec!(input.into()))
+            .map_err(|err| format!("the model failed on a frame: {err}"))?;
+        Ok(outputs[0]
+            .to_array_view::<f32>()
+            .map_err(|err| format!("the model's output isn't a list of numbers: {err}"))?
+            .iter()
+            .copied()
+            .collect())
+    }
+}
+
+// CLIP's text encoders take this many tokens, padded with zeros
+const CONTEXT_LENGTH: usize = 77;
+
+// an onnx text encoder with the tokenizer.json it was exported with
+pub struct TextModel {
+    plan: TypedSimplePlan<TypedModel>,
+    tokenizer: tokenizers::Tokenizer,
+    // some exports want the attention mask next to the token ids
+    takes_mask: bool,
+}
+
+impl TextModel {
+    pub fn load(path: &Path, tokenizer: &Path) -> Result<Self, String> {
+        let tokenizer = tokenizers::Tokenizer::from_file(tokenizer)
+            .map_err(|err| format!("failed to load {}: {err}", tokenizer.display()))?;
+
+        let failed = |err: TractError| format!("failed to load {}: {err}", path.display());
+        let mut model = tract_onnx::onnx().model_for_path(path).map_err(failed)?;
+        let takes_mask = model.input_outlets().map_err(failed)?.len() > 1;
+        for input in 0..if takes_mask { 2 } else { 1 } {
+            model = model
+                .with_input_fact(input, i64::fact([1, CONTEXT_LENGTH]).into())
+                .map_err(failed)?;
+        }
+        let plan = model
+            .into_optimized()
+            .and_then(|model| model.into_runnable())
+            .map_err(failed)?;
+        Ok(Self {
+            plan,
+            tokenizer,
+            takes_mask,
+        })
+    }
+
+    pub fn run(&self, text: &str) -> Result<Vec<f32>, String> {
+        let encoding = self
+            .tokenizer
+            .encode(text, true)
+            .map_err(|err| format!("failed to tokenize the prompt: {err}"))?;
+        let mut ids = encoding.get_ids().iter().map(|id| i64::from(*id)).collect::<Vec<_>>();
+        if ids.len() > CONTEXT_LENGTH {
+            // keep the end of text token, the encoder pools at it
+            let end = ids[ids.len() - 1];
+            ids.truncate(CONTEXT_LENGTH);
+            ids[CONTEXT_LENGTH - 1] = end;
+        }
+        let tokens = ids.len();
+        ids.resize(CONTEXT_LENGTH, 0);
+
+        let ids = tract_ndarray::Array2::from_shape_vec((1, CONTEXT_LENGTH), ids)
+            .expect("padded to the context length")
+            .into_tensor();
+        let mut inputs = tvec!(ids.into());
+        if self.takes_mask {
+            let mask = tract_ndarray::Array2::from_shape_fn((1, CONTEXT_LENGTH), |(_, idx)| {
+                i64::from(idx < tokens)
+            })
+            .into_tensor();
+            inputs.push(mask.into());
+        }
+
+        let outputs = self
+            .plan
+            .run(inputs)
+            .map_err(|err| format!("the model failed on the prompt: {err}"))?;
+        Ok(outputs[0]
+            .to_array_view::<f32>()
+            .map_err(|err| format!("the model's output isn't a list of numbers: {err}"))?
+            .iter()
+            .copied()
+            .collect())
+    }
+}
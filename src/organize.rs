@@ -0,0 +1,410 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use cushy::{
+    value::{Destination, Dynamic, MapEach, Source, Switchable},
+    widget::{MakeWidget, WidgetList},
+};
+
+use crate::database::{TagDatabase, TagQuery};
+use crate::formats::QueueEntry;
+use crate::media::open_input;
+use crate::renditions::Renditions;
+use crate::tags::TagStore;
+
+// the folder of files that have nothing to sort them by
+const UNSORTED: &str = "unsorted";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrganizeBy {
+    // the category with the most tags on the file
+    Category,
+    // a tag of the file's container, "make", "model", "creation_time"
+    Field,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    Copy,
+    Move,
+}
+
+impl Transfer {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Copy => "copy",
+            Self::Move => "move",
+        }
+    }
+}
+
+// where one file would go
+#[derive(Debug, Clone, PartialEq)]
+struct Placement {
+    video: PathBuf,
+    destination: PathBuf,
+}
+
+// what a dry run came up with, applied as is
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Plan {
+    placements: Vec<Placement>,
+    // and why they stay where they are
+    skipped: Vec<(PathBuf, String)>,
+}
+
+// ties go to the category first in the alphabet, so the same tags always sort the same
+fn dominant_category(
+    database: &TagDatabase,
+    tags: &Dynamic<TagStore>,
+    video: &Path,
+) -> Result<Option<String>, String> {
+    let query = TagQuery {
+        video: Some(video.to_path_buf()),
+        ..TagQuery::default()
+    };
+    let found = database.find(tags, &query).map_err(|err| err.to_string())?;
+    let mut counts = HashMap::<&str, usize>::new();
+    for tag in &found {
+        *counts.entry(tag.label.as_str()).or_default() += 1;
+    }
+    Ok(counts
+        .into_iter()
+        .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+        .map(|(label, _)| label.to_string()))
+}
+
+// from the container, or else the video stream, whatever case the key is in
+fn metadata_field(video: &Path, key: &str) -> Option<String> {
+    let ictx = open_input(video).ok()?;
+    let found = |metadata: ffmpeg_next::DictionaryRef<'_>| {
+        metadata
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.to_string())
+    };
+    found(ictx.metadata()).or_else(|| {
+        let stream = ictx.streams().best(ffmpeg_next::media::Type::Video)?;
+        found(stream.metadata())
+    })
+}
+
+// a single path component whatever the value holds
+fn folder_name(value: &str) -> String {
+    let name = value
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    match name.trim_matches('.') {
+        "" => String::from(UNSORTED),
+        name => name.to_string(),
+    }
+}
+
+fn plan(
+    videos: &[PathBuf],
+    database: &TagDatabase,
+    tags: &Dynamic<TagStore>,
+    by: OrganizeBy,
+    field: &str,
+    root: &Path,
+) -> Plan {
+    let mut plan = Plan::default();
+    for video in videos {
+        let value = match by {
+            OrganizeBy::Category => match dominant_category(database, tags, video) {
+                Ok(category) => category,
+                Err(err) => {
+                    plan.skipped.push((video.clone(), err));
+                    continue;
+                }
+            },
+            OrganizeBy::Field => metadata_field(video, field),
+        };
+        let Some(file_name) = video.file_name() else {
+            continue;
+        };
+        let destination = root
+            .join(folder_name(value.as_deref().unwrap_or(UNSORTED)))
+            .join(file_name);
+
+        if destination == *video {
+            plan.skipped.push((video.clone(), String::from("already there")));
+        } else if destination.exists()
+            || plan.placements.iter().any(|placement| placement.destination == destination)
+        {
+            let why = format!("{} is taken", destination.display());
+            plan.skipped.push((video.clone(), why));
+        } else {
+            plan.placements.push(Placement {
+                video: video.clone(),
+                destination,
+            });
+        }
+    }
+    plan
+}
+
+// renames within a drive, copies and deletes across them
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to)?;
+    std::fs::remove_file(from)
+}
+
+// what else points at a file that moved, pointed at its new place
+#[derive(Debug, Clone)]
+struct Followers {
+    tags: Dynamic<TagStore>,
+    renditions: Dynamic<Renditions>,
+    queue: Dynamic<Vec<QueueEntry>>,
+    video_source: Dynamic<Option<PathBuf>>,
+}
+
+impl Followers {
+    fn follow(&self, from: &Path, to: &Path) {
+        self.renditions.lock().retarget(from, to);
+        for entry in self.queue.lock().iter_mut().filter(|entry| entry.path == from) {
+            entry.path = to.to_path_buf();
+        }
+        if self.video_source.get().as_deref() == Some(from) {
+            self.video_source.set(Some(to.to_path_buf()));
+        }
+    }
+}
+
+// sorts the library into folders under one picked here, by the category most of a file's
+// tags have or by a metadata field of it. "preview" is a dry run listing what would go
+// where; "apply" does exactly that
+pub fn organize_panel(
+    library: impl Fn() -> Vec<PathBuf> + Send + Sync + 'static,
+    tags: Dynamic<TagStore>,
+    database: TagDatabase,
+    renditions: Dynamic<Renditions>,
+    queue: Dynamic<Vec<QueueEntry>>,
+    video_source: Dynamic<Option<PathBuf>>,
+    announcement: Dynamic<String>,
+) -> impl MakeWidget {
+    let root: Dynamic<Option<PathBuf>> = Dynamic::new(None);
+    let by = Dynamic::new(OrganizeBy::Category);
+    let field = Dynamic::new(String::from("model"));
+    let transfer = Dynamic::new(Transfer::Copy);
+    let plan: Dynamic<Option<Plan>> = Dynamic::new(None);
+    let busy = Dynamic::new(false);
+    // a different setup makes the last dry run stale
+    (&root, &by, &field, &transfer)
+        .for_each({
+            let plan = plan.clone();
+            move |_| plan.set(None)
+        })
+        .persist();
+
+    let pick_root = root
+        .map_each(|root| match root {
+            Some(root) => format!("into {}", root.display()),
+            None => String::from("choose a folder"),
+        })
+        .into_button()
+        .on_click({
+            let root = root.clone();
+            move |_| {
+                let root = root.clone();
+                std::thread::Builder::new()
+                    .name("File Picker Thread".into())
+                    .spawn(move || {
+                        if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                            root.set(Some(folder));
+                        }
+                    })
+                    .unwrap();
+            }
+        });
+    let by_button = by
+        .map_each(|by| match by {
+            OrganizeBy::Category => "by category",
+            OrganizeBy::Field => "by metadata field",
+        })
+        .into_button()
+        .on_click({
+            let by = by.clone();
+            move |_| {
+                let mut by = by.lock();
+                *by = match *by {
+                    OrganizeBy::Category => OrganizeBy::Field,
+                    OrganizeBy::Field => OrganizeBy::Category,
+                };
+            }
+        });
+    let field_input = field
+        .clone()
+        .into_input()
+        .placeholder("field, e.g. make or model")
+        .expand_horizontally()
+        .collapse_horizontally(by.map_each(|by| *by != OrganizeBy::Field));
+    let transfer_button = transfer
+        .map_each(|transfer| transfer.label())
+        .into_button()
+        .on_click({
+            let transfer = transfer.clone();
+            move |_| {
+                let mut transfer = transfer.lock();
+                *transfer = match *transfer {
+                    Transfer::Copy => Transfer::Move,
+                    Transfer::Move => Transfer::Copy,
+                };
+            }
+        });
+
+    let preview = "preview".into_button().on_click({
+        let root = root.clone();
+        let tags = tags.clone();
+        let database = database.clone();
+        let plan = plan.clone();
+        let busy = busy.clone();
+        let announcement = announcement.clone();
+        move |_| {
+            let Some(root) = root.get() else {
+                announcement.set(String::from("choose a folder to organize into"));
+                return;
+            };
+            let videos = library();
+            let (database, tags) = (database.clone(), tags.clone());
+            let (by, field) = (by.get(), field.get().trim().to_string());
+            let plan = plan.clone();
+            let busy = busy.clone();
+            busy.set(true);
+            // metadata fields need every file opened
+            std::thread::Builder::new()
+                .name("Organize Thread".into())
+                .spawn(move || {
+                    let planned = self::plan(&videos, &database, &tags, by, &field, &root);
+                    plan.set(Some(planned));
+                    busy.set(false);
+                })
+                .unwrap();
+        }
+    });
+
+    let apply = "apply".into_button().on_click({
+        let plan = plan.clone();
+        let busy = busy.clone();
+        let transfer = transfer.clone();
+        let followers = Followers {
+            tags: tags.clone(),
+            renditions,
+            queue,
+            video_source,
+        };
+        move |_| {
+            let Some(planned) = plan.get() else {
+                return;
+            };
+            let transfer = transfer.get();
+            let database = database.clone();
+            let followers = followers.clone();
+            let plan = plan.clone();
+            let busy = busy.clone();
+            let announcement = announcement.clone();
+            busy.set(true);
+            std::thread::Builder::new()
+                .name("Organize Thread".into())
+                .spawn(move || {
+                    let mut moved = Vec::new();
+                    let mut failed = Vec::new();
+                    for Placement { video, destination } in &planned.placements {
+                        let result = destination
+                            .parent()
+                            .map_or(Ok(()), std::fs::create_dir_all)
+                            .and_then(|()| match transfer {
+                                Transfer::Copy => std::fs::copy(video, destination).map(drop),
+                                Transfer::Move => move_file(video, destination),
+                            });
+                        match result {
+                            Ok(()) => moved.push((video, destination)),
+                            Err(err) => failed.push(format!("{}: {err}", video.display())),
+                        }
+                    }
+                    let done = moved.len();
+
+                    // copies leave the tags where they are. for moves, one undo puts the
+                    // tags back on the old paths, the files stay moved
+                    if transfer == Transfer::Move {
+                        for (video, _) in &moved {
+                            database.load_video(&followers.tags, video);
+                        }
+                        followers.tags.lock().group(|tags| {
+                            for (video, destination) in &moved {
+                                tags.move_video(video, destination.to_path_buf());
+                            }
+                        });
+                        for (video, destination) in &moved {
+                            followers.follow(video, destination);
+                        }
+                    }
+                    plan.set(None);
+                    busy.set(false);
+                    announcement.set(match failed.first() {
+                        None => format!("organized {done} files ({})", transfer.label()),
+                        Some(first) => format!(
+                            "organized {done} files, {} failed, first {first}",
+                            failed.len()
+                        ),
+                    });
+                })
+                .unwrap();
+        }
+    })
+    .with_enabled((&plan, &busy).map_each(|(plan, busy)| {
+        !*busy && plan.as_ref().is_some_and(|plan| !plan.placements.is_empty())
+    }));
+
+    let listing = plan.switcher(|plan, _| {
+        let Some(plan) = plan else {
+            return "preview to see what goes where".make_widget();
+        };
+        let name = |video: &Path| {
+            video.file_name().unwrap_or_default().to_string_lossy().into_owned()
+        };
+        plan.placements
+            .iter()
+            .map(|placement| {
+                let folder = placement
+                    .destination
+                    .parent()
+                    .and_then(Path::file_name)
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                format!("{} -> {folder}/", name(&placement.video))
+            })
+            .chain(
+                plan.skipped
+                    .iter()
+                    .map(|(video, why)| format!("{} stays: {why}", name(video))),
+            )
+            .map(|line| line.make_widget())
+            .collect::<WidgetList>()
+            .into_rows()
+            .make_widget()
+    });
+
+    "Organize files"
+        .and(pick_root)
+        .and(by_button.and(field_input).into_columns())
+        .and(
+            transfer_button
+                .and(preview.with_enabled(busy.map_each(|busy| !busy)))
+                .and(apply)
+                .into_columns(),
+        )
+        .and(listing.vertical_scroll())
+        .into_rows()
+}
@@ -0,0 +1,111 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    path::PathBuf,
+};
+
+use rosc::{OscPacket, OscType};
+
+use crate::remote::{Remote, RemoteCommand};
+
+const OSC_PORT_FLAG: &str = "--osc-port";
+const OSC_ADDRESS_FLAG: &str = "--osc-address";
+const MAX_PACKET_SIZE: usize = rosc::decoder::MTU;
+
+// `--osc-port <port>` listens for osc messages on that udp port. anyone who can reach it
+// controls the player, so only this machine can unless `--osc-address <ip>` says which
+// interface to take them on, 0.0.0.0 for all of them
+pub fn address_from_args() -> Option<SocketAddr> {
+    let mut port = None;
+    let mut ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == OSC_PORT_FLAG {
+            port = Some(args.next()?.parse().ok()?);
+        } else if arg == OSC_ADDRESS_FLAG {
+            ip = args.next()?.parse().ok()?;
+        }
+    }
+    Some(SocketAddr::new(ip, port?))
+}
+
+// controllers like bitfocus companion send fire-and-forget udp, so errors only get logged
+pub fn serve_udp(remote: Remote, address: SocketAddr) -> std::io::Result<()> {
+    let socket = UdpSocket::bind(address)?;
+
+    std::thread::Builder::new()
+        .name("OSC Listener Thread".into())
+        .spawn(move || {
+            let mut buf = [0u8; MAX_PACKET_SIZE];
+            while let Ok((len, _)) = socket.recv_from(&mut buf) {
+                match rosc::decoder::decode_udp(&buf[..len]) {
+                    Ok((_, packet)) => handle_packet(&remote, packet),
+                    Err(err) => tracing::warn!("invalid osc packet: {err:?}"),
+                }
+            }
+        })
+        .unwrap();
+
+    Ok(())
+}
+
+fn handle_packet(remote: &Remote, packet: OscPacket) {
+    match packet {
+        OscPacket::Message(message) => {
+            let Some(command) = command_for(&message.addr, &message.args) else {
+                tracing::warn!("unhandled osc address {}", message.addr);
+                return;
+            };
+            if let Err(err) = remote.execute(command) {
+                tracing::error!("osc {} failed: {err}", message.addr);
+            }
+        }
+        OscPacket::Bundle(bundle) => {
+            for packet in bundle.content {
+                handle_packet(remote, packet);
+            }
+        }
+    }
+}
+
+// /transport/{play,pause,toggle,stop}, /transport/seek <seconds>,
+// /transport/nudge <seconds>, /transport/speed <factor>, /transport/step/{forward,backward},
+// /video/open <path>, and /tag <category> or /tag/<category>
+fn command_for(addr: &str, args: &[OscType]) -> Option<RemoteCommand> {
+    let command = match addr {
+        "/transport/play" => RemoteCommand::Play,
+        "/transport/pause" => RemoteCommand::Pause,
+        "/transport/toggle" => RemoteCommand::TogglePlay,
+        "/transport/stop" => RemoteCommand::Stop,
+        "/transport/seek" => RemoteCommand::Seek(number(args)?),
+        "/transport/nudge" => RemoteCommand::SeekRelative(number(args)?),
+        "/transport/speed" => RemoteCommand::SetSpeed(number(args)?),
+        "/transport/step/forward" => RemoteCommand::StepForward,
+        "/transport/step/backward" => RemoteCommand::StepBackward,
+        "/video/open" => RemoteCommand::Open(PathBuf::from(string(args)?)),
+        "/tag" => RemoteCommand::Tag(string(args)?),
+        _ => {
+            let category = addr.strip_prefix("/tag/").filter(|category| !category.is_empty())?;
+            RemoteCommand::Tag(category.to_string())
+        }
+    };
+    Some(command)
+}
+
+// nan and infinity don't make a time or a speed
+fn number(args: &[OscType]) -> Option<f64> {
+    let number = match args.first()? {
+        OscType::Float(value) => f64::from(*value),
+        OscType::Double(value) => *value,
+        OscType::Int(value) => f64::from(*value),
+        OscType::Long(value) => *value as f64,
+        _ => return None,
+    };
+    number.is_finite().then_some(number)
+}
+
+fn string(args: &[OscType]) -> Option<String> {
+    match args.first()? {
+        OscType::String(value) => Some(value.clone()),
+        _ => None,
+    }
+}
@@ -0,0 +1,147 @@
+use cushy::styles::Color;
+use serde::{Deserialize, Serialize};
+
+// category colors, picked by position in the category list and repeating after
+// running out. all but `Default` stay distinguishable with the common forms of
+// color blindness, and every category also gets a marker shape so hue is never
+// the only cue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Palette {
+    #[default]
+    Default,
+    // Okabe & Ito, "Color Universal Design"
+    OkabeIto,
+    // Paul Tol's "bright" qualitative scheme
+    TolBright,
+    // for monochrome displays and printouts, shapes carry the distinction
+    Grayscale,
+}
+
+const DEFAULT_COLORS: [Color; 8] = [
+    Color::new(0xe6, 0x19, 0x4b, 0xff),
+    Color::new(0x3c, 0xb4, 0x4b, 0xff),
+    Color::new(0x43, 0x63, 0xd8, 0xff),
+    Color::new(0xf5, 0x82, 0x31, 0xff),
+    Color::new(0x91, 0x1e, 0xb4, 0xff),
+    Color::new(0x42, 0xd4, 0xf4, 0xff),
+    Color::new(0xf0, 0x32, 0xe6, 0xff),
+    Color::new(0xbf, 0xef, 0x45, 0xff),
+];
+
+const OKABE_ITO_COLORS: [Color; 8] = [
+    Color::new(0xe6, 0x9f, 0x00, 0xff),
+    Color::new(0x56, 0xb4, 0xe9, 0xff),
+    Color::new(0x00, 0x9e, 0x73, 0xff),
+    Color::new(0xf0, 0xe4, 0x42, 0xff),
+    Color::new(0x00, 0x72, 0xb2, 0xff),
+    Color::new(0xd5, 0x5e, 0x00, 0xff),
+    Color::new(0xcc, 0x79, 0xa7, 0xff),
+    Color::new(0x99, 0x99, 0x99, 0xff),
+];
+
+const TOL_BRIGHT_COLORS: [Color; 7] = [
+    Color::new(0x44, 0x77, 0xaa, 0xff),
+    Color::new(0xee, 0x66, 0x77, 0xff),
+    Color::new(0x22, 0x88, 0x33, 0xff),
+    Color::new(0xcc, 0xbb, 0x44, 0xff),
+    Color::new(0x66, 0xcc, 0xee, 0xff),
+    Color::new(0xaa, 0x33, 0x77, 0xff),
+    Color::new(0xbb, 0xbb, 0xbb, 0xff),
+];
+
+// five steps, as four against six shapes would repeat a pair every 12 categories
+const GRAYSCALE_COLORS: [Color; 5] = [
+    Color::new(0xf0, 0xf0, 0xf0, 0xff),
+    Color::new(0xc0, 0xc0, 0xc0, 0xff),
+    Color::new(0x90, 0x90, 0x90, 0xff),
+    Color::new(0x60, 0x60, 0x60, 0xff),
+    Color::new(0x30, 0x30, 0x30, 0xff),
+];
+
+impl Palette {
+    pub const ALL: [Self; 4] = [Self::Default, Self::OkabeIto, Self::TolBright, Self::Grayscale];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::OkabeIto => "Okabe-Ito",
+            Self::TolBright => "Tol bright",
+            Self::Grayscale => "grayscale",
+        }
+    }
+
+    fn colors(self) -> &'static [Color] {
+        match self {
+            Self::Default => &DEFAULT_COLORS,
+            Self::OkabeIto => &OKABE_ITO_COLORS,
+            Self::TolBright => &TOL_BRIGHT_COLORS,
+            Self::Grayscale => &GRAYSCALE_COLORS,
+        }
+    }
+
+    pub fn color(self, category_index: usize) -> Color {
+        let colors = self.colors();
+        colors[category_index % colors.len()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MarkerShape {
+    Circle,
+    Square,
+    Triangle,
+    Diamond,
+    Cross,
+    Star,
+}
+
+impl MarkerShape {
+    const ALL: [Self; 6] = [
+        Self::Circle,
+        Self::Square,
+        Self::Triangle,
+        Self::Diamond,
+        Self::Cross,
+        Self::Star,
+    ];
+
+    // shapes cycle at a different length than the palettes, so categories sharing a
+    // color after wrapping around get different shapes. a (color, shape) pair only
+    // comes back after 24 categories with the 8 color palettes, 30 with grayscale
+    // and 42 with Tol bright
+    pub fn for_category(category_index: usize) -> Self {
+        Self::ALL[category_index % Self::ALL.len()]
+    }
+
+    // for labels and buttons, the timeline draws the shape itself
+    pub fn glyph(self) -> char {
+        match self {
+            Self::Circle => '●',
+            Self::Square => '■',
+            Self::Triangle => '▲',
+            Self::Diamond => '◆',
+            Self::Cross => '✚',
+            Self::Star => '★',
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn pairs_dont_repeat_within_24_categories() {
+        for palette in Palette::ALL {
+            let pairs: HashSet<_> = (0..24)
+                .map(|index| {
+                    let color = palette.color(index);
+                    (color.red(), color.green(), color.blue(), MarkerShape::for_category(index))
+                })
+                .collect();
+            assert_eq!(pairs.len(), 24, "{}", palette.name());
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use std::path::PathBuf;
+
+use cushy::{
+    value::{Destination, Dynamic, MapEach, Source, Switchable},
+    widget::{MakeWidget, WidgetList},
+};
+use ffmpeg_next as ffmpeg;
+use serde::{Deserialize, Serialize};
+
+use crate::renditions::Renditions;
+use crate::stabilize::transform_filter;
+use crate::tags::TagStore;
+
+// a named chain of filters playback runs a video's frames through. presets live in
+// the tag database next to the tags, so do the videos they're used for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelinePreset {
+    pub name: String,
+    #[serde(default)]
+    pub deinterlace: bool,
+    #[serde(default)]
+    pub denoise: bool,
+    // -1 to 1, 0 leaves it as it is
+    #[serde(default)]
+    pub brightness: f32,
+    // a filtergraph typed in by hand, after everything above
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub custom: String,
+}
+
+impl PipelinePreset {
+    // the ffmpeg filtergraph, none when the preset doesn't change anything
+    pub fn filter_spec(&self) -> Option<String> {
+        let mut filters = Vec::new();
+        if self.deinterlace {
+            // only frames flagged interlaced, one frame out per frame in
+            filters.push(String::from("yadif=mode=send_frame:deint=interlaced"));
+        }
+        if self.denoise {
+            filters.push(String::from("hqdn3d"));
+        }
+        if self.brightness != 0. {
+            filters.push(format!("eq=brightness={:.3}", self.brightness.clamp(-1., 1.)));
+        }
+        if !self.custom.trim().is_empty() {
+            filters.push(self.custom.trim().to_string());
+        }
+        (!filters.is_empty()).then(|| filters.join(","))
+    }
+}
+
+// a quick denoise on top of whatever preset the video has, for noisy low light
+// footage. it's for watching, tags and exports don't see it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Denoise {
+    #[default]
+    Off,
+    // hqdn3d, cheap enough for most footage
+    Fast,
+    // nlmeans, much cleaner and much slower, big frames can't keep up
+    Strong,
+}
+
+impl Denoise {
+    pub fn next(self) -> Self {
+        match self {
+            Denoise::Off => Denoise::Fast,
+            Denoise::Fast => Denoise::Strong,
+            Denoise::Strong => Denoise::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Denoise::Off => "denoise",
+            Denoise::Fast => "[denoise: hqdn3d]",
+            Denoise::Strong => "[denoise: nlmeans]",
+        }
+    }
+
+    // what's worth knowing about the cost, for the status line
+    pub fn warning(self) -> Option<&'static str> {
+        match self {
+            Denoise::Off => None,
+            Denoise::Fast => Some("denoising with hqdn3d, adds a little latency per frame"),
+            Denoise::Strong => Some("denoising with nlmeans, expect lag and dropped frames on HD and up"),
+        }
+    }
+
+    // `spec` with this denoise appended
+    pub fn apply(self, spec: Option<String>) -> Option<String> {
+        let denoise = match self {
+            Denoise::Off => return spec,
+            Denoise::Fast => "hqdn3d=4:3:6:4.5",
+            Denoise::Strong => "nlmeans=s=3:p=7:r=9",
+        };
+        Some(chain(spec, denoise))
+    }
+}
+
+// `filter` after whatever `spec` already does
+pub fn chain(spec: Option<String>, filter: &str) -> String {
+    match spec {
+        Some(spec) => format!("{spec},{filter}"),
+        None => String::from(filter),
+    }
+}
+
+// whether ffmpeg's parser takes `spec` as a chain from one video input to one output,
+// its complaint when it doesn't. the input is made up, filters that only fail on some
+// formats or sizes still fail at playback
+pub fn check_filtergraph(spec: &str) -> Result<(), String> {
+    let checked = (|| {
+        let mut graph = ffmpeg::filter::Graph::new();
+        let buffer = ffmpeg::filter::find("buffer").ok_or(ffmpeg::Error::FilterNotFound)?;
+        let sink = ffmpeg::filter::find("buffersink").ok_or(ffmpeg::Error::FilterNotFound)?;
+        let args = format!(
+            "video_size=640x360:pix_fmt={}:time_base=1/25:pixel_aspect=1/1",
+            ffmpeg::ffi::AVPixelFormat::AV_PIX_FMT_YUV420P as i32,
+        );
+        graph.add(&buffer, "in", &args)?;
+        graph.add(&sink, "out", "")?;
+        graph.output("in", 0)?.input("out", 0)?.parse(spec)?;
+        graph.validate()
+    })();
+    checked.map_err(|err| format!("ffmpeg doesn't take the filtergraph: {err}"))
+}
+
+// runs decoded frames through a filtergraph. it's built on the first frame, once the
+// format the decoder hands out is known, and again whenever that changes
+pub struct FrameFilter {
+    // empty when there's only stabilization
+    spec: String,
+    time_base: ffmpeg::Rational,
+    // vid.stab's detected motion and the video's frame rate, see `stabilize`
+    stabilization: Option<(PathBuf, f64)>,
+    graph: Option<(ffmpeg::filter::Graph, (u32, u32, ffmpeg::format::Pixel))>,
+}
+
+impl FrameFilter {
+    pub fn new(spec: String, time_base: ffmpeg::Rational) -> Self {
+        Self {
+            spec,
+            time_base,
+            stabilization: None,
+            graph: None,
+        }
+    }
+
+    // smooths out the camera motion in `transforms` after the other filters
+    pub fn stabilized(mut self, transforms: PathBuf, frame_rate: f64) -> Self {
+        self.stabilization = Some((transforms, frame_rate));
+        self
+    }
+
+    // forgets the frames temporal filters hold on to, after a seek
+    pub fn reset(&mut self) {
+        self.graph = None;
+    }
+
+    // hands every frame the graph lets out for `frame` to `on_frame`, none while
+    // a filter is still filling up
+    pub fn process(
+        &mut self,
+        frame: &ffmpeg::util::frame::Video,
+        mut on_frame: impl FnMut(&ffmpeg::util::frame::Video),
+    ) -> Result<(), ffmpeg::Error> {
+        let input = (frame.width(), frame.height(), frame.format());
+        if self.graph.as_ref().is_none_or(|(_, built)| *built != input) {
+            self.graph = Some((self.build(frame)?, input));
+        }
+        let (graph, _) = self.graph.as_mut().expect("built above");
+
+        graph.get("in").expect("added in build").source().add(frame)?;
+        let mut filtered = ffmpeg::util::frame::Video::empty();
+        while graph
+            .get("out")
+            .expect("added in build")
+            .sink()
+            .frame(&mut filtered)
+            .is_ok()
+        {
+            on_frame(&filtered);
+        }
+        Ok(())
+    }
+
+    fn build(&self, frame: &ffmpeg::util::frame::Video) -> Result<ffmpeg::filter::Graph, ffmpeg::Error> {
+        let aspect = frame.aspect_ratio();
+        let (aspect_num, aspect_den) = if aspect.numerator() > 0 {
+            (aspect.numerator(), aspect.denominator())
+        } else {
+            (1, 1)
+        };
+        let args = format!(
+            "video_size={}x{}:pix_fmt={}:time_base={}/{}:pixel_aspect={aspect_num}/{aspect_den}",
+            frame.width(),
+            frame.height(),
+            ffmpeg::ffi::AVPixelFormat::from(frame.format()) as i32,
+            self.time_base.numerator(),
+            self.time_base.denominator(),
+        );
+
+        let mut spec = Some(self.spec.clone()).filter(|spec| !spec.is_empty());
+        if let Some((transforms, frame_rate)) = &self.stabilization {
+            // the graph starts over after seeks, the transforms have to as well
+            let first = frame.pts().map_or(0., |pts| pts as f64 * f64::from(self.time_base));
+            let from = (first * frame_rate).round().max(0.) as usize;
+            let transform = transform_filter(transforms, from).map_err(|err| {
+                tracing::error!("failed to read {}: {err}", transforms.display());
+                ffmpeg::Error::InvalidData
+            })?;
+            spec = Some(chain(spec, &transform));
+        }
+        let spec = spec.unwrap_or_else(|| String::from("null"));
+
+        let mut graph = ffmpeg::filter::Graph::new();
+        let buffer = ffmpeg::filter::find("buffer").ok_or(ffmpeg::Error::FilterNotFound)?;
+        let sink = ffmpeg::filter::find("buffersink").ok_or(ffmpeg::Error::FilterNotFound)?;
+        graph.add(&buffer, "in", &args)?;
+        graph.add(&sink, "out", "")?;
+        graph.output("in", 0)?.input("out", 0)?.parse(&spec)?;
+        graph.validate()?;
+        Ok(graph)
+    }
+}
+
+// defines presets and picks the one the open video plays through
+pub fn pipelines_panel(
+    tags: Dynamic<TagStore>,
+    video_source: Dynamic<Option<PathBuf>>,
+    renditions: Dynamic<Renditions>,
+) -> impl MakeWidget {
+    let name = Dynamic::new(String::new());
+    let deinterlace = Dynamic::new(false);
+    let denoise = Dynamic::new(false);
+    let brightness = Dynamic::new(String::from("0"));
+    // advanced mode shows the filtergraph typed in by hand
+    let advanced = Dynamic::new(false);
+    let custom = Dynamic::new(String::new());
+    let status = Dynamic::new(String::new());
+
+    let toggle = |value: &Dynamic<bool>, label: &'static str| {
+        value
+            .map_each(move |on| {
+                if *on {
+                    format!("[{label}]")
+                } else {
+                    String::from(label)
+                }
+            })
+            .into_button()
+            .on_click({
+                let value = value.clone();
+                move |_| value.toggle()
+            })
+    };
+
+    let save = "save preset".into_button().on_click({
+        let tags = tags.clone();
+        let name = name.clone();
+        let deinterlace = deinterlace.clone();
+        let denoise = denoise.clone();
+        let brightness = brightness.clone();
+        let custom = custom.clone();
+        let status = status.clone();
+        move |_| {
+            let preset_name = name.get().trim().to_string();
+            if preset_name.is_empty() {
+                status.set(String::from("name the preset first"));
+                return;
+            }
+            let brightness = match brightness.get().trim().parse::<f32>() {
+                Ok(brightness) if (-1. ..=1.).contains(&brightness) => brightness,
+                _ => {
+                    status.set(String::from("brightness goes from -1 to 1"));
+                    return;
+                }
+            };
+            let preset = PipelinePreset {
+                name: preset_name.clone(),
+                deinterlace: deinterlace.get(),
+                denoise: denoise.get(),
+                brightness,
+                custom: custom.get().trim().to_string(),
+            };
+            // a graph ffmpeg can't parse would only show up as unfiltered playback
+            if let Some(spec) = preset.filter_spec() {
+                if let Err(err) = check_filtergraph(&spec) {
+                    status.set(err);
+                    return;
+                }
+            }
+            tags.lock().set_pipeline(preset);
+            status.set(format!("saved {preset_name}"));
+        }
+    });
+
+    let delete = "delete preset".into_button().on_click({
+        let tags = tags.clone();
+        let name = name.clone();
+        let status = status.clone();
+        move |_| {
+            let preset_name = name.get().trim().to_string();
+            if tags.lock().remove_pipeline(&preset_name) {
+                status.set(format!("deleted {preset_name}"));
+            }
+        }
+    });
+
+    // the original of whatever is open, proxies play through the same preset
+    let original = {
+        let video_source = video_source.clone();
+        let renditions = renditions.clone();
+        move || {
+            let video = video_source.get()?;
+            Some(renditions.map_ref(|renditions| renditions.original_of(&video)))
+        }
+    };
+    let use_for_video = "use for this video".into_button().on_click({
+        let tags = tags.clone();
+        let name = name.clone();
+        let status = status.clone();
+        let original = original.clone();
+        move |_| {
+            let Some(video) = original() else {
+                status.set(String::from("no video open"));
+                return;
+            };
+            let preset_name = name.get().trim().to_string();
+            if !tags.lock().assign_pipeline(video, Some(preset_name.clone())) {
+                status.set(format!("save {preset_name} first"));
+            }
+        }
+    });
+    let unfiltered = "play unfiltered".into_button().on_click({
+        let tags = tags.clone();
+        move |_| {
+            if let Some(video) = original() {
+                tags.lock().assign_pipeline(video, None);
+            }
+        }
+    });
+
+    let in_use = (&tags, &video_source, &renditions).map_each(|(tags, video, renditions)| {
+        let preset = video
+            .as_ref()
+            .and_then(|video| tags.pipeline_for(&renditions.original_of(video)));
+        match preset {
+            Some(preset) => format!("playing through {}", preset.name),
+            None => String::from("playing unfiltered"),
+        }
+    });
+
+    // picking a preset loads it into the editor
+    let presets = tags
+        .map_each(|tags| tags.pipelines().to_vec())
+        .switcher({
+            let name = name.clone();
+            let deinterlace = deinterlace.clone();
+            let denoise = denoise.clone();
+            let brightness = brightness.clone();
+            let advanced = advanced.clone();
+            let custom = custom.clone();
+            move |presets, _| {
+                presets
+                    .iter()
+                    .map(|preset| {
+                        preset.name.clone().into_button().on_click({
+                            let preset = preset.clone();
+                            let name = name.clone();
+                            let deinterlace = deinterlace.clone();
+                            let denoise = denoise.clone();
+                            let brightness = brightness.clone();
+                            let advanced = advanced.clone();
+                            let custom = custom.clone();
+                            move |_| {
+                                name.set(preset.name.clone());
+                                deinterlace.set(preset.deinterlace);
+                                denoise.set(preset.denoise);
+                                brightness.set(preset.brightness.to_string());
+                                if !preset.custom.is_empty() {
+                                    advanced.set(true);
+                                }
+                                custom.set(preset.custom.clone());
+                            }
+                        })
+                    })
+                    .collect::<WidgetList>()
+                    .into_columns()
+                    .make_widget()
+            }
+        });
+
+    presets
+        .and(
+            "preset"
+                .and(name.into_input())
+                .and(toggle(&deinterlace, "deinterlace"))
+                .and(toggle(&denoise, "denoise"))
+                .and("brightness")
+                .and(brightness.into_input())
+                .and(toggle(&advanced, "advanced"))
+                .and(save)
+                .and(delete)
+                .into_columns(),
+        )
+        .and(
+            "filtergraph"
+                .and(
+                    custom
+                        .into_input()
+                        .placeholder("e.g. unsharp=5:5:1.0,hue=s=0")
+                        .expand_horizontally(),
+                )
+                .into_columns()
+                .collapse_vertically(advanced.map_each(|advanced| !advanced)),
+        )
+        .and(
+            use_for_video
+                .and(unfiltered)
+                .and(in_use)
+                .and(status)
+                .into_columns(),
+        )
+        .into_rows()
+}
@@ -0,0 +1,202 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use cushy::value::{Destination, Dynamic, Source};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::backups::{snapshot, BackupSettings};
+use crate::database::{self, DatabaseError, TagDatabase};
+use crate::settings::ScalingMode;
+use crate::tags::{TagId, TagStore};
+use crate::video_player::VideoPlayer;
+
+const PROJECT_EXTENSION: &str = "vtag";
+
+// a session to pick up later or hand to someone else: the tags, the video that was
+// open and where in it, and how the window looked
+#[derive(Debug, Serialize, Deserialize)]
+struct ProjectFile {
+    // in the tag database's format, migrated the same way when opened
+    tags: Value,
+    #[serde(default)]
+    video: Option<PathBuf>,
+    #[serde(default)]
+    position: Duration,
+    #[serde(default)]
+    view: ProjectView,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ProjectView {
+    #[serde(default)]
+    scaling: Option<ScalingMode>,
+    #[serde(default)]
+    selected_tag: Option<TagId>,
+    // the side panels that were open, by name
+    #[serde(default)]
+    panels: Vec<String>,
+}
+
+// the parts of the app a project saves and restores
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub video_source: Dynamic<Option<PathBuf>>,
+    pub video_player: VideoPlayer,
+    pub tags: Dynamic<TagStore>,
+    // projects carry every video's tags, not just the ones opened so far
+    pub database: TagDatabase,
+    pub selected_tag: Dynamic<Option<TagId>>,
+    pub scaling_mode: Dynamic<ScalingMode>,
+    pub panels: Vec<(&'static str, Dynamic<bool>)>,
+    // the tags a project replaces are backed up here first
+    pub backup_settings: Dynamic<BackupSettings>,
+    pub announcement: Dynamic<String>,
+}
+
+impl Workspace {
+    pub fn save(&self, path: &Path) -> Result<(), DatabaseError> {
+        let project = ProjectFile {
+            tags: database::to_document(&self.database.everything(&self.tags.get())?)?,
+            video: self.video_source.get(),
+            position: self.video_player.position().get(),
+            view: ProjectView {
+                scaling: Some(self.scaling_mode.get()),
+                selected_tag: self.selected_tag.get(),
+                panels: self
+                    .panels
+                    .iter()
+                    .filter(|(_, open)| open.get())
+                    .map(|(name, _)| name.to_string())
+                    .collect(),
+            },
+        };
+
+        let temp = path.with_extension("vtag.tmp");
+        std::fs::write(&temp, serde_json::to_vec_pretty(&project)?)?;
+        std::fs::rename(temp, path)?;
+        Ok(())
+    }
+
+    // replaces the tags with the project's, after backing up the ones there are
+    pub fn open(&self, path: &Path) -> Result<(), DatabaseError> {
+        let project: ProjectFile = serde_json::from_slice(&std::fs::read(path)?)?;
+        let tags = database::from_document(project.tags)?;
+        snapshot(&self.database.everything(&self.tags.get())?, &self.backup_settings.get())?;
+
+        self.tags.set(tags);
+        self.selected_tag.set(project.view.selected_tag);
+        for (name, open) in &self.panels {
+            open.set(project.view.panels.iter().any(|panel| panel == name));
+        }
+
+        let Some(video) = project.video.map(|video| locate(video, path)) else {
+            self.video_source.set(None);
+            return Ok(());
+        };
+        self.video_source.set(Some(video));
+        // opening the video picked its saved scaling, the project's goes on top
+        if let Some(scaling) = project.view.scaling {
+            self.scaling_mode.set(scaling);
+        }
+        if !project.position.is_zero() {
+            self.video_player.seek(project.position);
+        }
+        Ok(())
+    }
+
+    pub fn save_dialog(&self) {
+        let this = self.clone();
+        std::thread::Builder::new()
+            .name("Project Thread".into())
+            .spawn(move || {
+                let name = this
+                    .video_source
+                    .get()
+                    .and_then(|video| video.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+                    .unwrap_or_else(|| String::from("project"));
+                let Some(path) = rfd::FileDialog::new()
+                    .add_filter("video tagger project", &[PROJECT_EXTENSION])
+                    .set_file_name(format!("{name}.{PROJECT_EXTENSION}"))
+                    .save_file()
+                else {
+                    return;
+                };
+                match this.save(&path) {
+                    Ok(()) => this.announcement.set(format!("saved {}", path.display())),
+                    Err(err) => this
+                        .announcement
+                        .set(format!("failed to save {}: {err}", path.display())),
+                }
+            })
+            .unwrap();
+    }
+
+    pub fn open_dialog(&self) {
+        let this = self.clone();
+        std::thread::Builder::new()
+            .name("Project Thread".into())
+            .spawn(move || {
+                let Some(path) = rfd::FileDialog::new()
+                    .add_filter("video tagger project", &[PROJECT_EXTENSION])
+                    .pick_file()
+                else {
+                    return;
+                };
+                match this.open(&path) {
+                    Ok(()) => this.announcement.set(format!("opened {}", path.display())),
+                    Err(err) => this
+                        .announcement
+                        .set(format!("failed to open {}: {err}", path.display())),
+                }
+            })
+            .unwrap();
+    }
+}
+
+// a project moved along with its video still finds it next to itself
+fn locate(video: PathBuf, project: &Path) -> PathBuf {
+    if video.exists() {
+        return video;
+    }
+    video
+        .file_name()
+        .and_then(|name| Some(project.parent()?.join(name)))
+        .filter(|beside| beside.exists())
+        .unwrap_or(video)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use super::*;
+
+    // opening a project swaps in a store whose activity log starts empty, the observer
+    // in lib.rs must neither panic on it nor forward anything old
+    #[test]
+    fn open_after_an_edit() {
+        let video = PathBuf::from("match.mp4");
+        let mut tags = TagStore::default();
+        tags.add(video.clone(), Duration::from_secs(3), "kickoff".into());
+        let saved = serde_json::to_vec(&ProjectFile {
+            tags: database::to_document(&tags).unwrap(),
+            video: Some(video.clone()),
+            position: Duration::ZERO,
+            view: ProjectView::default(),
+        })
+        .unwrap();
+
+        tags.add(video.clone(), Duration::from_secs(8), "goal".into());
+        let seen = tags.activity_since(0).last().unwrap().sequence;
+
+        let project: ProjectFile = serde_json::from_slice(&saved).unwrap();
+        let mut opened = database::from_document(project.tags).unwrap();
+        assert!(opened.activity_since(seen).is_empty());
+
+        opened.add(video, Duration::from_secs(4), "corner".into());
+        assert_eq!(opened.activity_since(seen).len(), 1);
+    }
+}
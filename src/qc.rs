@@ -0,0 +1,537 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use cushy::{
+    styles::Color,
+    value::{Destination, Dynamic, Source},
+    widget::MakeWidget,
+};
+use ffmpeg_next as ffmpeg;
+
+use crate::database::{TagDatabase, TagQuery};
+use crate::format_timestamp;
+use crate::media::open_input;
+use crate::pipelines::FrameFilter;
+use crate::renditions::Renditions;
+use crate::tags::TagStore;
+
+// black for a tenth of a second, or the same picture for half of one
+const DETECT_FILTER: &str = "blackdetect=d=0.1:pix_th=0.10,freezedetect=n=-60dB:d=0.5";
+pub const QC_LABEL: &str = "QC";
+// a sample this close to full scale is taken as clipped, three in a row as clipping
+const CLIP_LEVEL: f32 = 0.999;
+const CLIP_RUN: usize = 3;
+// clipping this close together is reported as one defect
+const CLIP_GAP: f64 = 0.5;
+// digital silence, rather than a quiet passage
+const SILENCE_LEVEL: f32 = 1e-4;
+const DROPOUT_SECONDS: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DefectKind {
+    Black,
+    Freeze,
+    Clipping,
+    Dropout,
+}
+
+impl DefectKind {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::Black => "black frames",
+            Self::Freeze => "frozen frames",
+            Self::Clipping => "audio clipping",
+            Self::Dropout => "audio dropout",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Self::Black => Color::new(0x80, 0x80, 0x80, 0xff),
+            Self::Freeze => Color::new(0x40, 0xa0, 0xff, 0xff),
+            Self::Clipping => Color::new(0xff, 0x40, 0x40, 0xff),
+            Self::Dropout => Color::new(0xff, 0xa0, 0x20, 0xff),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    // for runs that get worse the longer they go on
+    fn of_duration(duration: f64) -> Self {
+        if duration < 0.5 {
+            Self::Low
+        } else if duration < 2. {
+            Self::Medium
+        } else {
+            Self::High
+        }
+    }
+
+    // for clipping, by the share of the run's samples that were clipped
+    fn of_ratio(ratio: f64) -> Self {
+        if ratio < 0.01 {
+            Self::Low
+        } else if ratio < 0.1 {
+            Self::Medium
+        } else {
+            Self::High
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
+// a run of frames failing a check, in the scanned file's time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Defect {
+    pub kind: DefectKind,
+    pub severity: Severity,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+impl Defect {
+    fn new(kind: DefectKind, severity: Severity, start: f64, end: f64) -> Self {
+        Self {
+            kind,
+            severity,
+            start: Duration::from_secs_f64(start.max(0.)),
+            end: Duration::from_secs_f64(end.max(start).max(0.)),
+        }
+    }
+
+    fn of_duration(kind: DefectKind, start: f64, end: f64) -> Self {
+        Self::new(kind, Severity::of_duration(end - start), start, end)
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{} ({})", self.kind.describe(), self.severity.label())
+    }
+}
+
+// decodes all of `video` through blackdetect and freezedetect, which mark the first
+// frame of a run and the one after it in the frames' metadata
+pub fn detect_picture_defects(
+    video: &Path,
+    mut progress: impl FnMut(f64),
+) -> Result<Vec<Defect>, ffmpeg::Error> {
+    let mut ictx = open_input(video)?;
+    let stream = ictx
+        .streams()
+        .best(ffmpeg::media::Type::Video)
+        .ok_or(ffmpeg::Error::StreamNotFound)?;
+    let stream_index = stream.index();
+    let time_base = stream.time_base();
+    let mut decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+        .decoder()
+        .video()?;
+    let duration = ictx.duration() as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE);
+    let mut filter = FrameFilter::new(DETECT_FILTER.to_string(), time_base);
+
+    let mut defects = Vec::new();
+    let mut open: HashMap<DefectKind, f64> = HashMap::new();
+    let mut last_time = 0.;
+    {
+        let mut frame = ffmpeg::util::frame::Video::empty();
+        let mut detect = |decoder: &mut ffmpeg::decoder::Video| -> Result<(), ffmpeg::Error> {
+            while decoder.receive_frame(&mut frame).is_ok() {
+                filter.process(&frame, |filtered| {
+                    let metadata = filtered.metadata();
+                    let seconds =
+                        |key: &str| metadata.get(key).and_then(|value| value.parse::<f64>().ok());
+                    for (kind, start_key, end_key) in [
+                        (DefectKind::Black, "lavfi.black_start", "lavfi.black_end"),
+                        (
+                            DefectKind::Freeze,
+                            "lavfi.freezedetect.freeze_start",
+                            "lavfi.freezedetect.freeze_end",
+                        ),
+                    ] {
+                        if let Some(start) = seconds(start_key) {
+                            open.insert(kind, start);
+                        }
+                        let Some(end) = seconds(end_key) else {
+                            continue;
+                        };
+                        if let Some(start) = open.remove(&kind) {
+                            defects.push(Defect::of_duration(kind, start, end));
+                        }
+                    }
+                })?;
+                if let Some(pts) = frame.pts() {
+                    last_time = pts as f64 * f64::from(time_base);
+                    progress(last_time / duration.max(1.));
+                }
+            }
+            Ok(())
+        };
+        for (stream, packet) in ictx.packets() {
+            if stream.index() == stream_index && decoder.send_packet(&packet).is_ok() {
+                detect(&mut decoder)?;
+            }
+        }
+        decoder.send_eof()?;
+        detect(&mut decoder)?;
+    }
+
+    // runs going on at the end of the video end with it
+    for (kind, start) in open {
+        defects.push(Defect::of_duration(kind, start, last_time));
+    }
+    defects.sort_by_key(|defect| defect.start);
+    Ok(defects)
+}
+
+// clipping and silence as the audio goes along, a sample frame (every channel) at a time
+#[derive(Debug, Default)]
+struct AudioCheck {
+    defects: Vec<Defect>,
+    clipped_run: usize,
+    // start, end and clipped sample frames of the clipping being gathered
+    clipping: Option<(f64, f64, usize)>,
+    silent_since: Option<f64>,
+    heard: bool,
+}
+
+impl AudioCheck {
+    fn sample(&mut self, time: f64, frame: &[f32], rate: f64) {
+        if frame.iter().any(|sample| sample.abs() >= CLIP_LEVEL) {
+            self.clipped_run += 1;
+            if self.clipped_run >= CLIP_RUN {
+                let clipped = if self.clipped_run == CLIP_RUN { CLIP_RUN } else { 1 };
+                if let Some((_, end, count)) = self
+                    .clipping
+                    .as_mut()
+                    .filter(|(_, end, _)| time - *end <= CLIP_GAP)
+                {
+                    *end = time;
+                    *count += clipped;
+                } else {
+                    self.close_clipping(rate);
+                    let start = time - (CLIP_RUN - 1) as f64 / rate;
+                    self.clipping = Some((start, time, clipped));
+                }
+            }
+        } else {
+            self.clipped_run = 0;
+        }
+
+        if frame.iter().all(|sample| sample.abs() < SILENCE_LEVEL) {
+            self.silent_since.get_or_insert(time);
+        } else {
+            // silence before anything was heard is the start of the track, not a dropout
+            if let Some(start) = self.silent_since.take() {
+                if self.heard && time - start >= DROPOUT_SECONDS {
+                    self.defects.push(Defect::of_duration(DefectKind::Dropout, start, time));
+                }
+            }
+            self.heard = true;
+        }
+    }
+
+    fn close_clipping(&mut self, rate: f64) {
+        if let Some((start, end, count)) = self.clipping.take() {
+            let frames = ((end - start) * rate).max(1.);
+            let severity = Severity::of_ratio(count as f64 / frames);
+            self.defects.push(Defect::new(DefectKind::Clipping, severity, start, end));
+        }
+    }
+
+    // silence running into the end is the track ending, not a dropout
+    fn finish(mut self, rate: f64) -> Vec<Defect> {
+        self.close_clipping(rate);
+        self.defects
+    }
+}
+
+// decodes the best audio stream as floats, looking for clipped samples and digital
+// silence in the middle of the track. a video without audio has nothing to find
+fn detect_audio_defects(
+    video: &Path,
+    mut progress: impl FnMut(f64),
+) -> Result<Vec<Defect>, ffmpeg::Error> {
+    let mut ictx = open_input(video)?;
+    let Some(stream) = ictx.streams().best(ffmpeg::media::Type::Audio) else {
+        return Ok(Vec::new());
+    };
+    let stream_index = stream.index();
+    let time_base = f64::from(stream.time_base());
+    let mut decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+        .decoder()
+        .audio()?;
+    let duration = ictx.duration() as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE);
+
+    let mut check = AudioCheck::default();
+    let mut resampler: Option<ffmpeg::software::resampling::Context> = None;
+    let mut rate = 0.;
+    let mut next_time = 0.;
+    {
+        let mut decoded = ffmpeg::util::frame::Audio::empty();
+        let mut resampled = ffmpeg::util::frame::Audio::empty();
+        let mut analyze = |decoder: &mut ffmpeg::decoder::Audio| -> Result<(), ffmpeg::Error> {
+            while decoder.receive_frame(&mut decoded).is_ok() {
+                if resampler.is_none() {
+                    let layout = if decoded.channel_layout().is_empty() {
+                        ffmpeg::ChannelLayout::default(i32::from(decoded.channels()))
+                    } else {
+                        decoded.channel_layout()
+                    };
+                    rate = f64::from(decoded.rate());
+                    resampler = Some(ffmpeg::software::resampling::Context::get(
+                        decoded.format(),
+                        layout,
+                        decoded.rate(),
+                        ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Packed),
+                        layout,
+                        decoded.rate(),
+                    )?);
+                }
+                let Some(resampler) = resampler.as_mut() else {
+                    continue;
+                };
+                resampler.run(&decoded, &mut resampled)?;
+
+                let channels = usize::from(decoded.channels()).max(1);
+                let len = resampled.samples() * channels;
+                let samples = resampled.data(0)[..len * std::mem::size_of::<f32>()]
+                    .chunks_exact(std::mem::size_of::<f32>())
+                    .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .collect::<Vec<_>>();
+                let start = decoded.pts().map_or(next_time, |pts| pts as f64 * time_base);
+                for (idx, frame) in samples.chunks_exact(channels).enumerate() {
+                    check.sample(start + idx as f64 / rate, frame, rate);
+                }
+                next_time = start + resampled.samples() as f64 / rate;
+                progress(next_time / duration.max(1.));
+            }
+            Ok(())
+        };
+        for (stream, packet) in ictx.packets() {
+            if stream.index() == stream_index && decoder.send_packet(&packet).is_ok() {
+                analyze(&mut decoder)?;
+            }
+        }
+        decoder.send_eof()?;
+        analyze(&mut decoder)?;
+    }
+
+    let mut defects = check.finish(rate.max(1.));
+    defects.sort_by_key(|defect| defect.start);
+    Ok(defects)
+}
+
+// one line per defect of every scanned file
+fn report_csv(results: &HashMap<PathBuf, Vec<Defect>>) -> String {
+    let mut videos = results.keys().collect::<Vec<_>>();
+    videos.sort();
+
+    let mut csv = String::from("file,check,severity,start,end\n");
+    for video in videos {
+        let file = video.display().to_string().replace('"', "\"\"");
+        for defect in &results[video] {
+            csv.push_str(&format!(
+                "\"{file}\",{},{},{},{}\n",
+                defect.kind.describe(),
+                defect.severity.label(),
+                format_timestamp(defect.start),
+                format_timestamp(defect.end),
+            ));
+        }
+    }
+    csv
+}
+
+// quality checks of whole videos in the background, kept for the session
+#[derive(Debug, Clone)]
+pub struct QcScanner {
+    // by the path that was scanned, proxies have their own
+    pub results: Dynamic<HashMap<PathBuf, Vec<Defect>>>,
+    // puts a "QC" range tag on every defect a scan finds
+    pub auto_tag: Dynamic<bool>,
+    scanning: Arc<Mutex<HashSet<PathBuf>>>,
+    tags: Dynamic<TagStore>,
+    database: TagDatabase,
+    renditions: Dynamic<Renditions>,
+    // the app's status line
+    announcement: Dynamic<String>,
+}
+
+impl QcScanner {
+    pub fn new(
+        tags: Dynamic<TagStore>,
+        database: TagDatabase,
+        renditions: Dynamic<Renditions>,
+        announcement: Dynamic<String>,
+    ) -> Self {
+        Self {
+            results: Dynamic::default(),
+            auto_tag: Dynamic::new(false),
+            scanning: Arc::default(),
+            tags,
+            database,
+            renditions,
+            announcement,
+        }
+    }
+
+    pub fn scan(&self, video: PathBuf) {
+        if !self.scanning.lock().unwrap().insert(video.clone()) {
+            return;
+        }
+
+        let this = self.clone();
+        std::thread::Builder::new()
+            .name("QC Thread".into())
+            .spawn(move || {
+                let name = video.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                // the picture is the first half of the work, the audio the second
+                let mut reported = 0;
+                let mut announce = |done: f64| {
+                    let percent = (done * 100.).clamp(0., 100.) as u32;
+                    if percent / 10 != reported / 10 {
+                        reported = percent;
+                        this.announcement.set(format!("checking {name}, {percent}%"));
+                    }
+                };
+                let detected = detect_picture_defects(&video, |done| announce(done / 2.))
+                    .and_then(|mut defects| {
+                        defects.extend(detect_audio_defects(&video, |done| {
+                            announce(0.5 + done / 2.);
+                        })?);
+                        defects.sort_by_key(|defect| defect.start);
+                        Ok(defects)
+                    });
+                this.scanning.lock().unwrap().remove(&video);
+                match detected {
+                    Ok(defects) => {
+                        let count =
+                            |kind| defects.iter().filter(|defect| defect.kind == kind).count();
+                        this.announcement.set(format!(
+                            "{name}: {} black, {} frozen, {} clipping, {} dropouts",
+                            count(DefectKind::Black),
+                            count(DefectKind::Freeze),
+                            count(DefectKind::Clipping),
+                            count(DefectKind::Dropout),
+                        ));
+                        if this.auto_tag.get() {
+                            this.tag(&video, &defects);
+                        }
+                        this.results.lock().insert(video, defects);
+                    }
+                    Err(err) => this.announcement.set(format!("couldn't check {name}: {err}")),
+                }
+            })
+            .unwrap();
+    }
+
+    // on the original's timeline, once: defects that already have their tag are skipped
+    fn tag(&self, video: &Path, defects: &[Defect]) {
+        let (original, ranges) = self.renditions.map_ref(|renditions| {
+            let ranges = defects
+                .iter()
+                .map(|defect| {
+                    (
+                        defect.describe(),
+                        renditions.to_original(video, defect.start),
+                        renditions.to_original(video, defect.end),
+                    )
+                })
+                .collect::<Vec<_>>();
+            (renditions.original_of(video), ranges)
+        });
+
+        // scans run on videos that aren't open too, the database has their tags
+        let query = TagQuery {
+            video: Some(original.clone()),
+            label: Some(QC_LABEL.to_string()),
+            ..TagQuery::default()
+        };
+        let mut tagged = match self.database.find(&self.tags, &query) {
+            Ok(tagged) => tagged.into_iter().map(|tag| tag.time).collect::<HashSet<_>>(),
+            Err(err) => {
+                self.announcement.set(format!("couldn't tag the defects: {err}"));
+                return;
+            }
+        };
+
+        self.tags.lock().group(|tags| {
+            for (note, start, end) in ranges {
+                if !tagged.insert(start) {
+                    continue;
+                }
+                if let Some(id) =
+                    tags.add_range(original.clone(), start, end, QC_LABEL.to_string())
+                {
+                    tags.set_note(id, note);
+                }
+            }
+        });
+    }
+
+    pub fn controls(&self, video_source: Dynamic<Option<PathBuf>>) -> impl MakeWidget {
+        let scan = "QC scan".into_button().on_click({
+            let this = self.clone();
+            move |_| {
+                if let Some(video) = video_source.get() {
+                    this.scan(video);
+                }
+            }
+        });
+        let auto_tag = self
+            .auto_tag
+            .map_each(|auto_tag| if *auto_tag { "[QC tags]" } else { "QC tags" })
+            .into_button()
+            .on_click({
+                let auto_tag = self.auto_tag.clone();
+                move |_| auto_tag.toggle()
+            });
+        let report = "QC report".into_button().on_click({
+            let this = self.clone();
+            move |_| this.export_report()
+        });
+        scan.and(auto_tag).and(report).into_columns()
+    }
+
+    fn export_report(&self) {
+        let results = self.results.get();
+        if results.is_empty() {
+            self.announcement.set(String::from("nothing has been checked yet"));
+            return;
+        }
+
+        let announcement = self.announcement.clone();
+        std::thread::Builder::new()
+            .name("QC Report Thread".into())
+            .spawn(move || {
+                let Some(path) = rfd::FileDialog::new()
+                    .add_filter("QC report", &["csv"])
+                    .set_file_name("qc-report.csv")
+                    .save_file()
+                else {
+                    return;
+                };
+                announcement.set(match std::fs::write(&path, report_csv(&results)) {
+                    Ok(()) => format!("QC report saved to {}", path.display()),
+                    Err(err) => format!("failed to write {}: {err}", path.display()),
+                });
+            })
+            .unwrap();
+    }
+}
@@ -0,0 +1,445 @@
+use std::path::{Path, PathBuf};
+
+use cushy::{
+    value::{Destination, Dynamic, MapEach, Source, Switchable},
+    widget::{MakeWidget, WidgetList},
+};
+use ffmpeg_next as ffmpeg;
+use serde::{Deserialize, Serialize};
+
+use crate::media::open_input;
+use crate::qc::{detect_picture_defects, DefectKind, QcScanner};
+
+// what every file in the library has to meet, unset limits and empty lists allow anything
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QcRules {
+    // seconds of black in one run
+    #[serde(default)]
+    pub max_black: Option<f64>,
+    // integrated loudness, LUFS
+    #[serde(default)]
+    pub min_loudness: Option<f64>,
+    #[serde(default)]
+    pub max_loudness: Option<f64>,
+    // EBU R128 loudness range, LU
+    #[serde(default)]
+    pub max_loudness_range: Option<f64>,
+    // "1920x1080"
+    #[serde(default)]
+    pub resolutions: Vec<String>,
+    // ffmpeg's names, "h264", "prores"
+    #[serde(default)]
+    pub codecs: Vec<String>,
+}
+
+impl QcRules {
+    fn checks_black(&self) -> bool {
+        self.max_black.is_some()
+    }
+
+    fn checks_loudness(&self) -> bool {
+        self.min_loudness.is_some()
+            || self.max_loudness.is_some()
+            || self.max_loudness_range.is_some()
+    }
+
+    // what `facts` break, nothing when the file passes
+    fn failures(&self, facts: &Facts) -> Vec<String> {
+        let mut failures = Vec::new();
+
+        let resolution = format!("{}x{}", facts.width, facts.height);
+        if !self.resolutions.is_empty() && !self.resolutions.contains(&resolution) {
+            failures.push(format!("resolution {resolution} not allowed"));
+        }
+        if !self.codecs.is_empty() && !self.codecs.contains(&facts.codec) {
+            failures.push(format!("codec {} not allowed", facts.codec));
+        }
+        if let (Some(max), Some(black)) = (self.max_black, facts.longest_black) {
+            if black > max {
+                failures.push(format!("{black:.1}s of black, at most {max}s"));
+            }
+        }
+
+        if self.checks_loudness() {
+            let Some(loudness) = facts.loudness else {
+                failures.push(String::from("no audio to measure"));
+                return failures;
+            };
+            if self.min_loudness.is_some_and(|min| loudness.integrated < min) {
+                failures.push(format!("{:.1} LUFS, too quiet", loudness.integrated));
+            }
+            if self.max_loudness.is_some_and(|max| loudness.integrated > max) {
+                failures.push(format!("{:.1} LUFS, too loud", loudness.integrated));
+            }
+            if let Some(max) = self.max_loudness_range.filter(|max| loudness.range > *max) {
+                failures.push(format!(
+                    "loudness range {:.1} LU, at most {max} LU",
+                    loudness.range
+                ));
+            }
+        }
+        failures
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Loudness {
+    integrated: f64,
+    range: f64,
+}
+
+// what the rules are checked against, only measured when a rule needs it
+#[derive(Debug, Clone, PartialEq)]
+struct Facts {
+    width: u32,
+    height: u32,
+    codec: String,
+    // seconds
+    longest_black: Option<f64>,
+    loudness: Option<Loudness>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verdict {
+    pub video: PathBuf,
+    // the rules it broke, or why it couldn't be checked
+    pub failures: Vec<String>,
+}
+
+impl Verdict {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn badge(&self) -> &'static str {
+        if self.passed() {
+            "PASS"
+        } else {
+            "FAIL"
+        }
+    }
+}
+
+// a scan that already ran for `video` spares decoding it again for black frames
+fn check(video: &Path, rules: &QcRules, scanner: &QcScanner) -> Verdict {
+    let measured = (|| {
+        let ictx = open_input(video)?;
+        let stream = ictx
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .ok_or(ffmpeg::Error::StreamNotFound)?;
+        let codec = stream.parameters().id().name().to_string();
+        let decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+            .decoder()
+            .video()?;
+
+        let longest_black = if rules.checks_black() {
+            let defects = match scanner.results.map_ref(|results| results.get(video).cloned()) {
+                Some(defects) => defects,
+                None => detect_picture_defects(video, |_| {})?,
+            };
+            let longest = defects
+                .iter()
+                .filter(|defect| defect.kind == DefectKind::Black)
+                .map(|defect| (defect.end - defect.start).as_secs_f64())
+                .fold(0., f64::max);
+            Some(longest)
+        } else {
+            None
+        };
+        let loudness = if rules.checks_loudness() {
+            measure_loudness(video)?
+        } else {
+            None
+        };
+
+        Ok::<_, ffmpeg::Error>(Facts {
+            width: decoder.width(),
+            height: decoder.height(),
+            codec,
+            longest_black,
+            loudness,
+        })
+    })();
+
+    Verdict {
+        video: video.to_path_buf(),
+        failures: match measured {
+            Ok(facts) => rules.failures(&facts),
+            Err(err) => vec![format!("couldn't be read: {err}")],
+        },
+    }
+}
+
+// the whole of the best audio stream through ebur128, none without audio
+fn measure_loudness(video: &Path) -> Result<Option<Loudness>, ffmpeg::Error> {
+    let mut ictx = open_input(video)?;
+    let Some(stream) = ictx.streams().best(ffmpeg::media::Type::Audio) else {
+        return Ok(None);
+    };
+    let stream_index = stream.index();
+    let mut decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+        .decoder()
+        .audio()?;
+
+    let mut graph = None;
+    let mut loudness = None;
+    {
+        let mut decoded = ffmpeg::util::frame::Audio::empty();
+        let mut measure = |decoder: &mut ffmpeg::decoder::Audio| -> Result<(), ffmpeg::Error> {
+            while decoder.receive_frame(&mut decoded).is_ok() {
+                if graph.is_none() {
+                    graph = Some(loudness_graph(&decoded)?);
+                }
+                let graph = graph.as_mut().expect("built above");
+                graph.get("in").expect("added in build").source().add(&decoded)?;
+                drain(graph, &mut loudness);
+            }
+            Ok(())
+        };
+        for (stream, packet) in ictx.packets() {
+            if stream.index() == stream_index && decoder.send_packet(&packet).is_ok() {
+                measure(&mut decoder)?;
+            }
+        }
+        decoder.send_eof()?;
+        measure(&mut decoder)?;
+    }
+
+    if let Some(graph) = graph.as_mut() {
+        graph.get("in").expect("added in build").source().flush()?;
+        drain(graph, &mut loudness);
+    }
+    Ok(loudness)
+}
+
+// ebur128 puts the loudness so far on every frame it lets out, the last one has it all
+fn drain(graph: &mut ffmpeg::filter::Graph, loudness: &mut Option<Loudness>) {
+    let mut filtered = ffmpeg::util::frame::Audio::empty();
+    while graph
+        .get("out")
+        .expect("added in build")
+        .sink()
+        .frame(&mut filtered)
+        .is_ok()
+    {
+        let metadata = filtered.metadata();
+        let value = |key: &str| metadata.get(key).and_then(|value| value.parse::<f64>().ok());
+        if let (Some(integrated), Some(range)) = (value("lavfi.r128.I"), value("lavfi.r128.LRA")) {
+            *loudness = Some(Loudness { integrated, range });
+        }
+    }
+}
+
+fn loudness_graph(frame: &ffmpeg::util::frame::Audio) -> Result<ffmpeg::filter::Graph, ffmpeg::Error> {
+    let layout = if frame.channel_layout().is_empty() {
+        ffmpeg::ChannelLayout::default(i32::from(frame.channels()))
+    } else {
+        frame.channel_layout()
+    };
+    let args = format!(
+        "time_base=1/{rate}:sample_rate={rate}:sample_fmt={}:channel_layout=0x{:x}",
+        frame.format().name(),
+        layout.bits(),
+        rate = frame.rate(),
+    );
+
+    let mut graph = ffmpeg::filter::Graph::new();
+    let buffer = ffmpeg::filter::find("abuffer").ok_or(ffmpeg::Error::FilterNotFound)?;
+    let sink = ffmpeg::filter::find("abuffersink").ok_or(ffmpeg::Error::FilterNotFound)?;
+    graph.add(&buffer, "in", &args)?;
+    graph.add(&sink, "out", "")?;
+    graph.output("in", 0)?.input("out", 0)?.parse("ebur128=metadata=1")?;
+    graph.validate()?;
+    Ok(graph)
+}
+
+// one line per checked file
+fn report_csv(verdicts: &[Verdict]) -> String {
+    let mut csv = String::from("file,result,failures\n");
+    for verdict in verdicts {
+        let quote = |text: &str| format!("\"{}\"", text.replace('"', "\"\""));
+        csv.push_str(&format!(
+            "{},{},{}\n",
+            quote(&verdict.video.display().to_string()),
+            verdict.badge(),
+            quote(&verdict.failures.join("; ")),
+        ));
+    }
+    csv
+}
+
+// "" is no limit
+fn parse_limit(text: &str, what: &str) -> Result<Option<f64>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    text.parse::<f64>()
+        .map(Some)
+        .map_err(|_| format!("{what} should be a number, not {text:?}"))
+}
+
+fn parse_list(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|item| item.trim().to_lowercase())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn show_limit(limit: Option<f64>) -> String {
+    limit.map(|limit| limit.to_string()).unwrap_or_default()
+}
+
+// edits the rules and checks every library video against them in the background,
+// with a badge per file and a report of the whole batch
+pub fn qc_rules_panel(
+    rules: Dynamic<QcRules>,
+    library: impl Fn() -> Vec<PathBuf> + Send + Sync + 'static,
+    scanner: QcScanner,
+    announcement: Dynamic<String>,
+) -> impl MakeWidget {
+    let current = rules.get();
+    let max_black = Dynamic::new(show_limit(current.max_black));
+    let min_loudness = Dynamic::new(show_limit(current.min_loudness));
+    let max_loudness = Dynamic::new(show_limit(current.max_loudness));
+    let max_range = Dynamic::new(show_limit(current.max_loudness_range));
+    let resolutions = Dynamic::new(current.resolutions.join(", "));
+    let codecs = Dynamic::new(current.codecs.join(", "));
+    let verdicts: Dynamic<Vec<Verdict>> = Dynamic::default();
+    let checking = Dynamic::new(false);
+
+    let field = |label: &'static str, value: &Dynamic<String>, placeholder: &'static str| {
+        label
+            .and(value.clone().into_input().placeholder(placeholder).expand_horizontally())
+            .into_columns()
+    };
+    let fields = field("max black", &max_black, "seconds")
+        .and(field("min loudness", &min_loudness, "LUFS, e.g. -24"))
+        .and(field("max loudness", &max_loudness, "LUFS, e.g. -22"))
+        .and(field("max loudness range", &max_range, "LU"))
+        .and(field("resolutions", &resolutions, "1920x1080, 3840x2160"))
+        .and(field("codecs", &codecs, "h264, hevc, prores"))
+        .into_rows();
+
+    let save = "save rules".into_button().on_click({
+        let rules = rules.clone();
+        let announcement = announcement.clone();
+        move |_| {
+            let parsed = (|| {
+                Ok::<_, String>(QcRules {
+                    max_black: parse_limit(&max_black.get(), "max black")?,
+                    min_loudness: parse_limit(&min_loudness.get(), "min loudness")?,
+                    max_loudness: parse_limit(&max_loudness.get(), "max loudness")?,
+                    max_loudness_range: parse_limit(&max_range.get(), "max loudness range")?,
+                    resolutions: parse_list(&resolutions.get()),
+                    codecs: parse_list(&codecs.get()),
+                })
+            })();
+            match parsed {
+                Ok(parsed) => {
+                    rules.set(parsed);
+                    announcement.set(String::from("QC rules saved"));
+                }
+                Err(err) => announcement.set(err),
+            }
+        }
+    });
+
+    let library = std::sync::Arc::new(library);
+    let check_library = "check library"
+        .into_button()
+        .on_click({
+            let verdicts = verdicts.clone();
+            let checking = checking.clone();
+            let announcement = announcement.clone();
+            move |_| {
+                let rules = rules.get();
+                let library = library.clone();
+                let scanner = scanner.clone();
+                let verdicts = verdicts.clone();
+                let checking = checking.clone();
+                let announcement = announcement.clone();
+                checking.set(true);
+                verdicts.set(Vec::new());
+                std::thread::Builder::new()
+                    .name("QC Rules Thread".into())
+                    .spawn(move || {
+                        let videos = library();
+                        for (idx, video) in videos.iter().enumerate() {
+                            announcement.set(format!(
+                                "checking {} ({}/{})",
+                                video.file_name().unwrap_or_default().to_string_lossy(),
+                                idx + 1,
+                                videos.len(),
+                            ));
+                            let verdict = check(video, &rules, &scanner);
+                            verdicts.lock().push(verdict);
+                        }
+                        let failed = verdicts.map_ref(|verdicts| {
+                            verdicts.iter().filter(|verdict| !verdict.passed()).count()
+                        });
+                        announcement.set(format!(
+                            "checked {} files, {failed} failed",
+                            videos.len()
+                        ));
+                        checking.set(false);
+                    })
+                    .unwrap();
+            }
+        })
+        .with_enabled(checking.map_each(|checking| !checking));
+
+    let report = "batch report".into_button().on_click({
+        let verdicts = verdicts.clone();
+        move |_| {
+            let verdicts = verdicts.get();
+            if verdicts.is_empty() {
+                announcement.set(String::from("check the library first"));
+                return;
+            }
+            let announcement = announcement.clone();
+            std::thread::Builder::new()
+                .name("QC Report Thread".into())
+                .spawn(move || {
+                    let Some(path) = rfd::FileDialog::new()
+                        .add_filter("QC report", &["csv"])
+                        .set_file_name("qc-rules-report.csv")
+                        .save_file()
+                    else {
+                        return;
+                    };
+                    announcement.set(match std::fs::write(&path, report_csv(&verdicts)) {
+                        Ok(()) => format!("QC report saved to {}", path.display()),
+                        Err(err) => format!("failed to write {}: {err}", path.display()),
+                    });
+                })
+                .unwrap();
+        }
+    });
+
+    let results = verdicts.switcher(|verdicts, _| {
+        if verdicts.is_empty() {
+            return "nothing checked yet".make_widget();
+        }
+        verdicts
+            .iter()
+            .map(|verdict| {
+                let name = verdict.video.file_name().unwrap_or_default().to_string_lossy();
+                if verdict.passed() {
+                    format!("[{}] {name}", verdict.badge())
+                } else {
+                    format!("[{}] {name}: {}", verdict.badge(), verdict.failures.join(", "))
+                }
+            })
+            .collect::<WidgetList>()
+            .into_rows()
+            .make_widget()
+    });
+
+    "QC rules"
+        .and(fields)
+        .and(save.and(check_library).and(report).into_columns())
+        .and(results.vertical_scroll())
+        .into_rows()
+}
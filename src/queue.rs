@@ -0,0 +1,180 @@
+use std::{collections::BTreeSet, path::PathBuf};
+
+use cushy::{
+    value::{Destination, Dynamic, Source, Switchable},
+    widget::{MakeWidget, WidgetList},
+};
+
+use crate::database::TagDatabase;
+use crate::formats::{read_playlist, read_watch_later, QueueEntry};
+use crate::format_timestamp;
+use crate::safe_mode::is_safe_mode;
+use crate::settings::config_dir;
+use crate::tags::TagStore;
+use crate::video_player::VideoPlayer;
+
+const QUEUE_FILE: &str = "queue.json";
+
+// every video with tags or in the queue, originals only
+pub fn library(
+    database: &TagDatabase,
+    tags: &Dynamic<TagStore>,
+    queue: &Dynamic<Vec<QueueEntry>>,
+) -> Vec<PathBuf> {
+    let tagged = database.videos(tags).unwrap_or_else(|err| {
+        tracing::error!("failed to list the tagged videos: {err}");
+        Vec::new()
+    });
+    let mut videos = tagged.into_iter().collect::<BTreeSet<_>>();
+    videos.extend(queue.get().into_iter().map(|entry| entry.path));
+    videos.into_iter().filter(|video| video.exists()).collect()
+}
+
+pub fn load_queue() -> Vec<QueueEntry> {
+    config_dir()
+        .map(|dir| dir.join(QUEUE_FILE))
+        .and_then(|path| std::fs::read(path).ok())
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_queue(queue: &[QueueEntry]) -> std::io::Result<()> {
+    let Some(dir) = config_dir().filter(|_| !is_safe_mode()) else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(&dir)?;
+
+    let json = serde_json::to_vec_pretty(queue)?;
+    std::fs::write(dir.join(QUEUE_FILE), json)
+}
+
+// queues the entries and turns their bookmarks into tags
+fn import(entries: Vec<QueueEntry>, queue: &Dynamic<Vec<QueueEntry>>, tags: &Dynamic<TagStore>) {
+    tags.lock().group(|tags| {
+        for entry in &entries {
+            for bookmark in &entry.bookmarks {
+                tags.add(entry.path.clone(), bookmark.time, bookmark.name.clone());
+            }
+        }
+    });
+    queue.lock().extend(entries);
+}
+
+pub fn queue_panel(
+    queue: Dynamic<Vec<QueueEntry>>,
+    tags: Dynamic<TagStore>,
+    video_source: Dynamic<Option<PathBuf>>,
+    video_player: VideoPlayer,
+) -> impl MakeWidget {
+    let import_playlist = "import playlist".into_button().on_click({
+        let queue = queue.clone();
+        let tags = tags.clone();
+        move |_| {
+            let queue = queue.clone();
+            let tags = tags.clone();
+            std::thread::Builder::new()
+                .name("File Picker Thread".into())
+                .spawn(move || {
+                    let Some(path) = rfd::FileDialog::new()
+                        .add_filter("playlist", &["m3u", "m3u8", "xspf"])
+                        .pick_file()
+                    else {
+                        return;
+                    };
+                    match read_playlist(&path) {
+                        Ok(entries) => import(entries, &queue, &tags),
+                        Err(err) => tracing::error!("failed to import {}: {err}", path.display()),
+                    }
+                })
+                .unwrap();
+        }
+    });
+
+    let import_mpv = "import mpv positions".into_button().on_click({
+        let queue = queue.clone();
+        let tags = tags.clone();
+        move |_| {
+            let queue = queue.clone();
+            let tags = tags.clone();
+            std::thread::Builder::new()
+                .name("File Picker Thread".into())
+                .spawn(move || {
+                    let Some(dir) = rfd::FileDialog::new()
+                        .set_title("mpv watch_later folder")
+                        .pick_folder()
+                    else {
+                        return;
+                    };
+                    match read_watch_later(&dir) {
+                        Ok(entries) => import(entries, &queue, &tags),
+                        Err(err) => tracing::error!("failed to import {}: {err}", dir.display()),
+                    }
+                })
+                .unwrap();
+        }
+    });
+
+    let clear = "clear".into_button().on_click({
+        let queue = queue.clone();
+        move |_| queue.lock().clear()
+    });
+
+    let entries = queue.clone().switcher(move |entries, _| {
+        if entries.is_empty() {
+            return "the queue is empty".make_widget();
+        }
+
+        entries
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                let name = entry.title.clone().unwrap_or_else(|| {
+                    entry
+                        .path
+                        .file_name()
+                        .map_or_else(|| entry.path.to_string_lossy(), |name| name.to_string_lossy())
+                        .into_owned()
+                });
+                let label = match entry.start {
+                    Some(start) => format!("{name} (from {})", format_timestamp(start)),
+                    None => name,
+                };
+
+                let open = "open".into_button().on_click({
+                    let video_source = video_source.clone();
+                    let video_player = video_player.clone();
+                    let entry = entry.clone();
+                    move |_| {
+                        // starts the new pipeline, the seek goes to it
+                        video_source.set(Some(entry.path.clone()));
+                        if let Some(start) = entry.start {
+                            video_player.seek(start);
+                        }
+                    }
+                });
+                let remove = "remove".into_button().on_click({
+                    let queue = queue.clone();
+                    move |_| {
+                        let mut queue = queue.lock();
+                        if idx < queue.len() {
+                            queue.remove(idx);
+                        }
+                    }
+                });
+
+                label
+                    .expand_horizontally()
+                    .and(open)
+                    .and(remove)
+                    .into_columns()
+            })
+            .collect::<WidgetList>()
+            .into_rows()
+            .make_widget()
+    });
+
+    "Queue"
+        .and(import_playlist.and(import_mpv).and(clear).into_columns())
+        .and(entries.vertical_scroll())
+        .into_rows()
+}
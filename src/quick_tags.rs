@@ -0,0 +1,64 @@
+use cushy::{
+    value::{Destination, Dynamic, Source},
+    widget::{MakeWidget, WidgetList},
+};
+
+use crate::tags::TagStore;
+
+pub const QUICK_TAG_KEYS: usize = 9;
+
+// the labels keys 1 to 9 tag with. they are kept with the tags, so projects carry them
+pub fn quick_tags_panel(tags: Dynamic<TagStore>, announcement: Dynamic<String>) -> impl MakeWidget {
+    let fields = (0..QUICK_TAG_KEYS)
+        .map(|_| Dynamic::new(String::new()))
+        .collect::<Vec<_>>();
+
+    // only reloaded when the presets themselves change, tagging doesn't undo typing
+    let mut shown = None;
+    tags.for_each({
+        let fields = fields.clone();
+        move |tags: &TagStore| {
+            if shown.as_deref() == Some(tags.quick_tags()) {
+                return;
+            }
+            shown = Some(tags.quick_tags().to_vec());
+            for (idx, field) in fields.iter().enumerate() {
+                field.set(tags.quick_tags().get(idx).cloned().unwrap_or_default());
+            }
+        }
+    })
+    .persist();
+
+    let rows = fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            format!("{}", idx + 1)
+                .and(
+                    field
+                        .clone()
+                        .into_input()
+                        .placeholder("the category in this place")
+                        .expand_horizontally(),
+                )
+                .into_columns()
+        })
+        .collect::<WidgetList>();
+
+    let save = "save".into_button().on_click({
+        let fields = fields.clone();
+        move |_| {
+            let labels = fields
+                .iter()
+                .map(|field| field.get().trim().to_string())
+                .collect();
+            tags.lock().set_quick_tags(labels);
+            announcement.set(String::from("quick tags saved"));
+        }
+    });
+
+    "Quick tags (keys 1-9)"
+        .and(rows.into_rows())
+        .and(save)
+        .into_rows()
+}
@@ -0,0 +1,263 @@
+use std::time::Duration;
+
+use cushy::styles::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::skeletons::Pose;
+
+// a box in frame coordinates, 0..1 from the top left, so it stays put when the
+// scaling mode changes or a lower resolution proxy plays
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Region {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Region {
+    // the box spanned by two corners in any order, clamped to the frame
+    pub fn from_corners(a: (f32, f32), b: (f32, f32)) -> Self {
+        let (left, right) = (a.0.min(b.0).clamp(0., 1.), a.0.max(b.0).clamp(0., 1.));
+        let (top, bottom) = (a.1.min(b.1).clamp(0., 1.), a.1.max(b.1).clamp(0., 1.));
+        Self {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Self {
+            x: mix(self.x, other.x),
+            y: mix(self.y, other.y),
+            width: mix(self.width, other.width),
+            height: mix(self.height, other.height),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0. || self.height <= 0.
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub time: Duration,
+    pub region: Region,
+}
+
+// the boxes of a region tag, keyframes sorted by time. between two keyframes the
+// box moves linearly, outside of them it stays where the nearest one put it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    // first and last keyframe times
+    pub fn span(&self) -> Option<(Duration, Duration)> {
+        Some((self.keyframes.first()?.time, self.keyframes.last()?.time))
+    }
+
+    // replaces the keyframe at exactly `time`, there is one per frame at most
+    pub fn set(&mut self, time: Duration, region: Region) {
+        match self.keyframes.binary_search_by_key(&time, |keyframe| keyframe.time) {
+            Ok(idx) => self.keyframes[idx].region = region,
+            Err(idx) => self.keyframes.insert(idx, Keyframe { time, region }),
+        }
+    }
+
+    // puts `keyframes` (sorted) in place of the ones between their first and last
+    pub fn replace(&mut self, keyframes: &[Keyframe]) {
+        let (Some(first), Some(last)) = (keyframes.first(), keyframes.last()) else {
+            return;
+        };
+        self.keyframes
+            .retain(|keyframe| keyframe.time < first.time || keyframe.time > last.time);
+        for keyframe in keyframes {
+            self.set(keyframe.time, keyframe.region);
+        }
+    }
+
+    pub fn remove(&mut self, time: Duration) -> bool {
+        match self.keyframes.binary_search_by_key(&time, |keyframe| keyframe.time) {
+            Ok(idx) => {
+                self.keyframes.remove(idx);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn shift(&mut self, shift: impl Fn(Duration) -> Duration) {
+        for keyframe in &mut self.keyframes {
+            keyframe.time = shift(keyframe.time);
+        }
+    }
+
+    pub fn at(&self, time: Duration) -> Option<Region> {
+        let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        match (next.checked_sub(1).map(|idx| self.keyframes[idx]), self.keyframes.get(next)) {
+            (Some(before), Some(after)) => {
+                let t = (time - before.time).as_secs_f64()
+                    / (after.time - before.time).as_secs_f64();
+                Some(before.region.lerp(after.region, t as f32))
+            }
+            (Some(only), None) | (None, Some(only)) => Some(only.region),
+            (None, None) => None,
+        }
+    }
+}
+
+// an outline in frame coordinates like `Region`, closed from the last point back to
+// the first. freehand masks are polygons with many points
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Polygon {
+    pub points: Vec<(f32, f32)>,
+}
+
+impl Polygon {
+    pub fn is_valid(&self) -> bool {
+        self.points.len() >= 3
+    }
+
+    // the smallest box around it
+    pub fn bounds(&self) -> Region {
+        let (mut min, mut max) = ((1f32, 1f32), (0f32, 0f32));
+        for &(x, y) in &self.points {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+        Region::from_corners(min, max)
+    }
+
+    // shoelace formula, in frame units. scale by width * height for pixels
+    pub fn area(&self) -> f32 {
+        let doubled: f32 = self
+            .points
+            .iter()
+            .zip(self.points.iter().cycle().skip(1))
+            .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+            .sum();
+        doubled.abs() / 2.
+    }
+
+    // drops points closer than `spacing` to the last one kept, a freehand stroke
+    // has a point per mouse event and most of them add nothing
+    pub fn thinned(self, spacing: f32) -> Self {
+        let mut points: Vec<(f32, f32)> = Vec::with_capacity(self.points.len());
+        for point in self.points {
+            let far_enough = points.last().is_none_or(|last| {
+                (point.0 - last.0).hypot(point.1 - last.1) >= spacing
+            });
+            if far_enough {
+                points.push(point);
+            }
+        }
+        Self { points }
+    }
+
+    pub fn clamped(mut self) -> Self {
+        for point in &mut self.points {
+            *point = (point.0.clamp(0., 1.), point.1.clamp(0., 1.));
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolygonKeyframe {
+    pub time: Duration,
+    pub polygon: Polygon,
+}
+
+// the outlines of a region tag, sorted by time. outlines can have any number of
+// points, so they aren't interpolated: each stays until the next one
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolygonTrack {
+    keyframes: Vec<PolygonKeyframe>,
+}
+
+impl PolygonTrack {
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    pub fn keyframes(&self) -> &[PolygonKeyframe] {
+        &self.keyframes
+    }
+
+    pub fn span(&self) -> Option<(Duration, Duration)> {
+        Some((self.keyframes.first()?.time, self.keyframes.last()?.time))
+    }
+
+    pub fn set(&mut self, time: Duration, polygon: Polygon) {
+        match self.keyframes.binary_search_by_key(&time, |keyframe| keyframe.time) {
+            Ok(idx) => self.keyframes[idx].polygon = polygon,
+            Err(idx) => self.keyframes.insert(idx, PolygonKeyframe { time, polygon }),
+        }
+    }
+
+    pub fn remove(&mut self, time: Duration) -> bool {
+        match self.keyframes.binary_search_by_key(&time, |keyframe| keyframe.time) {
+            Ok(idx) => {
+                self.keyframes.remove(idx);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn shift(&mut self, shift: impl Fn(Duration) -> Duration) {
+        for keyframe in &mut self.keyframes {
+            keyframe.time = shift(keyframe.time);
+        }
+    }
+
+    pub fn at(&self, time: Duration) -> Option<&Polygon> {
+        let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        let idx = next.saturating_sub(1);
+        self.keyframes.get(idx).map(|keyframe| &keyframe.polygon)
+    }
+}
+
+// how a region shows up on the frame
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outline {
+    Box(Region),
+    Polygon(Polygon),
+    // keypoints joined by the skeleton's edges
+    Pose { pose: Pose, edges: Vec<(usize, usize)> },
+}
+
+// what shift+dragging or shift+clicking on the video draws
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DrawTool {
+    #[default]
+    Box,
+    // shift+click adds points, clicking the first one again or enter closes it
+    Polygon,
+    Freehand,
+    // shift+click places the skeleton's keypoints in order
+    Keypoints,
+}
+
+// an outline the player draws on top of the frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionOverlay {
+    pub outline: Outline,
+    pub color: Color,
+    // the selected tag gets a thicker outline, it is the one drawing edits
+    pub selected: bool,
+}
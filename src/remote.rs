@@ -0,0 +1,181 @@
+use std::{path::PathBuf, time::Duration};
+
+use cushy::{
+    kludgine::image::RgbaImage,
+    value::{Destination, Dynamic, Generation, Source},
+    widget::SharedCallback,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::database::{TagDatabase, TagQuery};
+use crate::tags::Tag;
+use crate::video_player::{ControlCommand, VideoPlayer};
+
+// what external controllers (rpc, osc, midi, gamepads...) can ask the app to do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RemoteCommand {
+    Open(PathBuf),
+    Play,
+    Pause,
+    TogglePlay,
+    Stop,
+    Seek(f64),
+    SeekRelative(f64),
+    SetSpeed(f64),
+    StepForward,
+    StepBackward,
+    // by the file's stream index
+    SetAudioStream(usize),
+    Tag(String),
+    // the nth tag category in the order they are listed in the ui
+    TagCategory(usize),
+}
+
+impl From<ControlCommand> for RemoteCommand {
+    fn from(command: ControlCommand) -> Self {
+        match command {
+            ControlCommand::Play => Self::Play,
+            ControlCommand::Pause => Self::Pause,
+            ControlCommand::Stop => Self::Stop,
+            ControlCommand::Seek(time) => Self::Seek(time.as_secs_f64()),
+            ControlCommand::SeekRelative(seconds) => Self::SeekRelative(seconds),
+            ControlCommand::SetSpeed(speed) => Self::SetSpeed(speed),
+            ControlCommand::StepForward => Self::StepForward,
+            ControlCommand::StepBackward => Self::StepBackward,
+            ControlCommand::SetAudioStream(idx) => Self::SetAudioStream(idx),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Status {
+    pub video: Option<PathBuf>,
+    pub playing: bool,
+    pub position: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Remote {
+    video_source: Dynamic<Option<PathBuf>>,
+    video_player: VideoPlayer,
+    categories: Dynamic<Vec<String>>,
+    tag_current_moment: SharedCallback<String>,
+    database: TagDatabase,
+}
+
+impl Remote {
+    pub(crate) fn new(
+        video_source: Dynamic<Option<PathBuf>>,
+        video_player: VideoPlayer,
+        categories: Dynamic<Vec<String>>,
+        tag_current_moment: SharedCallback<String>,
+        database: TagDatabase,
+    ) -> Self {
+        Self {
+            video_source,
+            video_player,
+            categories,
+            tag_current_moment,
+            database,
+        }
+    }
+
+    pub fn execute(&self, command: RemoteCommand) -> Result<(), String> {
+        let needs_video = !matches!(command, RemoteCommand::Open(_));
+        if needs_video && self.video_source.get().is_none() {
+            return Err(String::from("no video open"));
+        }
+
+        match command {
+            RemoteCommand::Open(path) => {
+                if !path.exists() && !path.to_string_lossy().contains("://") {
+                    return Err(format!("{} does not exist", path.display()));
+                }
+                self.video_source.set(Some(path));
+            }
+            RemoteCommand::Play => {
+                if !self.video_player.playing().get() {
+                    self.video_player.toggle_playing();
+                }
+            }
+            RemoteCommand::Pause => {
+                if self.video_player.playing().get() {
+                    self.video_player.toggle_playing();
+                }
+            }
+            RemoteCommand::TogglePlay => self.video_player.toggle_playing(),
+            RemoteCommand::Stop => self.video_player.send_control_message(ControlCommand::Stop),
+            RemoteCommand::Seek(seconds) => {
+                if seconds.is_nan() {
+                    return Err(String::from("can't seek to nan seconds"));
+                }
+                // before the start is the start
+                let target = Duration::try_from_secs_f64(seconds.max(0.))
+                    .map_err(|_| format!("can't seek to {seconds} seconds"))?;
+                self.video_player.seek(target);
+            }
+            RemoteCommand::SeekRelative(seconds) => {
+                if Duration::try_from_secs_f64(seconds.abs()).is_err() {
+                    return Err(format!("can't seek by {seconds} seconds"));
+                }
+                self.video_player.send_control_message(ControlCommand::SeekRelative(seconds));
+            }
+            RemoteCommand::SetSpeed(speed) => {
+                if !speed.is_finite() || speed <= 0. {
+                    return Err(String::from("speed has to be a positive number"));
+                }
+                self.video_player
+                    .send_control_message(ControlCommand::SetSpeed(speed));
+            }
+            RemoteCommand::StepForward => self.video_player.step_forward(),
+            RemoteCommand::StepBackward => self.video_player.step_backward(),
+            RemoteCommand::SetAudioStream(idx) => self
+                .video_player
+                .send_control_message(ControlCommand::SetAudioStream(idx)),
+            RemoteCommand::Tag(label) => self.tag_current_moment.invoke(label),
+            RemoteCommand::TagCategory(idx) => {
+                let Some(category) = self.categories.map_ref(|categories| categories.get(idx).cloned())
+                else {
+                    return Err(format!("no tag category #{}", idx + 1));
+                };
+                self.tag_current_moment.invoke(category);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn status(&self) -> Status {
+        Status {
+            video: self.video_source.get(),
+            playing: self.video_player.playing().get(),
+            position: self.video_player.position().get().as_secs_f64(),
+        }
+    }
+
+    // answered by the tag storage, it doesn't need the video to be open
+    pub fn tags(&self, query: &TagQuery) -> Result<Vec<Tag>, String> {
+        self.database.query(query).map_err(|err| err.to_string())
+    }
+
+    pub fn position(&self) -> Duration {
+        self.video_player.position().get()
+    }
+
+    pub fn frame_duration(&self) -> Duration {
+        self.video_player.frame_duration().get()
+    }
+
+    // for presenting the player without the ui
+    pub fn displayed_frame(&self) -> Option<RgbaImage> {
+        self.video_player.displayed_frame()
+    }
+
+    pub fn frame_generation(&self) -> Generation {
+        self.video_player.frame_generation()
+    }
+
+    pub fn categories(&self) -> Vec<String> {
+        self.categories.get()
+    }
+}
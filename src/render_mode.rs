@@ -0,0 +1,122 @@
+use std::process::{Command, ExitStatus, Stdio};
+
+use cushy::kludgine::wgpu;
+
+use crate::safe_mode::is_safe_mode;
+
+const SOFTWARE_RENDER_FLAG: &str = "--software-render";
+const PROBE_FLAG: &str = "--probe-gpu";
+const SOFTWARE_RENDER_ENV: &str = "VIDEO_TAGGER_SOFTWARE_RENDER";
+const CPU_WINDOW_FLAG: &str = "--cpu-window";
+const CPU_WINDOW_ENV: &str = "VIDEO_TAGGER_CPU_WINDOW";
+
+// software rasterizers choke on uploading full-size 4k frames, so keep them small
+const SOFTWARE_MAX_FRAME_DIMENSION: u32 = 1280;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    Hardware,
+    // wgpu on a cpu rasterizer
+    Software,
+    // no wgpu at all, the player alone in a window the cpu fills
+    Cpu,
+}
+
+impl RenderMode {
+    pub fn from_env() -> Self {
+        if is_set(CPU_WINDOW_FLAG, CPU_WINDOW_ENV) {
+            if cfg!(feature = "cpu-window") {
+                return Self::Cpu;
+            }
+            tracing::warn!("built without the cpu-window feature, using software rendering");
+            return Self::Software;
+        }
+
+        if is_set(SOFTWARE_RENDER_FLAG, SOFTWARE_RENDER_ENV) || is_safe_mode() {
+            Self::Software
+        } else {
+            Self::Hardware
+        }
+    }
+
+    // a gpu driver that's there but broken can panic or crash the process while wgpu
+    // looks at it, which no error handling here would see. so a child process looks
+    // first, and when it doesn't come back fine this one renders in software
+    pub fn probed(self) -> Self {
+        if self != Self::Hardware {
+            return self;
+        }
+        let Ok(exe) = std::env::current_exe() else {
+            return self;
+        };
+        match Command::new(exe)
+            .arg(PROBE_FLAG)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+        {
+            Ok(status) if !status.success() => {
+                tracing::warn!("no working gpu found, using software rendering");
+                Self::Software
+            }
+            // not being able to start the probe says nothing about the gpu
+            _ => self,
+        }
+    }
+
+    // has to run before the window (and with it the wgpu instance) is created
+    pub fn apply(self) {
+        if self == Self::Software {
+            // GL through mesa's llvmpipe is the most widely available cpu rasterizer,
+            // it works over remote desktops and in VMs without any gpu passthrough
+            std::env::set_var("WGPU_BACKEND", "gl");
+            std::env::set_var("WGPU_POWER_PREF", "low");
+            std::env::set_var("LIBGL_ALWAYS_SOFTWARE", "1");
+        }
+    }
+
+    pub fn max_frame_dimension(self) -> Option<u32> {
+        match self {
+            Self::Hardware => None,
+            // every frame is converted to rgb on the cpu before it's shown
+            Self::Software | Self::Cpu => Some(SOFTWARE_MAX_FRAME_DIMENSION),
+        }
+    }
+}
+
+fn is_set(flag: &str, env: &str) -> bool {
+    std::env::args().any(|arg| arg == flag) || std::env::var(env).is_ok_and(|value| value != "0")
+}
+
+// the child `probed` starts, exits right away telling whether wgpu found an adapter.
+// called first thing in main, it does nothing for any other start
+pub fn probe_gpu_from_args() {
+    if !std::env::args().any(|arg| arg == PROBE_FLAG) {
+        return;
+    }
+    let instance = wgpu::Instance::default();
+    let found = !instance.enumerate_adapters(wgpu::Backends::all()).is_empty();
+    std::process::exit(if found { 0 } else { 1 });
+}
+
+// an event loop can only be created once per process, so when gpu init fails
+// the only way to retry is starting over in a fresh process
+pub fn relaunch_in_software_mode() -> std::io::Result<ExitStatus> {
+    relaunch(SOFTWARE_RENDER_FLAG, SOFTWARE_RENDER_ENV)
+}
+
+// for when not even a cpu rasterizer gives wgpu a window
+#[cfg(feature = "cpu-window")]
+pub fn relaunch_with_cpu_window() -> std::io::Result<ExitStatus> {
+    relaunch(CPU_WINDOW_FLAG, CPU_WINDOW_ENV)
+}
+
+fn relaunch(flag: &str, env: &str) -> std::io::Result<ExitStatus> {
+    let exe = std::env::current_exe()?;
+    let args = std::env::args()
+        .skip(1)
+        .filter(|arg| arg != SOFTWARE_RENDER_FLAG && arg != CPU_WINDOW_FLAG);
+
+    Command::new(exe).args(args).arg(flag).env(env, "1").status()
+}
@@ -0,0 +1,121 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use ffmpeg_next as ffmpeg;
+
+use crate::media::open_input;
+
+// a proxy plays the same footage as its original, but its timestamps can start
+// somewhere else. tags always live on the original's timeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rendition {
+    pub original: PathBuf,
+    // seconds added to a proxy timestamp to land on the original's timeline
+    pub offset: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Renditions {
+    proxies: HashMap<PathBuf, Rendition>,
+}
+
+impl Renditions {
+    pub fn link(&mut self, proxy: PathBuf, original: PathBuf, offset: f64) {
+        self.proxies.insert(proxy, Rendition { original, offset });
+    }
+
+    pub fn unlink(&mut self, proxy: &Path) {
+        self.proxies.remove(proxy);
+    }
+
+    // proxies of an original that moved follow it
+    pub fn retarget(&mut self, from: &Path, to: &Path) {
+        for rendition in self.proxies.values_mut().filter(|rendition| rendition.original == from) {
+            rendition.original = to.to_path_buf();
+        }
+    }
+
+    pub fn rendition(&self, path: &Path) -> Option<&Rendition> {
+        self.proxies.get(path)
+    }
+
+    pub fn original_of(&self, path: &Path) -> PathBuf {
+        self.proxies
+            .get(path)
+            .map_or_else(|| path.to_path_buf(), |rendition| rendition.original.clone())
+    }
+
+    pub fn to_original(&self, path: &Path, time: Duration) -> Duration {
+        match self.proxies.get(path) {
+            Some(rendition) => shift(time, rendition.offset),
+            None => time,
+        }
+    }
+
+    pub fn from_original(&self, path: &Path, time: Duration) -> Duration {
+        match self.proxies.get(path) {
+            Some(rendition) => shift(time, -rendition.offset),
+            None => time,
+        }
+    }
+}
+
+fn shift(time: Duration, seconds: f64) -> Duration {
+    Duration::from_secs_f64((time.as_secs_f64() + seconds).max(0.))
+}
+
+// start of the best video stream in seconds, what the player reports as position zero
+pub fn probe_start_time(path: &Path) -> Option<f64> {
+    let ictx = open_input(path).ok()?;
+    let stream = ictx.streams().best(ffmpeg::media::Type::Video)?;
+
+    let start_time = stream.start_time();
+    if start_time == ffmpeg::ffi::AV_NOPTS_VALUE {
+        return Some(0.);
+    }
+
+    let time_base = stream.time_base();
+    Some(start_time as f64 * time_base.numerator() as f64 / time_base.denominator() as f64)
+}
+
+// offset that maps the proxy's timeline onto the original's
+pub fn probe_offset(proxy: &Path, original: &Path) -> Option<f64> {
+    Some(probe_start_time(original)? - probe_start_time(proxy)?)
+}
+
+// container duration, for files that don't report one the last tag is the end
+pub fn probe_duration(path: &Path) -> Option<Duration> {
+    let ictx = open_input(path).ok()?;
+    let duration = ictx.duration();
+    if duration <= 0 {
+        return None;
+    }
+
+    Some(Duration::from_secs_f64(
+        duration as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE),
+    ))
+}
+
+// average frame rate of the best video stream, for formats that count in frames
+pub fn probe_frame_rate(path: &Path) -> Option<f64> {
+    let ictx = open_input(path).ok()?;
+    let stream = ictx.streams().best(ffmpeg::media::Type::Video)?;
+    let rate = stream.avg_frame_rate();
+    (rate.numerator() > 0 && rate.denominator() > 0)
+        .then(|| f64::from(rate.numerator()) / f64::from(rate.denominator()))
+}
+
+// width and height of the best video stream's frames
+pub fn probe_frame_size(path: &Path) -> Option<(u32, u32)> {
+    let ictx = open_input(path).ok()?;
+    let stream = ictx.streams().best(ffmpeg::media::Type::Video)?;
+    let decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+        .ok()?
+        .decoder()
+        .video()
+        .ok()?;
+    (decoder.width() > 0 && decoder.height() > 0).then(|| (decoder.width(), decoder.height()))
+}
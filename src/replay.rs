@@ -0,0 +1,139 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::remote::{Remote, RemoteCommand};
+
+const RECORD_FLAG: &str = "--record";
+const REPLAY_FLAG: &str = "--replay";
+// positions further apart than this during replay point at the bug being chased
+const POSITION_TOLERANCE: f64 = 0.25;
+
+// one line of a script
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Step {
+    // seconds since recording started
+    at: f64,
+    // where playback was when the action happened
+    position: f64,
+    command: RemoteCommand,
+}
+
+fn path_after(flag: &str) -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next().map(PathBuf::from);
+        }
+    }
+    None
+}
+
+// writes every open, transport command and tag as json lines, replayable with `--replay`
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    started: Instant,
+    writer: Arc<Mutex<BufWriter<File>>>,
+}
+
+impl Recorder {
+    // `--record <script>`
+    pub fn from_args() -> Option<Self> {
+        let path = path_after(RECORD_FLAG)?;
+        match File::create(&path) {
+            Ok(file) => Some(Self {
+                started: Instant::now(),
+                writer: Arc::new(Mutex::new(BufWriter::new(file))),
+            }),
+            Err(err) => {
+                tracing::error!("failed to create {}: {err}", path.display());
+                None
+            }
+        }
+    }
+
+    pub fn record(&self, command: RemoteCommand, position: Duration) {
+        let step = Step {
+            at: self.started.elapsed().as_secs_f64(),
+            position: position.as_secs_f64(),
+            command,
+        };
+
+        // flushed every time, the point is having the script when the app crashes
+        let mut writer = self.writer.lock().unwrap();
+        let written = serde_json::to_writer(&mut *writer, &step)
+            .map_err(std::io::Error::from)
+            .and_then(|()| writer.write_all(b"\n"))
+            .and_then(|()| writer.flush());
+        if let Err(err) = written {
+            tracing::error!("failed to record {:?}: {err}", step.command);
+        }
+    }
+}
+
+// `--replay <script>`
+pub fn script_from_args() -> Option<PathBuf> {
+    path_after(REPLAY_FLAG)
+}
+
+// runs a recorded script against the app with the original timing, reporting where
+// playback ends up somewhere else than it did while recording
+pub fn replay(remote: Remote, script: PathBuf) -> std::io::Result<()> {
+    let file = File::open(&script)?;
+
+    std::thread::Builder::new()
+        .name("Replay Thread".into())
+        .spawn(move || {
+            let started = Instant::now();
+            for (line, text) in BufReader::new(file).lines().map_while(Result::ok).enumerate() {
+                let step: Step = match serde_json::from_str(&text) {
+                    Ok(step) => step,
+                    Err(err) => {
+                        tracing::warn!("{}:{}: {err}", script.display(), line + 1);
+                        continue;
+                    }
+                };
+
+                let due = Duration::try_from_secs_f64(step.at)
+                    .ok()
+                    .and_then(|at| started.checked_add(at));
+                let Some(due) = due else {
+                    tracing::warn!(
+                        "{}:{}: {} isn't a time in the recording",
+                        script.display(),
+                        line + 1,
+                        step.at
+                    );
+                    continue;
+                };
+                std::thread::sleep(due.saturating_duration_since(Instant::now()));
+
+                // opening resets the position, there is nothing to compare yet
+                if !matches!(step.command, RemoteCommand::Open(_)) {
+                    let position = remote.position().as_secs_f64();
+                    if (position - step.position).abs() > POSITION_TOLERANCE {
+                        tracing::warn!(
+                            "{}:{}: position is {position:.3}s, was {:.3}s when recorded",
+                            script.display(),
+                            line + 1,
+                            step.position
+                        );
+                    }
+                }
+
+                if let Err(err) = remote.execute(step.command) {
+                    tracing::warn!("{}:{}: {err}", script.display(), line + 1);
+                }
+            }
+            tracing::info!("replay of {} finished", script.display());
+        })
+        .unwrap();
+
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use std::{path::PathBuf, time::Duration};
+
+use cushy::{
+    value::{Destination, Dynamic, Source, Switchable},
+    widget::{MakeWidget, WidgetList},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::format_timestamp;
+use crate::renditions::Renditions;
+use crate::safe_mode::is_safe_mode;
+use crate::settings::config_dir;
+use crate::video_player::VideoPlayer;
+
+const REVIEW_FILE: &str = "review_later.json";
+
+// a moment to come back to, whichever project it was found in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewItem {
+    // originals, like tags
+    pub video: PathBuf,
+    pub time: Duration,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+}
+
+impl ReviewItem {
+    fn describe(&self) -> String {
+        let name = self.video.file_name().unwrap_or_default().to_string_lossy();
+        let time = format_timestamp(self.time);
+        if self.note.is_empty() {
+            format!("{name} at {time}")
+        } else {
+            format!("{name} at {time}: {}", self.note)
+        }
+    }
+}
+
+// kept next to the settings rather than in the tags, so it's the same list whatever
+// project is open
+pub fn load_review_queue() -> Vec<ReviewItem> {
+    config_dir()
+        .map(|dir| dir.join(REVIEW_FILE))
+        .and_then(|path| std::fs::read(path).ok())
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn save_review_queue(items: &[ReviewItem]) -> std::io::Result<()> {
+    let Some(dir) = config_dir().filter(|_| !is_safe_mode()) else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(&dir)?;
+
+    let json = serde_json::to_vec_pretty(items)?;
+    std::fs::write(dir.join(REVIEW_FILE), json)
+}
+
+// changes the list as it is on disk, not as this window last saw it, so another window
+// adding to it meanwhile doesn't lose anything
+fn update(
+    items: &Dynamic<Vec<ReviewItem>>,
+    announcement: &Dynamic<String>,
+    change: impl FnOnce(&mut Vec<ReviewItem>),
+) {
+    let mut latest = load_review_queue();
+    change(&mut latest);
+    if let Err(err) = save_review_queue(&latest) {
+        announcement.set(format!("failed to save the review list: {err}"));
+    }
+    *items.lock() = latest;
+}
+
+// pushes the moment on screen, with a note, and goes through the list later: "go" opens
+// the video there, "done" takes it off
+pub fn review_later_panel(
+    items: Dynamic<Vec<ReviewItem>>,
+    video_source: Dynamic<Option<PathBuf>>,
+    renditions: Dynamic<Renditions>,
+    video_player: VideoPlayer,
+    announcement: Dynamic<String>,
+) -> impl MakeWidget {
+    let note = Dynamic::new(String::new());
+
+    let add = "review later".into_button().on_click({
+        let items = items.clone();
+        let note = note.clone();
+        let video_source = video_source.clone();
+        let video_player = video_player.clone();
+        let announcement = announcement.clone();
+        move |_| {
+            let Some(video) = video_source.get() else {
+                announcement.set(String::from("open a video first"));
+                return;
+            };
+            let position = video_player.position().get();
+            let item = renditions.map_ref(|renditions| ReviewItem {
+                video: renditions.original_of(&video),
+                time: renditions.to_original(&video, position),
+                note: note.get().trim().to_string(),
+            });
+            announcement.set(format!("to review later: {}", item.describe()));
+            update(&items, &announcement, |latest| latest.push(item));
+            note.set(String::new());
+        }
+    });
+
+    let reload = "reload".into_button().on_click({
+        let items = items.clone();
+        move |_| *items.lock() = load_review_queue()
+    });
+
+    let rows = items.clone().switcher(move |list, _| {
+        if list.is_empty() {
+            return "nothing to review".make_widget();
+        }
+
+        list.iter()
+            .map(|item| {
+                let go = "go".into_button().on_click({
+                    let video_source = video_source.clone();
+                    let video_player = video_player.clone();
+                    let item = item.clone();
+                    move |_| {
+                        // starts the new pipeline, the seek goes to it
+                        if video_source.get().as_ref() != Some(&item.video) {
+                            video_source.set(Some(item.video.clone()));
+                        }
+                        video_player.seek(item.time);
+                    }
+                });
+                let done = "done".into_button().on_click({
+                    let items = items.clone();
+                    let announcement = announcement.clone();
+                    let item = item.clone();
+                    move |_| {
+                        update(&items, &announcement, |latest| {
+                            if let Some(idx) = latest.iter().position(|other| *other == item) {
+                                latest.remove(idx);
+                            }
+                        });
+                    }
+                });
+
+                item.describe()
+                    .expand_horizontally()
+                    .and(go)
+                    .and(done)
+                    .into_columns()
+                    .make_widget()
+            })
+            .collect::<WidgetList>()
+            .into_rows()
+            .make_widget()
+    });
+
+    "Review later"
+        .and(
+            note.into_input()
+                .placeholder("what to look at")
+                .expand_horizontally()
+                .and(add)
+                .and(reload)
+                .into_columns(),
+        )
+        .and(rows.vertical_scroll())
+        .into_rows()
+}
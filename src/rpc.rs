@@ -0,0 +1,216 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+
+use crate::database::TagQuery;
+use crate::remote::{Remote, RemoteCommand};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+const RPC_STDIO_FLAG: &str = "--rpc";
+const RPC_PORT_FLAG: &str = "--rpc-port";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcTransport {
+    Stdio,
+    Tcp(u16),
+}
+
+impl RpcTransport {
+    // `--rpc` for stdio, `--rpc-port <port>` for a localhost socket
+    pub fn from_args() -> Option<Self> {
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == RPC_STDIO_FLAG {
+                return Some(Self::Stdio);
+            }
+            if arg == RPC_PORT_FLAG {
+                return args.next()?.parse().ok().map(Self::Tcp);
+            }
+        }
+        None
+    }
+
+    pub fn serve(self, remote: Remote) -> std::io::Result<()> {
+        match self {
+            Self::Stdio => {
+                serve_stdio(remote);
+                Ok(())
+            }
+            Self::Tcp(port) => serve_tcp(remote, port),
+        }
+    }
+}
+
+// newline delimited json-rpc 2.0 on stdin/stdout, for driving the app from
+// test harnesses and other tools
+pub fn serve_stdio(remote: Remote) {
+    std::thread::Builder::new()
+        .name("RPC Stdio Thread".into())
+        .spawn(move || {
+            let stdin = std::io::stdin();
+            let mut stdout = std::io::stdout();
+            serve(&remote, stdin.lock(), &mut stdout);
+        })
+        .unwrap();
+}
+
+// same protocol on a localhost tcp port, one connection per client
+pub fn serve_tcp(remote: Remote, port: u16) -> std::io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+
+    std::thread::Builder::new()
+        .name("RPC Listener Thread".into())
+        .spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                let remote = remote.clone();
+                std::thread::Builder::new()
+                    .name("RPC Connection Thread".into())
+                    .spawn(move || serve_connection(&remote, stream))
+                    .unwrap();
+            }
+        })
+        .unwrap();
+
+    Ok(())
+}
+
+fn serve_connection(remote: &Remote, stream: TcpStream) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    serve(remote, BufReader::new(stream), &mut writer);
+}
+
+fn serve(remote: &Remote, reader: impl BufRead, writer: &mut impl Write) {
+    for line in reader.lines().map_while(Result::ok) {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let Some(response) = handle_line(remote, &line) else {
+            continue;
+        };
+        if writeln!(writer, "{response}").and_then(|_| writer.flush()).is_err() {
+            break;
+        }
+    }
+}
+
+// notifications (requests without an id) get no response
+fn handle_line(remote: &Remote, line: &str) -> Option<Value> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(err) => return Some(error(Value::Null, PARSE_ERROR, err.to_string())),
+    };
+
+    let id = request.get("id").cloned();
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return Some(error(
+            id.unwrap_or(Value::Null),
+            INVALID_REQUEST,
+            "missing method",
+        ));
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = dispatch(remote, method, &params);
+    let id = id?;
+
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error(id, code, message),
+    })
+}
+
+fn dispatch(remote: &Remote, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+    let command = match method {
+        "status" => return Ok(json!(remote.status())),
+        "tags" => {
+            return remote
+                .tags(&tag_query(params)?)
+                .map(|tags| json!(tags))
+                .map_err(|err| (SERVER_ERROR, err))
+        }
+        "open" => RemoteCommand::Open(PathBuf::from(string_param(params, "path")?)),
+        "play" => RemoteCommand::Play,
+        "pause" => RemoteCommand::Pause,
+        "toggle" => RemoteCommand::TogglePlay,
+        "stop" => RemoteCommand::Stop,
+        "seek" => RemoteCommand::Seek(number_param(params, "seconds")?),
+        "seek_relative" => RemoteCommand::SeekRelative(number_param(params, "seconds")?),
+        "speed" => RemoteCommand::SetSpeed(number_param(params, "speed")?),
+        "step_forward" => RemoteCommand::StepForward,
+        "step_backward" => RemoteCommand::StepBackward,
+        "add_tag" => RemoteCommand::Tag(string_param(params, "label")?),
+        _ => return Err((METHOD_NOT_FOUND, format!("unknown method {method}"))),
+    };
+
+    remote
+        .execute(command)
+        .map(|()| json!(remote.status()))
+        .map_err(|err| (SERVER_ERROR, err))
+}
+
+fn string_param(params: &Value, name: &str) -> Result<String, (i64, String)> {
+    params
+        .get(name)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| (INVALID_PARAMS, format!("missing string param {name}")))
+}
+
+fn number_param(params: &Value, name: &str) -> Result<f64, (i64, String)> {
+    params
+        .get(name)
+        .and_then(Value::as_f64)
+        .ok_or_else(|| (INVALID_PARAMS, format!("missing number param {name}")))
+}
+
+// every param is optional, times are in seconds
+fn tag_query(params: &Value) -> Result<TagQuery, (i64, String)> {
+    let seconds = |name: &str| match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(_) => number_param(params, name).and_then(|seconds| {
+            Duration::try_from_secs_f64(seconds.max(0.))
+                .map(Some)
+                .map_err(|_| (INVALID_PARAMS, format!("{name} is out of range")))
+        }),
+    };
+    let string = |name: &str| match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(_) => string_param(params, name).map(Some),
+    };
+    Ok(TagQuery {
+        video: string("video")?.map(PathBuf::from),
+        label: string("label")?,
+        from: seconds("from")?,
+        to: seconds("to")?,
+        limit: match params.get("limit") {
+            None | Some(Value::Null) => None,
+            Some(limit) => Some(
+                limit
+                    .as_u64()
+                    .and_then(|limit| usize::try_from(limit).ok())
+                    .ok_or_else(|| (INVALID_PARAMS, String::from("limit has to be a count")))?,
+            ),
+        },
+    })
+}
+
+fn error(id: Value, code: i64, message: impl Into<String>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message.into() },
+    })
+}
@@ -0,0 +1,13 @@
+use std::sync::OnceLock;
+
+const SAFE_MODE_FLAG: &str = "--safe-mode";
+
+static SAFE_MODE: OnceLock<bool> = OnceLock::new();
+
+// `--safe-mode` gets users with broken configs or gpu drivers back into the app:
+// software rendering and decoding, default settings that are never written back
+// over their real ones, no caches and no controller input. tags and activity
+// history still load normally
+pub fn is_safe_mode() -> bool {
+    *SAFE_MODE.get_or_init(|| std::env::args().any(|arg| arg == SAFE_MODE_FLAG))
+}
@@ -0,0 +1,123 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use cushy::figures::Size;
+use cushy::widgets::image::{Aspect, ImageScaling};
+use serde::{Deserialize, Serialize};
+
+use crate::appearance::Appearance;
+use crate::backups::BackupSettings;
+use crate::export::ExportProfile;
+use crate::palette::Palette;
+use crate::qc_rules::QcRules;
+use crate::safe_mode::is_safe_mode;
+use crate::skeletons::Skeleton;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ScalingMode {
+    #[default]
+    Fit,
+    Fill,
+    Stretch,
+    Scale(f32),
+}
+
+impl From<ScalingMode> for ImageScaling {
+    fn from(mode: ScalingMode) -> Self {
+        match mode {
+            ScalingMode::Fit => ImageScaling::Aspect {
+                mode: Aspect::Fit,
+                orientation: Size::ZERO,
+            },
+            ScalingMode::Fill => ImageScaling::Aspect {
+                mode: Aspect::Fill,
+                orientation: Size::ZERO,
+            },
+            ScalingMode::Stretch => ImageScaling::Stretch,
+            ScalingMode::Scale(factor) => ImageScaling::Scale(factor),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub default_scaling: ScalingMode,
+    #[serde(default)]
+    pub video_scaling: HashMap<PathBuf, ScalingMode>,
+    #[serde(default)]
+    pub tag_categories: Vec<String>,
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+    #[serde(default)]
+    pub tablet_layout: bool,
+    // decode on the gpu, the video toolbox, vaapi or d3d11va. falls back to software
+    #[serde(default)]
+    pub hardware_decoding: bool,
+    #[serde(default)]
+    pub palette: Palette,
+    #[serde(default)]
+    pub appearance: Appearance,
+    #[serde(default)]
+    pub backups: BackupSettings,
+    // keypoint templates besides the built in ones
+    #[serde(default)]
+    pub skeletons: Vec<Skeleton>,
+    #[serde(default)]
+    pub qc_rules: QcRules,
+    // empty until one is changed, the built in ones are used till then
+    #[serde(default)]
+    pub export_profiles: Vec<ExportProfile>,
+    // the shared folder export workers on other machines pick jobs up from
+    #[serde(default)]
+    pub export_queue: Option<PathBuf>,
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        if is_safe_mode() {
+            return Self::default();
+        }
+
+        config_dir()
+            .map(|dir| dir.join(SETTINGS_FILE))
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(dir) = config_dir().filter(|_| !is_safe_mode()) else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(&dir)?;
+
+        let json = serde_json::to_vec_pretty(self)?;
+        std::fs::write(dir.join(SETTINGS_FILE), json)
+    }
+
+    pub fn scaling_for(&self, video: &Path) -> ScalingMode {
+        self.video_scaling
+            .get(video)
+            .copied()
+            .unwrap_or(self.default_scaling)
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    base.map(|base| base.join("video-tagger"))
+}
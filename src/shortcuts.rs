@@ -0,0 +1,128 @@
+use std::fmt;
+
+use cushy::context::EventContext;
+use cushy::kludgine::app::winit::event::DeviceId;
+use cushy::kludgine::app::winit::keyboard::{Key, ModifiersState, NamedKey};
+use cushy::widget::{EventHandling, MakeWidget, WidgetRef, WrapperWidget, IGNORED};
+use cushy::window::KeyEvent;
+
+// what a shortcut asks the app to do, whoever handles it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    TogglePlay,
+    SeekBackward,
+    SeekForward,
+    StepBackward,
+    StepForward,
+    // to where the open video's chapters start
+    PreviousChapter,
+    NextChapter,
+    // tags the current moment with the nth category, in the order they are listed
+    QuickTag(usize),
+    // tag changes
+    Undo,
+    Redo,
+}
+
+impl Action {
+    // the keys nothing closer to the focused widget wanted. other modifier combinations
+    // belong to tag nudging and bound categories
+    pub fn for_key(key: &Key, modifiers: ModifiersState) -> Option<Self> {
+        if modifiers.control_key() && !modifiers.alt_key() && !modifiers.super_key() {
+            return match key {
+                Key::Character(text) if text.eq_ignore_ascii_case("z") => {
+                    Some(if modifiers.shift_key() { Self::Redo } else { Self::Undo })
+                }
+                _ => None,
+            };
+        }
+        if modifiers.control_key() || modifiers.alt_key() || modifiers.super_key() {
+            return None;
+        }
+
+        match key {
+            Key::Named(NamedKey::Space) => Some(Self::TogglePlay),
+            Key::Named(NamedKey::ArrowLeft) => Some(Self::SeekBackward),
+            Key::Named(NamedKey::ArrowRight) => Some(Self::SeekForward),
+            Key::Named(NamedKey::PageUp) => Some(Self::PreviousChapter),
+            Key::Named(NamedKey::PageDown) => Some(Self::NextChapter),
+            Key::Character(text) => match text.as_str() {
+                "," => Some(Self::StepBackward),
+                "." => Some(Self::StepForward),
+                digit => match digit.parse::<usize>() {
+                    Ok(number @ 1..=9) => Some(Self::QuickTag(number - 1)),
+                    _ => None,
+                },
+            },
+            _ => None,
+        }
+    }
+}
+
+type Handler = Box<dyn FnMut(Action) -> EventHandling + Send>;
+
+// sits at the root, outside every other key handler, and hands the actions of keys
+// that got that far to the handlers features registered, in order, until one of them
+// handles it
+pub struct Shortcuts {
+    child: WidgetRef,
+    handlers: Vec<Handler>,
+}
+
+impl fmt::Debug for Shortcuts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shortcuts")
+            .field("child", &self.child)
+            .field("handlers", &self.handlers.len())
+            .finish()
+    }
+}
+
+impl Shortcuts {
+    pub fn new(child: impl MakeWidget) -> Self {
+        Self {
+            child: WidgetRef::new(child),
+            handlers: Vec::new(),
+        }
+    }
+
+    // `handler` returns IGNORED for actions it doesn't take care of
+    pub fn on_action(
+        mut self,
+        handler: impl FnMut(Action) -> EventHandling + Send + 'static,
+    ) -> Self {
+        self.handlers.push(Box::new(handler));
+        self
+    }
+}
+
+impl WrapperWidget for Shortcuts {
+    fn child_mut(&mut self) -> &mut WidgetRef {
+        &mut self.child
+    }
+
+    fn keyboard_input(
+        &mut self,
+        device_id: DeviceId,
+        input: KeyEvent,
+        is_synthetic: bool,
+        context: &mut EventContext<'_>,
+    ) -> EventHandling {
+        if !input.state.is_pressed() {
+            return IGNORED;
+        }
+        let Some(action) = Action::for_key(&input.logical_key, context.modifiers().state()) else {
+            return IGNORED;
+        };
+        // holding a seek key keeps seeking, everything else happens once per press
+        if input.repeat && !matches!(action, Action::SeekBackward | Action::SeekForward) {
+            return IGNORED;
+        }
+
+        self.handlers
+            .iter_mut()
+            .map(|handler| handler(action))
+            .find(|handled| handled.is_break())
+            .unwrap_or(IGNORED)
+    }
+}
@@ -0,0 +1,524 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use cushy::{
+    value::{Destination, Dynamic, MapEach, Source, Switchable},
+    widget::{MakeWidget, WidgetList},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::formats::QueueEntry;
+use crate::format_timestamp;
+use crate::database::TagDatabase;
+use crate::queue::library;
+#[cfg(feature = "onnx")]
+use crate::media::extract_frames;
+#[cfg(feature = "onnx")]
+use crate::onnx::{ImageModel, TextModel, CLIP};
+use crate::renditions::{probe_duration, Renditions};
+use crate::safe_mode::is_safe_mode;
+use crate::settings::config_dir;
+use crate::tags::{TagId, TagStore};
+use crate::video_player::VideoPlayer;
+
+const EMBEDDINGS_FILE: &str = "embeddings.json";
+const RESULT_COUNT: usize = 20;
+// what text encoders read their vocabulary from, next to the model
+#[cfg(feature = "onnx")]
+const TOKENIZER_FILE: &str = "tokenizer.json";
+// matches this close to the query on its own video, or to a better match, are the
+// same moment
+const SAME_MOMENT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameEmbedding {
+    pub video: PathBuf,
+    pub time: Duration,
+    // unit length, so the dot product of two is their cosine similarity
+    pub vector: Vec<f32>,
+}
+
+// embeddings of sampled frames across the library, all from the same model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingIndex {
+    pub model: Option<PathBuf>,
+    pub frames: Vec<FrameEmbedding>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub video: PathBuf,
+    pub time: Duration,
+    pub similarity: f32,
+}
+
+impl EmbeddingIndex {
+    pub fn load() -> Self {
+        config_dir()
+            .map(|dir| dir.join(EMBEDDINGS_FILE))
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(dir) = config_dir().filter(|_| !is_safe_mode()) else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(&dir)?;
+
+        let json = serde_json::to_vec(self)?;
+        std::fs::write(dir.join(EMBEDDINGS_FILE), json)
+    }
+
+    pub fn contains(&self, video: &Path) -> bool {
+        self.frames.iter().any(|frame| frame.video == video)
+    }
+
+    // vectors of different models can't be compared, switching starts over
+    pub fn use_model(&mut self, model: &Path) {
+        if self.model.as_deref() != Some(model) {
+            self.model = Some(model.to_path_buf());
+            self.frames.clear();
+        }
+    }
+
+    // the moments most like `query`, best first, leaving out the moment the query
+    // came from, if it came from one, and repeats of one shot
+    pub fn nearest(
+        &self,
+        query: &[f32],
+        from: Option<(&Path, Duration)>,
+        count: usize,
+    ) -> Vec<Match> {
+        let mut scored = self
+            .frames
+            .iter()
+            .filter(|frame| frame.vector.len() == query.len())
+            .filter(|frame| {
+                from.is_none_or(|(video, time)| {
+                    frame.video != video || frame.time.abs_diff(time) > SAME_MOMENT
+                })
+            })
+            .map(|frame| Match {
+                video: frame.video.clone(),
+                time: frame.time,
+                similarity: dot(&frame.vector, query),
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+
+        let mut matches = Vec::<Match>::new();
+        for candidate in scored {
+            if matches.len() == count {
+                break;
+            }
+            let repeat = matches.iter().any(|other| {
+                other.video == candidate.video
+                    && other.time.abs_diff(candidate.time) <= SAME_MOMENT
+            });
+            if !repeat {
+                matches.push(candidate);
+            }
+        }
+        matches
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+#[cfg(feature = "onnx")]
+fn normalized(mut vector: Vec<f32>) -> Vec<f32> {
+    let length = dot(&vector, &vector).sqrt();
+    if length > 0. {
+        vector.iter_mut().for_each(|value| *value /= length);
+    }
+    vector
+}
+
+// runs the image encoder at `model` on the frames of `video` at `times`
+#[cfg(feature = "onnx")]
+pub fn embed_frames(
+    model: &Path,
+    video: &Path,
+    times: &[Duration],
+    mut progress: impl FnMut(usize),
+) -> Result<Vec<(Duration, Vec<f32>)>, String> {
+    let model = ImageModel::load(model, CLIP)?;
+
+    let mut embeddings = Vec::with_capacity(times.len());
+    let mut failed = None;
+    extract_frames(video, times, |time, image| {
+        if failed.is_some() {
+            return;
+        }
+        match model.run(&image) {
+            Ok(vector) => embeddings.push((time, normalized(vector))),
+            Err(err) => failed = Some(err),
+        }
+        progress(embeddings.len());
+    })
+    .map_err(|err| format!("failed to extract frames: {err}"))?;
+    match failed {
+        Some(err) => Err(err),
+        None => Ok(embeddings),
+    }
+}
+
+#[cfg(not(feature = "onnx"))]
+pub fn embed_frames(
+    _model: &Path,
+    _video: &Path,
+    _times: &[Duration],
+    _progress: impl FnMut(usize),
+) -> Result<Vec<(Duration, Vec<f32>)>, String> {
+    Err(String::from("this build can't run models, rebuild with `--features onnx`"))
+}
+
+// runs the text encoder at `model` on `prompt`, with the tokenizer.json next to it
+#[cfg(feature = "onnx")]
+pub fn embed_text(model: &Path, prompt: &str) -> Result<Vec<f32>, String> {
+    let tokenizer = model.with_file_name(TOKENIZER_FILE);
+    let model = TextModel::load(model, &tokenizer)?;
+    model.run(prompt).map(normalized)
+}
+
+#[cfg(not(feature = "onnx"))]
+pub fn embed_text(_model: &Path, _prompt: &str) -> Result<Vec<f32>, String> {
+    Err(String::from("this build can't run models, rebuild with `--features onnx`"))
+}
+
+// everything a search needs, cloned into its worker thread
+#[derive(Debug, Clone)]
+struct Search {
+    // loaded on first use, it can get big
+    index: Arc<Mutex<Option<EmbeddingIndex>>>,
+    model: Dynamic<Option<PathBuf>>,
+    // the image encoder's other half, for text prompts
+    text_model: Dynamic<Option<PathBuf>>,
+    prompt: Dynamic<String>,
+    interval: Dynamic<String>,
+    tags: Dynamic<TagStore>,
+    database: TagDatabase,
+    queue: Dynamic<Vec<QueueEntry>>,
+    selected_tag: Dynamic<Option<TagId>>,
+    video_source: Dynamic<Option<PathBuf>>,
+    renditions: Dynamic<Renditions>,
+    player: VideoPlayer,
+    results: Dynamic<Vec<Match>>,
+    status: Dynamic<String>,
+}
+
+impl Search {
+    fn with_index<R>(&self, work: impl FnOnce(&mut EmbeddingIndex) -> R) -> R {
+        let mut index = self.index.lock().unwrap();
+        work(index.get_or_insert_with(EmbeddingIndex::load))
+    }
+
+
+    fn spawn(&self, work: impl FnOnce(&Self, PathBuf) + Send + 'static) {
+        let Some(model) = self.model.get() else {
+            self.status.set(String::from("choose a model first"));
+            return;
+        };
+        let this = self.clone();
+        std::thread::Builder::new()
+            .name("Similarity Thread".into())
+            .spawn(move || work(&this, model))
+            .unwrap();
+    }
+
+    // embeds a frame every interval of every library video not indexed yet
+    fn index_library(&self) {
+        let every = match self.interval.get().trim().parse::<f64>() {
+            Ok(every) if every > 0. => every,
+            _ => {
+                self.status.set(String::from("invalid interval, use seconds e.g. 2"));
+                return;
+            }
+        };
+        self.spawn(move |this, model| {
+            this.with_index(|index| index.use_model(&model));
+            let videos = library(&this.database, &this.tags, &this.queue);
+            for (idx, video) in videos.iter().enumerate() {
+                if this.with_index(|index| index.contains(video)) {
+                    continue;
+                }
+                let duration = probe_duration(video).unwrap_or_default();
+                let times = (0..)
+                    .map(|idx| Duration::from_secs_f64(idx as f64 * every))
+                    .take_while(|time| *time < duration)
+                    .collect::<Vec<_>>();
+                let name = video.file_name().unwrap_or_default().to_string_lossy();
+                let embedded = embed_frames(&model, video, &times, |done| {
+                    this.status.set(format!(
+                        "video {} of {}, {name}: frame {done} of {}",
+                        idx + 1,
+                        videos.len(),
+                        times.len()
+                    ));
+                });
+                match embedded {
+                    Ok(embeddings) => this.with_index(|index| {
+                        index.frames.extend(embeddings.into_iter().map(|(time, vector)| {
+                            FrameEmbedding {
+                                video: video.clone(),
+                                time,
+                                vector,
+                            }
+                        }));
+                    }),
+                    Err(err) => {
+                        this.status.set(format!("{name}: {err}"));
+                        return;
+                    }
+                }
+            }
+
+            let (frames, saved) = this.with_index(|index| (index.frames.len(), index.save()));
+            this.status.set(match saved {
+                Ok(()) => format!("{frames} frames of {} videos indexed", videos.len()),
+                Err(err) => format!("indexed, but failed to save the index: {err}"),
+            });
+        });
+    }
+
+    // the moment on screen, on the original's timeline
+    fn current_moment(&self) -> Option<(PathBuf, Duration)> {
+        let video = self.video_source.get()?;
+        let position = self.player.position().get();
+        Some(self.renditions.map_ref(|renditions| {
+            (
+                renditions.original_of(&video),
+                renditions.to_original(&video, position),
+            )
+        }))
+    }
+
+    fn selected_moment(&self) -> Option<(PathBuf, Duration)> {
+        let id = self.selected_tag.get()?;
+        self.tags
+            .map_ref(|tags| tags.get(id).map(|tag| (tag.video.clone(), tag.time)))
+    }
+
+    fn find_similar(&self, moment: Option<(PathBuf, Duration)>, missing: &str) {
+        let Some((video, time)) = moment else {
+            self.status.set(String::from(missing));
+            return;
+        };
+        self.spawn(move |this, model| {
+            this.status.set(String::from("searching"));
+            let query = match embed_frames(&model, &video, &[time], |_| {}) {
+                Ok(mut embeddings) => match embeddings.pop() {
+                    Some((_, query)) => query,
+                    None => {
+                        this.status.set(String::from("couldn't read that frame"));
+                        return;
+                    }
+                },
+                Err(err) => {
+                    this.status.set(err);
+                    return;
+                }
+            };
+
+            let results = this.with_index(|index| {
+                if index.model.as_deref() != Some(&*model) {
+                    return None;
+                }
+                Some(index.nearest(&query, Some((&video, time)), RESULT_COUNT))
+            });
+            this.show_results(results);
+        });
+    }
+
+    // moments matching a description, by the text encoder's embedding of it
+    fn find_described(&self) {
+        let prompt = self.prompt.get().trim().to_string();
+        if prompt.is_empty() {
+            self.status.set(String::from("describe what to look for"));
+            return;
+        }
+        let Some(text_model) = self.text_model.get() else {
+            self.status.set(String::from("choose the text model first"));
+            return;
+        };
+        self.spawn(move |this, model| {
+            this.status.set(String::from("searching"));
+            let query = match embed_text(&text_model, &prompt) {
+                Ok(query) => query,
+                Err(err) => {
+                    this.status.set(err);
+                    return;
+                }
+            };
+
+            let results = this.with_index(|index| {
+                if index.model.as_deref() != Some(&*model) {
+                    return None;
+                }
+                Some(index.nearest(&query, None, RESULT_COUNT))
+            });
+            this.show_results(results);
+        });
+    }
+
+    // none when the index was built with another model
+    fn show_results(&self, results: Option<Vec<Match>>) {
+        match results {
+            Some(results) if !results.is_empty() => {
+                self.status.set(format!("{} matching moments", results.len()));
+                self.results.set(results);
+            }
+            Some(_) => self.status.set(String::from("nothing similar indexed yet")),
+            None => self
+                .status
+                .set(String::from("the library isn't indexed with this model yet")),
+        }
+    }
+
+    fn choose_model(model: Dynamic<Option<PathBuf>>) {
+        std::thread::Builder::new()
+            .name("File Picker Thread".into())
+            .spawn(move || {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("onnx model", &["onnx"])
+                    .pick_file()
+                {
+                    model.set(Some(path));
+                }
+            })
+            .unwrap();
+    }
+}
+
+// "find similar moments" across every video in the library, by the embeddings of
+// a CLIP style image encoder. its text encoder finds moments by description too
+pub fn similarity_panel(
+    tags: Dynamic<TagStore>,
+    database: TagDatabase,
+    queue: Dynamic<Vec<QueueEntry>>,
+    selected_tag: Dynamic<Option<TagId>>,
+    video_source: Dynamic<Option<PathBuf>>,
+    renditions: Dynamic<Renditions>,
+    player: VideoPlayer,
+) -> impl MakeWidget {
+    let search = Search {
+        index: Arc::default(),
+        model: Dynamic::new(None),
+        text_model: Dynamic::new(None),
+        prompt: Dynamic::default(),
+        interval: Dynamic::new(String::from("2")),
+        tags,
+        database,
+        queue,
+        selected_tag,
+        video_source,
+        renditions,
+        player,
+        results: Dynamic::new(Vec::new()),
+        status: Dynamic::new(String::new()),
+    };
+
+    let model_button = |model: &Dynamic<Option<PathBuf>>, kind: &'static str| {
+        model
+            .map_each(move |model| match model {
+                Some(model) => format!(
+                    "{kind}: {}",
+                    model.file_name().unwrap_or_default().to_string_lossy()
+                ),
+                None => format!("choose {kind}"),
+            })
+            .into_button()
+            .on_click({
+                let model = model.clone();
+                move |_| Search::choose_model(model.clone())
+            })
+    };
+    let choose_model = model_button(&search.model, "model");
+    let choose_text_model = model_button(&search.text_model, "text model");
+    let index = "index library".into_button().on_click({
+        let search = search.clone();
+        move |_| search.index_library()
+    });
+    let from_frame = "similar to frame".into_button().on_click({
+        let search = search.clone();
+        move |_| search.find_similar(search.current_moment(), "no video open")
+    });
+    let from_tag = "similar to selected tag".into_button().on_click({
+        let search = search.clone();
+        move |_| search.find_similar(search.selected_moment(), "no tag selected")
+    });
+
+    let describe = search
+        .prompt
+        .clone()
+        .into_input()
+        .placeholder("person riding a bike");
+    let find_described = "search".into_button().on_click({
+        let search = search.clone();
+        move |_| search.find_described()
+    });
+
+    let results = search.results.clone().switcher({
+        let search = search.clone();
+        move |results, _| {
+            results
+                .iter()
+                .map(|result| {
+                    let name = result.video.file_name().unwrap_or_default().to_string_lossy();
+                    let label = format!(
+                        "{name} {} ({:.2})",
+                        format_timestamp(result.time),
+                        result.similarity
+                    );
+                    label.into_button().on_click({
+                        let search = search.clone();
+                        let result = result.clone();
+                        move |_| {
+                            // a proxy of the video can stay open, anything else is
+                            // swapped for it. that starts a new pipeline the seek goes to
+                            let open = search.video_source.get().filter(|open| {
+                                search.renditions.map_ref(|renditions| {
+                                    renditions.original_of(open) == result.video
+                                })
+                            });
+                            let open = open.unwrap_or_else(|| {
+                                search.video_source.set(Some(result.video.clone()));
+                                result.video.clone()
+                            });
+                            let time = search.renditions.map_ref(|renditions| {
+                                renditions.from_original(&open, result.time)
+                            });
+                            search.player.seek(time);
+                        }
+                    })
+                })
+                .collect::<WidgetList>()
+                .into_rows()
+                .make_widget()
+        }
+    });
+
+    choose_model
+        .and("sample every seconds")
+        .and(search.interval.clone().into_input())
+        .and(index)
+        .and(from_frame)
+        .and(from_tag)
+        .and(search.status.clone())
+        .into_columns()
+        .and(
+            choose_text_model
+                .and(describe.expand())
+                .and(find_described)
+                .into_columns(),
+        )
+        .and(results.vertical_scroll())
+        .into_rows()
+}
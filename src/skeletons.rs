@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+// a set of named keypoints and the bones between them. more can be added to
+// settings.json next to the built in ones
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Skeleton {
+    pub name: String,
+    pub keypoints: Vec<String>,
+    // pairs of keypoint indices, counted from 0
+    #[serde(default)]
+    pub edges: Vec<(usize, usize)>,
+}
+
+// the 17 keypoint person of the COCO dataset, in its order
+pub fn human_pose() -> Skeleton {
+    let keypoints = [
+        "nose",
+        "left_eye",
+        "right_eye",
+        "left_ear",
+        "right_ear",
+        "left_shoulder",
+        "right_shoulder",
+        "left_elbow",
+        "right_elbow",
+        "left_wrist",
+        "right_wrist",
+        "left_hip",
+        "right_hip",
+        "left_knee",
+        "right_knee",
+        "left_ankle",
+        "right_ankle",
+    ];
+    let edges = [
+        (15, 13),
+        (13, 11),
+        (16, 14),
+        (14, 12),
+        (11, 12),
+        (5, 11),
+        (6, 12),
+        (5, 6),
+        (5, 7),
+        (6, 8),
+        (7, 9),
+        (8, 10),
+        (1, 2),
+        (0, 1),
+        (0, 2),
+        (1, 3),
+        (2, 4),
+        (3, 5),
+        (4, 6),
+    ];
+    Skeleton {
+        name: String::from("person"),
+        keypoints: keypoints.into_iter().map(String::from).collect(),
+        edges: edges.to_vec(),
+    }
+}
+
+// the built in templates followed by the user's own
+pub fn skeleton_templates(custom: &[Skeleton]) -> Vec<Skeleton> {
+    let mut templates = vec![human_pose()];
+    templates.extend(custom.iter().cloned());
+    templates
+}
+
+// a skeleton placed on a frame. points line up with the skeleton's keypoints,
+// none where a keypoint wasn't visible or got skipped
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pose {
+    pub skeleton: String,
+    pub points: Vec<Option<(f32, f32)>>,
+}
+
+impl Pose {
+    pub fn is_empty(&self) -> bool {
+        self.points.iter().all(Option::is_none)
+    }
+
+    pub fn placed(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
+        self.points.iter().flatten().copied()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoseKeyframe {
+    pub time: Duration,
+    pub pose: Pose,
+}
+
+// the poses of a tag, sorted by time. like outlines, each stays until the next
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PoseTrack {
+    keyframes: Vec<PoseKeyframe>,
+}
+
+impl PoseTrack {
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    pub fn keyframes(&self) -> &[PoseKeyframe] {
+        &self.keyframes
+    }
+
+    pub fn span(&self) -> Option<(Duration, Duration)> {
+        Some((self.keyframes.first()?.time, self.keyframes.last()?.time))
+    }
+
+    pub fn set(&mut self, time: Duration, pose: Pose) {
+        match self.keyframes.binary_search_by_key(&time, |keyframe| keyframe.time) {
+            Ok(idx) => self.keyframes[idx].pose = pose,
+            Err(idx) => self.keyframes.insert(idx, PoseKeyframe { time, pose }),
+        }
+    }
+
+    pub fn remove(&mut self, time: Duration) -> bool {
+        match self.keyframes.binary_search_by_key(&time, |keyframe| keyframe.time) {
+            Ok(idx) => {
+                self.keyframes.remove(idx);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn shift(&mut self, shift: impl Fn(Duration) -> Duration) {
+        for keyframe in &mut self.keyframes {
+            keyframe.time = shift(keyframe.time);
+        }
+    }
+
+    pub fn at(&self, time: Duration) -> Option<&Pose> {
+        let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        self.keyframes
+            .get(next.saturating_sub(1))
+            .map(|keyframe| &keyframe.pose)
+    }
+}
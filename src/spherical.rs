@@ -0,0 +1,81 @@
+use ffmpeg_next as ffmpeg;
+use ffmpeg::ffi::{
+    av_packet_side_data_get, AVPacketSideDataType, AVSphericalMapping, AVSphericalProjection,
+};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_FOV: f32 = 90.;
+const MIN_FOV: f32 = 30.;
+const MAX_FOV: f32 = 120.;
+// looking straight up or down flips the picture over
+const MAX_PITCH: f32 = 89.;
+// how much narrower a wheel step makes the view
+const FOV_STEP: f32 = 0.9;
+
+// where a 360 video is looked at, in degrees. yaw turns right, pitch looks up
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ViewDirection {
+    pub yaw: f32,
+    pub pitch: f32,
+    // vertical field of view
+    pub fov: f32,
+}
+
+impl Default for ViewDirection {
+    fn default() -> Self {
+        Self {
+            yaw: 0.,
+            pitch: 0.,
+            fov: DEFAULT_FOV,
+        }
+    }
+}
+
+impl ViewDirection {
+    // dragged by fractions of the view's height, the picture follows the pointer
+    pub fn dragged(self, dx: f32, dy: f32) -> Self {
+        let yaw = (self.yaw - dx * self.fov + 180.).rem_euclid(360.) - 180.;
+        Self {
+            yaw,
+            pitch: (self.pitch + dy * self.fov).clamp(-MAX_PITCH, MAX_PITCH),
+            ..self
+        }
+    }
+
+    // positive steps zoom in
+    pub fn zoomed(self, steps: f32) -> Self {
+        Self {
+            fov: (self.fov * FOV_STEP.powf(steps)).clamp(MIN_FOV, MAX_FOV),
+            ..self
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("yaw {:.0}° pitch {:.0}° fov {:.0}°", self.yaw, self.pitch, self.fov)
+    }
+}
+
+// whether the stream carries the spherical metadata of 360 cameras and the
+// spatial media injector, with the frame being an equirectangular panorama
+pub fn is_equirectangular(stream: &ffmpeg::Stream) -> bool {
+    unsafe {
+        let parameters = (*stream.as_ptr()).codecpar;
+        if parameters.is_null() {
+            return false;
+        }
+        let side_data = av_packet_side_data_get(
+            (*parameters).coded_side_data,
+            (*parameters).nb_coded_side_data,
+            AVPacketSideDataType::AV_PKT_DATA_SPHERICAL,
+        );
+        if side_data.is_null() || (*side_data).data.is_null() {
+            return false;
+        }
+        let mapping = (*side_data).data as *const AVSphericalMapping;
+        matches!(
+            (*mapping).projection,
+            AVSphericalProjection::AV_SPHERICAL_EQUIRECTANGULAR
+                | AVSphericalProjection::AV_SPHERICAL_EQUIRECTANGULAR_TILE
+        )
+    }
+}
@@ -0,0 +1,441 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use rusqlite::{params, params_from_iter, types::Value as SqlValue, Connection, Transaction};
+use rusqlite_migration::{Migrations, M};
+use serde_json::{Map, Value};
+
+use crate::database::{DatabaseError, TagQuery, TagStorage};
+use crate::tags::{FrameLabels, StoreSettings, StoredRow, Tag, TagStore, TrashedTag};
+
+pub const SQLITE_FILE: &str = "tags.sqlite";
+
+// in order, each one builds on the previous and `PRAGMA user_version` counts how many
+// ran. a released migration is never edited, schema changes always go into a new one
+const MIGRATIONS: &[M<'_>] = &[
+    // a row per tag, in the json the document format stores it as, with the columns
+    // queries filter on next to it. the rest of the store as a single document. the
+    // first databases were made without a version, hence the "if not exists"
+    M::up(
+        "CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY,
+            video TEXT NOT NULL,
+            time REAL NOT NULL,
+            end_time REAL,
+            label TEXT NOT NULL,
+            tag TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS tags_by_video ON tags (video, time);
+        CREATE INDEX IF NOT EXISTS tags_by_label ON tags (label);
+        CREATE TABLE IF NOT EXISTS store (document TEXT NOT NULL);",
+    ),
+    // the document split into a row per trashed tag, labelled frame and setting, so a
+    // change to one doesn't write the rest
+    M::up(
+        "CREATE TABLE trash (id INTEGER PRIMARY KEY, trashed TEXT NOT NULL);
+        CREATE TABLE frame_labels (
+            video TEXT NOT NULL,
+            time_ns INTEGER NOT NULL,
+            frame TEXT NOT NULL,
+            PRIMARY KEY (video, time_ns)
+        );
+        CREATE TABLE settings (name TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT OR REPLACE INTO trash (id, trashed)
+            SELECT json_extract(trashed.value, '$.tag.id'), trashed.value
+            FROM store, json_each(store.document, '$.trash') AS trashed;
+        INSERT OR REPLACE INTO frame_labels (video, time_ns, frame)
+            SELECT
+                json_extract(frame.value, '$.video'),
+                json_extract(frame.value, '$.time.secs') * 1000000000
+                    + json_extract(frame.value, '$.time.nanos'),
+                frame.value
+            FROM store, json_each(store.document, '$.frame_labels') AS frame;
+        INSERT OR REPLACE INTO settings (name, value)
+            SELECT field.key, field.value
+            FROM store, json_each(store.document) AS field
+            WHERE field.key IN (
+                'next_id', 'locked_videos', 'locked_categories',
+                'pipelines', 'video_pipelines', 'quick_tags'
+            );
+        DROP TABLE store;",
+    ),
+];
+
+// tags for libraries too big to read whole: the app loads the tags of a video when it's
+// opened and writes back only the rows that changed
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    // refuses databases from a newer version of the app, and copies older ones next to
+    // themselves before migrating them
+    pub fn open(path: &Path) -> Result<Self, DatabaseError> {
+        let fresh = !path.exists();
+        let mut connection = Connection::open(path)?;
+        let version = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        let latest = MIGRATIONS.len() as u64;
+        if version > latest {
+            return Err(DatabaseError::TooNew {
+                found: version,
+                known: latest,
+            });
+        }
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+
+        if version < latest && !fresh {
+            // the copy has to have everything the write-ahead log holds
+            connection.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+            let backup = path.with_extension(format!("v{version}.sqlite.bak"));
+            std::fs::copy(path, &backup)?;
+            tracing::warn!(
+                "migrating the tag database from version {version} to {latest}, backup at {}",
+                backup.display()
+            );
+        }
+        Migrations::from_slice(MIGRATIONS)
+            .to_latest(&mut connection)
+            .map_err(|err| DatabaseError::Migration {
+                to: latest,
+                message: err.to_string(),
+            })?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+impl TagStorage for SqliteStorage {
+    // everything but the tags, those come with `query`
+    fn load(&self) -> Result<TagStore, DatabaseError> {
+        let connection = self.connection.lock().unwrap();
+
+        let mut fields = Map::new();
+        let mut statement = connection.prepare("SELECT name, value FROM settings")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            fields.insert(row.get(0)?, serde_json::from_str(&row.get::<_, String>(1)?)?);
+        }
+        let mut settings: StoreSettings = serde_json::from_value(Value::Object(fields))?;
+
+        let trash = json_rows::<TrashedTag>(&connection, "SELECT trashed FROM trash")?;
+        let frame_labels =
+            json_rows::<FrameLabels>(&connection, "SELECT frame FROM frame_labels")?;
+
+        // ids are never handed out twice, even if the settings row fell behind
+        let max_id = connection.query_row(
+            "SELECT max(id) FROM (SELECT id FROM tags UNION ALL SELECT id FROM trash)",
+            [],
+            |row| row.get::<_, Option<u64>>(0),
+        )?;
+        settings.next_id = settings.next_id.max(max_id.map_or(0, |id| id + 1));
+
+        Ok(TagStore::from_rows(settings, trash, frame_labels))
+    }
+
+    fn save(&self, store: &TagStore) -> Result<(), DatabaseError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(
+            "DELETE FROM tags; DELETE FROM trash; DELETE FROM frame_labels; DELETE FROM settings;",
+        )?;
+        for tag in store.tags() {
+            write_row(&transaction, &StoredRow::Tag(tag.id, Some(tag.clone())))?;
+        }
+        for trashed in store.trash() {
+            write_row(&transaction, &StoredRow::Trashed(trashed.tag.id, Some(trashed.clone())))?;
+        }
+        for frame in store.frame_labels() {
+            let row = StoredRow::Frame(frame.video.clone(), frame.time, Some(frame.clone()));
+            write_row(&transaction, &row)?;
+        }
+        write_row(&transaction, &StoredRow::Settings(store.settings()))?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn keeps_rows(&self) -> bool {
+        true
+    }
+
+    fn write_rows(&self, rows: &[StoredRow]) -> Result<(), DatabaseError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        for row in rows {
+            write_row(&transaction, row)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn query(&self, query: &TagQuery) -> Result<Vec<Tag>, DatabaseError> {
+        let mut sql = String::from("SELECT tag FROM tags WHERE 1 = 1");
+        let mut values = Vec::new();
+        if let Some(video) = &query.video {
+            sql.push_str(" AND video = ?");
+            values.push(SqlValue::Text(video.to_string_lossy().into_owned()));
+        }
+        if let Some(label) = &query.label {
+            sql.push_str(" AND label = ?");
+            values.push(SqlValue::Text(label.clone()));
+        }
+        if let Some(from) = query.from {
+            sql.push_str(" AND coalesce(end_time, time) >= ?");
+            values.push(SqlValue::Real(from.as_secs_f64()));
+        }
+        if let Some(to) = query.to {
+            sql.push_str(" AND time <= ?");
+            values.push(SqlValue::Real(to.as_secs_f64()));
+        }
+        sql.push_str(" ORDER BY video, time");
+        if let Some(limit) = query.limit {
+            sql.push_str(" LIMIT ?");
+            values.push(SqlValue::Integer(i64::try_from(limit).unwrap_or(i64::MAX)));
+        }
+
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(&sql)?;
+        let rows = statement.query_map(params_from_iter(values), |row| row.get::<_, String>(0))?;
+        rows.map(|text| Ok(serde_json::from_str(&text?)?)).collect()
+    }
+
+    fn videos(&self) -> Result<Vec<PathBuf>, DatabaseError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare("SELECT DISTINCT video FROM tags ORDER BY video")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+        Ok(rows.map(|video| video.map(PathBuf::from)).collect::<Result<_, _>>()?)
+    }
+}
+
+// upserts the row, or deletes it when the store doesn't have it anymore
+fn write_row(transaction: &Transaction<'_>, row: &StoredRow) -> Result<(), DatabaseError> {
+    match row {
+        StoredRow::Tag(id, Some(tag)) => {
+            transaction
+                .prepare_cached(
+                    "INSERT INTO tags (id, video, time, end_time, label, tag)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                     ON CONFLICT (id) DO UPDATE SET
+                        video = excluded.video,
+                        time = excluded.time,
+                        end_time = excluded.end_time,
+                        label = excluded.label,
+                        tag = excluded.tag",
+                )?
+                .execute(params![
+                    id.as_u64(),
+                    tag.video.to_string_lossy(),
+                    tag.time.as_secs_f64(),
+                    tag.end.map(|end| end.as_secs_f64()),
+                    tag.label,
+                    serde_json::to_string(tag)?,
+                ])?;
+        }
+        StoredRow::Tag(id, None) => {
+            transaction
+                .prepare_cached("DELETE FROM tags WHERE id = ?1")?
+                .execute([id.as_u64()])?;
+        }
+        StoredRow::Trashed(id, Some(trashed)) => {
+            transaction
+                .prepare_cached("INSERT OR REPLACE INTO trash (id, trashed) VALUES (?1, ?2)")?
+                .execute(params![id.as_u64(), serde_json::to_string(trashed)?])?;
+        }
+        StoredRow::Trashed(id, None) => {
+            transaction
+                .prepare_cached("DELETE FROM trash WHERE id = ?1")?
+                .execute([id.as_u64()])?;
+        }
+        StoredRow::Frame(video, time, Some(frame)) => {
+            transaction
+                .prepare_cached(
+                    "INSERT OR REPLACE INTO frame_labels (video, time_ns, frame)
+                     VALUES (?1, ?2, ?3)",
+                )?
+                .execute(params![
+                    video.to_string_lossy(),
+                    nanos_of(*time),
+                    serde_json::to_string(frame)?,
+                ])?;
+        }
+        StoredRow::Frame(video, time, None) => {
+            transaction
+                .prepare_cached("DELETE FROM frame_labels WHERE video = ?1 AND time_ns = ?2")?
+                .execute(params![video.to_string_lossy(), nanos_of(*time)])?;
+        }
+        StoredRow::Settings(settings) => {
+            let Value::Object(fields) = serde_json::to_value(settings)? else {
+                return Ok(());
+            };
+            let mut upsert = transaction
+                .prepare_cached("INSERT OR REPLACE INTO settings (name, value) VALUES (?1, ?2)")?;
+            for (name, value) in fields {
+                upsert.execute(params![name, value.to_string()])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn json_rows<T: serde::de::DeserializeOwned>(
+    connection: &Connection,
+    sql: &str,
+) -> Result<Vec<T>, DatabaseError> {
+    let mut statement = connection.prepare(sql)?;
+    let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+    rows.map(|text| Ok(serde_json::from_str(&text?)?)).collect()
+}
+
+fn nanos_of(time: Duration) -> i64 {
+    i64::try_from(time.as_nanos()).unwrap_or(i64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tag-sqlite-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn user_version(path: &Path) -> u64 {
+        let connection = Connection::open(path).unwrap();
+        connection.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn rows_round_trip() {
+        let dir = temp_dir("rows");
+        let storage = SqliteStorage::open(&dir.join(SQLITE_FILE)).unwrap();
+
+        let mut store = TagStore::new();
+        store.add("a.mp4".into(), Duration::from_secs(3), "goal".into());
+        let foul = store.add("b.mp4".into(), Duration::from_secs(5), "foul".into()).unwrap();
+        let corner = store.add("b.mp4".into(), Duration::from_secs(9), "corner".into()).unwrap();
+        storage.save(&store).unwrap();
+
+        // only what changed goes in after that
+        store.set_note(foul, "late".into());
+        store.remove(corner);
+        let rows = store
+            .changed_since(0)
+            .iter()
+            .map(|(_, part)| store.row(part))
+            .collect::<Vec<_>>();
+        storage.write_rows(&rows).unwrap();
+
+        let loaded = storage.load();
+        let videos = storage.videos();
+        let found = storage.query(&TagQuery {
+            video: Some("b.mp4".into()),
+            ..TagQuery::default()
+        });
+        drop(storage);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert!(loaded.tags().is_empty());
+        assert_eq!(loaded.trash().len(), 1);
+        assert_eq!(loaded.trash()[0].tag.id, corner);
+        assert_eq!(loaded.settings(), store.settings());
+        assert_eq!(videos.unwrap(), [PathBuf::from("a.mp4"), PathBuf::from("b.mp4")]);
+        let found = found.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].id, found[0].note.as_str()), (foul, "late"));
+    }
+
+    // as the first version made them: no user_version, everything but the tags in one
+    // document
+    #[test]
+    fn upgrades_an_unversioned_database_after_backing_it_up() {
+        let dir = temp_dir("upgrade");
+        let path = dir.join(SQLITE_FILE);
+        let tag = json!({
+            "id": 4, "video": "a.mp4", "time": {"secs": 3, "nanos": 0}, "label": "goal",
+        });
+        let trashed = json!({
+            "id": 5, "video": "a.mp4", "time": {"secs": 1, "nanos": 0}, "label": "foul",
+        });
+        let frame = json!({
+            "video": "a.mp4", "time": {"secs": 2, "nanos": 500}, "labels": ["goal"],
+        });
+        let document = json!({
+            "next_id": 7,
+            "locked_categories": ["goal"],
+            "trash": [{"tag": trashed, "deleted_at": 1}],
+            "frame_labels": [frame],
+            "schema_version": 1,
+        });
+        let old = Connection::open(&path).unwrap();
+        old.execute_batch(
+            "CREATE TABLE tags (
+                id INTEGER PRIMARY KEY,
+                video TEXT NOT NULL,
+                time REAL NOT NULL,
+                end_time REAL,
+                label TEXT NOT NULL,
+                tag TEXT NOT NULL
+            );
+            CREATE TABLE store (document TEXT NOT NULL);",
+        )
+        .unwrap();
+        old.execute(
+            "INSERT INTO tags (id, video, time, label, tag) VALUES (4, 'a.mp4', 3, 'goal', ?1)",
+            [tag.to_string()],
+        )
+        .unwrap();
+        old.execute("INSERT INTO store (document) VALUES (?1)", [document.to_string()])
+            .unwrap();
+        drop(old);
+
+        let storage = SqliteStorage::open(&path).unwrap();
+        let loaded = storage.load();
+        let found = storage.query(&TagQuery::default());
+        drop(storage);
+        let backup = Connection::open(dir.join("tags.v0.sqlite.bak")).unwrap();
+        let backed_up = backup.query_row("SELECT document FROM store", [], |row| {
+            row.get::<_, String>(0)
+        });
+        drop(backup);
+        let version = user_version(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(serde_json::from_str::<Value>(&backed_up.unwrap()).unwrap(), document);
+        assert_eq!(version, MIGRATIONS.len() as u64);
+        let loaded = loaded.unwrap();
+        let settings = loaded.settings();
+        assert_eq!(settings.next_id, 7);
+        assert!(settings.locked_categories.contains("goal"));
+        assert_eq!(loaded.trash()[0].tag.id.as_u64(), 5);
+        assert_eq!(loaded.frame_labels()[0].time, Duration::new(2, 500));
+        let found = found.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id.as_u64(), 4);
+    }
+
+    #[test]
+    fn refuses_newer_databases() {
+        let dir = temp_dir("newer");
+        let path = dir.join(SQLITE_FILE);
+        let newer = MIGRATIONS.len() as u64 + 1;
+        Connection::open(&path)
+            .unwrap()
+            .pragma_update(None, "user_version", newer)
+            .unwrap();
+
+        let opened = SqliteStorage::open(&path);
+        let version = user_version(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(opened, Err(DatabaseError::TooNew { found, .. }) if found == newer));
+        assert_eq!(version, newer);
+    }
+}
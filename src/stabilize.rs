@@ -0,0 +1,243 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use cushy::{
+    value::{Destination, Dynamic, MapEach, Source},
+    widget::MakeWidget,
+};
+use ffmpeg_next as ffmpeg;
+
+use crate::media::open_input;
+use crate::pipelines::{chain, FrameFilter};
+use crate::safe_mode::is_safe_mode;
+use crate::settings::config_dir;
+
+const STABILIZATION_DIR: &str = "stabilization";
+// single pass, for playback while the motion of the whole video is being detected
+const APPROXIMATE_FILTER: &str = "deshake";
+const DETECT_FILTER: &str = "vidstabdetect=shakiness=5:accuracy=15";
+const TRANSFORM_FILTER: &str = "vidstabtransform=smoothing=10:optzoom=1";
+
+// whether playback stabilizes shaky footage, and which videos have their camera
+// motion detected already
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stabilization {
+    pub enabled: bool,
+    analyzed: HashSet<PathBuf>,
+}
+
+impl Stabilization {
+    // `spec` with the approximation added while `video` isn't analyzed yet, and the
+    // transforms playback reads once it is
+    pub fn apply(&self, video: &Path, spec: Option<String>) -> (Option<String>, Option<PathBuf>) {
+        if !self.enabled {
+            return (spec, None);
+        }
+        if self.analyzed.contains(video) {
+            return (spec, Some(transforms_path(video)));
+        }
+        (Some(chain(spec, APPROXIMATE_FILTER)), None)
+    }
+}
+
+// where vid.stab's detection pass over `video` is cached. the hash is only good for
+// one build of the app, a new one detects again
+fn transforms_path(video: &Path) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    video.hash(&mut hasher);
+    config_dir()
+        .filter(|_| !is_safe_mode())
+        .unwrap_or_else(std::env::temp_dir)
+        .join(STABILIZATION_DIR)
+        .join(format!("{:016x}.trf", hasher.finish()))
+}
+
+// a path as a filter option value, escaped for the option parser and again for the
+// graph parser
+pub fn escape_filter_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace('\\', "/")
+        .replace(':', "\\\\:")
+        .replace('\'', "\\\\\\'")
+}
+
+// the transform filter for playback whose first frame is frame `from` of the video.
+// vidstabtransform counts frames from wherever its graph started, so after a seek
+// it reads a copy of the transforms renumbered to start there
+pub fn transform_filter(transforms: &Path, from: usize) -> std::io::Result<String> {
+    let playing = transforms.with_extension("playing.trf");
+    let mut writer = BufWriter::new(std::fs::File::create(&playing)?);
+    for line in BufReader::new(std::fs::File::open(transforms)?).lines() {
+        let line = line?;
+        // "Frame 12 (List ...)", one line per frame and numbered from 1
+        let Some(rest) = line.strip_prefix("Frame ") else {
+            writeln!(writer, "{line}")?;
+            continue;
+        };
+        let (number, motions) = rest.split_once(' ').unwrap_or((rest, ""));
+        match number.parse::<usize>() {
+            Ok(number) if number > from => writeln!(writer, "Frame {} {motions}", number - from)?,
+            Ok(_) => {}
+            Err(_) => writeln!(writer, "{line}")?,
+        }
+    }
+    writer.flush()?;
+    Ok(format!("{TRANSFORM_FILTER}:input={}", escape_filter_path(&playing)))
+}
+
+// vid.stab's first pass, the camera motion of every frame of `video` into `transforms`
+fn detect_motion(
+    video: &Path,
+    transforms: &Path,
+    mut progress: impl FnMut(f64),
+) -> Result<(), ffmpeg::Error> {
+    let mut ictx = open_input(video)?;
+    let stream = ictx
+        .streams()
+        .best(ffmpeg::media::Type::Video)
+        .ok_or(ffmpeg::Error::StreamNotFound)?;
+    let stream_index = stream.index();
+    let time_base = stream.time_base();
+    let mut decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+        .decoder()
+        .video()?;
+    let duration = ictx.duration() as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE);
+
+    // written as it goes, only renamed to where playback looks once it's complete
+    let partial = transforms.with_extension("trf.part");
+    if let Some(dir) = partial.parent() {
+        std::fs::create_dir_all(dir).map_err(|_| ffmpeg::Error::InvalidData)?;
+    }
+    let mut filter = FrameFilter::new(
+        format!("{DETECT_FILTER}:result={}", escape_filter_path(&partial)),
+        time_base,
+    );
+
+    {
+        let mut frame = ffmpeg::util::frame::Video::empty();
+        let mut detect = |decoder: &mut ffmpeg::decoder::Video| -> Result<(), ffmpeg::Error> {
+            while decoder.receive_frame(&mut frame).is_ok() {
+                filter.process(&frame, |_| {})?;
+                if let Some(pts) = frame.pts() {
+                    progress(pts as f64 * f64::from(time_base) / duration.max(1.));
+                }
+            }
+            Ok(())
+        };
+        for (stream, packet) in ictx.packets() {
+            if stream.index() == stream_index && decoder.send_packet(&packet).is_ok() {
+                detect(&mut decoder)?;
+            }
+        }
+        decoder.send_eof()?;
+        detect(&mut decoder)?;
+    }
+    // vidstabdetect finishes the file when its graph goes
+    drop(filter);
+
+    std::fs::rename(&partial, transforms).map_err(|_| ffmpeg::Error::InvalidData)
+}
+
+#[derive(Debug, Clone)]
+pub struct Stabilizer {
+    pub state: Dynamic<Stabilization>,
+    analyzing: Arc<Mutex<HashSet<PathBuf>>>,
+    // the app's status line
+    announcement: Dynamic<String>,
+}
+
+impl Stabilizer {
+    pub fn new(announcement: Dynamic<String>) -> Self {
+        Self {
+            state: Dynamic::default(),
+            analyzing: Arc::default(),
+            announcement,
+        }
+    }
+
+    // detects the motion of `video` in the background unless it's cached, playback
+    // switches from the approximation to the real thing when it's done
+    pub fn analyze(&self, video: PathBuf) {
+        if self.state.map_ref(|state| state.analyzed.contains(&video)) {
+            return;
+        }
+        let transforms = transforms_path(&video);
+        if transforms.exists() {
+            self.state.lock().analyzed.insert(video);
+            return;
+        }
+        if !self.analyzing.lock().unwrap().insert(video.clone()) {
+            return;
+        }
+
+        let this = self.clone();
+        std::thread::Builder::new()
+            .name("Stabilization Thread".into())
+            .spawn(move || {
+                let name = video.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                let mut reported = 0;
+                let detected = detect_motion(&video, &transforms, |done| {
+                    let percent = (done * 100.).clamp(0., 100.) as u32;
+                    if percent / 10 != reported / 10 {
+                        reported = percent;
+                        this.announcement.set(format!(
+                            "stabilizing {name}: detecting motion, {percent}%"
+                        ));
+                    }
+                });
+                this.analyzing.lock().unwrap().remove(&video);
+                match detected {
+                    Ok(()) => {
+                        this.state.lock().analyzed.insert(video);
+                        this.announcement.set(format!("stabilizing {name}"));
+                    }
+                    Err(err) => this.announcement.set(format!(
+                        "couldn't detect the motion of {name}, is ffmpeg built with vid.stab? {err}"
+                    )),
+                }
+            })
+            .unwrap();
+    }
+
+    // analyzes whatever opens while stabilization is on
+    pub fn follow(&self, video_source: &Dynamic<Option<PathBuf>>) {
+        let this = self.clone();
+        (video_source, &self.state.map_each(|state| state.enabled))
+            .for_each(move |(video, enabled)| {
+                if let (Some(video), true) = (video, enabled) {
+                    this.analyze(video.clone());
+                }
+            })
+            .persist();
+    }
+
+    pub fn button(&self) -> impl MakeWidget {
+        self.state
+            .map_each(|state| {
+                if state.enabled {
+                    String::from("[stabilize]")
+                } else {
+                    String::from("stabilize")
+                }
+            })
+            .into_button()
+            .on_click({
+                let state = self.state.clone();
+                let announcement = self.announcement.clone();
+                move |_| {
+                    let mut state = state.lock();
+                    state.enabled = !state.enabled;
+                    announcement.set(String::from(if state.enabled {
+                        "stabilizing, roughly until the motion of the video is detected"
+                    } else {
+                        "stabilization off"
+                    }));
+                }
+            })
+    }
+}
@@ -0,0 +1,108 @@
+use std::path::Path;
+
+use ffmpeg_next as ffmpeg;
+use ffmpeg::ffi::{av_packet_side_data_get, AVPacketSideDataType, AVStereo3D, AVStereo3DType};
+
+// how the two eyes of 3d footage share a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoLayout {
+    SideBySide,
+    TopBottom,
+}
+
+// what is shown of 3d footage, one eye as a flat video or both as a red/cyan anaglyph
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StereoView {
+    #[default]
+    Left,
+    Right,
+    Anaglyph,
+}
+
+impl StereoLayout {
+    // the stereo metadata of the stream, or what the file name says the way players
+    // like vlc and kodi read it (`movie.sbs.mkv`, `clip_TB.mp4`)
+    pub fn detect(stream: &ffmpeg::Stream, path: &Path) -> Option<Self> {
+        from_side_data(stream).or_else(|| from_file_name(path))
+    }
+
+    // off, side by side, top and bottom, and around
+    pub fn cycle(layout: Option<Self>) -> Option<Self> {
+        match layout {
+            None => Some(Self::SideBySide),
+            Some(Self::SideBySide) => Some(Self::TopBottom),
+            Some(Self::TopBottom) => None,
+        }
+    }
+
+    pub fn label(layout: Option<Self>) -> &'static str {
+        match layout {
+            None => "3D off",
+            Some(Self::SideBySide) => "3D SBS",
+            Some(Self::TopBottom) => "3D TB",
+        }
+    }
+
+    // the size one eye is shown at. full width frames hold two pictures of the usual
+    // shape, half width ones squeeze them and are stretched back to the frame's size
+    pub fn eye_size(self, width: u32, height: u32) -> (u32, u32) {
+        match self {
+            Self::SideBySide if width >= height * 3 => (width / 2, height),
+            Self::TopBottom if width * 4 <= height * 5 => (width, height / 2),
+            _ => (width, height),
+        }
+    }
+}
+
+impl StereoView {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Left => Self::Right,
+            Self::Right => Self::Anaglyph,
+            Self::Anaglyph => Self::Left,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Left => "left eye",
+            Self::Right => "right eye",
+            Self::Anaglyph => "anaglyph",
+        }
+    }
+}
+
+fn from_side_data(stream: &ffmpeg::Stream) -> Option<StereoLayout> {
+    unsafe {
+        let parameters = (*stream.as_ptr()).codecpar;
+        if parameters.is_null() {
+            return None;
+        }
+        let side_data = av_packet_side_data_get(
+            (*parameters).coded_side_data,
+            (*parameters).nb_coded_side_data,
+            AVPacketSideDataType::AV_PKT_DATA_STEREO3D,
+        );
+        if side_data.is_null() || (*side_data).data.is_null() {
+            return None;
+        }
+        match (*((*side_data).data as *const AVStereo3D)).type_ {
+            AVStereo3DType::AV_STEREO3D_SIDEBYSIDE
+            | AVStereo3DType::AV_STEREO3D_SIDEBYSIDE_QUINCUNX => Some(StereoLayout::SideBySide),
+            AVStereo3DType::AV_STEREO3D_TOPBOTTOM => Some(StereoLayout::TopBottom),
+            _ => None,
+        }
+    }
+}
+
+fn from_file_name(path: &Path) -> Option<StereoLayout> {
+    let stem = path.file_stem()?.to_string_lossy().to_lowercase();
+    stem.split(|c: char| !c.is_ascii_alphanumeric())
+        .find_map(|word| match word {
+            "sbs" | "hsbs" | "fsbs" | "3dsbs" => Some(StereoLayout::SideBySide),
+            "tb" | "htb" | "ftb" | "tab" | "htab" | "ou" | "hou" | "3dtab" => {
+                Some(StereoLayout::TopBottom)
+            }
+            _ => None,
+        })
+}
@@ -0,0 +1,286 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use ffmpeg_next as ffmpeg;
+
+use crate::formats::from_seconds;
+use crate::media::open_input;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start: Duration,
+    pub end: Duration,
+    // one or more lines, markup stripped
+    pub text: String,
+}
+
+// the cues of an .srt file, sorted by when they start
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Subtitles {
+    cues: Vec<Cue>,
+}
+
+impl Subtitles {
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Ok(Self::parse(&String::from_utf8_lossy(&bytes)))
+    }
+
+    // forgiving about what's found in the wild: a byte order mark, windows line endings,
+    // missing counters, periods for commas and cues that don't parse, which are skipped
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim_start_matches('\u{feff}');
+        let mut cues = Vec::new();
+        let mut block = Vec::new();
+        for line in text.lines().chain(std::iter::once("")) {
+            if !line.trim().is_empty() {
+                block.push(line);
+                continue;
+            }
+            if let Some(cue) = cue(&block) {
+                cues.push(cue);
+            }
+            block.clear();
+        }
+        Self::from_cues(cues)
+    }
+
+    fn from_cues(mut cues: Vec<Cue>) -> Self {
+        cues.sort_by_key(|cue| cue.start);
+        Self { cues }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cues.is_empty()
+    }
+
+    // what's on screen at `time`, more than one cue where they overlap
+    pub fn at(&self, time: Duration) -> impl Iterator<Item = &str> {
+        let started = self.cues.partition_point(|cue| cue.start <= time);
+        self.cues[..started]
+            .iter()
+            .filter(move |cue| time < cue.end)
+            .map(|cue| cue.text.as_str())
+    }
+}
+
+// a subtitle stream of the video's container
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub stream: usize,
+    // its language and title, as far as the container says
+    pub label: String,
+}
+
+// where the cues the player shows come from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum SubtitleSource {
+    #[default]
+    Off,
+    File(PathBuf),
+    Embedded(Track),
+}
+
+impl SubtitleSource {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Off => String::from("off"),
+            Self::File(path) => path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+            Self::Embedded(track) => track.label.clone(),
+        }
+    }
+
+    // the next of the file found next to the video, the embedded tracks and off
+    pub fn next(&self, beside: Option<&Path>, tracks: &[Track]) -> Self {
+        let choices = beside
+            .map(|path| Self::File(path.to_path_buf()))
+            .into_iter()
+            .chain(tracks.iter().cloned().map(Self::Embedded))
+            .chain(std::iter::once(Self::Off))
+            .collect::<Vec<_>>();
+        let at = choices.iter().position(|choice| choice == self);
+        let next = at.map_or(0, |at| (at + 1) % choices.len());
+        choices[next].clone()
+    }
+
+    pub fn load(&self, video: &Path) -> Result<Option<Subtitles>, String> {
+        match self {
+            Self::Off => Ok(None),
+            Self::File(path) => Subtitles::load(path)
+                .map(Some)
+                .map_err(|err| format!("failed to read {}: {err}", path.display())),
+            Self::Embedded(track) => decode(video, track.stream)
+                .map(Some)
+                .map_err(|err| format!("failed to decode subtitles {}: {err}", track.label)),
+        }
+    }
+}
+
+// the ones that are text. dvd and blu-ray subtitles are pictures, which aren't shown
+pub fn tracks(video: &Path) -> Vec<Track> {
+    let Ok(ictx) = open_input(video) else {
+        return Vec::new();
+    };
+    ictx.streams()
+        .filter(|stream| {
+            matches!(
+                stream.parameters().id(),
+                ffmpeg::codec::Id::SUBRIP
+                    | ffmpeg::codec::Id::SRT
+                    | ffmpeg::codec::Id::ASS
+                    | ffmpeg::codec::Id::SSA
+                    | ffmpeg::codec::Id::MOV_TEXT
+                    | ffmpeg::codec::Id::WEBVTT
+                    | ffmpeg::codec::Id::TEXT
+            )
+        })
+        .enumerate()
+        .map(|(idx, stream)| {
+            let metadata = stream.metadata();
+            let described = [metadata.get("language"), metadata.get("title")]
+                .into_iter()
+                .flatten()
+                .filter(|value| !value.is_empty() && *value != "und")
+                .collect::<Vec<_>>()
+                .join(", ");
+            Track {
+                stream: stream.index(),
+                label: match described.as_str() {
+                    "" => format!("track {}", idx + 1),
+                    described => format!("track {}: {described}", idx + 1),
+                },
+            }
+        })
+        .collect()
+}
+
+// every cue of a subtitle stream, timed like the video's frames. this reads the whole
+// file, so it runs off the ui thread
+pub fn decode(video: &Path, stream_index: usize) -> Result<Subtitles, ffmpeg::Error> {
+    let mut ictx = open_input(video)?;
+    let stream = ictx.stream(stream_index).ok_or(ffmpeg::Error::StreamNotFound)?;
+    let time_base = f64::from(stream.time_base());
+    let mut decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+        .decoder()
+        .subtitle()?;
+
+    let mut cues = Vec::new();
+    let mut subtitle = ffmpeg::Subtitle::new();
+    for (stream, packet) in ictx.packets() {
+        if stream.index() != stream_index {
+            continue;
+        }
+        let Some(pts) = packet.pts() else {
+            continue;
+        };
+        // a broken cue is left out rather than losing the track
+        if !matches!(decoder.decode(&packet, &mut subtitle), Ok(true)) {
+            continue;
+        }
+        let at = pts as f64 * time_base;
+        let start = at + f64::from(subtitle.start()) / 1000.;
+        let end = match subtitle.end() {
+            end if end > subtitle.start() => at + f64::from(end) / 1000.,
+            _ => at + packet.duration() as f64 * time_base,
+        };
+        let text = subtitle
+            .rects()
+            .filter_map(|rect| match rect {
+                ffmpeg::subtitle::Rect::Text(text) => Some(text.get().to_string()),
+                ffmpeg::subtitle::Rect::Ass(ass) => Some(dialogue_text(ass.get())),
+                _ => None,
+            })
+            .flat_map(|text| {
+                text.lines().map(str::trim).map(strip_markup).collect::<Vec<_>>()
+            })
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let (Some(start), Some(end)) = (from_seconds(start), from_seconds(end)) else {
+            continue;
+        };
+        if end > start && !text.is_empty() {
+            cues.push(Cue { start, end, text });
+        }
+    }
+    Ok(Subtitles::from_cues(cues))
+}
+
+// the text of an ass dialogue event as ffmpeg hands it over, after its eight fields of
+// order, layer, style, name, margins and effect. "\N" breaks lines
+fn dialogue_text(event: &str) -> String {
+    let text = event.splitn(9, ',').nth(8).unwrap_or(event);
+    text.replace("\\N", "\n").replace("\\n", "\n")
+}
+
+// an .srt next to the video with the same name, or with a language in between like
+// "movie.en.srt"
+pub fn beside(video: &Path) -> Option<PathBuf> {
+    let stem = video.file_stem()?.to_string_lossy().into_owned();
+    let same = video.with_extension("srt");
+    if same.is_file() {
+        return Some(same);
+    }
+    let mut found = std::fs::read_dir(video.parent()?)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let is_srt = path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("srt"));
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            is_srt && name.strip_prefix(&stem).is_some_and(|rest| rest.starts_with('.'))
+        })
+        .collect::<Vec<_>>();
+    // the same pick every time there's more than one
+    found.sort();
+    found.into_iter().next()
+}
+
+fn cue(block: &[&str]) -> Option<Cue> {
+    let timing = block.iter().position(|line| line.contains("-->"))?;
+    let (start, end) = block[timing].split_once("-->")?;
+    let text = block[timing + 1..]
+        .iter()
+        .map(|line| strip_markup(line.trim()))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (!text.is_empty()).then_some(Cue {
+        start: timestamp(start)?,
+        end: timestamp(end)?,
+        text,
+    })
+}
+
+// "01:02:03,456", hours optional. positions some files put after the end are ignored
+fn timestamp(text: &str) -> Option<Duration> {
+    let text = text.split_whitespace().next()?;
+    let (clock, fraction) = text.split_once([',', '.']).unwrap_or((text, "0"));
+    let mut units = clock.rsplit(':');
+    let seconds = units.next()?.parse::<u64>().ok()?;
+    let minutes = units.next().map_or(Some(0), |minutes| minutes.parse::<u64>().ok())?;
+    let hours = units.next().map_or(Some(0), |hours| hours.parse::<u64>().ok())?;
+    let digits = fraction.len().min(9) as u32;
+    let nanos = fraction[..digits as usize].parse::<u64>().ok()? * 10_u64.pow(9 - digits);
+    Some(Duration::from_secs(hours * 3600 + minutes * 60 + seconds) + Duration::from_nanos(nanos))
+}
+
+// italics, colors and positioning, "<i>", "<font ...>" and "{\an8}"
+fn strip_markup(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut closing = None;
+    for c in line.chars() {
+        match (closing, c) {
+            (None, '<') => closing = Some('>'),
+            (None, '{') => closing = Some('}'),
+            (None, c) => text.push(c),
+            (Some(end), c) if c == end => closing = None,
+            (Some(_), _) => {}
+        }
+    }
+    text.trim().to_string()
+}
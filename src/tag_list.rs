@@ -0,0 +1,113 @@
+use std::{path::PathBuf, time::Duration};
+
+use cushy::{
+    value::{Destination, Dynamic, MapEach, Switchable},
+    widget::{MakeWidget, WidgetList},
+};
+
+use crate::format_timestamp;
+use crate::renditions::Renditions;
+use crate::tags::{TagId, TagStore};
+use crate::video_player::VideoPlayer;
+
+// a tag of the open video, on the player's timeline
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    id: TagId,
+    time: Duration,
+    end: Option<Duration>,
+    label: String,
+    note: String,
+}
+
+impl Entry {
+    fn describe(&self) -> String {
+        let time = match self.end {
+            Some(end) => format!("{} - {}", format_timestamp(self.time), format_timestamp(end)),
+            None => format_timestamp(self.time),
+        };
+        if self.note.is_empty() {
+            format!("{time}  {}", self.label)
+        } else {
+            format!("{time}  {}: {}", self.label, self.note)
+        }
+    }
+}
+
+// every tag of the open video in order, marking the one playback is at. clicking one
+// selects it and seeks there
+pub fn tag_list_panel(
+    tags: Dynamic<TagStore>,
+    selected_tag: Dynamic<Option<TagId>>,
+    video_source: Dynamic<Option<PathBuf>>,
+    renditions: Dynamic<Renditions>,
+    video_player: VideoPlayer,
+) -> impl MakeWidget {
+    let entries = (&tags, &video_source, &renditions).map_each(|(tags, video, renditions)| {
+        let Some(video) = video else {
+            return Vec::new();
+        };
+        let original = renditions.original_of(video);
+        tags.tags()
+            .iter()
+            .filter(|tag| tag.video == original)
+            .map(|tag| Entry {
+                id: tag.id,
+                time: renditions.from_original(video, tag.time),
+                end: tag.end.map(|end| renditions.from_original(video, end)),
+                label: tag.label.clone(),
+                note: tag.note.clone(),
+            })
+            .collect::<Vec<_>>()
+    });
+
+    // the last tag to start before the playhead, the first one until then. only
+    // changes as playback passes a tag, not on every frame
+    let position = video_player.position();
+    let current = (&entries, &position).map_each(|(entries, position)| {
+        entries
+            .iter()
+            .rev()
+            .find(|entry| entry.time <= *position)
+            .or(entries.first())
+            .map(|entry| entry.id)
+    });
+
+    let rows = entries.switcher(move |entries, _| {
+        if entries.is_empty() {
+            return "no tags on this video".make_widget();
+        }
+
+        entries
+            .iter()
+            .map(|entry| {
+                let id = entry.id;
+                let time = entry.time;
+                let text = entry.describe();
+                (&current, &selected_tag)
+                    .map_each(move |(current, selected)| {
+                        let marker = if *current == Some(id) { "> " } else { "  " };
+                        if *selected == Some(id) {
+                            format!("{marker}[{text}]")
+                        } else {
+                            format!("{marker}{text}")
+                        }
+                    })
+                    .into_button()
+                    .on_click({
+                        let selected_tag = selected_tag.clone();
+                        let video_player = video_player.clone();
+                        move |_| {
+                            selected_tag.set(Some(id));
+                            video_player.seek(time);
+                        }
+                    })
+                    .make_widget()
+            })
+            .collect::<WidgetList>()
+            .into_rows()
+            .make_widget()
+    });
+
+    "Tags".and(rows.vertical_scroll().expand()).into_rows()
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use cushy::{
+    context::{GraphicsContext, LayoutContext},
+    figures::{
+        units::{Px, UPx},
+        FloatConversion, IntoSigned, Point, Rect, Size,
+    },
+    kludgine::shapes::Shape,
+    styles::Color,
+    value::{Dynamic, Source},
+    widget::Widget,
+    ConstraintLimit,
+};
+
+const STRIP_HEIGHT: u32 = 10;
+// instants and spans shorter than this still get a visible mark
+const TICK_WIDTH: i32 = 2;
+
+// a tag of the open video, on the player's timeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub start: Duration,
+    // instants are drawn as ticks
+    pub end: Option<Duration>,
+    pub color: Color,
+}
+
+// colored spans along the open video's duration, a thin strip under the timeline
+#[derive(Debug)]
+pub struct TagSpans {
+    spans: Dynamic<Vec<Span>>,
+    duration: Dynamic<Duration>,
+}
+
+impl TagSpans {
+    pub fn new(spans: Dynamic<Vec<Span>>, duration: Dynamic<Duration>) -> Self {
+        Self { spans, duration }
+    }
+}
+
+impl Widget for TagSpans {
+    fn redraw(&mut self, context: &mut GraphicsContext<'_, '_, '_, '_>) {
+        use cushy::context::Trackable;
+
+        self.spans.redraw_when_changed(context);
+        let duration = self.duration.get_tracking_redraw(context);
+        if duration.is_zero() {
+            return;
+        }
+
+        let size = context.gfx.size().into_signed();
+        let x = |time: Duration| {
+            Px::from(time.as_secs_f32() / duration.as_secs_f32() * size.width.into_float())
+        };
+        let tick = Px::new(TICK_WIDTH);
+        self.spans.map_ref(|spans| {
+            for span in spans {
+                let start = x(span.start);
+                let width = span.end.map_or(tick, |end| (x(end) - start).max(tick));
+                context.gfx.draw_shape(&Shape::filled_rect(
+                    Rect::new(Point::new(start, Px::ZERO), Size::new(width, size.height)),
+                    span.color,
+                ));
+            }
+        });
+    }
+
+    fn layout(
+        &mut self,
+        available_space: Size<ConstraintLimit>,
+        context: &mut LayoutContext<'_, '_, '_, '_>,
+    ) -> Size<UPx> {
+        Size::new(available_space.width.max(), UPx::new(STRIP_HEIGHT))
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use cushy::{
+    value::{Destination, Dynamic, Source},
+    widget::MakeWidget,
+};
+
+use crate::{
+    format_timestamp,
+    renditions::{probe_duration, Renditions},
+    tags::{shift_by, TagStore},
+};
+
+// shifts every tag after a point in time, the point defaults to the playhead
+pub fn ripple_shift_tool(
+    tags: Dynamic<TagStore>,
+    video_source: Dynamic<Option<PathBuf>>,
+    position: Dynamic<Duration>,
+    renditions: Dynamic<Renditions>,
+) -> impl MakeWidget {
+    let after = Dynamic::new(String::from("00:00:00.000"));
+    let offset = Dynamic::new(String::from("0"));
+    let status = Dynamic::new(String::new());
+
+    // the timestamp is on the original's timeline, same as the tags
+    let use_playhead = "at playhead".into_button().on_click({
+        let after = after.clone();
+        let video_source = video_source.clone();
+        let renditions = renditions.clone();
+        move |_| {
+            let position = match video_source.get() {
+                Some(video) => renditions.map_ref(|r| r.to_original(&video, position.get())),
+                None => position.get(),
+            };
+            after.set(format_timestamp(position));
+        }
+    });
+
+    let shift = "shift".into_button().on_click({
+        let after = after.clone();
+        let offset = offset.clone();
+        let status = status.clone();
+        move |_| {
+            let Some(video) = video_source.get() else {
+                status.set(String::from("no video open"));
+                return;
+            };
+            let Some(after) = parse_timestamp(&after.get()) else {
+                status.set(String::from("invalid timestamp, use hh:mm:ss.mmm"));
+                return;
+            };
+            let seconds = offset.get().trim().parse::<f64>().ok();
+            let Some(seconds) = seconds.filter(|seconds| shift_by(*seconds).is_some()) else {
+                status.set(String::from("invalid offset, use seconds e.g. -2.5"));
+                return;
+            };
+
+            let video = renditions.map_ref(|renditions| renditions.original_of(&video));
+            let shifted = tags.lock().ripple_shift(&video, after, seconds);
+            status.set(format!("shifted {shifted} tags by {seconds}s"));
+        }
+    });
+
+    "shift tags after"
+        .and(after.into_input())
+        .and(use_playhead)
+        .and("by seconds")
+        .and(offset.into_input())
+        .and(shift)
+        .and(status)
+        .into_columns()
+}
+
+// accepts hh:mm:ss.mmm, mm:ss.mmm or plain seconds
+pub fn parse_timestamp(text: &str) -> Option<Duration> {
+    let mut seconds = 0.;
+    for part in text.trim().split(':') {
+        let value = part.trim().parse::<f64>().ok()?;
+        if !value.is_finite() || value < 0. {
+            return None;
+        }
+        seconds = seconds * 60. + value;
+    }
+    // "1e20" parses, but is further than a duration goes
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+pub fn gap_report_tool(
+    tags: Dynamic<TagStore>,
+    video_source: Dynamic<Option<PathBuf>>,
+    renditions: Dynamic<Renditions>,
+) -> impl MakeWidget {
+    let threshold = Dynamic::new(String::from("30"));
+    let status = Dynamic::new(String::new());
+
+    let export = "export gaps csv".into_button().on_click({
+        let threshold = threshold.clone();
+        let status = status.clone();
+        move |_| {
+            let Some(video) = video_source.get() else {
+                status.set(String::from("no video open"));
+                return;
+            };
+            let Some(threshold) = parse_timestamp(&threshold.get()) else {
+                status.set(String::from("invalid threshold, use seconds"));
+                return;
+            };
+            let original = renditions.map_ref(|renditions| renditions.original_of(&video));
+
+            let tags = tags.clone();
+            let status = status.clone();
+            std::thread::Builder::new()
+                .name("Gap Report Thread".into())
+                .spawn(move || {
+                    let duration = probe_duration(&original).unwrap_or_default();
+                    let gaps = tags.map_ref(|tags| tags.untagged_gaps(&original, duration, threshold));
+
+                    let Some(path) = rfd::FileDialog::new()
+                        .add_filter("csv", &["csv"])
+                        .set_file_name("untagged-gaps.csv")
+                        .save_file()
+                    else {
+                        return;
+                    };
+
+                    match std::fs::write(&path, gaps_csv(&original, &gaps)) {
+                        Ok(()) => status.set(format!("{} gaps written", gaps.len())),
+                        Err(err) => status.set(format!("failed to write report: {err}")),
+                    }
+                })
+                .unwrap();
+        }
+    });
+
+    "untagged gaps longer than seconds"
+        .and(threshold.into_input())
+        .and(export)
+        .and(status)
+        .into_columns()
+}
+
+fn gaps_csv(video: &Path, gaps: &[(Duration, Duration)]) -> String {
+    let mut csv = String::from("video,start,end,duration_seconds\n");
+    for (start, end) in gaps {
+        csv.push_str(&format!(
+            "{},{},{},{:.3}\n",
+            csv_field(&video.to_string_lossy()),
+            format_timestamp(*start),
+            format_timestamp(*end),
+            (*end - *start).as_secs_f64()
+        ));
+    }
+    csv
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_timestamp("01:30.5"), Some(Duration::from_millis(90_500)));
+        assert_eq!(parse_timestamp("1:00:00"), Some(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn timestamps_that_arent_durations() {
+        for text in ["", "-1", "abc", "nan", "inf", "1e20", "1e300:00"] {
+            assert_eq!(parse_timestamp(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn offsets() {
+        let shift = shift_by(-2.5).unwrap();
+        assert_eq!(shift(Duration::from_secs(10)), Duration::from_millis(7500));
+        assert_eq!(shift(Duration::from_secs(1)), Duration::ZERO);
+        assert!(shift_by(f64::NAN).is_none());
+        assert!(shift_by(1e300).is_none());
+    }
+}
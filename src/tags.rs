@@ -0,0 +1,1188 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::history::{Change, Operation, UNDO_LIMIT};
+use crate::pipelines::PipelinePreset;
+use crate::regions::{Keyframe, Polygon, PolygonTrack, Region, Track};
+use crate::skeletons::{Pose, PoseTrack};
+use crate::spherical::ViewDirection;
+use crate::vertical_crop::CropTrack;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TagId(u64);
+
+impl TagId {
+    // for storage that keys its rows by it
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: TagId,
+    pub video: PathBuf,
+    pub time: Duration,
+    // instant tags have no end
+    #[serde(default)]
+    pub end: Option<Duration>,
+    pub label: String,
+    // free text about what the label doesn't say
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+    // where a 360 video was being looked at when it was tagged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub view: Option<ViewDirection>,
+    // region tags box something in the frame, most tags don't
+    #[serde(default, skip_serializing_if = "Track::is_empty")]
+    pub track: Track,
+    #[serde(default, skip_serializing_if = "PolygonTrack::is_empty")]
+    pub polygons: PolygonTrack,
+    #[serde(default, skip_serializing_if = "PoseTrack::is_empty")]
+    pub poses: PoseTrack,
+    // the upright window a range is exported through for phones
+    #[serde(default, skip_serializing_if = "CropTrack::is_empty")]
+    pub crop: CropTrack,
+}
+
+impl Tag {
+    // while a region tag's outlines are on screen: its keyframes, widened to the tag's range
+    pub fn region_span(&self) -> Option<(Duration, Duration)> {
+        let (first, last) = [self.track.span(), self.polygons.span(), self.poses.span()]
+            .into_iter()
+            .flatten()
+            .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))?;
+        Some((self.time.min(first), self.end.unwrap_or(last).max(last)))
+    }
+}
+
+// whole-frame class labels, for building an image classification dataset. a frame
+// can carry several and, unlike tags, they have no extent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameLabels {
+    pub video: PathBuf,
+    pub time: Duration,
+    pub labels: Vec<String>,
+}
+
+// frames closer than this are the same frame, times go through float conversions
+const SAME_FRAME: Duration = Duration::from_millis(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActivityKind {
+    Created,
+    Edited,
+    // moved to the trash
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityEvent {
+    // unix seconds
+    pub at: u64,
+    pub kind: ActivityKind,
+    // the tag that changed, older logs don't have it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<TagId>,
+    pub video: PathBuf,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+    // where the tag sits after the change, older logs don't have it
+    #[serde(default)]
+    pub time: Duration,
+    #[serde(default)]
+    pub end: Option<Duration>,
+    // counts up across every store of this process, so a store swapped in by a restore
+    // or a project open can't be mistaken for more of the old one. 0 when read from a log
+    #[serde(skip)]
+    pub sequence: u64,
+}
+
+static NEXT_SEQUENCE: AtomicU64 = AtomicU64::new(1);
+
+// deleted tags stay restorable for this long before `purge_trash` drops them
+pub const TRASH_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashedTag {
+    pub tag: Tag,
+    // unix seconds
+    pub deleted_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagEdge {
+    Start,
+    End,
+}
+
+// a part of the store that storage with rows of its own keeps apart, see `changed_since`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StoredPart {
+    Tag(TagId),
+    Trashed(TagId),
+    Frame(PathBuf, Duration),
+    Settings,
+}
+
+// a part as the store has it now, none when it's gone
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoredRow {
+    Tag(TagId, Option<Tag>),
+    Trashed(TagId, Option<TrashedTag>),
+    Frame(PathBuf, Duration, Option<FrameLabels>),
+    Settings(StoreSettings),
+}
+
+// the rest of the store, small enough to be written whole whenever any of it changes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StoreSettings {
+    #[serde(default)]
+    pub next_id: u64,
+    #[serde(default)]
+    pub locked_videos: HashSet<PathBuf>,
+    #[serde(default)]
+    pub locked_categories: HashSet<String>,
+    #[serde(default)]
+    pub pipelines: Vec<PipelinePreset>,
+    #[serde(default)]
+    pub video_pipelines: HashMap<PathBuf, String>,
+    #[serde(default)]
+    pub quick_tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TagStore {
+    tags: Vec<Tag>,
+    next_id: u64,
+    // reviewed work, nothing matching these can be added, moved or edited
+    #[serde(default)]
+    locked_videos: HashSet<PathBuf>,
+    #[serde(default)]
+    locked_categories: HashSet<String>,
+    #[serde(default)]
+    trash: Vec<TrashedTag>,
+    // sorted by video, then time
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    frame_labels: Vec<FrameLabels>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pipelines: Vec<PipelinePreset>,
+    // the preset each video plays through, by name, on originals
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    video_pipelines: HashMap<PathBuf, String>,
+    // the labels keys 1 to 9 tag with, empty ones fall back to the categories
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    quick_tags: Vec<String>,
+    // every creation and edit of this session in order, see `activity`
+    #[serde(skip)]
+    activity: Vec<ActivityEvent>,
+    // what this session changed, newest last, see `undo`
+    #[serde(skip)]
+    undo: Vec<Operation>,
+    #[serde(skip)]
+    redo: Vec<Operation>,
+    // gathers the changes made inside `group`
+    #[serde(skip)]
+    grouping: Option<Operation>,
+    // every part changed this session, numbered like the activity
+    #[serde(skip)]
+    changed: Vec<(u64, StoredPart)>,
+    // numbered the same when the store was read from a document. a restore or a project
+    // open swaps one in, and nothing storage has is current after that
+    #[serde(skip, default = "read_now")]
+    read_from_document: Option<u64>,
+    // the videos whose tags are in `tags`, none when every video's are. storage that
+    // can query its rows hands them over as the videos are opened
+    #[serde(skip)]
+    loaded: Option<HashSet<PathBuf>>,
+}
+
+fn read_now() -> Option<u64> {
+    Some(NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed))
+}
+
+impl TagStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // a store without any tags loaded yet, from storage that keeps them in rows
+    pub fn from_rows(
+        settings: StoreSettings,
+        mut trash: Vec<TrashedTag>,
+        mut frame_labels: Vec<FrameLabels>,
+    ) -> Self {
+        trash.sort_by_key(|trashed| (trashed.deleted_at, trashed.tag.id));
+        frame_labels.sort_by(|a, b| (&a.video, a.time).cmp(&(&b.video, b.time)));
+        Self {
+            next_id: settings.next_id,
+            locked_videos: settings.locked_videos,
+            locked_categories: settings.locked_categories,
+            trash,
+            frame_labels,
+            pipelines: settings.pipelines,
+            video_pipelines: settings.video_pipelines,
+            quick_tags: settings.quick_tags,
+            loaded: Some(HashSet::new()),
+            ..Self::default()
+        }
+    }
+
+    // whether `tags` has the tags of every video
+    pub fn is_complete(&self) -> bool {
+        self.loaded.is_none()
+    }
+
+    pub fn is_loaded(&self, video: &Path) -> bool {
+        self.loaded.as_ref().is_none_or(|loaded| loaded.contains(video))
+    }
+
+    // adds the stored tags of `video`. ones the store has already, added or edited
+    // before the video was opened, stay as they are
+    pub fn load_video(&mut self, video: PathBuf, tags: Vec<Tag>) {
+        let Some(loaded) = &mut self.loaded else {
+            return;
+        };
+        if loaded.insert(video) {
+            self.add_loaded(tags);
+        }
+    }
+
+    // the same for every video, for backups and projects
+    pub fn load_everything(&mut self, tags: Vec<Tag>) {
+        if self.loaded.take().is_some() {
+            self.add_loaded(tags);
+        }
+    }
+
+    fn add_loaded(&mut self, tags: Vec<Tag>) {
+        for tag in tags {
+            if self.get(tag.id).is_none() {
+                self.insert_sorted(tag);
+            }
+        }
+    }
+
+    // the parts changed after the change numbered `seen`, oldest first
+    pub fn changed_since(&self, seen: u64) -> &[(u64, StoredPart)] {
+        let from = self.changed.partition_point(|(sequence, _)| *sequence <= seen);
+        &self.changed[from..]
+    }
+
+    fn changed(&mut self, part: StoredPart) {
+        let sequence = NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        self.changed.push((sequence, part));
+    }
+
+    pub fn read_from_document(&self) -> Option<u64> {
+        self.read_from_document
+    }
+
+    pub fn row(&self, part: &StoredPart) -> StoredRow {
+        match part {
+            StoredPart::Tag(id) => StoredRow::Tag(*id, self.get(*id).cloned()),
+            StoredPart::Trashed(id) => StoredRow::Trashed(
+                *id,
+                self.trash.iter().find(|trashed| trashed.tag.id == *id).cloned(),
+            ),
+            StoredPart::Frame(video, time) => StoredRow::Frame(
+                video.clone(),
+                *time,
+                self.frame_labels
+                    .iter()
+                    .find(|frame| frame.video == *video && frame.time == *time)
+                    .cloned(),
+            ),
+            StoredPart::Settings => StoredRow::Settings(self.settings()),
+        }
+    }
+
+    pub fn settings(&self) -> StoreSettings {
+        StoreSettings {
+            next_id: self.next_id,
+            locked_videos: self.locked_videos.clone(),
+            locked_categories: self.locked_categories.clone(),
+            pipelines: self.pipelines.clone(),
+            video_pipelines: self.video_pipelines.clone(),
+            quick_tags: self.quick_tags.clone(),
+        }
+    }
+
+    pub fn add(&mut self, video: PathBuf, time: Duration, label: String) -> Option<TagId> {
+        self.insert(video, time, None, label)
+    }
+
+    // a tag covering `time` to `end`, for imports that carry durations
+    pub fn add_range(
+        &mut self,
+        video: PathBuf,
+        time: Duration,
+        end: Duration,
+        label: String,
+    ) -> Option<TagId> {
+        self.insert(video, time.min(end), Some(time.max(end)), label)
+    }
+
+    fn insert(
+        &mut self,
+        video: PathBuf,
+        time: Duration,
+        end: Option<Duration>,
+        label: String,
+    ) -> Option<TagId> {
+        if self.is_video_locked(&video) || self.is_category_locked(&label) {
+            return None;
+        }
+
+        let id = TagId(self.next_id);
+        self.next_id += 1;
+        self.changed(StoredPart::Settings);
+
+        let tag = Tag {
+            id,
+            video,
+            time,
+            end,
+            label,
+            note: String::new(),
+            view: None,
+            track: Track::default(),
+            polygons: PolygonTrack::default(),
+            poses: PoseTrack::default(),
+            crop: CropTrack::default(),
+        };
+        self.commit(vec![Change::Inserted(tag)]);
+        Some(id)
+    }
+
+    pub fn activity(&self) -> &[ActivityEvent] {
+        &self.activity
+    }
+
+    // the events after the one numbered `seen`, none when this is another store that
+    // hasn't had anything happen yet
+    pub fn activity_since(&self, seen: u64) -> &[ActivityEvent] {
+        let from = self.activity.partition_point(|event| event.sequence <= seen);
+        &self.activity[from..]
+    }
+
+    fn record(&mut self, kind: ActivityKind, tag: &Tag) {
+        self.activity.push(ActivityEvent {
+            at: unix_now(),
+            kind,
+            tag: Some(tag.id),
+            video: tag.video.clone(),
+            label: tag.label.clone(),
+            note: tag.note.clone(),
+            time: tag.time,
+            end: tag.end,
+            sequence: NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed),
+        });
+    }
+
+    pub fn is_video_locked(&self, video: &Path) -> bool {
+        self.locked_videos.contains(video)
+    }
+
+    pub fn is_category_locked(&self, category: &str) -> bool {
+        self.locked_categories.contains(category)
+    }
+
+    pub fn is_locked(&self, tag: &Tag) -> bool {
+        self.is_video_locked(&tag.video) || self.is_category_locked(&tag.label)
+    }
+
+    pub fn set_video_locked(&mut self, video: PathBuf, locked: bool) {
+        if locked {
+            self.locked_videos.insert(video);
+        } else {
+            self.locked_videos.remove(&video);
+        }
+        self.changed(StoredPart::Settings);
+    }
+
+    pub fn set_category_locked(&mut self, category: String, locked: bool) {
+        if locked {
+            self.locked_categories.insert(category);
+        } else {
+            self.locked_categories.remove(&category);
+        }
+        self.changed(StoredPart::Settings);
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    pub fn get(&self, id: TagId) -> Option<&Tag> {
+        self.tags.iter().find(|tag| tag.id == id)
+    }
+
+    pub fn frame_labels(&self) -> &[FrameLabels] {
+        &self.frame_labels
+    }
+
+    pub fn labels_at(&self, video: &Path, time: Duration) -> &[String] {
+        self.frame_labels
+            .iter()
+            .find(|frame| frame.video == video && frame.time.abs_diff(time) < SAME_FRAME)
+            .map_or(&[], |frame| &frame.labels)
+    }
+
+    // puts `label` on the frame at `time` or takes it off again, returns whether the
+    // frame has it now. none if the video or category is locked
+    pub fn toggle_frame_label(
+        &mut self,
+        video: PathBuf,
+        time: Duration,
+        label: String,
+    ) -> Option<bool> {
+        if self.is_video_locked(&video) || self.is_category_locked(&label) {
+            return None;
+        }
+
+        let before = self
+            .frame_labels
+            .iter()
+            .find(|frame| frame.video == video && frame.time.abs_diff(time) < SAME_FRAME)
+            .cloned();
+        let mut frame = before.clone().unwrap_or(FrameLabels {
+            video,
+            time,
+            labels: Vec::new(),
+        });
+        let added = match frame.labels.iter().position(|other| *other == label) {
+            Some(label) => {
+                frame.labels.remove(label);
+                false
+            }
+            None => {
+                frame.labels.push(label);
+                true
+            }
+        };
+        let after = (!frame.labels.is_empty()).then_some(frame);
+        self.commit(vec![Change::FrameEdited { before, after }]);
+        Some(added)
+    }
+
+    // moves one edge of a tag, the edges never cross and never go below zero
+    pub fn nudge(&mut self, id: TagId, edge: TagEdge, seconds: f64) {
+        let Some(shift) = shift_by(seconds) else {
+            return;
+        };
+        self.edit(id, |tag| {
+            match (edge, tag.end) {
+                (TagEdge::Start, end) => {
+                    let start = shift(tag.time);
+                    tag.time = end.map_or(start, |end| start.min(end));
+                }
+                (TagEdge::End, Some(end)) => tag.end = Some(shift(end).max(tag.time)),
+                // an instant only has a start
+                (TagEdge::End, None) => tag.time = shift(tag.time),
+            }
+            true
+        });
+    }
+
+    pub fn set_note(&mut self, id: TagId, note: String) -> bool {
+        self.edit(id, |tag| {
+            tag.note = note;
+            true
+        })
+    }
+
+    pub fn set_view(&mut self, id: TagId, view: Option<ViewDirection>) -> bool {
+        self.edit(id, |tag| {
+            tag.view = view;
+            true
+        })
+    }
+
+    // boxes the tag's subject at `time`, making it a region tag if it wasn't one
+    pub fn set_keyframe(&mut self, id: TagId, time: Duration, region: Region) -> bool {
+        self.edit(id, |tag| {
+            tag.track.set(time, region);
+            true
+        })
+    }
+
+    // a tracked stretch of boxes, replacing the ones the tag had in between
+    pub fn set_keyframes(&mut self, id: TagId, keyframes: &[Keyframe]) -> bool {
+        self.edit(id, |tag| {
+            tag.track.replace(keyframes);
+            true
+        })
+    }
+
+    // outlines the tag's subject at `time`, next to or instead of a box
+    pub fn set_polygon(&mut self, id: TagId, time: Duration, polygon: Polygon) -> bool {
+        if !polygon.is_valid() {
+            return false;
+        }
+        self.edit(id, |tag| {
+            tag.polygons.set(time, polygon);
+            true
+        })
+    }
+
+    // keypoints of the tag's subject at `time`
+    pub fn set_pose(&mut self, id: TagId, time: Duration, pose: Pose) -> bool {
+        if pose.is_empty() {
+            return false;
+        }
+        self.edit(id, |tag| {
+            tag.poses.set(time, pose);
+            true
+        })
+    }
+
+    // where the upright export window of the tag is at `time`
+    pub fn set_crop(&mut self, id: TagId, time: Duration, center: f32) -> bool {
+        self.edit(id, |tag| {
+            tag.crop.set(time, center);
+            true
+        })
+    }
+
+    // the box, outline, pose and crop keyframed at `time`, whichever there are
+    pub fn remove_keyframe(&mut self, id: TagId, time: Duration) -> bool {
+        self.edit(id, |tag| {
+            let removed = [
+                tag.track.remove(time),
+                tag.polygons.remove(time),
+                tag.poses.remove(time),
+                tag.crop.remove(time),
+            ];
+            removed.contains(&true)
+        })
+    }
+
+    // shifts every tag of `video` starting at or after `after`, for when footage got
+    // trimmed or re-exported with a different head. returns how many tags moved
+    pub fn ripple_shift(&mut self, video: &Path, after: Duration, seconds: f64) -> usize {
+        let Some(shift) = shift_by(seconds) else {
+            return 0;
+        };
+        if self.is_video_locked(video) {
+            return 0;
+        }
+
+        let locked_categories = &self.locked_categories;
+        let mut changes = self
+            .tags
+            .iter()
+            .filter(|tag| {
+                tag.video == video && tag.time >= after && !locked_categories.contains(&tag.label)
+            })
+            .map(|before| {
+                let mut after = before.clone();
+                after.time = shift(after.time);
+                after.end = after.end.map(shift);
+                after.track.shift(shift);
+                after.polygons.shift(shift);
+                after.poses.shift(shift);
+                after.crop.shift(shift);
+                Change::Edited {
+                    before: before.clone(),
+                    after,
+                }
+            })
+            .collect::<Vec<_>>();
+        let count = changes.len();
+
+        // frame labels move with the footage too, unless one of them is locked
+        changes.extend(
+            self.frame_labels
+                .iter()
+                .filter(|frame| {
+                    frame.video == video
+                        && frame.time >= after
+                        && !frame.labels.iter().any(|label| locked_categories.contains(label))
+                })
+                .filter_map(|before| {
+                    let after = FrameLabels {
+                        time: shift(before.time),
+                        ..before.clone()
+                    };
+                    (after != *before).then(|| Change::FrameEdited {
+                        before: Some(before.clone()),
+                        after: Some(after),
+                    })
+                }),
+        );
+
+        self.commit(changes);
+        count
+    }
+
+    // stretches of `video` longer than `threshold` that no tag covers, ranges cover
+    // their whole span and instants just the moment they were placed at
+    pub fn untagged_gaps(
+        &self,
+        video: &Path,
+        duration: Duration,
+        threshold: Duration,
+    ) -> Vec<(Duration, Duration)> {
+        let mut gaps = Vec::new();
+        let mut covered_until = Duration::ZERO;
+
+        for tag in self.tags.iter().filter(|tag| tag.video == video) {
+            if tag.time > covered_until && tag.time - covered_until > threshold {
+                gaps.push((covered_until, tag.time));
+            }
+            covered_until = covered_until.max(tag.end.unwrap_or(tag.time));
+        }
+
+        if duration > covered_until && duration - covered_until > threshold {
+            gaps.push((covered_until, duration));
+        }
+
+        gaps
+    }
+
+    // moves a tag to the trash, locked tags stay where they are
+    pub fn remove(&mut self, id: TagId) -> bool {
+        let Some(tag) = self.get(id).cloned() else {
+            return false;
+        };
+        if self.is_locked(&tag) {
+            return false;
+        }
+
+        self.commit(vec![Change::Trashed(tag)]);
+        true
+    }
+
+    // trashes every unlocked tag of `video`, returns how many went
+    pub fn remove_video(&mut self, video: &Path) -> usize {
+        if self.is_video_locked(video) {
+            return 0;
+        }
+
+        let changes = self
+            .tags
+            .iter()
+            .filter(|tag| tag.video == video && !self.locked_categories.contains(&tag.label))
+            .cloned()
+            .map(Change::Trashed)
+            .collect::<Vec<_>>();
+        let count = changes.len();
+        self.commit(changes);
+        count
+    }
+
+    // for when the file itself moved: its tags, frame labels, trashed tags and preset go
+    // with it. returns how many tags moved, none if the video is locked
+    pub fn move_video(&mut self, from: &Path, to: PathBuf) -> Option<usize> {
+        if self.is_video_locked(from) {
+            return None;
+        }
+
+        let mut changes = self
+            .tags
+            .iter()
+            .filter(|tag| tag.video == from)
+            .map(|before| Change::Edited {
+                before: before.clone(),
+                after: Tag {
+                    video: to.clone(),
+                    ..before.clone()
+                },
+            })
+            .collect::<Vec<_>>();
+        let count = changes.len();
+
+        changes.extend(
+            self.frame_labels
+                .iter()
+                .filter(|frame| frame.video == from)
+                .map(|before| Change::FrameEdited {
+                    before: Some(before.clone()),
+                    after: Some(FrameLabels {
+                        video: to.clone(),
+                        ..before.clone()
+                    }),
+                }),
+        );
+        changes.extend(
+            self.trash
+                .iter()
+                .filter(|trashed| trashed.tag.video == from)
+                .map(|trashed| Change::TrashEdited {
+                    before: trashed.tag.clone(),
+                    after: Tag {
+                        video: to.clone(),
+                        ..trashed.tag.clone()
+                    },
+                }),
+        );
+        self.commit(changes);
+
+        if let Some(preset) = self.video_pipelines.remove(from) {
+            self.video_pipelines.insert(to, preset);
+            self.changed(StoredPart::Settings);
+        }
+        if let Some(loaded) = &mut self.loaded {
+            loaded.insert(to);
+        }
+        Some(count)
+    }
+
+    // newest last
+    pub fn trash(&self) -> &[TrashedTag] {
+        &self.trash
+    }
+
+    // puts a trashed tag back, unless its video or category got locked meanwhile
+    pub fn restore(&mut self, id: TagId) -> bool {
+        let Some(idx) = self.trash.iter().position(|trashed| trashed.tag.id == id) else {
+            return false;
+        };
+        if self.is_locked(&self.trash[idx].tag) {
+            return false;
+        }
+
+        let tag = self.trash[idx].tag.clone();
+        self.commit(vec![Change::Restored(tag)]);
+        true
+    }
+
+    pub fn empty_trash(&mut self) {
+        for trashed in std::mem::take(&mut self.trash) {
+            self.changed(StoredPart::Trashed(trashed.tag.id));
+        }
+    }
+
+    // drops whatever has been in the trash longer than `TRASH_RETENTION`
+    pub fn purge_trash(&mut self) {
+        let cutoff = trash_cutoff();
+        let (kept, expired): (Vec<_>, Vec<_>) = std::mem::take(&mut self.trash)
+            .into_iter()
+            .partition(|trashed| trashed.deleted_at >= cutoff);
+        self.trash = kept;
+        for trashed in expired {
+            self.changed(StoredPart::Trashed(trashed.tag.id));
+        }
+    }
+
+    // whether `purge_trash` has anything to drop, checked first so it doesn't count as
+    // a change when it doesn't
+    pub fn has_expired_trash(&self) -> bool {
+        let cutoff = trash_cutoff();
+        self.trash.iter().any(|trashed| trashed.deleted_at < cutoff)
+    }
+
+    pub fn pipelines(&self) -> &[PipelinePreset] {
+        &self.pipelines
+    }
+
+    // adds `preset`, or replaces the one of the same name
+    pub fn set_pipeline(&mut self, preset: PipelinePreset) {
+        match self.pipelines.iter_mut().find(|other| other.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.pipelines.push(preset),
+        }
+        self.changed(StoredPart::Settings);
+    }
+
+    // the videos using it go back to playing unfiltered
+    pub fn remove_pipeline(&mut self, name: &str) -> bool {
+        let before = self.pipelines.len();
+        self.pipelines.retain(|preset| preset.name != name);
+        self.video_pipelines.retain(|_, preset| preset != name);
+        self.changed(StoredPart::Settings);
+        self.pipelines.len() != before
+    }
+
+    // false if there's no preset of that name
+    pub fn assign_pipeline(&mut self, video: PathBuf, name: Option<String>) -> bool {
+        match name {
+            Some(name) if self.pipelines.iter().any(|preset| preset.name == name) => {
+                self.video_pipelines.insert(video, name);
+            }
+            Some(_) => return false,
+            None => {
+                self.video_pipelines.remove(&video);
+            }
+        }
+        self.changed(StoredPart::Settings);
+        true
+    }
+
+    pub fn pipeline_for(&self, video: &Path) -> Option<&PipelinePreset> {
+        let name = self.video_pipelines.get(video)?;
+        self.pipelines.iter().find(|preset| preset.name == *name)
+    }
+
+    pub fn quick_tags(&self) -> &[String] {
+        &self.quick_tags
+    }
+
+    pub fn quick_tag(&self, idx: usize) -> Option<&str> {
+        self.quick_tags
+            .get(idx)
+            .map(String::as_str)
+            .filter(|label| !label.is_empty())
+    }
+
+    pub fn set_quick_tags(&mut self, mut labels: Vec<String>) {
+        while labels.last().is_some_and(String::is_empty) {
+            labels.pop();
+        }
+        self.quick_tags = labels;
+        self.changed(StoredPart::Settings);
+    }
+
+    // edits a copy of the tag and commits it, unless the tag is locked or `edit` says
+    // nothing changed
+    fn edit(&mut self, id: TagId, edit: impl FnOnce(&mut Tag) -> bool) -> bool {
+        let Some(before) = self.get(id).cloned() else {
+            return false;
+        };
+        if self.is_locked(&before) {
+            return false;
+        }
+        let mut after = before.clone();
+        if !edit(&mut after) {
+            return false;
+        }
+
+        self.commit(vec![Change::Edited { before, after }]);
+        true
+    }
+
+    // every change to the tags goes through here, applied and kept for undo as one
+    // operation. anything undone is no longer redoable after a new change
+    fn commit(&mut self, changes: Vec<Change>) {
+        if changes.is_empty() {
+            return;
+        }
+        for change in &changes {
+            self.apply(change.clone());
+        }
+
+        self.redo.clear();
+        match &mut self.grouping {
+            Some(group) => group.changes.extend(changes),
+            None => self.push_undo(Operation { changes }),
+        }
+    }
+
+    fn apply(&mut self, change: Change) {
+        for part in change.parts() {
+            self.changed(part);
+        }
+        match change {
+            Change::Inserted(tag) => {
+                self.record(ActivityKind::Created, &tag);
+                self.insert_sorted(tag);
+            }
+            Change::Discarded(tag) => {
+                self.record(ActivityKind::Deleted, &tag);
+                self.take(tag.id);
+            }
+            Change::Edited { after, .. } => {
+                self.record(ActivityKind::Edited, &after);
+                match self.tags.iter().position(|tag| tag.id == after.id) {
+                    // edits that don't move the tag keep its place among the ones at its time
+                    Some(idx) if self.tags[idx].time == after.time => self.tags[idx] = after,
+                    _ => {
+                        self.take(after.id);
+                        self.insert_sorted(after);
+                    }
+                }
+            }
+            Change::Trashed(tag) => {
+                self.record(ActivityKind::Deleted, &tag);
+                self.take(tag.id);
+                self.trash.push(TrashedTag {
+                    tag,
+                    deleted_at: unix_now(),
+                });
+            }
+            Change::Restored(tag) => {
+                self.record(ActivityKind::Created, &tag);
+                self.trash.retain(|trashed| trashed.tag.id != tag.id);
+                self.insert_sorted(tag);
+            }
+            Change::FrameEdited { before, after } => {
+                if let Some(before) = before {
+                    if let Some(idx) = self.frame_labels.iter().position(|frame| *frame == before) {
+                        self.frame_labels.remove(idx);
+                    }
+                }
+                if let Some(after) = after {
+                    let idx = self.frame_labels.partition_point(|frame| {
+                        (&frame.video, frame.time) <= (&after.video, after.time)
+                    });
+                    self.frame_labels.insert(idx, after);
+                }
+            }
+            Change::TrashEdited { after, .. } => {
+                if let Some(trashed) =
+                    self.trash.iter_mut().find(|trashed| trashed.tag.id == after.id)
+                {
+                    trashed.tag = after;
+                }
+            }
+        }
+    }
+
+    fn push_undo(&mut self, operation: Operation) {
+        self.undo.push(operation);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    // everything `changes` does is undone and redone as one operation, for imports and
+    // other bulk edits
+    pub fn group<R>(&mut self, changes: impl FnOnce(&mut Self) -> R) -> R {
+        if self.grouping.is_some() {
+            return changes(self);
+        }
+
+        self.grouping = Some(Operation::default());
+        let result = changes(self);
+        if let Some(operation) = self.grouping.take() {
+            if !operation.changes.is_empty() {
+                self.push_undo(operation);
+            }
+        }
+        result
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    // takes back the last operation, returns what it was about. refused while any tag
+    // it touched is locked
+    pub fn undo(&mut self) -> Result<String, String> {
+        let operation = self.undo.pop().ok_or_else(|| String::from("nothing to undo"))?;
+        if let Err(err) = self.check_unlocked(&operation) {
+            self.undo.push(operation);
+            return Err(err);
+        }
+
+        let description = operation.describe();
+        for change in operation.clone().inverse().changes {
+            self.apply(change);
+        }
+        self.redo.push(operation);
+        Ok(description)
+    }
+
+    pub fn redo(&mut self) -> Result<String, String> {
+        let operation = self.redo.pop().ok_or_else(|| String::from("nothing to redo"))?;
+        if let Err(err) = self.check_unlocked(&operation) {
+            self.redo.push(operation);
+            return Err(err);
+        }
+
+        let description = operation.describe();
+        for change in operation.changes.iter().cloned() {
+            self.apply(change);
+        }
+        self.push_undo(operation);
+        Ok(description)
+    }
+
+    fn check_unlocked(&self, operation: &Operation) -> Result<(), String> {
+        let changes = &operation.changes;
+        if let Some(tag) = changes.iter().flat_map(Change::tags).find(|tag| self.is_locked(tag)) {
+            return Err(format!("\"{}\" is locked", tag.label));
+        }
+        match changes.iter().flat_map(Change::frames).find(|frame| self.is_frame_locked(frame)) {
+            Some(frame) => Err(format!("the frame labels of {} are locked", frame.video.display())),
+            None => Ok(()),
+        }
+    }
+
+    fn is_frame_locked(&self, frame: &FrameLabels) -> bool {
+        self.is_video_locked(&frame.video)
+            || frame.labels.iter().any(|label| self.is_category_locked(label))
+    }
+
+    fn take(&mut self, id: TagId) -> Option<Tag> {
+        let idx = self.tags.iter().position(|tag| tag.id == id)?;
+        Some(self.tags.remove(idx))
+    }
+
+    fn insert_sorted(&mut self, tag: Tag) {
+        let idx = self.tags.partition_point(|other| other.time <= tag.time);
+        self.tags.insert(idx, tag);
+    }
+}
+
+// moves times by `seconds` either way, stopping at zero. none for offsets that aren't a
+// duration, like nan or 1e300
+pub fn shift_by(seconds: f64) -> Option<impl Fn(Duration) -> Duration + Copy> {
+    let offset = Duration::try_from_secs_f64(seconds.abs()).ok()?;
+    Some(move |time: Duration| {
+        if seconds < 0. {
+            time.saturating_sub(offset)
+        } else {
+            time.saturating_add(offset)
+        }
+    })
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+// trashed before this many unix seconds is past `TRASH_RETENTION`
+fn trash_cutoff() -> u64 {
+    unix_now().saturating_sub(TRASH_RETENTION.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn frames(store: &TagStore) -> Vec<(Duration, Vec<String>)> {
+        store
+            .frame_labels()
+            .iter()
+            .map(|frame| (frame.time, frame.labels.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn undoing_a_ripple_keeps_later_frame_labels() {
+        let video = PathBuf::from("match.mp4");
+        let mut store = TagStore::new();
+        store.toggle_frame_label(video.clone(), secs(10), "goal".into());
+        store.ripple_shift(&video, Duration::ZERO, 5.);
+        store.toggle_frame_label(video.clone(), secs(30), "foul".into());
+
+        store.undo().unwrap();
+        assert_eq!(frames(&store), [(secs(15), vec!["goal".into()])]);
+        store.undo().unwrap();
+        assert_eq!(frames(&store), [(secs(10), vec!["goal".into()])]);
+
+        store.redo().unwrap();
+        store.redo().unwrap();
+        assert_eq!(
+            frames(&store),
+            [(secs(15), vec!["goal".into()]), (secs(30), vec!["foul".into()])]
+        );
+    }
+
+    #[test]
+    fn frame_labels_of_a_locked_video_stay() {
+        let video = PathBuf::from("match.mp4");
+        let mut store = TagStore::new();
+        store.toggle_frame_label(video.clone(), secs(10), "goal".into());
+        store.ripple_shift(&video, Duration::ZERO, 5.);
+        store.set_video_locked(video, true);
+
+        assert!(store.undo().is_err());
+        assert_eq!(frames(&store), [(secs(15), vec!["goal".into()])]);
+    }
+
+    #[test]
+    fn undoing_a_move_takes_the_trash_back() {
+        let from = PathBuf::from("old/match.mp4");
+        let to = PathBuf::from("new/match.mp4");
+        let mut store = TagStore::new();
+        let id = store.add(from.clone(), secs(3), "goal".into()).unwrap();
+        store.remove(id);
+
+        store.move_video(&from, to.clone());
+        assert_eq!(store.trash()[0].tag.video, to);
+        store.undo().unwrap();
+        assert_eq!(store.trash()[0].tag.video, from);
+        assert!(store.restore(id));
+        assert_eq!(store.get(id).unwrap().video, from);
+    }
+
+    #[test]
+    fn only_expired_trash_is_purged() {
+        let mut store = TagStore::new();
+        let old = store.add("a.mp4".into(), secs(1), "goal".into()).unwrap();
+        let recent = store.add("a.mp4".into(), secs(2), "foul".into()).unwrap();
+        store.remove(old);
+        store.remove(recent);
+        assert!(!store.has_expired_trash());
+
+        store.trash[0].deleted_at = trash_cutoff() - 1;
+        assert!(store.has_expired_trash());
+        store.purge_trash();
+        assert_eq!(store.trash().len(), 1);
+        assert_eq!(store.trash()[0].tag.id, recent);
+    }
+
+    #[test]
+    fn changes_name_the_rows_they_touch() {
+        let video = PathBuf::from("match.mp4");
+        let mut store = TagStore::new();
+        let id = store.add(video.clone(), secs(3), "goal".into()).unwrap();
+        let seen = store.changed_since(0).last().unwrap().0;
+
+        store.set_note(id, "header".into());
+        store.remove(id);
+        store.toggle_frame_label(video.clone(), secs(5), "goal".into());
+        let parts = store
+            .changed_since(seen)
+            .iter()
+            .map(|(_, part)| part.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parts,
+            [
+                StoredPart::Tag(id),
+                StoredPart::Tag(id),
+                StoredPart::Trashed(id),
+                StoredPart::Frame(video, secs(5)),
+            ]
+        );
+        assert_eq!(store.row(&StoredPart::Tag(id)), StoredRow::Tag(id, None));
+    }
+
+    #[test]
+    fn loading_a_video_keeps_what_changed_before() {
+        let video = PathBuf::from("match.mp4");
+        let mut stored = TagStore::new();
+        let kept = stored.add(video.clone(), secs(1), "goal".into()).unwrap();
+        let other = stored.add(video.clone(), secs(2), "foul".into()).unwrap();
+
+        let mut store = TagStore::from_rows(stored.settings(), Vec::new(), Vec::new());
+        assert!(!store.is_loaded(&video));
+        let added = store.add(video.clone(), secs(9), "corner".into()).unwrap();
+        store.set_note(added, "late".into());
+        assert!(![kept, other].contains(&added));
+
+        // the storage has the new tag too, as it was before the note
+        let mut rows = stored.tags().to_vec();
+        rows.push(Tag {
+            note: String::new(),
+            ..store.get(added).unwrap().clone()
+        });
+        store.load_video(video.clone(), rows);
+        assert!(store.is_loaded(&video));
+        let ids = store.tags().iter().map(|tag| tag.id).collect::<Vec<_>>();
+        assert_eq!(ids, [kept, other, added]);
+        assert_eq!(store.get(added).unwrap().note, "late");
+        assert!(!store.is_complete());
+    }
+}
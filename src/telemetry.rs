@@ -0,0 +1,501 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use cushy::{
+    context::{EventContext, GraphicsContext, LayoutContext},
+    figures::{
+        units::{Px, UPx},
+        FloatConversion, IntoSigned, Point, Rect, Size,
+    },
+    kludgine::app::winit::event::{DeviceId, MouseButton},
+    kludgine::shapes::{PathBuilder, Shape, StrokeOptions},
+    styles::Color,
+    value::{Destination, Dynamic, MapEach, Source},
+    widget::{EventHandling, MakeWidget, SharedCallback, Widget, HANDLED, IGNORED},
+    ConstraintLimit,
+};
+use ffmpeg_next as ffmpeg;
+
+use crate::media::open_input;
+use crate::renditions::Renditions;
+
+// gopro writes its metadata track under this handler name
+const GPMF_HANDLER: &str = "GoPro MET";
+const EARTH_RADIUS_METERS: f64 = 6_371_000.;
+const MAP_WIDTH: u32 = 240;
+const MAP_HEIGHT: u32 = 180;
+// keeps the ends of the track off the edges
+const MAP_PADDING: f32 = 8.;
+const MARKER_SIZE: i32 = 8;
+const MAP_BACKGROUND: Color = Color::new(0x20, 0x24, 0x28, 0xff);
+const TRACK_COLOR: Color = Color::new(0x42, 0xd4, 0xf4, 0xff);
+const MARKER_COLOR: Color = Color::new(0xe6, 0x19, 0x4b, 0xff);
+
+// where the camera was at one moment of the video
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub time: Duration,
+    pub latitude: f64,
+    pub longitude: f64,
+    // meters, above sea level when the camera knows it, above takeoff otherwise
+    pub altitude: Option<f64>,
+    // meters per second
+    pub speed: Option<f64>,
+}
+
+impl Sample {
+    // one line for the readout and for tag notes
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(speed) = self.speed {
+            parts.push(format!("{:.1} km/h", speed * 3.6));
+        }
+        parts.push(format!("{:.6}, {:.6}", self.latitude, self.longitude));
+        if let Some(altitude) = self.altitude {
+            parts.push(format!("alt {altitude:.1} m"));
+        }
+        parts.join("  ")
+    }
+
+    fn meters_to(&self, other: &Sample) -> f64 {
+        let (lat_a, lat_b) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat_b - lat_a;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+        let a = (d_lat / 2.).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.).sin().powi(2);
+        2. * EARTH_RADIUS_METERS * a.sqrt().asin()
+    }
+}
+
+// the gps track recorded along with a video, sorted by time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Telemetry {
+    samples: Vec<Sample>,
+}
+
+impl Telemetry {
+    // a dji subtitle file next to `video`, or the gpmf track inside a gopro video
+    pub fn load(video: &Path) -> Option<Self> {
+        let samples = match sidecar(video) {
+            Some(srt) => match std::fs::read_to_string(&srt) {
+                Ok(text) => read_dji_srt(&text),
+                Err(err) => {
+                    tracing::error!("failed to read {}: {err}", srt.display());
+                    Vec::new()
+                }
+            },
+            None => read_gpmf(video).unwrap_or_else(|err| {
+                tracing::error!("failed to read the telemetry of {}: {err}", video.display());
+                Vec::new()
+            }),
+        };
+        (!samples.is_empty()).then(|| Self::new(samples))
+    }
+
+    fn new(mut samples: Vec<Sample>) -> Self {
+        samples.sort_by_key(|sample| sample.time);
+        // dji only logs positions, the speed comes from how far apart they are
+        for idx in 1..samples.len() {
+            if samples[idx].speed.is_some() {
+                continue;
+            }
+            let (before, after) = (samples[idx - 1], samples[idx]);
+            let seconds = (after.time - before.time).as_secs_f64();
+            if seconds > 0. {
+                samples[idx].speed = Some(before.meters_to(&after) / seconds);
+            }
+        }
+        Self { samples }
+    }
+
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    // the last sample at or before `time`
+    pub fn at(&self, time: Duration) -> Option<&Sample> {
+        let idx = self.samples.partition_point(|sample| sample.time <= time);
+        self.samples.get(idx.checked_sub(1)?)
+    }
+}
+
+fn sidecar(video: &Path) -> Option<PathBuf> {
+    ["SRT", "srt"]
+        .into_iter()
+        .map(|extension| video.with_extension(extension))
+        .find(|path| path.is_file())
+}
+
+// the number after `key` in a dji subtitle line, e.g. "[latitude: 22.5]" or "H.S 3.2m/s"
+fn value_after(text: &str, key: &str) -> Option<f64> {
+    let rest = &text[text.find(key)? + key.len()..];
+    let rest = rest.trim_start_matches([':', ' ', '=']);
+    let end = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+fn parse_srt_time(text: &str) -> Option<Duration> {
+    let (clock, millis) = text.trim().split_once([',', '.'])?;
+    let mut parts = clock.split(':').map(|part| part.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (parts.next()??, parts.next()??, parts.next()??);
+    let millis = Duration::from_millis(millis.parse().ok()?);
+    Some(Duration::from_secs(hours * 3600 + minutes * 60 + seconds) + millis)
+}
+
+// one subtitle per frame or per second depending on the drone. newer ones write
+// "[latitude: ..] [longitude: ..] [rel_alt: .. abs_alt: ..]", older ones
+// "GPS(lon,lat,..) .. H 12.3m H.S 3.2m/s"
+fn read_dji_srt(text: &str) -> Vec<Sample> {
+    let text = text.replace("\r\n", "\n");
+    text.split("\n\n")
+        .filter_map(|block| {
+            let mut lines = block.trim().lines();
+            let _index = lines.next()?;
+            let (start, _) = lines.next()?.split_once("-->")?;
+            let time = parse_srt_time(start)?;
+            let body = lines.collect::<Vec<_>>().join(" ");
+
+            let (latitude, longitude, altitude, speed) = if body.contains("latitude") {
+                (
+                    value_after(&body, "latitude")?,
+                    value_after(&body, "longitude")?,
+                    value_after(&body, "abs_alt")
+                        .or_else(|| value_after(&body, "altitude"))
+                        .or_else(|| value_after(&body, "rel_alt")),
+                    None,
+                )
+            } else {
+                let gps = &body[body.find("GPS(")? + 4..];
+                let mut coordinates = gps[..gps.find(')')?]
+                    .split(',')
+                    .map(|value| value.trim().trim_end_matches(['M', 'm']).parse::<f64>().ok());
+                let longitude = coordinates.next()??;
+                let latitude = coordinates.next()??;
+                (
+                    latitude,
+                    longitude,
+                    value_after(&body, "BAROMETER").or_else(|| value_after(&body, "H ")),
+                    value_after(&body, "H.S"),
+                )
+            };
+            // no fix yet
+            if latitude == 0. && longitude == 0. {
+                return None;
+            }
+            Some(Sample {
+                time,
+                latitude,
+                longitude,
+                altitude,
+                speed,
+            })
+        })
+        .collect()
+}
+
+fn read_gpmf(video: &Path) -> Result<Vec<Sample>, ffmpeg::Error> {
+    let mut ictx = open_input(video)?;
+    let Some(stream) = ictx.streams().find(|stream| {
+        stream
+            .metadata()
+            .get("handler_name")
+            .is_some_and(|handler| handler.contains(GPMF_HANDLER))
+    }) else {
+        return Ok(Vec::new());
+    };
+    let stream_index = stream.index();
+    let time_base = f64::from(stream.time_base());
+
+    let mut samples = Vec::new();
+    for (stream, packet) in ictx.packets() {
+        if stream.index() != stream_index {
+            continue;
+        }
+        let (Some(data), Some(pts)) = (packet.data(), packet.pts()) else {
+            continue;
+        };
+        // a packet holds about a second of fixes, spread evenly over it
+        let start = pts as f64 * time_base;
+        let length = packet.duration() as f64 * time_base;
+        let mut fixes = Vec::new();
+        read_gps5(data, &mut fixes);
+        let count = fixes.len() as f64;
+        for (idx, [latitude, longitude, altitude, speed, _]) in fixes.into_iter().enumerate() {
+            if latitude == 0. && longitude == 0. {
+                continue;
+            }
+            samples.push(Sample {
+                time: Duration::from_secs_f64((start + length * idx as f64 / count).max(0.)),
+                latitude,
+                longitude,
+                altitude: Some(altitude),
+                speed: Some(speed),
+            });
+        }
+    }
+    Ok(samples)
+}
+
+// walks gpmf's key, type, size, repeat items for GPS5 (latitude, longitude, altitude,
+// 2d and 3d speed). SCAL divides the values of the stream it's in. the GPS9 of newer
+// cameras isn't read
+fn read_gps5(data: &[u8], fixes: &mut Vec<[f64; 5]>) {
+    let mut scale = vec![1.];
+    let mut offset = 0;
+    while offset + 8 <= data.len() {
+        let key = &data[offset..offset + 4];
+        let kind = data[offset + 4];
+        let size = usize::from(data[offset + 5]);
+        let repeat = usize::from(u16::from_be_bytes([data[offset + 6], data[offset + 7]]));
+        let start = offset + 8;
+        let end = start + size * repeat;
+        let Some(value) = data.get(start..end) else {
+            return;
+        };
+        match (key, kind) {
+            // nested items
+            (_, 0) => read_gps5(value, fixes),
+            (b"SCAL", _) => scale = read_numbers(kind, value),
+            (b"GPS5", b'l') => {
+                for fix in value.chunks_exact(20) {
+                    let mut values = [0.; 5];
+                    for (idx, value) in fix.chunks_exact(4).enumerate() {
+                        let raw = i32::from_be_bytes([value[0], value[1], value[2], value[3]]);
+                        let divisor = scale.get(idx).or(scale.last()).copied().unwrap_or(1.);
+                        values[idx] = f64::from(raw) / if divisor == 0. { 1. } else { divisor };
+                    }
+                    fixes.push(values);
+                }
+            }
+            _ => {}
+        }
+        // items are padded to 4 bytes
+        offset = start + (size * repeat).div_ceil(4) * 4;
+    }
+}
+
+fn read_numbers(kind: u8, value: &[u8]) -> Vec<f64> {
+    match kind {
+        b'l' => value
+            .chunks_exact(4)
+            .map(|bytes| f64::from(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])))
+            .collect(),
+        b'L' => value
+            .chunks_exact(4)
+            .map(|bytes| f64::from(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])))
+            .collect(),
+        b's' => value
+            .chunks_exact(2)
+            .map(|bytes| f64::from(i16::from_be_bytes([bytes[0], bytes[1]])))
+            .collect(),
+        b'S' => value
+            .chunks_exact(2)
+            .map(|bytes| f64::from(u16::from_be_bytes([bytes[0], bytes[1]])))
+            .collect(),
+        _ => vec![1.],
+    }
+}
+
+// reads the telemetry of whatever opens, proxies use their original's
+pub fn follow(
+    video_source: &Dynamic<Option<PathBuf>>,
+    renditions: Dynamic<Renditions>,
+    telemetry: Dynamic<Option<Telemetry>>,
+) {
+    let current = video_source.clone();
+    video_source
+        .for_each(move |video| {
+            telemetry.set(None);
+            let Some(video) = video.clone() else {
+                return;
+            };
+            let original = renditions.map_ref(|renditions| renditions.original_of(&video));
+            let current = current.clone();
+            let telemetry = telemetry.clone();
+            std::thread::Builder::new()
+                .name("Telemetry Thread".into())
+                .spawn(move || {
+                    let loaded = Telemetry::load(&original);
+                    // another video may have opened in the meantime
+                    if current.get().as_ref() == Some(&video) {
+                        telemetry.set(loaded);
+                    }
+                })
+                .unwrap();
+        })
+        .persist();
+}
+
+// the gps track drawn as a line, north up, with a marker where playback is. there are
+// no map tiles, only the shape of the route. clicking it seeks to the closest fix
+#[derive(Debug)]
+struct TrackMap {
+    telemetry: Dynamic<Option<Telemetry>>,
+    time: Dynamic<Duration>,
+    on_seek: SharedCallback<Duration>,
+    // where the last redraw put every fix, for clicks
+    projected: Vec<(Point<Px>, Duration)>,
+}
+
+impl TrackMap {
+    // equirectangular with longitude shrunk by the latitude, close enough at the
+    // scale of one video, fitted into `size` keeping its proportions
+    fn project(samples: &[Sample], size: Size<Px>) -> Vec<(Point<Px>, Duration)> {
+        let Some(first) = samples.first() else {
+            return Vec::new();
+        };
+        let shrink = first.latitude.to_radians().cos();
+        let (mut min_x, mut max_x, mut min_y, mut max_y) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+        for sample in samples {
+            let x = sample.longitude * shrink;
+            min_x = min_x.min(x);
+            max_x = max_x.max(x);
+            min_y = min_y.min(sample.latitude);
+            max_y = max_y.max(sample.latitude);
+        }
+        let width = f64::from(size.width.into_float() - MAP_PADDING * 2.).max(1.);
+        let height = f64::from(size.height.into_float() - MAP_PADDING * 2.).max(1.);
+        let scale = (width / (max_x - min_x).max(f64::EPSILON))
+            .min(height / (max_y - min_y).max(f64::EPSILON));
+        // centered along whichever side has room to spare
+        let left = f64::from(MAP_PADDING) + (width - (max_x - min_x) * scale) / 2.;
+        let top = f64::from(MAP_PADDING) + (height - (max_y - min_y) * scale) / 2.;
+
+        samples
+            .iter()
+            .map(|sample| {
+                let x = left + (sample.longitude * shrink - min_x) * scale;
+                let y = top + (max_y - sample.latitude) * scale;
+                (Point::new(Px::from(x as f32), Px::from(y as f32)), sample.time)
+            })
+            .collect()
+    }
+}
+
+impl Widget for TrackMap {
+    fn redraw(&mut self, context: &mut GraphicsContext<'_, '_, '_, '_>) {
+        use cushy::context::Trackable;
+
+        self.telemetry.redraw_when_changed(context);
+        let time = self.time.get_tracking_redraw(context);
+
+        let size = context.gfx.size().into_signed();
+        context
+            .gfx
+            .draw_shape(&Shape::filled_rect(Rect::from(size), MAP_BACKGROUND));
+
+        let (projected, current) = self.telemetry.map_ref(|telemetry| {
+            let Some(telemetry) = telemetry else {
+                return (Vec::new(), None);
+            };
+            let current = telemetry.at(time).map(|sample| sample.time);
+            (Self::project(telemetry.samples(), size), current)
+        });
+        self.projected = projected;
+
+        let mut points = self.projected.iter().map(|(point, _)| *point);
+        if let Some(first) = points.next() {
+            let path = points.fold(PathBuilder::new(first), PathBuilder::line_to).build();
+            context
+                .gfx
+                .draw_shape(&path.stroke(StrokeOptions::px_wide(Px::new(2)).colored(TRACK_COLOR)));
+        }
+
+        let marker = current
+            .and_then(|current| self.projected.iter().find(|(_, time)| *time == current));
+        if let Some((center, _)) = marker {
+            let size = Px::new(MARKER_SIZE);
+            context.gfx.draw_shape(&Shape::filled_rect(
+                Rect::new(*center - Point::new(size / 2, size / 2), Size::new(size, size)),
+                MARKER_COLOR,
+            ));
+        }
+    }
+
+    fn layout(
+        &mut self,
+        available_space: Size<ConstraintLimit>,
+        context: &mut LayoutContext<'_, '_, '_, '_>,
+    ) -> Size<UPx> {
+        Size::new(UPx::new(MAP_WIDTH), UPx::new(MAP_HEIGHT))
+    }
+
+    fn hit_test(&mut self, location: Point<Px>, context: &mut EventContext<'_>) -> bool {
+        true
+    }
+
+    fn mouse_down(
+        &mut self,
+        location: Point<Px>,
+        device_id: DeviceId,
+        button: MouseButton,
+        context: &mut EventContext<'_>,
+    ) -> EventHandling {
+        if button != MouseButton::Left {
+            return IGNORED;
+        }
+        let closest = self.projected.iter().min_by_key(|(point, _)| {
+            let distance = *point - location;
+            let (x, y) = (distance.x.get(), distance.y.get());
+            i64::from(x) * i64::from(x) + i64::from(y) * i64::from(y)
+        });
+        match closest {
+            Some((_, time)) => {
+                self.on_seek.invoke(*time);
+                HANDLED
+            }
+            None => IGNORED,
+        }
+    }
+}
+
+// speed, position and altitude at `time` on the original's timeline, whether new tags
+// get them in their note, and the route on a map that seeks with `on_seek`
+pub fn telemetry_bar(
+    telemetry: &Dynamic<Option<Telemetry>>,
+    time: &Dynamic<Duration>,
+    stamp: Dynamic<bool>,
+    on_seek: SharedCallback<Duration>,
+) -> impl MakeWidget {
+    let readout = (telemetry, time).map_each(|(telemetry, time)| {
+        telemetry
+            .as_ref()
+            .and_then(|telemetry| telemetry.at(*time))
+            .map_or_else(|| String::from("no fix"), Sample::describe)
+    });
+    let stamp_button = stamp
+        .map_each(|stamp| {
+            if *stamp {
+                "[telemetry in tags]"
+            } else {
+                "telemetry in tags"
+            }
+        })
+        .into_button()
+        .on_click(move |_| stamp.toggle());
+    let show_map = Dynamic::new(false);
+    let map_button = show_map
+        .map_each(|show| if *show { "[map]" } else { "map" })
+        .into_button()
+        .on_click({
+            let show_map = show_map.clone();
+            move |_| show_map.toggle()
+        });
+    let map = TrackMap {
+        telemetry: telemetry.clone(),
+        time: time.clone(),
+        on_seek,
+        projected: Vec::new(),
+    };
+
+    readout
+        .and(stamp_button)
+        .and(map_button)
+        .into_columns()
+        .and(map.collapse_vertically(show_map.map_each(|show| !show)))
+        .into_rows()
+        .collapse_vertically(telemetry.map_each(Option::is_none))
+}
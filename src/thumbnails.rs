@@ -0,0 +1,130 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use cushy::{
+    kludgine::{image::DynamicImage, wgpu::FilterMode, LazyTexture},
+    value::{Destination, Dynamic, Source},
+};
+use ffmpeg_next as ffmpeg;
+
+use crate::media::open_input;
+
+// across the whole video, however long
+const THUMBNAIL_COUNT: usize = 60;
+// longest side in pixels, the strip only shows them small
+const THUMBNAIL_SIZE: u32 = 128;
+
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    // of the keyframe it shows, on the playing file's timeline
+    pub time: Duration,
+    // width over height
+    pub aspect: f32,
+    pub texture: LazyTexture,
+}
+
+// fills `thumbnails` for whatever video opens, a few at a time as they're decoded, on
+// its own input so playback isn't disturbed
+pub fn follow(video_source: &Dynamic<Option<PathBuf>>, thumbnails: Dynamic<Vec<Thumbnail>>) {
+    let current = video_source.clone();
+    video_source
+        .for_each(move |video| {
+            thumbnails.lock().clear();
+            let Some(video) = video.clone() else {
+                return;
+            };
+            let current = current.clone();
+            let thumbnails = thumbnails.clone();
+            std::thread::Builder::new()
+                .name("Thumbnail Thread".into())
+                .spawn(move || {
+                    let generated = generate(&video, |thumbnail| {
+                        // another video may have opened in the meantime
+                        if current.get().as_ref() != Some(&video) {
+                            return false;
+                        }
+                        thumbnails.lock().push(thumbnail);
+                        true
+                    });
+                    if let Err(err) = generated {
+                        tracing::error!("failed to make thumbnails of {}: {err}", video.display());
+                    }
+                })
+                .unwrap();
+        })
+        .persist();
+}
+
+// seeks to evenly spaced points and shows the keyframe each lands on. nothing but
+// keyframes is decoded, so this stays quick on long videos. stops when `on_thumbnail`
+// returns false
+fn generate(
+    video: &Path,
+    mut on_thumbnail: impl FnMut(Thumbnail) -> bool,
+) -> Result<(), ffmpeg::Error> {
+    let mut ictx = open_input(video)?;
+    let stream = ictx
+        .streams()
+        .best(ffmpeg::media::Type::Video)
+        .ok_or(ffmpeg::Error::StreamNotFound)?;
+    let stream_index = stream.index();
+    let time_base = f64::from(stream.time_base());
+    let mut decoder =
+        ffmpeg::codec::context::Context::from_parameters(stream.parameters())?.decoder();
+    decoder.skip_frame(ffmpeg::Discard::NonKey);
+    let mut decoder = decoder.video()?;
+    let duration = ictx.duration() as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE);
+    if duration <= 0. {
+        return Ok(());
+    }
+
+    let mut frame = ffmpeg::util::frame::Video::empty();
+    let mut rescaler = None;
+    let mut last = None;
+    for idx in 0..THUMBNAIL_COUNT {
+        let seconds = duration * idx as f64 / THUMBNAIL_COUNT as f64;
+        let target = (seconds * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+        ictx.seek(target, ..=target)?;
+        decoder.flush();
+
+        let mut decoded = false;
+        for (stream, packet) in ictx.packets() {
+            if stream.index() != stream_index
+                || !packet.is_key()
+                || decoder.send_packet(&packet).is_err()
+            {
+                continue;
+            }
+            if decoder.receive_frame(&mut frame).is_ok() {
+                decoded = true;
+                break;
+            }
+        }
+        if !decoded {
+            continue;
+        }
+
+        // long gaps between keyframes land several seeks on the same one
+        let time = frame.timestamp().map_or(seconds, |pts| pts as f64 * time_base);
+        let time = Duration::from_secs_f64(time.max(0.));
+        if last == Some(time) {
+            continue;
+        }
+        last = Some(time);
+
+        let image = crate::frame_to_image(&frame, Some(THUMBNAIL_SIZE), &mut rescaler);
+        let aspect = image.width() as f32 / image.height().max(1) as f32;
+        let texture = LazyTexture::from_image(DynamicImage::from(image), FilterMode::Linear);
+        if !on_thumbnail(Thumbnail {
+            time,
+            aspect,
+            texture,
+        }) {
+            break;
+        }
+    }
+
+    Ok(())
+}
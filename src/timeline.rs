@@ -0,0 +1,251 @@
+use std::time::Duration;
+
+use cushy::{
+    context::{EventContext, GraphicsContext, LayoutContext},
+    figures::{
+        units::{Px, UPx},
+        FloatConversion, IntoSigned, Point, Rect, Size,
+    },
+    kludgine::app::winit::event::{DeviceId, MouseButton},
+    kludgine::app::winit::window::CursorIcon,
+    animation::ZeroToOne,
+    kludgine::shapes::Shape,
+    styles::Color,
+    value::{Destination, Dynamic, Source},
+    widget::{EventHandling, SharedCallback, Widget, HANDLED, IGNORED},
+    ConstraintLimit,
+};
+
+use crate::chapters::Chapter;
+use crate::hover_preview::Hover;
+use crate::tag_spans::Span;
+use crate::thumbnails::Thumbnail;
+
+// the filmstrip on top, the tags under it
+const FILMSTRIP_HEIGHT: u32 = 40;
+const TIMELINE_HEIGHT: u32 = 24;
+// range tags fill the middle of the bar, instants go through all of it
+const RANGE_INSET: i32 = 6;
+const TICK_WIDTH: i32 = 2;
+const PLAYHEAD_WIDTH: i32 = 2;
+const BACKGROUND: Color = Color::new(0x30, 0x30, 0x30, 0xff);
+const PLAYED: Color = Color::new(0x50, 0x50, 0x50, 0xff);
+const PLAYHEAD_COLOR: Color = Color::new(0xff, 0xff, 0xff, 0xff);
+const MARK_IN_COLOR: Color = Color::new(0xff, 0xd0, 0x40, 0xff);
+// chapter starts go down the filmstrip, over the thumbnails
+const CHAPTER_COLOR: Color = Color::new(0x40, 0xc0, 0xff, 0xff);
+
+// the open video's whole duration as a bar: thumbnails of what's where, where its
+// chapters start, how far playback got, its tags, the in point of a range being marked
+// and the playhead.
+// clicking or dragging along it seeks
+#[derive(Debug)]
+pub struct Timeline {
+    thumbnails: Dynamic<Vec<Thumbnail>>,
+    spans: Dynamic<Vec<Span>>,
+    chapters: Dynamic<Vec<Chapter>>,
+    mark_in: Dynamic<Option<Duration>>,
+    position: Dynamic<Duration>,
+    duration: Dynamic<Duration>,
+    on_seek: SharedCallback<Duration>,
+    // where the pointer is, for the frame preview
+    hover: Dynamic<Option<Hover>>,
+    // as of the last redraw, for turning clicks into times
+    width: Px,
+}
+
+impl Timeline {
+    pub fn new(
+        thumbnails: Dynamic<Vec<Thumbnail>>,
+        spans: Dynamic<Vec<Span>>,
+        mark_in: Dynamic<Option<Duration>>,
+        position: Dynamic<Duration>,
+        duration: Dynamic<Duration>,
+        on_seek: SharedCallback<Duration>,
+        hover: Dynamic<Option<Hover>>,
+    ) -> Self {
+        Self {
+            thumbnails,
+            spans,
+            chapters: Dynamic::default(),
+            mark_in,
+            position,
+            duration,
+            on_seek,
+            hover,
+            width: Px::ZERO,
+        }
+    }
+
+    pub fn with_chapters(mut self, chapters: Dynamic<Vec<Chapter>>) -> Self {
+        self.chapters = chapters;
+        self
+    }
+
+    // the time under `location`, none before a video is open
+    fn hover_at(&self, location: Point<Px>) -> Option<Hover> {
+        let duration = self.duration.get();
+        if duration.is_zero() || self.width <= Px::ZERO {
+            return None;
+        }
+        let fraction = (location.x.into_float() / self.width.into_float()).clamp(0., 1.);
+        Some(Hover {
+            fraction,
+            time: duration.mul_f32(fraction),
+        })
+    }
+
+    fn seek_to(&self, location: Point<Px>) {
+        if let Some(hover) = self.hover_at(location) {
+            self.on_seek.invoke(hover.time);
+        }
+    }
+}
+
+impl Widget for Timeline {
+    fn redraw(&mut self, context: &mut GraphicsContext<'_, '_, '_, '_>) {
+        use cushy::context::Trackable;
+
+        self.thumbnails.redraw_when_changed(context);
+        self.spans.redraw_when_changed(context);
+        self.chapters.redraw_when_changed(context);
+        let duration = self.duration.get_tracking_redraw(context);
+        let position = self.position.get_tracking_redraw(context);
+        let mark_in = self.mark_in.get_tracking_redraw(context);
+
+        let size = context.gfx.size().into_signed();
+        self.width = size.width;
+        context
+            .gfx
+            .draw_shape(&Shape::filled_rect(Rect::from(size), BACKGROUND));
+        if duration.is_zero() {
+            return;
+        }
+
+        let x = |time: Duration| {
+            Px::from(time.as_secs_f32() / duration.as_secs_f32() * size.width.into_float())
+        };
+        let strip = Px::new(FILMSTRIP_HEIGHT as i32);
+        let bar_height = size.height - strip;
+        let bar = |start: Px, width: Px, inset: Px, color: Color| {
+            Shape::filled_rect(
+                Rect::new(
+                    Point::new(start, strip + inset),
+                    Size::new(width, bar_height - inset - inset),
+                ),
+                color,
+            )
+        };
+
+        // each thumbnail from its keyframe up to the next one, keeping its proportions
+        self.thumbnails.map_ref(|thumbnails| {
+            for (idx, thumbnail) in thumbnails.iter().enumerate() {
+                let start = x(thumbnail.time);
+                let next = thumbnails.get(idx + 1).map_or(duration, |next| next.time);
+                let width = (x(next) - start).min(strip * thumbnail.aspect);
+                if width <= Px::ZERO {
+                    continue;
+                }
+                context.gfx.draw_texture(
+                    &thumbnail.texture,
+                    Rect::new(Point::new(start, Px::ZERO), Size::new(width, strip)),
+                    ZeroToOne::new(1.),
+                );
+            }
+        });
+
+        let tick = Px::new(TICK_WIDTH);
+        self.chapters.map_ref(|chapters| {
+            for chapter in chapters.iter().filter(|chapter| !chapter.start.is_zero()) {
+                context.gfx.draw_shape(&Shape::filled_rect(
+                    Rect::new(Point::new(x(chapter.start), Px::ZERO), Size::new(tick, strip)),
+                    CHAPTER_COLOR,
+                ));
+            }
+        });
+
+        let playhead = x(position.min(duration));
+        context
+            .gfx
+            .draw_shape(&bar(Px::ZERO, playhead, Px::ZERO, PLAYED));
+
+        self.spans.map_ref(|spans| {
+            // ranges under the instants, so a marker inside a range stays visible
+            for span in spans {
+                if let Some(end) = span.end {
+                    let start = x(span.start);
+                    let width = (x(end) - start).max(tick);
+                    context
+                        .gfx
+                        .draw_shape(&bar(start, width, Px::new(RANGE_INSET), span.color));
+                }
+            }
+            for span in spans.iter().filter(|span| span.end.is_none()) {
+                context
+                    .gfx
+                    .draw_shape(&bar(x(span.start), tick, Px::ZERO, span.color));
+            }
+        });
+
+        if let Some(mark_in) = mark_in {
+            context
+                .gfx
+                .draw_shape(&bar(x(mark_in), tick, Px::ZERO, MARK_IN_COLOR));
+        }
+        // the playhead goes through the filmstrip as well
+        let width = Px::new(PLAYHEAD_WIDTH);
+        context.gfx.draw_shape(&Shape::filled_rect(
+            Rect::new(Point::new(playhead - width / 2, Px::ZERO), Size::new(width, size.height)),
+            PLAYHEAD_COLOR,
+        ));
+    }
+
+    fn layout(
+        &mut self,
+        available_space: Size<ConstraintLimit>,
+        context: &mut LayoutContext<'_, '_, '_, '_>,
+    ) -> Size<UPx> {
+        Size::new(
+            available_space.width.max(),
+            UPx::new(FILMSTRIP_HEIGHT + TIMELINE_HEIGHT),
+        )
+    }
+
+    fn hit_test(&mut self, location: Point<Px>, context: &mut EventContext<'_>) -> bool {
+        true
+    }
+
+    fn hover(&mut self, location: Point<Px>, context: &mut EventContext<'_>) -> Option<CursorIcon> {
+        self.hover.set(self.hover_at(location));
+        None
+    }
+
+    fn unhover(&mut self, context: &mut EventContext<'_>) {
+        self.hover.set(None);
+    }
+
+    fn mouse_down(
+        &mut self,
+        location: Point<Px>,
+        device_id: DeviceId,
+        button: MouseButton,
+        context: &mut EventContext<'_>,
+    ) -> EventHandling {
+        if button != MouseButton::Left || self.duration.get().is_zero() {
+            return IGNORED;
+        }
+        self.seek_to(location);
+        HANDLED
+    }
+
+    fn mouse_drag(
+        &mut self,
+        location: Point<Px>,
+        device_id: DeviceId,
+        button: MouseButton,
+        context: &mut EventContext<'_>,
+    ) {
+        self.seek_to(location);
+        self.hover.set(self.hover_at(location));
+    }
+}
@@ -0,0 +1,188 @@
+use std::{collections::VecDeque, time::Duration};
+
+use cushy::{
+    context::{GraphicsContext, LayoutContext},
+    figures::{
+        units::{Px, UPx},
+        IntoSigned, Point, Rect, Size,
+    },
+    kludgine::shapes::Shape,
+    styles::Color,
+    value::{Dynamic, MapEach, Source},
+    widget::{MakeWidget, Widget},
+    ConstraintLimit,
+};
+
+// about four seconds of 30fps footage
+const HISTORY: usize = 120;
+const GRAPH_WIDTH: u32 = 360;
+const GRAPH_HEIGHT: u32 = 90;
+// one frame at 60fps, drawn as a line so spikes over budget stand out
+const FRAME_BUDGET: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+const DECODE_COLOR: Color = Color::new(0x44, 0x77, 0xaa, 0xff);
+const CONVERT_COLOR: Color = Color::new(0xcc, 0xbb, 0x44, 0xff);
+const UPLOAD_COLOR: Color = Color::new(0x22, 0x88, 0x33, 0xff);
+const DRAW_COLOR: Color = Color::new(0xee, 0x66, 0x77, 0xff);
+const BUDGET_COLOR: Color = Color::new(0xff, 0xff, 0xff, 0x80);
+const BACKGROUND_COLOR: Color = Color::new(0x00, 0x00, 0x00, 0xa0);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameTiming {
+    // packet in to frame out of the decoder
+    pub decode: Duration,
+    // yuv to rgba, including scaling
+    pub convert: Duration,
+    // building the texture data handed to the gpu
+    pub upload: Duration,
+    // the player widget drawing the frame, textures are uploaded lazily so this includes
+    // the transfer. submitting and presenting the window's frame come after and aren't
+    // measured. none until the frame has been drawn
+    pub draw: Option<Duration>,
+}
+
+impl FrameTiming {
+    fn stages(&self) -> [(Duration, Color); 4] {
+        [
+            (self.decode, DECODE_COLOR),
+            (self.convert, CONVERT_COLOR),
+            (self.upload, UPLOAD_COLOR),
+            (self.draw.unwrap_or_default(), DRAW_COLOR),
+        ]
+    }
+
+    fn total(&self) -> Duration {
+        self.stages().iter().map(|(duration, _)| *duration).sum()
+    }
+}
+
+// the last few seconds of frames, oldest first
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameTimings {
+    frames: VecDeque<FrameTiming>,
+    // late frames left out since the video opened, see `FrameDue::Drop`
+    dropped: usize,
+}
+
+impl FrameTimings {
+    pub fn push(&mut self, timing: FrameTiming) {
+        if self.frames.len() == HISTORY {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(timing);
+    }
+
+    pub fn awaiting_draw(&self) -> bool {
+        self.frames.back().is_some_and(|frame| frame.draw.is_none())
+    }
+
+    pub fn drawn(&mut self, draw: Duration) {
+        if let Some(frame) = self.frames.back_mut() {
+            frame.draw.get_or_insert(draw);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.dropped = 0;
+    }
+
+    pub fn set_dropped(&mut self, dropped: usize) {
+        self.dropped = dropped;
+    }
+
+    fn average(&self) -> FrameTiming {
+        let count = self.frames.len().max(1) as u32;
+        let drawn = self.frames.iter().filter(|frame| frame.draw.is_some()).count().max(1) as u32;
+        FrameTiming {
+            decode: self.frames.iter().map(|frame| frame.decode).sum::<Duration>() / count,
+            convert: self.frames.iter().map(|frame| frame.convert).sum::<Duration>() / count,
+            upload: self.frames.iter().map(|frame| frame.upload).sum::<Duration>() / count,
+            draw: Some(
+                self.frames
+                    .iter()
+                    .filter_map(|frame| frame.draw)
+                    .sum::<Duration>()
+                    / drawn,
+            ),
+        }
+    }
+}
+
+// stacked bars per frame, newest on the right
+#[derive(Debug)]
+struct TimingGraph {
+    timings: Dynamic<FrameTimings>,
+}
+
+impl Widget for TimingGraph {
+    fn redraw(&mut self, context: &mut GraphicsContext<'_, '_, '_, '_>) {
+        use cushy::context::Trackable;
+
+        self.timings.redraw_when_changed(context);
+
+        let size = context.gfx.size().into_signed();
+        context
+            .gfx
+            .draw_shape(&Shape::filled_rect(Rect::from(size), BACKGROUND_COLOR));
+
+        self.timings.map_ref(|timings| {
+            let slowest = timings
+                .frames
+                .iter()
+                .map(FrameTiming::total)
+                .max()
+                .unwrap_or_default()
+                .max(FRAME_BUDGET * 2);
+            let height_of = |duration: Duration| {
+                Px::new((duration.as_secs_f64() / slowest.as_secs_f64() * f64::from(size.height.get())) as i32)
+            };
+            let bar_width = (size.width / HISTORY as i32).max(Px::new(1));
+            let first = HISTORY - timings.frames.len();
+
+            for (idx, frame) in timings.frames.iter().enumerate() {
+                let x = bar_width * (first + idx) as i32;
+                let mut bottom = size.height;
+                for (duration, color) in frame.stages() {
+                    let height = height_of(duration);
+                    bottom -= height;
+                    context.gfx.draw_shape(&Shape::filled_rect(
+                        Rect::new(Point::new(x, bottom), Size::new(bar_width, height)),
+                        color,
+                    ));
+                }
+            }
+
+            let budget = size.height - height_of(FRAME_BUDGET);
+            context.gfx.draw_shape(&Shape::filled_rect(
+                Rect::new(Point::new(Px::ZERO, budget), Size::new(size.width, Px::new(1))),
+                BUDGET_COLOR,
+            ));
+        });
+    }
+
+    fn layout(
+        &mut self,
+        available_space: Size<ConstraintLimit>,
+        context: &mut LayoutContext<'_, '_, '_, '_>,
+    ) -> Size<UPx> {
+        Size::new(UPx::new(GRAPH_WIDTH), UPx::new(GRAPH_HEIGHT))
+    }
+}
+
+pub fn timing_overlay(timings: Dynamic<FrameTimings>) -> impl MakeWidget {
+    let legend = timings.map_each(|timings| {
+        let average = timings.average();
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.;
+        format!(
+            "decode {:.1}ms  convert {:.1}ms  upload {:.1}ms  draw {:.1}ms  dropped {}",
+            ms(average.decode),
+            ms(average.convert),
+            ms(average.upload),
+            ms(average.draw.unwrap_or_default()),
+            timings.dropped,
+        )
+    });
+
+    legend.and(TimingGraph { timings }).into_rows()
+}
@@ -0,0 +1,176 @@
+use std::{path::Path, time::Duration};
+
+use cushy::kludgine::image::{imageops::FilterType, DynamicImage, GrayImage, RgbaImage};
+use ffmpeg_next as ffmpeg;
+
+use crate::media::decode_forward;
+use crate::regions::{Keyframe, Region};
+
+// frames are matched at this width, plenty for a box and quick enough to follow along
+const TRACKING_WIDTH: u32 = 320;
+// how far a box can move from one frame to the next, in tracking pixels
+const SEARCH_RADIUS: i32 = 16;
+// template pixels compared per candidate position, bigger boxes are sampled sparser
+const SAMPLES: u32 = 1024;
+// mean difference per pixel, out of 255, above which the subject counts as lost
+const LOST_THRESHOLD: f32 = 40.;
+// a keyframe every this many frames, the box in between is interpolated and few
+// enough keyframes stay to fix by hand
+const KEYFRAME_EVERY: usize = 5;
+// how far one "track forward" goes at most
+pub const TRACK_LENGTH: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tracked {
+    pub keyframes: Vec<Keyframe>,
+    // the subject was lost before `TRACK_LENGTH` or the end of the video
+    pub lost: bool,
+}
+
+// follows `region` from the frame on screen at `from` through the next frames by
+// block matching, the box keeps its size. keyframe times are on `path`'s timeline
+pub fn track_forward(
+    path: &Path,
+    from: Duration,
+    region: Region,
+    mut progress: impl FnMut(Duration),
+) -> Result<Tracked, ffmpeg::Error> {
+    let mut tracker: Option<Tracker> = None;
+    let mut keyframes = Vec::new();
+    let mut last = None;
+    let mut lost = false;
+    let mut frames = 0;
+
+    decode_forward(path, from, |time, image| {
+        let frame = to_gray(image);
+        let Some(tracker) = tracker.as_mut() else {
+            // the frame the box was drawn on
+            tracker = Tracker::new(frame, region);
+            return tracker.is_some();
+        };
+
+        let Some(region) = tracker.follow(frame) else {
+            lost = true;
+            return false;
+        };
+        frames += 1;
+        last = Some(Keyframe { time, region });
+        if frames % KEYFRAME_EVERY == 0 {
+            keyframes.push(Keyframe { time, region });
+            progress(time);
+        }
+        time < from + TRACK_LENGTH
+    })?;
+
+    // where the box was last seen, so the track doesn't stop short of it
+    if let Some(last) = last {
+        if keyframes.last() != Some(&last) {
+            keyframes.push(last);
+        }
+    }
+
+    Ok(Tracked { keyframes, lost })
+}
+
+fn to_gray(image: RgbaImage) -> GrayImage {
+    let width = TRACKING_WIDTH.min(image.width()).max(1);
+    let height = (u64::from(image.height()) * u64::from(width) / u64::from(image.width().max(1)))
+        .max(1) as u32;
+    DynamicImage::ImageRgba8(image)
+        .resize_exact(width, height, FilterType::Triangle)
+        .to_luma8()
+}
+
+// where the box is in the last frame, and what it looked like there
+struct Tracker {
+    frame: GrayImage,
+    left: i32,
+    top: i32,
+    width: i32,
+    height: i32,
+    // every how many template pixels are compared, in both directions
+    step: usize,
+    region: Region,
+}
+
+impl Tracker {
+    fn new(frame: GrayImage, region: Region) -> Option<Self> {
+        let (frame_width, frame_height) = (frame.width() as f32, frame.height() as f32);
+        let width = ((region.width * frame_width).round() as i32).max(2);
+        let height = ((region.height * frame_height).round() as i32).max(2);
+        let (max_left, max_top) = (frame.width() as i32 - width, frame.height() as i32 - height);
+        if max_left < 0 || max_top < 0 {
+            // a box bigger than the frame has nowhere to move
+            return None;
+        }
+        let left = ((region.x * frame_width).round() as i32).clamp(0, max_left);
+        let top = ((region.y * frame_height).round() as i32).clamp(0, max_top);
+
+        let step = ((width * height) as f32 / SAMPLES as f32).sqrt().max(1.) as usize;
+        Some(Self {
+            frame,
+            left,
+            top,
+            width,
+            height,
+            step,
+            region,
+        })
+    }
+
+    // the box in `frame`, none once nothing around it looks like it anymore
+    fn follow(&mut self, frame: GrayImage) -> Option<Region> {
+        if frame.dimensions() != self.frame.dimensions() {
+            return None;
+        }
+        let (max_left, max_top) = (
+            frame.width() as i32 - self.width,
+            frame.height() as i32 - self.height,
+        );
+
+        let mut best: Option<(f32, i32, i32)> = None;
+        for dy in -SEARCH_RADIUS..=SEARCH_RADIUS {
+            for dx in -SEARCH_RADIUS..=SEARCH_RADIUS {
+                let (left, top) = (self.left + dx, self.top + dy);
+                if left < 0 || top < 0 || left > max_left || top > max_top {
+                    continue;
+                }
+                let cost = self.difference(&frame, left, top);
+                if best.is_none_or(|(best, ..)| cost < best) {
+                    best = Some((cost, left, top));
+                }
+            }
+        }
+
+        let (cost, left, top) = best?;
+        if cost > LOST_THRESHOLD {
+            return None;
+        }
+
+        // the template follows the subject as it turns or the light changes
+        self.frame = frame;
+        self.left = left;
+        self.top = top;
+        self.region = Region {
+            x: left as f32 / self.frame.width() as f32,
+            y: top as f32 / self.frame.height() as f32,
+            ..self.region
+        };
+        Some(self.region)
+    }
+
+    // mean absolute difference between the box in the last frame and at (left, top)
+    fn difference(&self, frame: &GrayImage, left: i32, top: i32) -> f32 {
+        let mut total = 0u64;
+        let mut count = 0u64;
+        for y in (0..self.height).step_by(self.step) {
+            for x in (0..self.width).step_by(self.step) {
+                let before = self.frame.get_pixel((self.left + x) as u32, (self.top + y) as u32)[0];
+                let after = frame.get_pixel((left + x) as u32, (top + y) as u32)[0];
+                total += u64::from(before.abs_diff(after));
+                count += 1;
+            }
+        }
+        total as f32 / count.max(1) as f32
+    }
+}
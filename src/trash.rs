@@ -0,0 +1,86 @@
+use cushy::{
+    value::{Dynamic, MapEach, Source, Switchable},
+    widget::{MakeWidget, WidgetList},
+};
+
+use crate::activity::format_day;
+use crate::format_timestamp;
+use crate::tags::{TagStore, TrashedTag, TRASH_RETENTION};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+fn describe(trashed: &TrashedTag) -> String {
+    let TrashedTag { tag, deleted_at } = trashed;
+    let video = tag
+        .video
+        .file_name()
+        .map_or_else(|| tag.video.to_string_lossy(), |name| name.to_string_lossy());
+    let time = match tag.end {
+        Some(end) => format!("{} - {}", format_timestamp(tag.time), format_timestamp(end)),
+        None => format_timestamp(tag.time),
+    };
+    format!(
+        "{} in {video} at {time}, deleted {}",
+        tag.label,
+        format_day(deleted_at / SECONDS_PER_DAY)
+    )
+}
+
+// `shown` purges expired tags each time the panel opens, a session can outlast them
+pub fn trash_panel(tags: Dynamic<TagStore>, shown: &Dynamic<bool>) -> impl MakeWidget {
+    shown
+        .for_each({
+            let tags = tags.clone();
+            move |shown| {
+                if *shown && tags.map_ref(TagStore::has_expired_trash) {
+                    tags.lock().purge_trash();
+                }
+            }
+        })
+        .persist();
+
+    let trash = tags.map_each(|tags| tags.trash().to_vec());
+
+    let entries = trash.switcher({
+        let tags = tags.clone();
+        move |trash, _| {
+            if trash.is_empty() {
+                return "the trash is empty".make_widget();
+            }
+
+            trash
+                .iter()
+                .rev()
+                .map(|trashed| {
+                    let restore = "restore".into_button().on_click({
+                        let tags = tags.clone();
+                        let id = trashed.tag.id;
+                        move |_| {
+                            tags.lock().restore(id);
+                        }
+                    });
+
+                    describe(trashed)
+                        .expand_horizontally()
+                        .and(restore)
+                        .into_columns()
+                })
+                .collect::<WidgetList>()
+                .into_rows()
+                .make_widget()
+        }
+    });
+
+    let empty = "empty trash".into_button().on_click({
+        let tags = tags.clone();
+        move |_| tags.lock().empty_trash()
+    });
+
+    format!(
+        "Trash (kept for {} days)",
+        TRASH_RETENTION.as_secs() / SECONDS_PER_DAY
+    )
+    .and(empty)
+    .and(entries.vertical_scroll())
+    .into_rows()
+}
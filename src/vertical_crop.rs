@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+// width over height of the window, phones held upright
+pub const VERTICAL_ASPECT: f32 = 9. / 16.;
+// what the window is scaled to once cut out
+pub const VERTICAL_SIZE: (u32, u32) = (1080, 1920);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CropKeyframe {
+    pub time: Duration,
+    // middle of the window across the frame, 0..1 from the left
+    pub center: f32,
+}
+
+// where a range tag's upright window sits across a landscape frame, keyframes sorted
+// by time. it is as tall as the frame, so only its position moves: linearly between
+// keyframes, and outside of them where the nearest one put it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CropTrack {
+    keyframes: Vec<CropKeyframe>,
+}
+
+impl CropTrack {
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    pub fn keyframes(&self) -> &[CropKeyframe] {
+        &self.keyframes
+    }
+
+    pub fn set(&mut self, time: Duration, center: f32) {
+        let center = center.clamp(0., 1.);
+        match self.keyframes.binary_search_by_key(&time, |keyframe| keyframe.time) {
+            Ok(idx) => self.keyframes[idx].center = center,
+            Err(idx) => self.keyframes.insert(idx, CropKeyframe { time, center }),
+        }
+    }
+
+    pub fn remove(&mut self, time: Duration) -> bool {
+        match self.keyframes.binary_search_by_key(&time, |keyframe| keyframe.time) {
+            Ok(idx) => {
+                self.keyframes.remove(idx);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn shift(&mut self, shift: impl Fn(Duration) -> Duration) {
+        for keyframe in &mut self.keyframes {
+            keyframe.time = shift(keyframe.time);
+        }
+    }
+
+    // centered until the first keyframe is set
+    pub fn at(&self, time: Duration) -> f32 {
+        let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        match (next.checked_sub(1).map(|idx| self.keyframes[idx]), self.keyframes.get(next)) {
+            (Some(before), Some(after)) => {
+                let t = (time - before.time).as_secs_f64()
+                    / (after.time - before.time).as_secs_f64();
+                before.center + (after.center - before.center) * t as f32
+            }
+            (Some(only), None) | (None, Some(only)) => only.center,
+            (None, None) => 0.5,
+        }
+    }
+
+    // ffmpeg's crop filter cutting the window out, the same interpolation as `at` as
+    // an expression of t. crop evaluates x again for every frame, the frames going in
+    // have to be timed from `start`
+    pub fn filter(&self, start: Duration) -> String {
+        let seconds = |time: Duration| time.as_secs_f64() - start.as_secs_f64();
+        let center = match (self.keyframes.first(), self.keyframes.last()) {
+            (Some(first), Some(last)) => {
+                let mut expr = format!("{:.4}", last.center);
+                for pair in self.keyframes.windows(2).rev() {
+                    let (before, after) = (pair[0], pair[1]);
+                    expr = format!(
+                        "if(lt(t,{to:.3}),\
+                         {from_center:.4}+({to_center:.4}-{from_center:.4})\
+                         *(t-{from:.3})/{span:.3},\
+                         {expr})",
+                        from = seconds(before.time),
+                        to = seconds(after.time),
+                        span = (after.time - before.time).as_secs_f64(),
+                        from_center = before.center,
+                        to_center = after.center,
+                    );
+                }
+                format!("if(lt(t,{:.3}),{:.4},{expr})", seconds(first.time), first.center)
+            }
+            _ => String::from("0.5"),
+        };
+
+        format!(
+            "crop=w='trunc(min(iw,ih*9/16)/2)*2':h='trunc(min(ih,iw*16/9)/2)*2'\
+             :x='clip(({center})*iw-ow/2,0,iw-ow)':y='(ih-oh)/2'"
+        )
+    }
+}
@@ -1,86 +1,848 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use cushy::animation::ZeroToOne;
-use cushy::context::{GraphicsContext, LayoutContext};
+use cushy::context::{EventContext, GraphicsContext, LayoutContext};
 use cushy::figures::units::{Px, UPx};
-use cushy::figures::{FloatConversion, IntoSigned, IntoUnsigned, Point, Rect, Size, Zero};
-use cushy::kludgine::image::DynamicImage;
+use cushy::figures::{FloatConversion, IntoSigned, IntoUnsigned, Point, Rect, Size};
+use cushy::kludgine::image::{DynamicImage, RgbaImage};
+use cushy::kludgine::shapes::{PathBuilder, Shape, StrokeOptions};
+use cushy::kludgine::app::winit::event::{DeviceId, MouseButton, MouseScrollDelta, TouchPhase};
+use cushy::kludgine::app::winit::keyboard::{Key, NamedKey};
 use cushy::kludgine::wgpu::FilterMode;
-use cushy::kludgine::{AnyTexture, LazyTexture};
-use cushy::value::{Dynamic, IntoValue, Source, Value};
-use cushy::widget::Widget;
+use cushy::kludgine::text::Text;
+use cushy::kludgine::{AnyTexture, DrawableExt, LazyTexture};
+use cushy::styles::Color;
+use cushy::value::{Destination, Dynamic, Generation, Source};
+use cushy::widget::{
+    EventHandling, MakeWidget, SharedCallback, Widget, WidgetList, HANDLED, IGNORED,
+};
 use cushy::widgets::image::{Aspect, ImageScaling};
+use cushy::widgets::layers::{OverlayLayer, Overlayable};
+use cushy::widgets::slider::Slidable;
+use cushy::widgets::Menu;
+use cushy::window::KeyEvent;
 use cushy::ConstraintLimit;
 
-#[derive(Debug)]
+use crate::format_timestamp;
+use crate::menu::VideoMenuOptions;
+use crate::regions::{DrawTool, Outline, Polygon, Region, RegionOverlay};
+use crate::replay::Recorder;
+use crate::skeletons::{human_pose, Pose, Skeleton};
+use crate::spherical::ViewDirection;
+use crate::stereo::{StereoLayout, StereoView};
+use crate::subtitles::Subtitles;
+use crate::settings::ScalingMode;
+use crate::timings::FrameTimings;
+use crate::vertical_crop::VERTICAL_ASPECT;
+use crate::yuv::{Channel, YuvDraw, YuvFrame, YuvRenderer};
+
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+// for a wheel notch and an arrow key press
+pub const WHEEL_SEEK_SECONDS: f64 = 5.;
+const DEFAULT_FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 30);
+// touch input reaches widgets as the primary pointer, anything shorter than this
+// is a tap rather than a swipe
+const SWIPE_THRESHOLD: f32 = 40.;
+// swiping across the whole width of the player seeks this far
+const SWIPE_SECONDS_PER_WIDTH: f64 = 30.;
+const MAX_ZOOM: f32 = 8.;
+// touchpad pinches arrive as ctrl+wheel, in pixels rather than lines
+const PIXELS_PER_ZOOM_STEP: f32 = 50.;
+const ZOOM_STEP: f32 = 1.1;
+const REGION_OUTLINE: i32 = 2;
+const SELECTED_REGION_OUTLINE: i32 = 4;
+const RUBBER_BAND_COLOR: Color = Color::new(0xff, 0xff, 0xff, 0xc0);
+// how close to a point of an outline a click has to land to grab it, in pixels
+const VERTEX_GRAB_RADIUS: f32 = 8.;
+const VERTEX_HANDLE: i32 = 6;
+// freehand strokes keep a point every this much of the frame's size
+const FREEHAND_SPACING: f32 = 0.005;
+// frames kept for stepping back without a seek, full size ones add up quickly
+const FRAME_HISTORY_LENGTH: usize = 16;
+const WIPE_LINE: i32 = 2;
+// over what the upright export window leaves out
+const CROP_SHADE: Color = Color::new(0, 0, 0, 0xa0);
+// behind subtitle lines, and between them and the bottom of the frame
+const SUBTITLE_BACKDROP: Color = Color::new(0, 0, 0, 0xb0);
+const SUBTITLE_MARGIN: i32 = 24;
+const SUBTITLE_PADDING: i32 = 6;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ControlCommand {
     Play,
     Pause,
     Stop,
+    Seek(Duration),
+    SeekRelative(f64),
+    SetSpeed(f64),
+    // one frame at a time, these pause playback first
+    StepForward,
+    StepBackward,
+    // plays another of the file's audio streams from where playback is
+    SetAudioStream(usize),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Release)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+// the last few frames the pipeline presented, newest last. stepping back walks
+// through them, stepping forward walks back out before asking the decoder again
+#[derive(Debug, Clone, Default)]
+pub struct FrameHistory(Arc<Mutex<HistoryState>>);
+
+#[derive(Debug, Default)]
+struct HistoryState {
+    frames: VecDeque<(Duration, VideoFrame)>,
+    // how far back from the newest frame the player is, 0 while it follows the decoder
+    back: usize,
+}
+
+impl FrameHistory {
+    pub fn push(&self, position: Duration, frame: VideoFrame) {
+        let mut state = self.0.lock().unwrap();
+        if state.frames.len() == FRAME_HISTORY_LENGTH {
+            state.frames.pop_front();
+        }
+        state.frames.push_back((position, frame));
+        state.back = 0;
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.0.lock().unwrap();
+        state.frames.clear();
+        state.back = 0;
+    }
+
+    pub fn is_stepped_back(&self) -> bool {
+        self.0.lock().unwrap().back > 0
+    }
+
+    // none once the oldest frame is on screen
+    fn step_backward(&self) -> Option<(Duration, VideoFrame)> {
+        let mut state = self.0.lock().unwrap();
+        if state.back + 1 >= state.frames.len() {
+            return None;
+        }
+        state.back += 1;
+        let idx = state.frames.len() - 1 - state.back;
+        state.frames.get(idx).cloned()
+    }
+
+    // none while the newest frame is on screen, the decoder has the next one
+    fn step_forward(&self) -> Option<(Duration, VideoFrame)> {
+        let mut state = self.0.lock().unwrap();
+        if state.back == 0 {
+            return None;
+        }
+        state.back -= 1;
+        let idx = state.frames.len() - 1 - state.back;
+        state.frames.get(idx).cloned()
+    }
+}
+
+// everything the playback closure gets handed when a pipeline starts
+// what playback does at the end of the video, or past the B point
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Repeat {
+    // back to A, or the start, at the end of the video
+    pub looping: bool,
+    pub a: Option<Duration>,
+    pub b: Option<Duration>,
+}
+
+impl Repeat {
+    // where looping goes back to
+    pub fn restart(&self) -> Duration {
+        self.a.unwrap_or_default()
+    }
+
+    // the segment a-b repeat plays over and over, once both points are set in order
+    pub fn segment(&self) -> Option<(Duration, Duration)> {
+        match (self.a, self.b) {
+            (Some(a), Some(b)) if a < b => Some((a, b)),
+            _ => None,
+        }
+    }
+}
+
+pub struct PipelineContext {
+    pub contents: Dynamic<VideoFrame>,
+    pub position: Dynamic<Duration>,
+    pub frame_duration: Dynamic<Duration>,
+    // zero until the container says, and for live streams
+    pub duration: Dynamic<Duration>,
+    pub loading: Dynamic<bool>,
+    pub controls: Receiver<ControlCommand>,
+    pub cancel: CancelToken,
+    pub timings: Dynamic<FrameTimings>,
+    // timings are only collected while the overlay is shown
+    pub show_timings: Dynamic<bool>,
+    pub history: FrameHistory,
+    pub repeat: Dynamic<Repeat>,
+    // set by pipelines that run frames through filters
+    pub filtered: Dynamic<bool>,
+    // where the comparison wipe splits the frame, the pipeline only keeps the
+    // unfiltered frames around while it's on
+    pub wipe: Dynamic<Option<f32>>,
+    // set by pipelines that find the video is a 360 panorama
+    pub view: Dynamic<Option<ViewDirection>>,
+    // set by pipelines that find the video is 3d
+    pub stereo: Dynamic<Option<StereoLayout>>,
 }
 
 #[derive(Debug)]
-pub struct VideoPlayer {
-    contents: Dynamic<AnyTexture>,
-    scaling: Value<ImageScaling>,
-    playback_thread: Option<JoinHandle<()>>,
-    control_sender: Option<std::sync::mpsc::Sender<ControlCommand>>,
+struct Pipeline {
+    thread: Option<JoinHandle<()>>,
+    control_sender: Sender<ControlCommand>,
+    cancel: CancelToken,
 }
 
-impl Drop for VideoPlayer {
+impl Pipeline {
+    fn stop(mut self) {
+        self.cancel.cancel();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// dropping without `stop` (e.g. a mosaic tile going away) only signals the thread
+impl Drop for Pipeline {
     fn drop(&mut self) {
-        if let Some(handle) = self.playback_thread.take() {
-            drop(handle)
+        self.cancel.cancel();
+    }
+}
+
+// cloning shares the same frame texture and pipeline, so the app can keep a handle
+// to swap sources on the instance that lives in the widget tree
+#[derive(Debug, Clone)]
+pub struct VideoPlayer {
+    contents: Dynamic<VideoFrame>,
+    scaling: Dynamic<ImageScaling>,
+    pipeline: Arc<Mutex<Option<Pipeline>>>,
+    position: Dynamic<Duration>,
+    frame_duration: Dynamic<Duration>,
+    duration: Dynamic<Duration>,
+    loading: Dynamic<bool>,
+    history: FrameHistory,
+    repeat: Dynamic<Repeat>,
+    filtered: Dynamic<bool>,
+    // the original frame is shown left of this fraction of the width, the filtered
+    // one right of it. none while not comparing
+    wipe: Dynamic<Option<f32>>,
+    // where a 360 video is looked at, none for flat ones
+    view: Dynamic<Option<ViewDirection>>,
+    // how the eyes of 3d footage share its frames, none for flat ones
+    stereo: Dynamic<Option<StereoLayout>>,
+    stereo_view: Dynamic<StereoView>,
+    channel: Dynamic<Channel>,
+    context_menu: Option<(Menu<VideoMenuOptions>, OverlayLayer)>,
+    playing: Dynamic<bool>,
+    fullscreen: Dynamic<bool>,
+    last_click: Option<Instant>,
+    interactive: bool,
+    zoom: Dynamic<f32>,
+    pan: Dynamic<Point<Px>>,
+    drag: Option<Drag>,
+    timings: Dynamic<FrameTimings>,
+    show_timings: Dynamic<bool>,
+    recorder: Option<Recorder>,
+    regions: Dynamic<Vec<RegionOverlay>>,
+    on_region: Option<SharedCallback<Region>>,
+    draw_tool: Dynamic<DrawTool>,
+    on_polygon: Option<SharedCallback<Polygon>>,
+    // the points of a polygon being clicked together, in frame coordinates
+    sketch: Vec<(f32, f32)>,
+    skeleton: Dynamic<Skeleton>,
+    on_pose: Option<SharedCallback<Pose>>,
+    // keypoints placed so far, in the skeleton's order
+    pose_sketch: Vec<Option<(f32, f32)>>,
+    pose_progress: Dynamic<usize>,
+    // middle of the upright export window while it's shown
+    crop: Dynamic<Option<f32>>,
+    on_crop: Option<SharedCallback<f32>>,
+    // cues for what's playing, timed like `position`
+    subtitles: Dynamic<Option<Subtitles>>,
+    // where the last redraw put the frame, boxes are drawn and picked relative to it
+    frame_rect: Rect<Px>,
+}
+
+#[derive(Debug, Clone)]
+struct Drag {
+    start: Point<Px>,
+    last: Point<Px>,
+    mode: DragMode,
+}
+
+#[derive(Debug, Clone)]
+enum DragMode {
+    // a tap toggles playback, a swipe seeks, and while zoomed in dragging pans
+    Navigate,
+    // shift+drag with the box tool
+    Box,
+    // shift+drag with the freehand tool, the stroke so far in frame coordinates
+    Freehand(Vec<(f32, f32)>),
+    // moving a point of the selected tag's outline while paused
+    Vertex { polygon: Polygon, idx: usize },
+    // same for a keypoint of its pose
+    Keypoint { pose: Pose, idx: usize },
+    // moving the split between the original and the filtered frame
+    Wipe,
+    // sliding the upright export window across, where its middle is now
+    Crop(f32),
+}
+
+// what the player shows
+#[derive(Debug, Clone)]
+pub enum VideoFrame {
+    // shown before anything is decoded
+    Placeholder(AnyTexture),
+    // planes from the decoder, written into textures the renderer keeps
+    Decoded(Arc<YuvFrame>),
+    // a filtered frame and the one that went into the filters, for the wipe
+    Compared {
+        filtered: Arc<YuvFrame>,
+        original: Arc<YuvFrame>,
+    },
+}
+
+impl VideoFrame {
+    pub fn size(&self) -> Size<UPx> {
+        match self {
+            VideoFrame::Placeholder(texture) => texture.size(),
+            VideoFrame::Decoded(frame) | VideoFrame::Compared { filtered: frame, .. } => frame.size(),
         }
     }
 }
 
+fn placeholder_texture() -> VideoFrame {
+    let dyn_image = DynamicImage::new_rgb8(500, 300);
+    let lazy_texture = LazyTexture::from_image(dyn_image, FilterMode::Nearest);
+    VideoFrame::Placeholder(AnyTexture::Lazy(lazy_texture))
+}
+
 impl VideoPlayer {
     pub fn new() -> Self {
-        let dyn_image = DynamicImage::new_rgb8(500, 300);
-        let lazy_texture = LazyTexture::from_image(dyn_image, FilterMode::Nearest);
-        let contents = Dynamic::new(AnyTexture::Lazy(lazy_texture));
+        let contents = Dynamic::new(placeholder_texture());
 
-        let scaling = ImageScaling::Aspect {
-            mode: Aspect::Fit,
-            orientation: Size::ZERO,
-        }
-        .into_value();
+        let scaling = Dynamic::new(ImageScaling::from(ScalingMode::default()));
 
         Self {
             contents,
             scaling,
-            playback_thread: None,
-            control_sender: None,
+            pipeline: Arc::new(Mutex::new(None)),
+            position: Dynamic::new(Duration::ZERO),
+            frame_duration: Dynamic::new(DEFAULT_FRAME_DURATION),
+            duration: Dynamic::new(Duration::ZERO),
+            loading: Dynamic::new(false),
+            history: FrameHistory::default(),
+            repeat: Dynamic::default(),
+            filtered: Dynamic::new(false),
+            wipe: Dynamic::new(None),
+            view: Dynamic::new(None),
+            stereo: Dynamic::new(None),
+            stereo_view: Dynamic::default(),
+            channel: Dynamic::default(),
+            context_menu: None,
+            playing: Dynamic::new(false),
+            fullscreen: Dynamic::new(false),
+            last_click: None,
+            interactive: true,
+            zoom: Dynamic::new(1.),
+            pan: Dynamic::new(Point::default()),
+            drag: None,
+            timings: Dynamic::default(),
+            show_timings: Dynamic::new(false),
+            recorder: None,
+            regions: Dynamic::default(),
+            on_region: None,
+            draw_tool: Dynamic::default(),
+            on_polygon: None,
+            sketch: Vec::new(),
+            skeleton: Dynamic::new(human_pose()),
+            on_pose: None,
+            pose_sketch: Vec::new(),
+            pose_progress: Dynamic::new(0),
+            crop: Dynamic::new(None),
+            on_crop: None,
+            subtitles: Dynamic::new(None),
+            frame_rect: Rect::default(),
         }
     }
 
-    pub fn start<F>(&mut self, playback: F)
+    // the menu is shown in `overlay` when the video is right clicked
+    pub fn with_context_menu(mut self, menu: Menu<VideoMenuOptions>, overlay: OverlayLayer) -> Self {
+        self.context_menu = Some((menu, overlay));
+        self
+    }
+
+    // every control message sent to the pipeline also goes into the script
+    pub fn with_recorder(mut self, recorder: Option<Recorder>) -> Self {
+        self.recorder = recorder;
+        self
+    }
+
+    // draws `regions` over the frame. shift+dragging a box hands it to `on_region`
+    pub fn with_regions(
+        mut self,
+        regions: Dynamic<Vec<RegionOverlay>>,
+        on_region: SharedCallback<Region>,
+    ) -> Self {
+        self.regions = regions;
+        self.on_region = Some(on_region);
+        self
+    }
+
+    // shift+clicking with the polygon tool or shift+dragging with the freehand one
+    // hands the outline to `on_polygon`, so does moving a point of the selected one
+    pub fn with_polygons(
+        mut self,
+        draw_tool: Dynamic<DrawTool>,
+        on_polygon: SharedCallback<Polygon>,
+    ) -> Self {
+        self.draw_tool = draw_tool;
+        self.on_polygon = Some(on_polygon);
+        self
+    }
+
+    // shift+clicking with the keypoints tool places `skeleton`'s keypoints one after
+    // the other, the finished pose goes to `on_pose`
+    pub fn with_poses(mut self, skeleton: Dynamic<Skeleton>, on_pose: SharedCallback<Pose>) -> Self {
+        self.skeleton = skeleton;
+        self.on_pose = Some(on_pose);
+        self
+    }
+
+    // shades the frame around an upright window centered on `crop`. dragging the
+    // window slides it across and hands where it ends up to `on_crop`
+    pub fn with_crop(mut self, crop: Dynamic<Option<f32>>, on_crop: SharedCallback<f32>) -> Self {
+        self.crop = crop;
+        self.on_crop = Some(on_crop);
+        self
+    }
+
+    // shows the cues of `subtitles` at the bottom of the frame as the video plays
+    pub fn with_subtitles(mut self, subtitles: Dynamic<Option<Subtitles>>) -> Self {
+        self.subtitles = subtitles;
+        self
+    }
+
+    // lets clicks through to whatever wraps the player, e.g. a mosaic tile button
+    pub fn non_interactive(mut self) -> Self {
+        self.interactive = false;
+        self
+    }
+
+    // each player runs at most one pipeline, the previous one is torn down before
+    // the next starts so switching videos never leaves a decoder running behind
+    pub fn start<F>(&self, playback: F)
     where
-        F: FnOnce(Dynamic<AnyTexture>) + Send + Sync + 'static,
+        F: FnOnce(PipelineContext) + Send + Sync + 'static,
     {
-        let contents = self.contents.clone();
-        let texture = contents.clone();
-        let playback_thread = Some(
-            std::thread::Builder::new()
-                .name("Playback Thread".into())
-                .spawn(|| playback(texture))
-                .unwrap(),
-        );
+        self.stop();
+        self.reset_zoom();
+        self.timings.lock().clear();
+        self.history.clear();
+        // a and b belong to the video they were set on, looping stays on
+        self.clear_ab();
+
+        let (control_sender, controls) = std::sync::mpsc::channel();
+        let cancel = CancelToken::default();
+        self.position.set(Duration::ZERO);
+        self.duration.set(Duration::ZERO);
+        self.loading.set(true);
+        self.filtered.set(false);
+        self.view.set(None);
+        self.stereo.set(None);
+        let context = PipelineContext {
+            contents: self.contents.clone(),
+            position: self.position.clone(),
+            frame_duration: self.frame_duration.clone(),
+            duration: self.duration.clone(),
+            loading: self.loading.clone(),
+            controls,
+            cancel: cancel.clone(),
+            timings: self.timings.clone(),
+            show_timings: self.show_timings.clone(),
+            history: self.history.clone(),
+            repeat: self.repeat.clone(),
+            filtered: self.filtered.clone(),
+            wipe: self.wipe.clone(),
+            view: self.view.clone(),
+            stereo: self.stereo.clone(),
+        };
+        let thread = std::thread::Builder::new()
+            .name("Playback Thread".into())
+            .spawn(move || playback(context))
+            .unwrap();
+
+        *self.pipeline.lock().unwrap() = Some(Pipeline {
+            thread: Some(thread),
+            control_sender,
+            cancel,
+        });
+        self.playing.set(true);
+    }
+
+    pub fn stop(&self) {
+        let pipeline = self.pipeline.lock().unwrap().take();
+        if let Some(pipeline) = pipeline {
+            pipeline.stop();
+        }
+        self.playing.set(false);
+        self.loading.set(false);
+        self.release_texture();
+    }
+
+    pub fn send_control_message(&self, message: ControlCommand) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(message.clone().into(), self.position.get());
+        }
+        let message = match message {
+            ControlCommand::Play => {
+                // stepped back into the history, the decoder is ahead of what is shown
+                if self.history.is_stepped_back() {
+                    self.send_to_pipeline(ControlCommand::Seek(self.position.get()));
+                }
+                self.playing.set(true);
+                message
+            }
+            ControlCommand::Pause | ControlCommand::Stop => {
+                self.playing.set(false);
+                message
+            }
+            ControlCommand::StepForward | ControlCommand::StepBackward => {
+                // stepping only makes sense on a still picture
+                if self.playing.get() {
+                    self.playing.set(false);
+                    self.send_to_pipeline(ControlCommand::Pause);
+                }
+                let stepped = if message == ControlCommand::StepForward {
+                    self.history.step_forward()
+                } else {
+                    self.history.step_backward()
+                };
+                if let Some((position, frame)) = stepped {
+                    self.position.set(position);
+                    self.contents.set(frame);
+                    return;
+                }
+                if message == ControlCommand::StepForward {
+                    message
+                } else {
+                    // further back than the history goes, a frame accurate seek gets there
+                    let target = self
+                        .position
+                        .get()
+                        .saturating_sub(self.frame_duration.get());
+                    self.position.set(target);
+                    ControlCommand::Seek(target)
+                }
+            }
+            ControlCommand::Seek(_)
+            | ControlCommand::SeekRelative(_)
+            | ControlCommand::SetSpeed(_)
+            | ControlCommand::SetAudioStream(_) => message,
+        };
+        self.send_to_pipeline(message);
+    }
+
+    fn send_to_pipeline(&self, message: ControlCommand) {
+        if let Some(pipeline) = &*self.pipeline.lock().unwrap() {
+            let _ = pipeline.control_sender.send(message);
+        }
+    }
+
+    // shows the next decoded frame and stays paused
+    pub fn step_forward(&self) {
+        self.send_control_message(ControlCommand::StepForward);
+    }
+
+    // shows the frame before, from the last few presented ones when it can
+    pub fn step_backward(&self) {
+        self.send_control_message(ControlCommand::StepBackward);
+    }
+
+    // frame accurate: decoding restarts at the keyframe before `time` and frames up
+    // to it are skipped. paused players show the frame at `time` and stay paused
+    pub fn seek(&self, time: Duration) {
+        self.send_control_message(ControlCommand::Seek(time));
+        self.position.set(time);
+    }
+
+    pub fn toggle_playing(&self) {
+        self.send_control_message(if self.playing.get() {
+            ControlCommand::Pause
+        } else {
+            ControlCommand::Play
+        });
+    }
+
+    pub fn repeat(&self) -> Dynamic<Repeat> {
+        self.repeat.clone()
+    }
 
-        self.contents = contents;
-        self.playback_thread = playback_thread;
-        self.control_sender = None;
+    pub fn toggle_looping(&self) {
+        let mut repeat = self.repeat.lock();
+        repeat.looping = !repeat.looping;
+    }
+
+    // marks the playhead as the start of the segment to repeat, a B before it goes
+    pub fn set_a(&self) {
+        let position = self.position.get();
+        let mut repeat = self.repeat.lock();
+        repeat.a = Some(position);
+        if repeat.b.is_some_and(|b| b <= position) {
+            repeat.b = None;
+        }
+    }
+
+    // marks the playhead as the end of the segment, playback jumps back to A here
+    pub fn set_b(&self) {
+        let position = self.position.get();
+        let mut repeat = self.repeat.lock();
+        repeat.b = Some(position);
+        if repeat.a.is_some_and(|a| a >= position) {
+            repeat.a = None;
+        }
+    }
+
+    pub fn clear_ab(&self) {
+        let mut repeat = self.repeat.lock();
+        repeat.a = None;
+        repeat.b = None;
+    }
+
+    pub fn position(&self) -> Dynamic<Duration> {
+        self.position.clone()
+    }
+
+    // whether the playing video goes through filters, there's nothing to compare otherwise
+    pub fn filtered(&self) -> Dynamic<bool> {
+        self.filtered.clone()
+    }
+
+    pub fn wipe(&self) -> Dynamic<Option<f32>> {
+        self.wipe.clone()
+    }
+
+    // splits the frame down the middle, or goes back to only the filtered one
+    pub fn toggle_wipe(&self) {
+        let mut wipe = self.wipe.lock();
+        *wipe = match *wipe {
+            Some(_) => None,
+            None => Some(0.5),
+        };
+    }
+
+    pub fn view(&self) -> Dynamic<Option<ViewDirection>> {
+        self.view.clone()
+    }
+
+    // for 360 videos without the metadata that says so, or to see the whole panorama
+    pub fn toggle_spherical(&self) {
+        let mut view = self.view.lock();
+        *view = match *view {
+            Some(_) => None,
+            None => Some(ViewDirection::default()),
+        };
+    }
+
+    pub fn stereo(&self) -> Dynamic<Option<StereoLayout>> {
+        self.stereo.clone()
+    }
+
+    pub fn stereo_view(&self) -> Dynamic<StereoView> {
+        self.stereo_view.clone()
+    }
+
+    pub fn channel(&self) -> Dynamic<Channel> {
+        self.channel.clone()
+    }
+
+    // how many keypoints of the pose being placed are done
+    pub fn pose_progress(&self) -> Dynamic<usize> {
+        self.pose_progress.clone()
+    }
+
+    pub fn frame_duration(&self) -> Dynamic<Duration> {
+        self.frame_duration.clone()
+    }
+
+    pub fn duration(&self) -> Dynamic<Duration> {
+        self.duration.clone()
+    }
+
+    // true from `start` until the pipeline delivers its first frame
+    pub fn loading(&self) -> Dynamic<bool> {
+        self.loading.clone()
+    }
+
+    // writes the frame on screen to `path`, so a paused player captures what it shows
+    pub fn capture_frame(&self, path: PathBuf) {
+        let Some(image) = self.displayed_frame() else {
+            return;
+        };
+        std::thread::Builder::new()
+            .name("Frame Capture Thread".into())
+            .spawn(move || {
+                if let Err(err) = image.save(&path) {
+                    tracing::error!("failed to save {}: {err}", path.display());
+                }
+            })
+            .unwrap();
+    }
+
+    // the frame on screen as it's drawn, the filtered one while comparing. none
+    // before anything was decoded
+    pub fn displayed_frame(&self) -> Option<RgbaImage> {
+        self.contents.map_ref(|frame| match frame {
+            VideoFrame::Placeholder(_) => None,
+            VideoFrame::Decoded(frame) | VideoFrame::Compared { filtered: frame, .. } => {
+                Some(frame.to_rgba())
+            }
+        })
+    }
+
+    // moves on every time another frame goes on screen
+    pub fn frame_generation(&self) -> Generation {
+        self.contents.generation()
+    }
+
+    // asks where to save the frame on screen, as png or jpeg by the extension picked.
+    // `video` is what's playing, it goes into the suggested name with the moment
+    pub fn save_frame(&self, video: &Path) {
+        let Some(image) = self.displayed_frame() else {
+            return;
+        };
+        let name = snapshot_name(video, self.position.get());
+        std::thread::Builder::new()
+            .name("Save Frame Thread".into())
+            .spawn(move || {
+                let Some(path) = rfd::FileDialog::new()
+                    .set_file_name(name)
+                    .add_filter("PNG", &["png"])
+                    .add_filter("JPEG", &["jpg", "jpeg"])
+                    .save_file()
+                else {
+                    return;
+                };
+                let jpeg = path.extension().is_some_and(|extension| {
+                    extension.eq_ignore_ascii_case("jpg") || extension.eq_ignore_ascii_case("jpeg")
+                });
+                // jpeg has no alpha to keep
+                let saved = if jpeg {
+                    DynamicImage::from(image).to_rgb8().save(&path)
+                } else {
+                    image.save(&path)
+                };
+                if let Err(err) = saved {
+                    tracing::error!("failed to save {}: {err}", path.display());
+                }
+            })
+            .unwrap();
+    }
+
+    pub fn playing(&self) -> Dynamic<bool> {
+        self.playing.clone()
+    }
+
+    pub fn fullscreen(&self) -> Dynamic<bool> {
+        self.fullscreen.clone()
+    }
+
+    pub fn scaling(&self) -> Dynamic<ImageScaling> {
+        self.scaling.clone()
+    }
+
+    // per stage durations of the last frames, for the debug overlay
+    pub fn timings(&self) -> Dynamic<FrameTimings> {
+        self.timings.clone()
+    }
+
+    pub fn show_timings(&self) -> Dynamic<bool> {
+        self.show_timings.clone()
+    }
+
+    // magnification on top of the scaling mode, around the middle of the player
+    pub fn zoom(&self) -> Dynamic<f32> {
+        self.zoom.clone()
+    }
+
+    pub fn reset_zoom(&self) {
+        self.zoom.set(1.);
+        self.pan.set(Point::default());
+    }
+
+    fn zoom_by(&self, steps: f32) {
+        let zoom = (self.zoom.get() * ZOOM_STEP.powf(steps)).clamp(1., MAX_ZOOM);
+        if zoom <= 1. {
+            self.reset_zoom();
+        } else {
+            self.zoom.set(zoom);
+        }
+    }
+
+    fn show_context_menu(&self, location: Point<Px>, context: &mut EventContext<'_>) -> EventHandling {
+        let Some((menu, overlay)) = &self.context_menu else {
+            return IGNORED;
+        };
+        let origin = context.last_layout().map(|rect| rect.origin).unwrap_or_default();
+        menu.overlay_in(overlay).at(origin + location).show();
+        HANDLED
+    }
+
+    fn tap(&mut self) {
+        let now = Instant::now();
+        let double_click = self
+            .last_click
+            .is_some_and(|last| now.duration_since(last) < DOUBLE_CLICK_INTERVAL);
+
+        // a double click toggles playback twice, leaving it where it was
+        self.toggle_playing();
+        if double_click {
+            self.fullscreen.toggle();
+            self.last_click = None;
+        } else {
+            self.last_click = Some(now);
+        }
+    }
+
+    // drops the last decoded frame (and its gpu texture) so nothing of the
+    // previous video stays alive while the next one is opening
+    pub fn release_texture(&self) {
+        self.contents.set(placeholder_texture());
     }
 
     fn calculate_frame_rect(
         &self,
-        texture: &AnyTexture,
+        frame: &VideoFrame,
         within_size: Size<UPx>,
         context: &mut GraphicsContext<'_, '_, '_, '_>,
     ) -> Rect<Px> {
         let within_size = within_size.into_signed();
-        let size = texture.size().into_signed();
+        let mut size = frame.size();
+        if let Some(layout) = self.stereo.get_tracking_invalidate(context) {
+            let (width, height) = layout.eye_size(size.width.get(), size.height.get());
+            size = Size::new(UPx::new(width), UPx::new(height));
+        }
+        let size = size.into_signed();
 
         match self.scaling.get_tracking_invalidate(context) {
             ImageScaling::Aspect { mode, orientation } => {
@@ -107,16 +869,840 @@ impl VideoPlayer {
     }
 }
 
+// the playing file's path, folders and all, then the moment. only the end of a long
+// path is kept, names can't be longer than 255 bytes
+fn snapshot_name(video: &Path, position: Duration) -> String {
+    const MAX_PATH_PART: usize = 160;
+
+    let path = video
+        .with_extension("")
+        .components()
+        .filter_map(|component| match component {
+            std::path::Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("_")
+        .replace(|c: char| c.is_whitespace() || c == ':', "-");
+    let start = path.len().saturating_sub(MAX_PATH_PART);
+    let start = (start..=path.len()).find(|&idx| path.is_char_boundary(idx)).unwrap_or(0);
+    let timestamp = format_timestamp(position).replace([':', '.'], "-");
+    format!("{}_{timestamp}.png", &path[start..])
+}
+
+// the frame relative spot under `location`
+fn frame_point(frame: Rect<Px>, location: Point<Px>) -> (f32, f32) {
+    let size = frame.size.into_float();
+    (
+        (location.x - frame.origin.x).into_float() / size.width.max(1.),
+        (location.y - frame.origin.y).into_float() / size.height.max(1.),
+    )
+}
+
+fn region_rect(frame: Rect<Px>, region: Region) -> Rect<Px> {
+    let size = frame.size.into_float();
+    Rect::new(
+        frame.origin
+            + Point::new(
+                Px::from(region.x * size.width),
+                Px::from(region.y * size.height),
+            ),
+        Size::new(
+            Px::from(region.width * size.width),
+            Px::from(region.height * size.height),
+        ),
+    )
+}
+
+// centered lines on dark bands along the bottom of the frame, the last one lowest
+fn draw_subtitles(
+    context: &mut GraphicsContext<'_, '_, '_, '_>,
+    frame_rect: Rect<Px>,
+    lines: &[String],
+) {
+    let padding = Px::new(SUBTITLE_PADDING);
+    let center = frame_rect.origin.x + frame_rect.size.width / 2;
+    let mut bottom = frame_rect.origin.y + frame_rect.size.height - Px::new(SUBTITLE_MARGIN);
+    for line in lines.iter().rev() {
+        let size = context.gfx.measure_text::<Px>(Text::new(line, Color::WHITE)).size;
+        let origin = Point::new(center - size.width / 2, bottom - size.height);
+        context.gfx.draw_shape(&Shape::filled_rect(
+            Rect::new(
+                Point::new(origin.x - padding, origin.y),
+                Size::new(size.width + padding * 2, size.height),
+            ),
+            SUBTITLE_BACKDROP,
+        ));
+        context.gfx.draw_text(Text::new(line, Color::WHITE).translate_by(origin));
+        bottom = origin.y;
+    }
+}
+
+fn draw_outline(
+    context: &mut GraphicsContext<'_, '_, '_, '_>,
+    rect: Rect<Px>,
+    color: Color,
+    thickness: i32,
+) {
+    let thickness = Px::new(thickness);
+    let Rect { origin, size } = rect;
+    let edges = [
+        Rect::new(origin, Size::new(size.width, thickness)),
+        Rect::new(
+            Point::new(origin.x, origin.y + size.height - thickness),
+            Size::new(size.width, thickness),
+        ),
+        Rect::new(origin, Size::new(thickness, size.height)),
+        Rect::new(
+            Point::new(origin.x + size.width - thickness, origin.y),
+            Size::new(thickness, size.height),
+        ),
+    ];
+    for edge in edges {
+        context.gfx.draw_shape(&Shape::filled_rect(edge, color));
+    }
+}
+
+// the upright window as tall as the frame, kept inside it
+fn crop_rect(frame: Rect<Px>, center: f32) -> Rect<Px> {
+    let size = frame.size.into_float();
+    let width = (size.height * VERTICAL_ASPECT).min(size.width);
+    let left = (center * size.width - width / 2.).clamp(0., size.width - width);
+    Rect::new(
+        frame.origin + Point::new(Px::from(left), Px::ZERO),
+        Size::new(Px::from(width), frame.size.height),
+    )
+}
+
+// the pixel at a frame relative spot, the inverse of `frame_point`
+fn frame_location(frame: Rect<Px>, (x, y): (f32, f32)) -> Point<Px> {
+    let size = frame.size.into_float();
+    frame.origin + Point::new(Px::from(x * size.width), Px::from(y * size.height))
+}
+
+fn draw_polyline(
+    context: &mut GraphicsContext<'_, '_, '_, '_>,
+    frame: Rect<Px>,
+    points: &[(f32, f32)],
+    color: Color,
+    thickness: i32,
+    closed: bool,
+) {
+    let mut locations = points.iter().map(|&point| frame_location(frame, point));
+    let Some(first) = locations.next() else {
+        return;
+    };
+    let path = locations.fold(PathBuilder::new(first), PathBuilder::line_to);
+    let path = if closed { path.close() } else { path.build() };
+    context
+        .gfx
+        .draw_shape(&path.stroke(StrokeOptions::px_wide(Px::new(thickness)).colored(color)));
+}
+
+fn draw_handles(
+    context: &mut GraphicsContext<'_, '_, '_, '_>,
+    frame: Rect<Px>,
+    points: &[(f32, f32)],
+    color: Color,
+) {
+    let size = Px::new(VERTEX_HANDLE);
+    for &point in points {
+        let center = frame_location(frame, point);
+        let handle = Rect::new(
+            center - Point::new(size / 2, size / 2),
+            Size::new(size, size),
+        );
+        context.gfx.draw_shape(&Shape::filled_rect(handle, color));
+    }
+}
+
+// bones between the placed keypoints, and a handle on every one of them
+fn draw_pose(
+    context: &mut GraphicsContext<'_, '_, '_, '_>,
+    frame: Rect<Px>,
+    pose: &Pose,
+    edges: &[(usize, usize)],
+    color: Color,
+    thickness: i32,
+) {
+    for &(a, b) in edges {
+        if let (Some(Some(a)), Some(Some(b))) = (pose.points.get(a), pose.points.get(b)) {
+            draw_polyline(context, frame, &[*a, *b], color, thickness, false);
+        }
+    }
+    let placed = pose.placed().collect::<Vec<_>>();
+    draw_handles(context, frame, &placed, color);
+}
+
+impl VideoPlayer {
+    // where the wipe splits the frame, while there is an original to show next to it
+    fn wipe_split(&self) -> Option<f32> {
+        let split = self.wipe.get()?;
+        self.contents
+            .map_ref(|frame| matches!(frame, VideoFrame::Compared { .. }))
+            .then_some(split)
+    }
+
+    fn is_near_wipe(&self, location: Point<Px>) -> bool {
+        let Some(split) = self.wipe_split() else {
+            return false;
+        };
+        let (top_left, bottom_right) = self.frame_rect.extents();
+        let x = frame_location(self.frame_rect, (split, 0.)).x;
+        (location.x - x).into_float().abs() <= VERTEX_GRAB_RADIUS
+            && location.y >= top_left.y
+            && location.y <= bottom_right.y
+    }
+
+    fn is_near(&self, point: (f32, f32), location: Point<Px>) -> bool {
+        let distance = frame_location(self.frame_rect, point) - location;
+        distance.x.into_float().hypot(distance.y.into_float()) <= VERTEX_GRAB_RADIUS
+    }
+
+    fn grab_crop(&self, location: Point<Px>) -> Option<DragMode> {
+        let center = self.crop.get().filter(|_| self.on_crop.is_some())?;
+        crop_rect(self.frame_rect, center)
+            .contains(location)
+            .then_some(DragMode::Crop(center))
+    }
+
+    // the point of the selected outline or pose under `location`, only while paused
+    // so clicks during playback keep toggling it
+    fn grab_point(&self, location: Point<Px>) -> Option<DragMode> {
+        if self.playing.get() {
+            return None;
+        }
+        self.regions.map_ref(|regions| {
+            regions
+                .iter()
+                .filter(|overlay| overlay.selected)
+                .find_map(|overlay| match &overlay.outline {
+                    Outline::Polygon(polygon) if self.on_polygon.is_some() => {
+                        let idx = polygon
+                            .points
+                            .iter()
+                            .position(|&point| self.is_near(point, location))?;
+                        Some(DragMode::Vertex {
+                            polygon: polygon.clone(),
+                            idx,
+                        })
+                    }
+                    Outline::Pose { pose, .. } if self.on_pose.is_some() => {
+                        let idx = pose
+                            .points
+                            .iter()
+                            .position(|point| point.is_some_and(|point| self.is_near(point, location)))?;
+                        Some(DragMode::Keypoint {
+                            pose: pose.clone(),
+                            idx,
+                        })
+                    }
+                    _ => None,
+                })
+        })
+    }
+
+    // none skips a keypoint that can't be seen. the pose is done after the last one
+    fn place_keypoint(&mut self, point: Option<(f32, f32)>) {
+        self.pose_sketch.push(point);
+        if self.pose_sketch.len() >= self.skeleton.map_ref(|skeleton| skeleton.keypoints.len()) {
+            self.finish_pose();
+        } else {
+            self.pose_progress.set(self.pose_sketch.len());
+        }
+    }
+
+    // keypoints not placed yet count as not visible
+    fn finish_pose(&mut self) {
+        let mut points = std::mem::take(&mut self.pose_sketch);
+        self.pose_progress.set(0);
+        let skeleton = self.skeleton.get();
+        points.resize(skeleton.keypoints.len(), None);
+        self.hand_pose(Pose {
+            skeleton: skeleton.name,
+            points,
+        });
+    }
+
+    fn hand_pose(&self, mut pose: Pose) {
+        for point in pose.points.iter_mut().flatten() {
+            *point = (point.0.clamp(0., 1.), point.1.clamp(0., 1.));
+        }
+        if let (Some(on_pose), false) = (&self.on_pose, pose.is_empty()) {
+            on_pose.invoke(pose);
+        }
+    }
+
+    // clicking the first point again closes the polygon
+    fn add_sketch_point(&mut self, location: Point<Px>) {
+        if let Some(&first) = self.sketch.first() {
+            if self.is_near(first, location) && self.sketch.len() >= 3 {
+                self.finish_sketch();
+                return;
+            }
+        }
+        self.sketch.push(frame_point(self.frame_rect, location));
+    }
+
+    fn finish_sketch(&mut self) {
+        let polygon = Polygon {
+            points: std::mem::take(&mut self.sketch),
+        };
+        self.hand_polygon(polygon);
+    }
+
+    fn hand_polygon(&self, polygon: Polygon) {
+        let polygon = polygon.clamped();
+        if let (Some(on_polygon), true) = (&self.on_polygon, polygon.is_valid()) {
+            on_polygon.invoke(polygon);
+        }
+    }
+}
+
 impl Widget for VideoPlayer {
+    fn hit_test(&mut self, location: Point<Px>, context: &mut EventContext<'_>) -> bool {
+        self.interactive
+    }
+
+    fn accept_focus(&mut self, context: &mut EventContext<'_>) -> bool {
+        self.interactive
+    }
+
+    fn focus(&mut self, context: &mut EventContext<'_>) {
+        context.set_needs_redraw();
+    }
+
+    fn blur(&mut self, context: &mut EventContext<'_>) {
+        context.set_needs_redraw();
+    }
+
+    // everything the mouse can do, for when the player has keyboard focus
+    fn keyboard_input(
+        &mut self,
+        device_id: DeviceId,
+        input: KeyEvent,
+        is_synthetic: bool,
+        context: &mut EventContext<'_>,
+    ) -> EventHandling {
+        if !input.state.is_pressed() {
+            return IGNORED;
+        }
+
+        let modifiers = context.modifiers().state();
+        // alt and ctrl combinations belong to tag nudging and category shortcuts
+        if modifiers.alt_key() || modifiers.control_key() {
+            return IGNORED;
+        }
+
+        match &input.logical_key {
+            Key::Named(NamedKey::Enter) if !self.sketch.is_empty() => {
+                self.finish_sketch();
+                context.set_needs_redraw();
+            }
+            Key::Named(NamedKey::Escape) if !self.sketch.is_empty() => {
+                self.sketch.clear();
+                context.set_needs_redraw();
+            }
+            Key::Named(NamedKey::Enter) if !self.pose_sketch.is_empty() => {
+                self.finish_pose();
+                context.set_needs_redraw();
+            }
+            Key::Named(NamedKey::Escape) if !self.pose_sketch.is_empty() => {
+                self.pose_sketch.clear();
+                self.pose_progress.set(0);
+                context.set_needs_redraw();
+            }
+            Key::Named(NamedKey::Backspace) if !self.pose_sketch.is_empty() => {
+                self.pose_sketch.pop();
+                self.pose_progress.set(self.pose_sketch.len());
+                context.set_needs_redraw();
+            }
+            Key::Named(NamedKey::Space) if !input.repeat => self.toggle_playing(),
+            Key::Named(NamedKey::ArrowLeft) => self.send_control_message(
+                ControlCommand::SeekRelative(-WHEEL_SEEK_SECONDS),
+            ),
+            Key::Named(NamedKey::ArrowRight) => {
+                self.send_control_message(ControlCommand::SeekRelative(WHEEL_SEEK_SECONDS))
+            }
+            Key::Named(NamedKey::Escape) if self.fullscreen.get() => self.fullscreen.set(false),
+            Key::Named(NamedKey::F3) if !input.repeat => self.show_timings.toggle(),
+            Key::Named(NamedKey::ContextMenu) => {
+                let center = context
+                    .last_layout()
+                    .map(|rect| Point::new(rect.size.width / 2, rect.size.height / 2))
+                    .unwrap_or_default();
+                return self.show_context_menu(center, context);
+            }
+            Key::Named(NamedKey::F10) if modifiers.shift_key() => {
+                return self.show_context_menu(Point::default(), context);
+            }
+            Key::Character(text) => match text.as_str() {
+                "f" | "F" if !input.repeat => self.fullscreen.toggle(),
+                // the keypoint about to be placed isn't visible
+                "x" | "X" if self.draw_tool.get() == DrawTool::Keypoints && self.on_pose.is_some() => {
+                    self.place_keypoint(None);
+                    context.set_needs_redraw();
+                }
+                "l" | "L" if !input.repeat => self.toggle_looping(),
+                "c" | "C" if !input.repeat && self.filtered.get() => self.toggle_wipe(),
+                "[" => self.set_a(),
+                "]" => self.set_b(),
+                "\\" => self.clear_ab(),
+                "," => self.step_backward(),
+                "." => self.step_forward(),
+                "+" | "=" => self.zoom_by(1.),
+                "-" => self.zoom_by(-1.),
+                "0" => self.reset_zoom(),
+                _ => return IGNORED,
+            },
+            _ => return IGNORED,
+        }
+
+        HANDLED
+    }
+
+    fn mouse_down(
+        &mut self,
+        location: Point<Px>,
+        device_id: DeviceId,
+        button: MouseButton,
+        context: &mut EventContext<'_>,
+    ) -> EventHandling {
+        if !self.interactive {
+            return IGNORED;
+        }
+
+        if button == MouseButton::Right {
+            return self.show_context_menu(location, context);
+        }
+
+        if button != MouseButton::Left {
+            return IGNORED;
+        }
+
+        context.focus();
+
+        let mode = if context.modifiers().state().shift_key() {
+            match self.draw_tool.get() {
+                DrawTool::Box if self.on_region.is_some() => DragMode::Box,
+                DrawTool::Freehand if self.on_polygon.is_some() => {
+                    DragMode::Freehand(vec![frame_point(self.frame_rect, location)])
+                }
+                DrawTool::Polygon if self.on_polygon.is_some() => {
+                    self.add_sketch_point(location);
+                    context.set_needs_redraw();
+                    return HANDLED;
+                }
+                DrawTool::Keypoints if self.on_pose.is_some() => {
+                    self.place_keypoint(Some(frame_point(self.frame_rect, location)));
+                    context.set_needs_redraw();
+                    return HANDLED;
+                }
+                _ => DragMode::Navigate,
+            }
+        } else if let Some(mode) = self.grab_point(location).or_else(|| self.grab_crop(location)) {
+            mode
+        } else if self.is_near_wipe(location) {
+            DragMode::Wipe
+        } else {
+            // whether this was a tap, a swipe or a pan is only known once it ends
+            DragMode::Navigate
+        };
+
+        self.drag = Some(Drag {
+            start: location,
+            last: location,
+            mode,
+        });
+        HANDLED
+    }
+
+    fn mouse_drag(
+        &mut self,
+        location: Point<Px>,
+        device_id: DeviceId,
+        button: MouseButton,
+        context: &mut EventContext<'_>,
+    ) {
+        let Some(drag) = &mut self.drag else {
+            return;
+        };
+
+        let point = frame_point(self.frame_rect, location);
+        match &mut drag.mode {
+            DragMode::Navigate => {
+                if self.view.get().is_some() {
+                    // looking around a 360 video instead of seeking
+                    let height = self.frame_rect.size.height.into_float().max(1.);
+                    let delta = location - drag.last;
+                    if let Some(view) = &mut *self.view.lock() {
+                        *view = view.dragged(
+                            delta.x.into_float() / height,
+                            delta.y.into_float() / height,
+                        );
+                    }
+                } else if self.zoom.get() > 1. {
+                    // while zoomed in, dragging moves the picture instead of seeking
+                    let delta = location - drag.last;
+                    *self.pan.lock() += delta;
+                }
+            }
+            DragMode::Box => context.set_needs_redraw(),
+            DragMode::Freehand(points) => {
+                points.push(point);
+                context.set_needs_redraw();
+            }
+            DragMode::Vertex { polygon, idx } => {
+                polygon.points[*idx] = (point.0.clamp(0., 1.), point.1.clamp(0., 1.));
+                context.set_needs_redraw();
+            }
+            DragMode::Keypoint { pose, idx } => {
+                pose.points[*idx] = Some(point);
+                context.set_needs_redraw();
+            }
+            DragMode::Wipe => self.wipe.set(Some(point.0.clamp(0., 1.))),
+            DragMode::Crop(center) => {
+                let size = self.frame_rect.size.into_float();
+                let half = (size.height * VERTICAL_ASPECT / size.width.max(1.)).min(1.) / 2.;
+                *center = (*center + (location.x - drag.last.x).into_float() / size.width.max(1.))
+                    .clamp(half, 1. - half);
+                context.set_needs_redraw();
+            }
+        }
+        drag.last = location;
+    }
+
+    fn mouse_up(
+        &mut self,
+        location: Option<Point<Px>>,
+        device_id: DeviceId,
+        button: MouseButton,
+        context: &mut EventContext<'_>,
+    ) {
+        let Some(drag) = self.drag.take() else {
+            return;
+        };
+
+        let end = location.unwrap_or(drag.last);
+        match drag.mode {
+            DragMode::Navigate => {}
+            DragMode::Box => {
+                context.set_needs_redraw();
+                let region = Region::from_corners(
+                    frame_point(self.frame_rect, drag.start),
+                    frame_point(self.frame_rect, end),
+                );
+                if let (Some(on_region), false) = (&self.on_region, region.is_empty()) {
+                    on_region.invoke(region);
+                }
+                return;
+            }
+            DragMode::Freehand(points) => {
+                context.set_needs_redraw();
+                self.hand_polygon(Polygon { points }.thinned(FREEHAND_SPACING));
+                return;
+            }
+            DragMode::Vertex { polygon, .. } => {
+                context.set_needs_redraw();
+                if end != drag.start {
+                    self.hand_polygon(polygon);
+                }
+                return;
+            }
+            DragMode::Keypoint { pose, .. } => {
+                context.set_needs_redraw();
+                if end != drag.start {
+                    self.hand_pose(pose);
+                }
+                return;
+            }
+            DragMode::Wipe => return,
+            DragMode::Crop(center) => {
+                context.set_needs_redraw();
+                if let (Some(on_crop), true) = (&self.on_crop, end != drag.start) {
+                    on_crop.invoke(center);
+                }
+                return;
+            }
+        }
+
+        let dx = (end.x - drag.start.x).into_float();
+        let dy = (end.y - drag.start.y).into_float();
+        let moved = dx.abs().max(dy.abs()) >= SWIPE_THRESHOLD;
+
+        if !moved {
+            self.tap();
+        } else if self.zoom.get() <= 1. && self.view.get().is_none() && dx.abs() > dy.abs() {
+            let width = context
+                .last_layout()
+                .map_or(1., |rect| rect.size.width.into_float())
+                .max(1.);
+            self.send_control_message(ControlCommand::SeekRelative(
+                f64::from(dx / width) * SWIPE_SECONDS_PER_WIDTH,
+            ));
+        }
+    }
+
+    fn mouse_wheel(
+        &mut self,
+        device_id: DeviceId,
+        delta: MouseScrollDelta,
+        phase: TouchPhase,
+        context: &mut EventContext<'_>,
+    ) -> EventHandling {
+        if !self.interactive {
+            return IGNORED;
+        }
+
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(position) => position.y as f32,
+        };
+
+        if lines == 0. {
+            return IGNORED;
+        }
+
+        if context.modifiers().state().control_key() {
+            let steps = match delta {
+                MouseScrollDelta::LineDelta(..) => lines,
+                MouseScrollDelta::PixelDelta(_) => lines / PIXELS_PER_ZOOM_STEP,
+            };
+            if let Some(view) = &mut *self.view.lock() {
+                *view = view.zoomed(steps);
+            } else {
+                self.zoom_by(steps);
+            }
+            return HANDLED;
+        }
+
+        self.send_control_message(ControlCommand::SeekRelative(
+            WHEEL_SEEK_SECONDS.copysign(f64::from(lines)),
+        ));
+
+        HANDLED
+    }
+
     fn redraw(&mut self, context: &mut GraphicsContext<'_, '_, '_, '_>) {
         use cushy::context::Trackable;
 
+        let started = Instant::now();
         self.contents.redraw_when_changed(context);
+        let zoom = self.zoom.get_tracking_redraw(context);
+        let pan = self.pan.get_tracking_redraw(context);
+        let wipe = self.wipe.get_tracking_redraw(context);
+        // the widget takes all the space it gets while looking into a panorama
+        let view = self.view.get_tracking_invalidate(context);
+        let stereo = self
+            .stereo
+            .get_tracking_invalidate(context)
+            .map(|layout| (layout, self.stereo_view.get_tracking_redraw(context)));
+        let channel = self.channel.get_tracking_redraw(context);
+
+        let frame_rect = self.contents.map_ref(|frame| {
+            let mut rect = self.calculate_frame_rect(frame, context.gfx.size(), context);
+            if view.is_some() {
+                rect = Rect::from(context.gfx.size().into_signed());
+            } else if zoom > 1. {
+                let size = context.gfx.size().into_signed();
+                let center = Point::new(size.width / 2, size.height / 2);
+                rect = Rect::new(
+                    center + (rect.origin - center) * zoom + pan,
+                    rect.size * zoom,
+                );
+            }
+            match (frame, wipe) {
+                (VideoFrame::Placeholder(texture), _) => {
+                    context.gfx.draw_texture(texture, rect, ZeroToOne::new(1.));
+                }
+                // the original left of the split, the filtered frame right of it
+                (VideoFrame::Compared { filtered, original }, Some(split)) => {
+                    context.gfx.draw_with::<YuvRenderer>(YuvDraw {
+                        frame: original.clone(),
+                        rect,
+                        span: (0., split),
+                        view,
+                        stereo,
+                        channel,
+                    });
+                    context.gfx.draw_with::<YuvRenderer>(YuvDraw {
+                        frame: filtered.clone(),
+                        rect,
+                        span: (split, 1.),
+                        view,
+                        stereo,
+                        channel,
+                    });
+                    let x = frame_location(rect, (split, 0.)).x - Px::new(WIPE_LINE / 2);
+                    let line = Rect::new(
+                        Point::new(x, rect.origin.y),
+                        Size::new(Px::new(WIPE_LINE), rect.size.height),
+                    );
+                    context.gfx.draw_shape(&Shape::filled_rect(line, RUBBER_BAND_COLOR));
+                }
+                (VideoFrame::Decoded(frame) | VideoFrame::Compared { filtered: frame, .. }, _) => {
+                    context.gfx.draw_with::<YuvRenderer>(YuvDraw {
+                        frame: frame.clone(),
+                        rect,
+                        span: (0., 1.),
+                        view,
+                        stereo,
+                        channel,
+                    });
+                }
+            }
+            rect
+        });
+        self.frame_rect = frame_rect;
+
+        // the window being dragged over the one it's keyframed at
+        let crop = match &self.drag {
+            Some(Drag {
+                mode: DragMode::Crop(center),
+                ..
+            }) => Some(*center),
+            _ => self.crop.get_tracking_redraw(context),
+        };
+        if let Some(center) = crop {
+            let window = crop_rect(frame_rect, center);
+            let (left, right) = (window.origin.x, window.origin.x + window.size.width);
+            let frame_right = frame_rect.origin.x + frame_rect.size.width;
+            for (from, to) in [(frame_rect.origin.x, left), (right, frame_right)] {
+                context.gfx.draw_shape(&Shape::filled_rect(
+                    Rect::new(
+                        Point::new(from, frame_rect.origin.y),
+                        Size::new(to - from, frame_rect.size.height),
+                    ),
+                    CROP_SHADE,
+                ));
+            }
+            draw_outline(context, window, RUBBER_BAND_COLOR, REGION_OUTLINE);
+        }
 
-        self.contents.map_ref(|texture| {
-            let rect = self.calculate_frame_rect(texture, context.gfx.size(), context);
-            context.gfx.draw_texture(texture, rect, ZeroToOne::new(1.));
+        let position = self.position.get_tracking_redraw(context);
+        self.subtitles.redraw_when_changed(context);
+        let cues = self.subtitles.map_ref(|subtitles| {
+            subtitles.as_ref().map_or_else(Vec::new, |subtitles| {
+                subtitles.at(position).flat_map(str::lines).map(String::from).collect()
+            })
         });
+        if !cues.is_empty() {
+            draw_subtitles(context, frame_rect, &cues);
+        }
+
+        self.regions.redraw_when_changed(context);
+        let playing = self.playing.get_tracking_redraw(context);
+        let moving_point = matches!(
+            self.drag,
+            Some(Drag {
+                mode: DragMode::Vertex { .. } | DragMode::Keypoint { .. },
+                ..
+            })
+        );
+        self.regions.map_ref(|regions| {
+            for overlay in regions {
+                let thickness = if overlay.selected {
+                    SELECTED_REGION_OUTLINE
+                } else {
+                    REGION_OUTLINE
+                };
+                match &overlay.outline {
+                    Outline::Box(region) => draw_outline(
+                        context,
+                        region_rect(frame_rect, *region),
+                        overlay.color,
+                        thickness,
+                    ),
+                    // the one being edited is drawn from the drag below
+                    Outline::Polygon(_) | Outline::Pose { .. } if overlay.selected && moving_point => {}
+                    Outline::Polygon(polygon) => {
+                        draw_polyline(
+                            context,
+                            frame_rect,
+                            &polygon.points,
+                            overlay.color,
+                            thickness,
+                            true,
+                        );
+                        // points can be moved while paused
+                        if overlay.selected && !playing {
+                            draw_handles(context, frame_rect, &polygon.points, overlay.color);
+                        }
+                    }
+                    Outline::Pose { pose, edges } => {
+                        draw_pose(context, frame_rect, pose, edges, overlay.color, thickness);
+                    }
+                }
+            }
+        });
+        match self.drag.as_ref().map(|drag| (drag, &drag.mode)) {
+            Some((drag, DragMode::Box)) => {
+                let region = Region::from_corners(
+                    frame_point(frame_rect, drag.start),
+                    frame_point(frame_rect, drag.last),
+                );
+                draw_outline(
+                    context,
+                    region_rect(frame_rect, region),
+                    RUBBER_BAND_COLOR,
+                    REGION_OUTLINE,
+                );
+            }
+            Some((_, DragMode::Freehand(points))) => {
+                draw_polyline(context, frame_rect, points, RUBBER_BAND_COLOR, REGION_OUTLINE, false);
+            }
+            Some((_, DragMode::Vertex { polygon, .. })) => {
+                draw_polyline(
+                    context,
+                    frame_rect,
+                    &polygon.points,
+                    RUBBER_BAND_COLOR,
+                    SELECTED_REGION_OUTLINE,
+                    true,
+                );
+                draw_handles(context, frame_rect, &polygon.points, RUBBER_BAND_COLOR);
+            }
+            Some((_, DragMode::Keypoint { pose, .. })) => {
+                let edges = self.regions.map_ref(|regions| {
+                    regions.iter().find_map(|overlay| match &overlay.outline {
+                        Outline::Pose { edges, .. } if overlay.selected => Some(edges.clone()),
+                        _ => None,
+                    })
+                });
+                draw_pose(
+                    context,
+                    frame_rect,
+                    pose,
+                    &edges.unwrap_or_default(),
+                    RUBBER_BAND_COLOR,
+                    SELECTED_REGION_OUTLINE,
+                );
+            }
+            Some((_, DragMode::Navigate | DragMode::Wipe | DragMode::Crop(_))) | None => {}
+        }
+        if !self.sketch.is_empty() {
+            draw_polyline(context, frame_rect, &self.sketch, RUBBER_BAND_COLOR, REGION_OUTLINE, false);
+            draw_handles(context, frame_rect, &self.sketch, RUBBER_BAND_COLOR);
+        }
+        if !self.pose_sketch.is_empty() {
+            let (name, edges) = self
+                .skeleton
+                .map_ref(|skeleton| (skeleton.name.clone(), skeleton.edges.clone()));
+            let pose = Pose {
+                skeleton: name,
+                points: self.pose_sketch.clone(),
+            };
+            draw_pose(context, frame_rect, &pose, &edges, RUBBER_BAND_COLOR, REGION_OUTLINE);
+        }
+
+        // only the first draw of a frame counts, later ones are hovers and resizes
+        if self.timings.map_ref(FrameTimings::awaiting_draw) {
+            self.timings.lock().drawn(started.elapsed());
+        }
+
+        if context.focused(true) {
+            context.draw_focus_ring();
+        }
     }
 
     fn layout(
@@ -124,9 +1710,213 @@ impl Widget for VideoPlayer {
         available_space: Size<ConstraintLimit>,
         context: &mut LayoutContext<'_, '_, '_, '_>,
     ) -> cushy::figures::Size<cushy::figures::units::UPx> {
-        let rect = self.contents.map_ref(|texture| {
-            self.calculate_frame_rect(texture, available_space.map(ConstraintLimit::max), context)
+        if self.view.get().is_some() {
+            return available_space.map(ConstraintLimit::max);
+        }
+        let rect = self.contents.map_ref(|frame| {
+            self.calculate_frame_rect(frame, available_space.map(ConstraintLimit::max), context)
         });
         rect.size.into_unsigned()
     }
 }
+
+pub fn scaling_controls(mode: Dynamic<ScalingMode>, on_make_default: impl FnMut() + Send + 'static) -> impl MakeWidget {
+    let modes = [
+        (ScalingMode::Fit, "fit"),
+        (ScalingMode::Fill, "fill"),
+        (ScalingMode::Stretch, "stretch"),
+        (ScalingMode::Scale(1.), "1:1"),
+    ];
+
+    let buttons = modes
+        .into_iter()
+        .map(|(value, label)| {
+            let selected = mode.map_each(move |mode| *mode == value);
+            let label = selected.map_each(move |selected| {
+                if *selected {
+                    format!("[{label}]")
+                } else {
+                    label.to_string()
+                }
+            });
+
+            label.into_button().on_click({
+                let mode = mode.clone();
+                move |_| mode.set(value)
+            })
+        })
+        .collect::<WidgetList>();
+
+    let mut on_make_default = on_make_default;
+    buttons
+        .and("set as default".into_button().on_click(move |_| on_make_default()))
+        .into_columns()
+}
+
+// what shift+drag and shift+click on the video draw
+pub fn draw_tool_controls(tool: Dynamic<DrawTool>) -> impl MakeWidget {
+    let tools = [
+        (DrawTool::Box, "box"),
+        (DrawTool::Polygon, "polygon"),
+        (DrawTool::Freehand, "freehand"),
+        (DrawTool::Keypoints, "keypoints"),
+    ];
+
+    tools
+        .into_iter()
+        .map(|(value, label)| {
+            let label = tool.map_each(move |tool| {
+                if *tool == value {
+                    format!("[{label}]")
+                } else {
+                    label.to_string()
+                }
+            });
+
+            label.into_button().on_click({
+                let tool = tool.clone();
+                move |_| tool.set(value)
+            })
+        })
+        .collect::<WidgetList>()
+        .into_columns()
+}
+
+// play/pause, where playback is and a slider to move it, under the video
+pub fn transport_bar(player: &VideoPlayer) -> impl MakeWidget {
+    let play_pause = player
+        .playing()
+        .map_each(|playing| if *playing { "pause" } else { "play" })
+        .into_button()
+        .on_click({
+            let player = player.clone();
+            move |_| player.toggle_playing()
+        });
+
+    // follows playback, and seeks when it gets moved to somewhere playback isn't
+    let position = player.position();
+    let seconds = Dynamic::new(position.get().as_secs_f32());
+    position
+        .for_each({
+            let seconds = seconds.clone();
+            move |position| seconds.set(position.as_secs_f32())
+        })
+        .persist();
+    seconds
+        .for_each({
+            let player = player.clone();
+            move |seconds| {
+                if *seconds != player.position().get().as_secs_f32() {
+                    player.seek(Duration::from_secs_f32(seconds.max(0.)));
+                }
+            }
+        })
+        .persist();
+
+    let duration = player.duration();
+    let slider = seconds
+        .slider_between(0., duration.map_each(Duration::as_secs_f32))
+        .with_enabled(duration.map_each(|duration| !duration.is_zero()));
+
+    let repeat = player.repeat();
+    let looping = repeat
+        .map_each(|repeat| if repeat.looping { "[loop]" } else { "loop" })
+        .into_button()
+        .on_click({
+            let player = player.clone();
+            move |_| player.toggle_looping()
+        });
+    let set_a = repeat
+        .map_each(|repeat| match repeat.a {
+            Some(a) => format!("A {}", format_timestamp(a)),
+            None => String::from("set A"),
+        })
+        .into_button()
+        .on_click({
+            let player = player.clone();
+            move |_| player.set_a()
+        });
+    let set_b = repeat
+        .map_each(|repeat| match repeat.b {
+            Some(b) => format!("B {}", format_timestamp(b)),
+            None => String::from("set B"),
+        })
+        .into_button()
+        .on_click({
+            let player = player.clone();
+            move |_| player.set_b()
+        });
+    let clear_ab = "clear A-B"
+        .into_button()
+        .on_click({
+            let player = player.clone();
+            move |_| player.clear_ab()
+        })
+        .with_enabled(repeat.map_each(|repeat| repeat.a.is_some() || repeat.b.is_some()));
+    let compare = player
+        .wipe()
+        .map_each(|wipe| if wipe.is_some() { "[compare]" } else { "compare" })
+        .into_button()
+        .on_click({
+            let player = player.clone();
+            move |_| player.toggle_wipe()
+        })
+        .with_enabled(player.filtered());
+    let stereo = player
+        .stereo()
+        .map_each(|layout| StereoLayout::label(*layout))
+        .into_button()
+        .on_click({
+            let stereo = player.stereo();
+            move |_| {
+                let mut layout = stereo.lock();
+                *layout = StereoLayout::cycle(*layout);
+            }
+        });
+    let eye = player
+        .stereo_view()
+        .map_each(|view| view.label())
+        .into_button()
+        .on_click({
+            let stereo_view = player.stereo_view();
+            move |_| {
+                let mut view = stereo_view.lock();
+                *view = view.next();
+            }
+        })
+        .with_enabled(player.stereo().map_each(Option::is_some));
+    let channel = player
+        .channel()
+        .map_each(|channel| channel.label())
+        .into_button()
+        .on_click({
+            let channel = player.channel();
+            move |_| {
+                let mut shown = channel.lock();
+                *shown = shown.next();
+            }
+        });
+    let spherical = player
+        .view()
+        .map_each(|view| if view.is_some() { "[360]" } else { "360" })
+        .into_button()
+        .on_click({
+            let player = player.clone();
+            move |_| player.toggle_spherical()
+        });
+
+    play_pause
+        .and(position.map_each(|position| format_timestamp(*position)))
+        .and(slider.expand_horizontally())
+        .and(duration.map_each(|duration| format_timestamp(*duration)))
+        .and(looping)
+        .and(set_a)
+        .and(set_b)
+        .and(clear_ab)
+        .and(compare)
+        .and(spherical)
+        .and(stereo)
+        .and(eye)
+        .and(channel)
+        .into_columns()
+}
@@ -0,0 +1,176 @@
+use std::{sync::mpsc::Sender, time::Duration};
+
+use cushy::{
+    value::{Destination, Dynamic, Source, Switchable},
+    widget::{MakeWidget, WidgetList},
+};
+use serde::Serialize;
+
+use crate::tags::{ActivityEvent, ActivityKind, TagId};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    event: &'static str,
+    // stays the same for as long as the tag exists, so updates and deletes can be
+    // matched to the creation
+    id: Option<TagId>,
+    video: &'a str,
+    label: &'a str,
+    // the label when it's one of the tag categories, none for free labels
+    category: Option<&'a str>,
+    note: &'a str,
+    // seconds into the video
+    start: f64,
+    end: Option<f64>,
+    // unix seconds
+    at: u64,
+}
+
+impl<'a> Payload<'a> {
+    fn new(event: &'a ActivityEvent, video: &'a str, categories: &[String]) -> Self {
+        Self {
+            event: match event.kind {
+                ActivityKind::Created => "tag.created",
+                ActivityKind::Edited => "tag.updated",
+                ActivityKind::Deleted => "tag.deleted",
+            },
+            id: event.tag,
+            video,
+            label: &event.label,
+            category: categories
+                .contains(&event.label)
+                .then_some(event.label.as_str()),
+            note: &event.note,
+            start: event.time.as_secs_f64(),
+            end: event.end.map(|end| end.as_secs_f64()),
+            at: event.at,
+        }
+    }
+}
+
+// what one change to the tags sends, with the settings at the time
+#[derive(Debug)]
+struct Batch {
+    urls: Vec<String>,
+    categories: Vec<String>,
+    events: Vec<ActivityEvent>,
+}
+
+// posts tag events to every configured url from a background thread so a slow
+// endpoint never holds up tagging
+#[derive(Debug, Clone)]
+pub struct Webhooks {
+    sender: Sender<Batch>,
+}
+
+impl Webhooks {
+    pub fn start() -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel::<Batch>();
+
+        std::thread::Builder::new()
+            .name("Webhook Thread".into())
+            .spawn(move || {
+                let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+
+                for batch in receiver {
+                    for event in &batch.events {
+                        let video = event.video.to_string_lossy();
+                        let payload = Payload::new(event, &video, &batch.categories);
+
+                        for url in &batch.urls {
+                            if let Err(err) = agent.post(url).send_json(&payload) {
+                                tracing::error!("webhook {url} failed: {err}");
+                            }
+                        }
+                    }
+                }
+            })
+            .unwrap();
+
+        Self { sender }
+    }
+
+    pub fn notify(&self, urls: Vec<String>, categories: Vec<String>, events: &[ActivityEvent]) {
+        if urls.is_empty() || events.is_empty() {
+            return;
+        }
+        let _ = self.sender.send(Batch {
+            urls,
+            categories,
+            events: events.to_vec(),
+        });
+    }
+}
+
+pub fn webhook_editor(urls: Dynamic<Vec<String>>) -> impl MakeWidget {
+    let new_url = Dynamic::new(String::new());
+
+    let add = "add".into_button().on_click({
+        let urls = urls.clone();
+        let new_url = new_url.clone();
+        move |_| {
+            let url = new_url.take();
+            let url = url.trim();
+            if url.starts_with("http://") || url.starts_with("https://") {
+                urls.lock().push(url.to_string());
+            }
+        }
+    });
+
+    let list = urls.clone().switcher(move |current, _| {
+        current
+            .iter()
+            .enumerate()
+            .map(|(idx, url)| {
+                let urls = urls.clone();
+                url.clone()
+                    .expand_horizontally()
+                    .and("remove".into_button().on_click(move |_| {
+                        let mut urls = urls.lock();
+                        if idx < urls.len() {
+                            urls.remove(idx);
+                        }
+                    }))
+                    .into_columns()
+            })
+            .collect::<WidgetList>()
+            .into_rows()
+            .make_widget()
+    });
+
+    "Webhooks"
+        .and(list)
+        .and(new_url.into_input().expand_horizontally().and(add).into_columns())
+        .into_rows()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::TagStore;
+
+    #[test]
+    fn updates_name_the_tag_they_are_about() {
+        let mut store = TagStore::new();
+        let id = store
+            .add("match.mp4".into(), Duration::from_secs(3), "goal".into())
+            .unwrap();
+        store.set_note(id, "header".into());
+        let events = store.activity_since(0);
+
+        let payloads = events
+            .iter()
+            .map(|event| serde_json::to_value(Payload::new(event, "match.mp4", &["goal".into()])))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(payloads[0]["event"], "tag.created");
+        assert_eq!(payloads[1]["event"], "tag.updated");
+        assert_eq!(payloads[0]["id"], payloads[1]["id"]);
+        assert!(payloads[1]["id"].is_u64());
+        assert_eq!(payloads[1]["category"], "goal");
+        assert_eq!(payloads[1]["note"], "header");
+    }
+}
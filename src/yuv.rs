@@ -0,0 +1,735 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use cushy::figures::units::{Px, UPx};
+use cushy::figures::{FloatConversion, Point, Rect, Size};
+use cushy::graphics::RenderOperation;
+use cushy::kludgine::wgpu::util::DeviceExt;
+use cushy::kludgine::image::RgbaImage;
+use cushy::kludgine::{self, wgpu, RenderingGraphics};
+use ffmpeg_next as ffmpeg;
+
+use crate::spherical::ViewDirection;
+use crate::stereo::{StereoLayout, StereoView};
+
+// sets of plane textures the renderer keeps around for the next frames of the same
+// size to be written into, beyond the ones being drawn
+const POOLED_FRAMES: usize = 4;
+
+static NEXT_FRAME_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    // u and v in planes of their own, yuv420p
+    Planar,
+    // one plane of uv pairs, nv12 as hardware decoders hand it out
+    Interleaved,
+    // already rgb, swscale converted it for formats the shader doesn't take
+    Rgba,
+}
+
+// the planes of a decoded 8 bit 4:2:0 frame, as they came out of the decoder, or
+// the rgba swscale made of any other. the player's shader turns them into rgb while
+// drawing
+#[derive(Debug)]
+pub struct YuvFrame {
+    // each frame is uploaded once, however often it is drawn
+    id: u64,
+    width: u32,
+    height: u32,
+    layout: Layout,
+    // luma, then chroma, with their row strides in bytes
+    planes: Vec<(Vec<u8>, u32)>,
+    // rows of r, g and b for (y, u, v, 1)
+    matrix: [[f32; 4]; 3],
+}
+
+impl YuvFrame {
+    // none for formats the shader doesn't take, those go through swscale
+    pub fn from_frame(frame: &ffmpeg::util::frame::Video) -> Option<Self> {
+        use ffmpeg::format::Pixel;
+
+        let (layout, plane_count, full_range) = match frame.format() {
+            Pixel::YUV420P => (Layout::Planar, 3, false),
+            Pixel::YUVJ420P => (Layout::Planar, 3, true),
+            Pixel::NV12 => (Layout::Interleaved, 2, false),
+            _ => return None,
+        };
+        let full_range = full_range || frame.color_range() == ffmpeg::color::Range::JPEG;
+
+        let planes = (0..plane_count)
+            .map(|idx| (frame.data(idx).to_vec(), frame.stride(idx) as u32))
+            .collect();
+        Some(Self {
+            id: NEXT_FRAME_ID.fetch_add(1, Ordering::Relaxed),
+            width: frame.width(),
+            height: frame.height(),
+            layout,
+            planes,
+            matrix: conversion_matrix(frame.color_space(), frame.height(), full_range),
+        })
+    }
+
+    pub fn from_rgba(image: RgbaImage) -> Self {
+        let (width, height) = image.dimensions();
+        Self {
+            id: NEXT_FRAME_ID.fetch_add(1, Ordering::Relaxed),
+            width,
+            height,
+            layout: Layout::Rgba,
+            planes: vec![(image.into_raw(), width * 4)],
+            // the shader passes rgba through
+            matrix: [[1., 0., 0., 0.], [0., 1., 0., 0.], [0., 0., 1., 0.]],
+        }
+    }
+
+    pub fn size(&self) -> Size<UPx> {
+        Size::new(UPx::new(self.width), UPx::new(self.height))
+    }
+
+    // what the shader would draw, worked out on the cpu for saving a still. chroma is
+    // taken from the nearest sample rather than filtered
+    pub fn to_rgba(&self) -> RgbaImage {
+        let (width, height) = (self.width as usize, self.height as usize);
+        if self.layout == Layout::Rgba {
+            let (data, stride) = &self.planes[0];
+            let mut rgba = Vec::with_capacity(width * height * 4);
+            for row in data.chunks(*stride as usize).take(height) {
+                rgba.extend_from_slice(&row[..width * 4]);
+            }
+            return RgbaImage::from_raw(self.width, self.height, rgba)
+                .expect("rows are width * 4 bytes");
+        }
+
+        let sample = |plane: usize, x: usize, y: usize| {
+            let (data, stride) = &self.planes[plane];
+            f32::from(data[y * *stride as usize + x]) / 255.
+        };
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for x in 0..width {
+                let (u, v) = match self.layout {
+                    Layout::Planar => (sample(1, x / 2, y / 2), sample(2, x / 2, y / 2)),
+                    _ => (sample(1, x / 2 * 2, y / 2), sample(1, x / 2 * 2 + 1, y / 2)),
+                };
+                let yuv = [sample(0, x, y), u, v, 1.];
+                for row in &self.matrix {
+                    let value: f32 = row.iter().zip(yuv).map(|(a, b)| a * b).sum();
+                    rgba.push((value.clamp(0., 1.) * 255.).round() as u8);
+                }
+                rgba.push(u8::MAX);
+            }
+        }
+        RgbaImage::from_raw(self.width, self.height, rgba).expect("filled pixel by pixel")
+    }
+
+    fn chroma_size(&self) -> (u32, u32) {
+        (self.width.div_ceil(2), self.height.div_ceil(2))
+    }
+
+    // textures, with their sizes, the planes go into
+    fn textures(&self) -> Vec<(wgpu::TextureFormat, u32, u32)> {
+        let (chroma_width, chroma_height) = self.chroma_size();
+        let luma = (wgpu::TextureFormat::R8Unorm, self.width, self.height);
+        match self.layout {
+            Layout::Planar => vec![
+                luma,
+                (wgpu::TextureFormat::R8Unorm, chroma_width, chroma_height),
+                (wgpu::TextureFormat::R8Unorm, chroma_width, chroma_height),
+            ],
+            Layout::Interleaved => vec![
+                luma,
+                (wgpu::TextureFormat::Rg8Unorm, chroma_width, chroma_height),
+            ],
+            Layout::Rgba => vec![(wgpu::TextureFormat::Rgba8Unorm, self.width, self.height)],
+        }
+    }
+}
+
+// y'cbcr to r'g'b' for the frame's colorspace. untagged frames are assumed bt.709
+// from hd up and bt.601 below, like most players do
+fn conversion_matrix(space: ffmpeg::color::Space, height: u32, full_range: bool) -> [[f32; 4]; 3] {
+    use ffmpeg::color::Space;
+
+    let (kr, kb) = match space {
+        Space::BT709 => (0.2126, 0.0722),
+        Space::BT2020NCL | Space::BT2020CL => (0.2627, 0.0593),
+        Space::BT470BG | Space::SMPTE170M => (0.299, 0.114),
+        _ if height >= 720 => (0.2126, 0.0722),
+        _ => (0.299, 0.114),
+    };
+    let kg = 1. - kr - kb;
+    // limited range puts black at 16 and white at 235, chroma between 16 and 240
+    let (y_offset, y_scale, c_scale) = if full_range {
+        (0., 1., 1.)
+    } else {
+        (16. / 255., 255. / 219., 255. / 224.)
+    };
+
+    let r_cr = 2. * (1. - kr) * c_scale;
+    let g_cb = 2. * kb * (1. - kb) / kg * c_scale;
+    let g_cr = 2. * kr * (1. - kr) / kg * c_scale;
+    let b_cb = 2. * (1. - kb) * c_scale;
+    let y_base = -y_offset * y_scale;
+    [
+        [y_scale, 0., r_cr, y_base - 0.5 * r_cr],
+        [y_scale, -g_cb, -g_cr, y_base + 0.5 * (g_cb + g_cr)],
+        [y_scale, b_cb, 0., y_base - 0.5 * b_cb],
+    ]
+}
+
+const SHADER: &str = r#"
+struct Params {
+    // left, top, right, bottom in clip space
+    rect: vec4<f32>,
+    r: vec4<f32>,
+    g: vec4<f32>,
+    b: vec4<f32>,
+    // interleaved chroma, linearize the output, opacity, rgba passed through
+    flags: vec4<f32>,
+    // the horizontal slice of the frame drawn, from and to as fractions of its width,
+    // and whether the frame is an equirectangular panorama looked into
+    span: vec4<f32>,
+    // yaw and pitch in radians, tan of half the vertical fov, width over height
+    view: vec4<f32>,
+    // how 3d footage holds its eyes (none, side by side, top and bottom) and which
+    // is shown (left, right, both as an anaglyph)
+    stereo: vec4<f32>,
+    // the one channel shown in gray, see `Channel`
+    channel: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var luma: texture_2d<f32>;
+@group(0) @binding(2) var chroma_u: texture_2d<f32>;
+@group(0) @binding(3) var chroma_v: texture_2d<f32>;
+@group(0) @binding(4) var planes: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let corner = vec2<f32>(f32(index & 1u), f32(index >> 1u));
+    let across = mix(params.span.x, params.span.y, corner.x);
+    var out: VertexOutput;
+    out.position = vec4<f32>(
+        mix(params.rect.x, params.rect.z, across),
+        mix(params.rect.y, params.rect.w, corner.y),
+        0.0,
+        1.0,
+    );
+    out.uv = vec2<f32>(across, corner.y);
+    return out;
+}
+
+const PI: f32 = 3.14159265;
+
+// where in the panorama the ray through `screen` of the view hits
+fn look_up(screen: vec2<f32>) -> vec2<f32> {
+    let x = (screen.x * 2.0 - 1.0) * params.view.z * params.view.w;
+    let y = (1.0 - screen.y * 2.0) * params.view.z;
+    var ray = normalize(vec3<f32>(x, y, 1.0));
+    let pitch = vec2<f32>(cos(params.view.y), sin(params.view.y));
+    ray = vec3<f32>(ray.x, ray.y * pitch.x + ray.z * pitch.y, ray.z * pitch.x - ray.y * pitch.y);
+    let yaw = vec2<f32>(cos(params.view.x), sin(params.view.x));
+    ray = vec3<f32>(ray.x * yaw.x + ray.z * yaw.y, ray.y, ray.z * yaw.x - ray.x * yaw.y);
+    let longitude = atan2(ray.x, ray.z);
+    let latitude = asin(clamp(ray.y, -1.0, 1.0));
+    return vec2<f32>(longitude / (2.0 * PI) + 0.5, 0.5 - latitude / PI);
+}
+
+fn to_linear(color: vec3<f32>) -> vec3<f32> {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, color <= vec3<f32>(0.04045));
+}
+
+// `at` within one eye of a 3d frame, 0 for the left one and 1 for the right
+fn eye(at: vec2<f32>, which: f32) -> vec2<f32> {
+    if params.stereo.x > 1.5 {
+        return vec2<f32>(at.x, (at.y + which) * 0.5);
+    }
+    if params.stereo.x > 0.5 {
+        return vec2<f32>((at.x + which) * 0.5, at.y);
+    }
+    return at;
+}
+
+fn rgb_at(at: vec2<f32>) -> vec3<f32> {
+    let y = textureSample(luma, planes, at).r;
+    let u_sample = textureSample(chroma_u, planes, at);
+    let v_sample = textureSample(chroma_v, planes, at);
+    let uv = select(vec2<f32>(u_sample.r, v_sample.r), u_sample.rg, params.flags.x > 0.5);
+    let yuv = vec4<f32>(y, uv, 1.0);
+
+    var rgb = clamp(
+        vec3<f32>(dot(params.r, yuv), dot(params.g, yuv), dot(params.b, yuv)),
+        vec3<f32>(0.0),
+        vec3<f32>(1.0),
+    );
+    if params.flags.w > 0.5 {
+        rgb = textureSample(luma, planes, at).rgb;
+    }
+    return rgb;
+}
+
+// one channel of the frame as it was decoded: r, g, b, a, then y, u, v. rgb frames
+// get their yuv worked out with bt.709, yuv frames have no alpha
+fn channel_at(at: vec2<f32>, channel: f32) -> f32 {
+    if params.flags.w > 0.5 {
+        let rgba = textureSample(luma, planes, at);
+        let y = dot(rgba.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+        let yuv = vec3<f32>(y, (rgba.b - y) / 1.8556 + 0.5, (rgba.r - y) / 1.5748 + 0.5);
+        if channel > 4.5 {
+            return yuv[u32(channel) - 5u];
+        }
+        return rgba[u32(channel) - 1u];
+    }
+    if channel > 4.5 {
+        let y = textureSample(luma, planes, at).r;
+        let u_sample = textureSample(chroma_u, planes, at);
+        let v_sample = textureSample(chroma_v, planes, at);
+        let uv = select(vec2<f32>(u_sample.r, v_sample.r), u_sample.rg, params.flags.x > 0.5);
+        return vec3<f32>(y, uv)[u32(channel) - 5u];
+    }
+    if channel > 3.5 {
+        return 1.0;
+    }
+    return rgb_at(at)[u32(channel) - 1u];
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    var at = in.uv;
+    if params.span.z > 0.5 {
+        at = look_up(in.uv);
+    }
+    var rgb: vec3<f32>;
+    if params.channel.x > 0.5 {
+        // the left eye of an anaglyph
+        let which = select(params.stereo.y, 0.0, params.stereo.y > 1.5);
+        rgb = vec3<f32>(channel_at(eye(at, which), params.channel.x));
+    } else if params.stereo.y > 1.5 {
+        // the left eye's brightness in red, the right eye's color in cyan
+        let left = rgb_at(eye(at, 0.0));
+        let right = rgb_at(eye(at, 1.0));
+        rgb = vec3<f32>(dot(left, vec3<f32>(0.299, 0.587, 0.114)), right.g, right.b);
+    } else {
+        rgb = rgb_at(eye(at, params.stereo.y));
+    }
+    // srgb targets expect linear light, like the textures of cpu converted frames
+    if params.flags.y > 0.5 {
+        rgb = to_linear(rgb);
+    }
+    return vec4<f32>(rgb, 1.0) * params.flags.z;
+}
+"#;
+
+// a yuv frame and where in the widget it goes
+#[derive(Debug)]
+pub struct YuvDraw {
+    pub frame: Arc<YuvFrame>,
+    pub rect: Rect<Px>,
+    // the part of the frame's width drawn into the same part of `rect`, 0 to 1
+    // for all of it
+    pub span: (f32, f32),
+    // set for a 360 frame, which is then reprojected to fill `rect` looking this way
+    pub view: Option<ViewDirection>,
+    // set for 3d footage, of which only what `StereoView` asks for is drawn
+    pub stereo: Option<(StereoLayout, StereoView)>,
+    pub channel: Channel,
+}
+
+// the whole picture, or one channel of it in gray for checking chroma artifacts and
+// keys. for yuv frames y, u and v are the decoded planes as they are
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Channel {
+    #[default]
+    All,
+    Red,
+    Green,
+    Blue,
+    Alpha,
+    Luma,
+    Cb,
+    Cr,
+}
+
+impl Channel {
+    pub fn next(self) -> Self {
+        match self {
+            Self::All => Self::Red,
+            Self::Red => Self::Green,
+            Self::Green => Self::Blue,
+            Self::Blue => Self::Alpha,
+            Self::Alpha => Self::Luma,
+            Self::Luma => Self::Cb,
+            Self::Cb => Self::Cr,
+            Self::Cr => Self::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::All => "RGB",
+            Self::Red => "R",
+            Self::Green => "G",
+            Self::Blue => "B",
+            Self::Alpha => "A",
+            Self::Luma => "Y",
+            Self::Cb => "U",
+            Self::Cr => "V",
+        }
+    }
+
+    // as the shader numbers them
+    fn index(self) -> f32 {
+        match self {
+            Self::All => 0.,
+            Self::Red => 1.,
+            Self::Green => 2.,
+            Self::Blue => 3.,
+            Self::Alpha => 4.,
+            Self::Luma => 5.,
+            Self::Cb => 6.,
+            Self::Cr => 7.,
+        }
+    }
+}
+
+// plane textures, written again in place for every frame of the same size
+#[derive(Debug)]
+struct UploadedFrame {
+    // of the frame in them right now
+    id: AtomicU64,
+    size: (u32, u32, Layout),
+    textures: Vec<(wgpu::Texture, wgpu::TextureView)>,
+}
+
+impl UploadedFrame {
+    fn write(&self, frame: &YuvFrame, graphics: &kludgine::Graphics<'_>) {
+        for ((texture, _), (data, stride)) in self.textures.iter().zip(&frame.planes) {
+            let size = texture.size();
+            graphics.queue().write_texture(
+                wgpu::ImageCopyTexture {
+                    texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(*stride),
+                    rows_per_image: Some(size.height),
+                },
+                size,
+            );
+        }
+        self.id.store(frame.id, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+pub struct PreparedYuv {
+    bind_group: wgpu::BindGroup,
+    // keeps the textures from being written over while this draws them
+    _uploaded: Arc<UploadedFrame>,
+}
+
+// draws yuv frames, converting on the gpu. one per window, shared by its players
+#[derive(Debug)]
+pub struct YuvRenderer {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    linearize: bool,
+    uploaded: Vec<Arc<UploadedFrame>>,
+}
+
+impl YuvRenderer {
+    fn upload(&mut self, frame: &YuvFrame, graphics: &kludgine::Graphics<'_>) -> Arc<UploadedFrame> {
+        let already = self
+            .uploaded
+            .iter()
+            .find(|uploaded| uploaded.id.load(Ordering::Relaxed) == frame.id);
+        if let Some(uploaded) = already {
+            return uploaded.clone();
+        }
+
+        // textures of the same size no draw holds on to anymore get the new frame
+        let size = (frame.width, frame.height, frame.layout);
+        let free = self
+            .uploaded
+            .iter()
+            .find(|uploaded| uploaded.size == size && Arc::strong_count(uploaded) == 1);
+        if let Some(uploaded) = free {
+            uploaded.write(frame, graphics);
+            return uploaded.clone();
+        }
+
+        let textures = frame
+            .textures()
+            .into_iter()
+            .map(|(format, width, height)| {
+                let texture = graphics.device().create_texture(&wgpu::TextureDescriptor {
+                    label: Some("yuv plane"),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                    view_formats: &[],
+                });
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                (texture, view)
+            })
+            .collect();
+        let uploaded = Arc::new(UploadedFrame {
+            id: AtomicU64::new(frame.id),
+            size,
+            textures,
+        });
+        uploaded.write(frame, graphics);
+
+        // a new size, or more frames drawn at once than before. what isn't in use
+        // beyond the pool's size goes
+        let mut free = self
+            .uploaded
+            .iter()
+            .filter(|uploaded| Arc::strong_count(uploaded) == 1)
+            .count();
+        self.uploaded.retain(|uploaded| {
+            if free > POOLED_FRAMES && Arc::strong_count(uploaded) == 1 {
+                free -= 1;
+                false
+            } else {
+                true
+            }
+        });
+        self.uploaded.push(uploaded.clone());
+        uploaded
+    }
+}
+
+impl RenderOperation for YuvRenderer {
+    type DrawInfo = YuvDraw;
+    type Prepared = PreparedYuv;
+
+    fn new(graphics: &mut kludgine::Graphics<'_>) -> Self {
+        let device = graphics.device();
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("yuv"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("yuv"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(1),
+                texture_entry(2),
+                texture_entry(3),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("yuv"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let format = graphics.texture_format();
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("yuv"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vertex"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fragment"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..wgpu::PrimitiveState::default()
+            },
+            depth_stencil: None,
+            multisample: graphics.multisample_state(),
+            multiview: None,
+            cache: None,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("yuv"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..wgpu::SamplerDescriptor::default()
+        });
+
+        Self {
+            pipeline,
+            layout,
+            sampler,
+            linearize: format.is_srgb(),
+            uploaded: Vec::new(),
+        }
+    }
+
+    fn prepare(
+        &mut self,
+        draw: Self::DrawInfo,
+        origin: Point<Px>,
+        graphics: &mut kludgine::Graphics<'_>,
+    ) -> Self::Prepared {
+        let uploaded = self.upload(&draw.frame, graphics);
+
+        // the frame's corners in clip space, y points up there
+        let target = graphics.size().into_float();
+        let rect = Rect::new(origin + draw.rect.origin, draw.rect.size);
+        let (top_left, bottom_right) = rect.extents();
+        let clip_x = |x: Px| x.into_float() / target.width.max(1.) * 2. - 1.;
+        let clip_y = |y: Px| 1. - y.into_float() / target.height.max(1.) * 2.;
+        let interleaved = if draw.frame.layout == Layout::Interleaved { 1. } else { 0. };
+        let rgba = if draw.frame.layout == Layout::Rgba { 1. } else { 0. };
+        let linearize = if self.linearize { 1. } else { 0. };
+
+        let [r, g, b] = draw.frame.matrix;
+        let params = [
+            [
+                clip_x(top_left.x),
+                clip_y(top_left.y),
+                clip_x(bottom_right.x),
+                clip_y(bottom_right.y),
+            ],
+            r,
+            g,
+            b,
+            // opacity is filled in per render, the widget always draws opaque
+            [interleaved, linearize, 1., rgba],
+            [draw.span.0, draw.span.1, if draw.view.is_some() { 1. } else { 0. }, 0.],
+            draw.view.map_or([0.; 4], |view| {
+                [
+                    view.yaw.to_radians(),
+                    view.pitch.to_radians(),
+                    (view.fov.to_radians() / 2.).tan(),
+                    rect.size.width.into_float() / rect.size.height.into_float().max(1.),
+                ]
+            }),
+            match draw.stereo {
+                None => [0.; 4],
+                Some((layout, view)) => [
+                    match layout {
+                        StereoLayout::SideBySide => 1.,
+                        StereoLayout::TopBottom => 2.,
+                    },
+                    match view {
+                        StereoView::Left => 0.,
+                        StereoView::Right => 1.,
+                        StereoView::Anaglyph => 2.,
+                    },
+                    0.,
+                    0.,
+                ],
+            },
+            [draw.channel.index(), 0., 0., 0.],
+        ];
+        let bytes = params
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let buffer = graphics
+            .device()
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("yuv params"),
+                contents: &bytes,
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+
+        // missing planes are bound to what's there, the shader doesn't read them
+        let view = |idx: usize| &uploaded.textures[idx.min(uploaded.textures.len() - 1)].1;
+        let bind_group = graphics.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("yuv"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(view(0)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(view(1)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(view(2)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+        PreparedYuv {
+            bind_group,
+            _uploaded: uploaded,
+        }
+    }
+
+    fn render(
+        &self,
+        prepared: &Self::Prepared,
+        _origin: Point<Px>,
+        _opacity: f32,
+        graphics: &mut RenderingGraphics<'_, '_>,
+    ) {
+        let pass = graphics.pass_mut();
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &prepared.bind_group, &[]);
+        pass.draw(0..4, 0..1);
+    }
+}